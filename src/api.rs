@@ -0,0 +1,767 @@
+use crate::ids::ExternalId;
+use crate::models::{Category, MediaItem, Query, SavedSearch, SortField, SortKey, SortOrder};
+use crate::repo::{RepoError, Repository};
+use crate::search;
+use crate::util;
+use chrono::Local;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::json;
+
+pub const DEFAULT_PORT: u16 = 8765;
+const DEFAULT_LIMIT: usize = 100;
+const MAX_LIMIT: usize = 1000;
+
+/// An HTTP request as seen by [`handle`], independent of the server.
+pub struct ApiRequest<'a> {
+    pub method: &'a str,
+    /// Path with query string, e.g. `/api/items?q=cat:book`.
+    pub url: &'a str,
+    /// Value of the `Authorization` header.
+    pub authorization: Option<&'a str>,
+    /// Values of the `Host`, `Origin` and `Cookie` headers.
+    pub host: Option<&'a str>,
+    pub origin: Option<&'a str>,
+    pub cookie: Option<&'a str>,
+    pub body: &'a str,
+}
+
+#[derive(Debug)]
+pub struct ApiResponse {
+    pub status: u16,
+    pub content_type: &'static str,
+    /// Headers besides `Content-Type`, e.g. `Location`.
+    pub headers: Vec<(&'static str, String)>,
+    pub body: Vec<u8>,
+}
+
+impl ApiResponse {
+    fn json(status: u16, value: &impl Serialize) -> Self {
+        Self {
+            status,
+            content_type: "application/json",
+            headers: vec![],
+            body: serde_json::to_vec_pretty(value).unwrap_or_default(),
+        }
+    }
+
+    pub fn error(status: u16, message: impl Into<String>) -> Self {
+        Self::json(status, &json!({ "error": message.into() }))
+    }
+
+    fn no_content() -> Self {
+        Self {
+            status: 204,
+            content_type: "application/json",
+            headers: vec![],
+            body: vec![],
+        }
+    }
+
+    /// True for a successful request that changed the catalog.
+    pub fn is_write(&self, method: &str) -> bool {
+        method != "GET" && (200..300).contains(&self.status)
+    }
+}
+
+impl From<RepoError> for ApiResponse {
+    fn from(e: RepoError) -> Self {
+        match e {
+            RepoError::Search(_) | RepoError::Id(_) => Self::error(400, e.to_string()),
+            RepoError::DuplicateId(_) => Self::error(409, e.to_string()),
+            _ => Self::error(500, e.to_string()),
+        }
+    }
+}
+
+type ApiResult = Result<ApiResponse, ApiResponse>;
+
+/// Fields accepted when creating or updating an item. Missing fields are left
+/// unchanged; `null` clears an optional field.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ItemInput {
+    title: Option<String>,
+    #[serde(default, deserialize_with = "nullable")]
+    sort_title: Option<Option<String>>,
+    category: Option<String>,
+    status: Option<String>,
+    #[serde(default, deserialize_with = "nullable")]
+    rating: Option<Option<u8>>,
+    #[serde(default, deserialize_with = "nullable")]
+    notes: Option<Option<String>>,
+    #[serde(default, deserialize_with = "nullable")]
+    cover_path: Option<Option<String>>,
+    #[serde(default, deserialize_with = "nullable")]
+    progress: Option<Option<u32>>,
+    #[serde(default, deserialize_with = "nullable")]
+    progress_total: Option<Option<u32>>,
+    #[serde(default, deserialize_with = "nullable")]
+    file_path: Option<Option<String>>,
+    #[serde(default, deserialize_with = "nullable")]
+    format: Option<Option<String>>,
+    #[serde(default, deserialize_with = "nullable")]
+    priority: Option<Option<String>>,
+    #[serde(default, deserialize_with = "nullable")]
+    target_price: Option<Option<f64>>,
+    #[serde(default, deserialize_with = "nullable")]
+    store: Option<Option<String>>,
+    /// Replaces all ids, as `scheme:value` strings.
+    external_ids: Option<Vec<String>>,
+}
+
+// Distinguishes an explicit `null` (Some(None)) from a missing field (None).
+fn nullable<'de, D, T>(d: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(d).map(Some)
+}
+
+/// Routes one request. Everything under `/api` except the OpenAPI description
+/// requires `Authorization: Bearer <token>`.
+pub fn handle(repo: &dyn Repository, token: &str, req: &ApiRequest) -> ApiResponse {
+    let (path, query_string) = req.url.split_once('?').unwrap_or((req.url, ""));
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    if req.method == "GET" && segments == ["api", "openapi.json"] {
+        return ApiResponse::json(200, &openapi());
+    }
+    if segments.first() != Some(&"api") {
+        return ApiResponse::error(404, "Not found");
+    }
+    if !authorized(token, req.authorization) {
+        return ApiResponse::error(401, "Missing or invalid bearer token");
+    }
+    let result = match (req.method, &segments[1..]) {
+        ("GET", ["items"]) => list_items(repo, query_string),
+        ("POST", ["items"]) => create_item(repo, req.body),
+        ("GET", ["items", id]) => find_item(repo, id).map(|item| ApiResponse::json(200, &item)),
+        ("PUT" | "PATCH", ["items", id]) => update_item(repo, id, req.body),
+        ("DELETE", ["items", id]) => delete_item(repo, id),
+        ("GET", ["external-ids", id]) => find_by_external_id(repo, id),
+        ("GET", ["stats"]) => stats(repo),
+        ("GET", ["saved-searches"]) => saved_searches(repo),
+        (_, ["items"] | ["items", _] | ["external-ids", _] | ["stats"] | ["saved-searches"]) => {
+            Err(ApiResponse::error(405, "Method not allowed"))
+        }
+        _ => Err(ApiResponse::error(404, "Not found")),
+    };
+    result.unwrap_or_else(|e| e)
+}
+
+fn authorized(token: &str, header: Option<&str>) -> bool {
+    header
+        .and_then(|h| h.strip_prefix("Bearer "))
+        .is_some_and(|given| token_matches(token, given))
+}
+
+/// Whether `given` is `token`, compared in constant time so the token can't
+/// be guessed byte by byte.
+pub fn token_matches(token: &str, given: &str) -> bool {
+    let (a, b) = (given.trim().as_bytes(), token.as_bytes());
+    !token.is_empty()
+        && a.len() == b.len()
+        && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn list_items(repo: &dyn Repository, query_string: &str) -> ApiResult {
+    let params = util::query_params(query_string);
+    let saved = match params
+        .iter()
+        .find(|(name, value)| name == "saved" && !value.is_empty())
+    {
+        Some((_, name)) => find_saved(repo, name)?.query,
+        None => Query::default(),
+    };
+    let mut query = narrow_query(saved, &params).map_err(|msg| ApiResponse::error(400, msg))?;
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT);
+    query.limit = Some(limit);
+    let total = repo.count(&query)?;
+    let items = repo.list(&query)?;
+    Ok(ApiResponse::json(
+        200,
+        &json!({ "total": total, "offset": query.offset, "limit": limit, "items": items }),
+    ))
+}
+
+fn create_item(repo: &dyn Repository, body: &str) -> ApiResult {
+    let input = parse_input(body)?;
+    let title = input.title.clone().unwrap_or_default();
+    let mut item = MediaItem::new(title, Category::Other);
+    apply_input(&mut item, input)?;
+    repo.add(&mut item)?;
+    Ok(ApiResponse::json(201, &item))
+}
+
+fn update_item(repo: &dyn Repository, id: &str, body: &str) -> ApiResult {
+    let mut item = find_item(repo, id)?;
+    apply_input(&mut item, parse_input(body)?)?;
+    item.updated_at = Local::now();
+    repo.update(&item)?;
+    Ok(ApiResponse::json(200, &item))
+}
+
+fn delete_item(repo: &dyn Repository, id: &str) -> ApiResult {
+    let item = find_item(repo, id)?;
+    repo.delete(item.id.unwrap_or_default())?;
+    Ok(ApiResponse::no_content())
+}
+
+fn find_item(repo: &dyn Repository, id: &str) -> Result<MediaItem, ApiResponse> {
+    let id: i64 = id
+        .parse()
+        .map_err(|_| ApiResponse::error(400, format!("`{}` is not an item id", id)))?;
+    repo.get(id)?
+        .ok_or_else(|| ApiResponse::error(404, format!("No item with id {}", id)))
+}
+
+fn saved_searches(repo: &dyn Repository) -> ApiResult {
+    Ok(ApiResponse::json(200, &repo.list_saved_searches()?))
+}
+
+fn find_saved(repo: &dyn Repository, name: &str) -> Result<SavedSearch, ApiResponse> {
+    repo.list_saved_searches()?
+        .into_iter()
+        .find(|s| s.name.eq_ignore_ascii_case(name))
+        .ok_or_else(|| ApiResponse::error(404, format!("No saved search named `{}`", name)))
+}
+
+fn find_by_external_id(repo: &dyn Repository, id: &str) -> ApiResult {
+    let id = ExternalId::parse(id).map_err(|e| ApiResponse::error(400, e.to_string()))?;
+    let item = repo
+        .find_by_external_id(&id)?
+        .ok_or_else(|| ApiResponse::error(404, format!("No item with id {}", id)))?;
+    Ok(ApiResponse::json(200, &item))
+}
+
+fn stats(repo: &dyn Repository) -> ApiResult {
+    let stats = repo.stats()?;
+    let by_category: serde_json::Map<String, serde_json::Value> = stats
+        .by_category
+        .into_iter()
+        .map(|(cat, n)| (cat, n.into()))
+        .collect();
+    Ok(ApiResponse::json(
+        200,
+        &json!({
+            "total": stats.total,
+            "finished": stats.finished,
+            "unfinished": stats.unfinished,
+            "wishlist": stats.wishlist,
+            "by_category": by_category,
+        }),
+    ))
+}
+
+fn parse_input(body: &str) -> Result<ItemInput, ApiResponse> {
+    serde_json::from_str(body).map_err(|e| ApiResponse::error(400, format!("Invalid body: {}", e)))
+}
+
+fn apply_input(item: &mut MediaItem, input: ItemInput) -> Result<(), ApiResponse> {
+    let bad = |msg: String| ApiResponse::error(400, msg);
+    if let Some(title) = input.title {
+        if title.trim().is_empty() {
+            return Err(bad("Title cannot be empty".into()));
+        }
+        item.title = title;
+    }
+    if item.title.trim().is_empty() {
+        return Err(bad("Title is required".into()));
+    }
+    if let Some(cat) = input.category {
+        item.category = search::parse_category(&cat)
+            .filter(|_| !cat.is_empty())
+            .ok_or_else(|| bad(format!("Unknown category `{}`", cat)))?;
+    }
+    if let Some(status) = input.status {
+        let status = search::parse_status(&status)
+            .ok_or_else(|| bad(format!("Unknown status `{}`", status)))?;
+        item.set_status(status);
+    }
+    if let Some(rating) = input.rating {
+        if rating.is_some_and(|r| r > 10) {
+            return Err(bad("Rating must be between 0 and 10".into()));
+        }
+        item.rating = rating;
+    }
+    let non_empty = |s: Option<String>| s.filter(|s| !s.trim().is_empty());
+    if let Some(sort_title) = input.sort_title {
+        item.sort_title = non_empty(sort_title);
+    }
+    if let Some(notes) = input.notes {
+        item.notes = non_empty(notes);
+    }
+    if let Some(cover_path) = input.cover_path {
+        item.cover_path = non_empty(cover_path);
+    }
+    if let Some(progress) = input.progress {
+        item.progress = progress;
+    }
+    if let Some(progress_total) = input.progress_total {
+        item.progress_total = progress_total;
+    }
+    if let Some(file_path) = input.file_path {
+        item.file_path = non_empty(file_path);
+    }
+    if let Some(format) = input.format {
+        item.format = match non_empty(format) {
+            Some(f) => Some(
+                search::parse_format(&f).ok_or_else(|| bad(format!("Unknown format `{}`", f)))?,
+            ),
+            None => None,
+        };
+    }
+    if let Some(priority) = input.priority {
+        item.priority = match non_empty(priority) {
+            Some(p) => Some(
+                search::parse_priority(&p)
+                    .ok_or_else(|| bad(format!("Unknown priority `{}`", p)))?,
+            ),
+            None => None,
+        };
+    }
+    if let Some(target_price) = input.target_price {
+        if target_price.is_some_and(|p| p < 0.0) {
+            return Err(bad("Target price cannot be negative".into()));
+        }
+        item.target_price = target_price;
+    }
+    if let Some(store) = input.store {
+        item.store = non_empty(store);
+    }
+    if let Some(ids) = input.external_ids {
+        item.external_ids.clear();
+        for id in ids {
+            let id = ExternalId::parse(&id).map_err(|e| bad(e.to_string()))?;
+            item.add_external_id(id);
+        }
+    }
+    Ok(())
+}
+
+/// Builds a [`Query`] from `q`, `category`, `status`, `min_rating`, `owned`,
+/// `on_loan`, `sort` (comma-separated fields, `-` prefix for descending),
+/// `ignore_articles`, `limit` and `offset` parameters.
+pub fn query_from_params(params: &[(String, String)]) -> Result<Query, String> {
+    narrow_query(Query::default(), params)
+}
+
+/// Applies the parameters of [`query_from_params`] to `query`: `q` narrows
+/// its search, `sort` replaces its sort and the rest override its filters.
+fn narrow_query(mut query: Query, params: &[(String, String)]) -> Result<Query, String> {
+    let bad = |name: &str, value: &str| format!("Invalid value `{}` for `{}`", value, name);
+    for (name, value) in params {
+        if value.is_empty() {
+            continue;
+        }
+        match name.as_str() {
+            "q" => query.narrow(value),
+            "category" => {
+                query.category =
+                    Some(search::parse_category(value).ok_or_else(|| bad(name, value))?)
+            }
+            "status" => {
+                query.status = Some(search::parse_status(value).ok_or_else(|| bad(name, value))?)
+            }
+            "min_rating" => query.min_rating = Some(value.parse().map_err(|_| bad(name, value))?),
+            "owned" => query.owned = Some(value.parse().map_err(|_| bad(name, value))?),
+            "on_loan" => query.on_loan = Some(value.parse().map_err(|_| bad(name, value))?),
+            "ignore_articles" => query.ignore_articles = value == "true" || value == "1",
+            "limit" => query.limit = Some(value.parse().map_err(|_| bad(name, value))?),
+            "offset" => query.offset = value.parse().map_err(|_| bad(name, value))?,
+            "sort" => {
+                query.sort.clear();
+                for key in value.split(',').filter(|k| !k.is_empty()) {
+                    let (order, field) = match key.strip_prefix('-') {
+                        Some(field) => (SortOrder::Desc, field),
+                        None => (SortOrder::Asc, key),
+                    };
+                    let field = SortField::ALL
+                        .into_iter()
+                        .find(|f| f.as_str().eq_ignore_ascii_case(field))
+                        .ok_or_else(|| bad(name, key))?;
+                    query.sort.push(SortKey::new(field, order));
+                }
+                query.dedup_sort();
+            }
+            _ => {}
+        }
+    }
+    search::parse(&query.search).map_err(|e| RepoError::from(e).to_string())?;
+    Ok(query)
+}
+
+/// Server settings, stored in the catalog database.
+pub struct ApiSettings {
+    pub enabled: bool,
+    pub port: u16,
+    pub token: String,
+    /// Whether the HTML view may change items. The JSON API always can.
+    pub web_edits: bool,
+}
+
+impl ApiSettings {
+    /// Loads the settings, creating and storing a token on first use.
+    pub fn load(repo: &dyn Repository) -> Result<Self, RepoError> {
+        let token = match repo.get_setting("api.token")? {
+            Some(token) if !token.is_empty() => token,
+            _ => {
+                let token = generate_token();
+                repo.set_setting("api.token", &token)?;
+                token
+            }
+        };
+        Ok(Self {
+            enabled: repo.get_setting("api.enabled")?.as_deref() == Some("true"),
+            port: repo
+                .get_setting("api.port")?
+                .and_then(|p| p.parse().ok())
+                .unwrap_or(DEFAULT_PORT),
+            token,
+            web_edits: repo.get_setting("web.edits")?.as_deref() == Some("true"),
+        })
+    }
+
+    pub fn save(&self, repo: &dyn Repository) -> Result<(), RepoError> {
+        repo.set_setting("api.enabled", &self.enabled.to_string())?;
+        repo.set_setting("api.port", &self.port.to_string())?;
+        repo.set_setting("web.edits", &self.web_edits.to_string())?;
+        repo.set_setting("api.token", &self.token)
+    }
+}
+
+/// Random token for a new API configuration.
+pub fn generate_token() -> String {
+    let mut rng = fastrand::Rng::new();
+    (0..32).map(|_| rng.alphanumeric()).collect()
+}
+
+fn openapi() -> serde_json::Value {
+    let item_input = json!({
+        "type": "object",
+        "properties": {
+            "title": { "type": "string" },
+            "sort_title": { "type": ["string", "null"] },
+            "category": { "type": "string", "enum": ["Book", "Movie", "Game", "Music", "Other"] },
+            "status": { "type": "string", "enum": ["Wishlist", "Planned", "InProgress", "Finished"] },
+            "rating": { "type": ["integer", "null"], "minimum": 0, "maximum": 10 },
+            "notes": { "type": ["string", "null"] },
+            "cover_path": { "type": ["string", "null"] },
+            "progress": { "type": ["integer", "null"], "minimum": 0 },
+            "progress_total": { "type": ["integer", "null"], "minimum": 0 },
+            "file_path": { "type": ["string", "null"] },
+            "format": { "type": ["string", "null"], "enum": ["Physical", "Digital", null] },
+            "priority": { "type": ["string", "null"], "enum": ["High", "Normal", "Low", null] },
+            "target_price": { "type": ["number", "null"], "minimum": 0 },
+            "store": { "type": ["string", "null"], "description": "Store name or URL" },
+            "external_ids": {
+                "type": "array",
+                "items": { "type": "string", "example": "isbn:9780441172719" },
+                "description": "Ids in other catalogs as `scheme:value`; schemes: isbn, imdb, tmdb, igdb, musicbrainz, openlibrary, goodreads, letterboxd, mal, anilist"
+            }
+        }
+    });
+    let mut item = item_input.clone();
+    item["properties"]["id"] = json!({ "type": "integer" });
+    for field in ["created_at", "updated_at", "started_at", "finished_at"] {
+        item["properties"][field] = json!({ "type": ["string", "null"], "format": "date-time" });
+    }
+    let id_param = json!({
+        "name": "id", "in": "path", "required": true, "schema": { "type": "integer" }
+    });
+    let query_param = |name: &str, schema: serde_json::Value, description: &str| json!({ "name": name, "in": "query", "schema": schema, "description": description });
+    let item_response = |description: &str| {
+        json!({
+            "description": description,
+            "content": { "application/json": { "schema": { "$ref": "#/components/schemas/Item" } } }
+        })
+    };
+    let item_body = json!({
+        "required": true,
+        "content": { "application/json": { "schema": { "$ref": "#/components/schemas/ItemInput" } } }
+    });
+    json!({
+        "openapi": "3.1.0",
+        "info": {
+            "title": "Media Catalog API",
+            "version": env!("CARGO_PKG_VERSION"),
+            "description": "Local API over the media catalog. Send `Authorization: Bearer <token>`."
+        },
+        "servers": [{ "url": "/api" }],
+        "security": [{ "bearer": [] }],
+        "paths": {
+            "/items": {
+                "get": {
+                    "summary": "List items matching a query",
+                    "parameters": [
+                        query_param("saved", json!({ "type": "string" }), "Name of a saved search to start from; the other parameters narrow it"),
+                        query_param("q", json!({ "type": "string" }), "Search, e.g. `cat:book rating>=7`"),
+                        query_param("category", json!({ "type": "string" }), "Category name"),
+                        query_param("status", json!({ "type": "string" }), "Status name"),
+                        query_param("min_rating", json!({ "type": "integer" }), "Minimum rating"),
+                        query_param("owned", json!({ "type": "boolean" }), "Only items with (`true`) or without (`false`) a copy"),
+                        query_param("on_loan", json!({ "type": "boolean" }), "Only items lent and not returned (`true`) or not lent out (`false`)"),
+                        query_param("sort", json!({ "type": "string" }), "Comma-separated fields (title, category, status, rating, created, updated); prefix `-` for descending"),
+                        query_param("ignore_articles", json!({ "type": "boolean" }), "Sort titles ignoring leading articles"),
+                        query_param("limit", json!({ "type": "integer", "default": DEFAULT_LIMIT, "maximum": MAX_LIMIT }), "Page size"),
+                        query_param("offset", json!({ "type": "integer", "default": 0 }), "Items to skip")
+                    ],
+                    "responses": {
+                        "200": {
+                            "description": "One page of items and the total number of matches",
+                            "content": { "application/json": { "schema": {
+                                "type": "object",
+                                "properties": {
+                                    "total": { "type": "integer" },
+                                    "offset": { "type": "integer" },
+                                    "limit": { "type": "integer" },
+                                    "items": { "type": "array", "items": { "$ref": "#/components/schemas/Item" } }
+                                }
+                            } } }
+                        },
+                        "400": { "description": "Invalid query" },
+                        "404": { "description": "No saved search has the name" }
+                    }
+                },
+                "post": {
+                    "summary": "Add an item",
+                    "requestBody": item_body,
+                    "responses": { "201": item_response("The new item"), "400": { "description": "Invalid item" } }
+                }
+            },
+            "/items/{id}": {
+                "parameters": [id_param],
+                "get": {
+                    "summary": "Get an item",
+                    "responses": { "200": item_response("The item"), "404": { "description": "No such item" } }
+                },
+                "put": {
+                    "summary": "Update the given fields of an item",
+                    "requestBody": item_body,
+                    "responses": { "200": item_response("The updated item"), "404": { "description": "No such item" } }
+                },
+                "delete": {
+                    "summary": "Delete an item",
+                    "responses": { "204": { "description": "Deleted" }, "404": { "description": "No such item" } }
+                }
+            },
+            "/external-ids/{external_id}": {
+                "parameters": [{
+                    "name": "external_id", "in": "path", "required": true,
+                    "schema": { "type": "string" }, "description": "`scheme:value`, e.g. `imdb:tt0133093`"
+                }],
+                "get": {
+                    "summary": "Get the item with an external id",
+                    "responses": {
+                        "200": item_response("The item"),
+                        "400": { "description": "Invalid id" },
+                        "404": { "description": "No item has the id" }
+                    }
+                }
+            },
+            "/saved-searches": {
+                "get": {
+                    "summary": "Saved searches",
+                    "responses": { "200": {
+                        "description": "Each saved search's name and query, for use with `/items?saved=<name>`",
+                        "content": { "application/json": { "schema": {
+                            "type": "array",
+                            "items": {
+                                "type": "object",
+                                "properties": { "name": { "type": "string" }, "query": { "type": "object" } }
+                            }
+                        } } }
+                    } }
+                }
+            },
+            "/stats": {
+                "get": {
+                    "summary": "Catalog totals",
+                    "responses": { "200": { "description": "Totals overall and per category" } }
+                }
+            }
+        },
+        "components": {
+            "securitySchemes": { "bearer": { "type": "http", "scheme": "bearer" } },
+            "schemas": { "Item": item, "ItemInput": item_input }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sqlite_repo::SqliteRepo;
+    use serde_json::Value;
+    use std::path::Path;
+
+    const TOKEN: &str = "secret-token";
+
+    fn memory_repo() -> SqliteRepo {
+        let repo = SqliteRepo::new(Path::new(":memory:"));
+        repo.init().unwrap();
+        repo
+    }
+
+    fn call(
+        repo: &dyn Repository,
+        method: &str,
+        url: &str,
+        authorization: Option<&str>,
+        body: &str,
+    ) -> (u16, Value) {
+        let response = handle(
+            repo,
+            TOKEN,
+            &ApiRequest {
+                method,
+                url,
+                authorization,
+                host: None,
+                origin: None,
+                cookie: None,
+                body,
+            },
+        );
+        let json = serde_json::from_slice(&response.body).unwrap_or(Value::Null);
+        (response.status, json)
+    }
+
+    fn authed(repo: &dyn Repository, method: &str, url: &str, body: &str) -> (u16, Value) {
+        call(repo, method, url, Some("Bearer secret-token"), body)
+    }
+
+    #[test]
+    fn rejects_requests_without_the_token() {
+        let repo = memory_repo();
+        let mut item = MediaItem::new("Dune", Category::Book);
+        repo.add(&mut item).unwrap();
+        for (method, url, body) in [
+            ("GET", "/api/items", ""),
+            ("GET", "/api/items/1", ""),
+            ("POST", "/api/items", r#"{"title": "Emma"}"#),
+            ("PATCH", "/api/items/1", r#"{"rating": 9}"#),
+            ("DELETE", "/api/items/1", ""),
+        ] {
+            for authorization in [None, Some("Bearer wrong"), Some("secret-token")] {
+                let (status, _) = call(&repo, method, url, authorization, body);
+                assert_eq!(status, 401, "{} {} with {:?}", method, url, authorization);
+            }
+        }
+        let unchanged = repo.get(1).unwrap().unwrap();
+        assert_eq!(unchanged.rating, None);
+        assert_eq!(repo.count(&Query::default()).unwrap(), 1);
+
+        let (status, spec) = call(&repo, "GET", "/api/openapi.json", None, "");
+        assert_eq!(status, 200);
+        assert_eq!(spec["openapi"], "3.1.0");
+    }
+
+    #[test]
+    fn creates_reads_updates_and_deletes_items() {
+        let repo = memory_repo();
+        let (status, created) = authed(
+            &repo,
+            "POST",
+            "/api/items",
+            r#"{"title": "Dune", "category": "book", "status": "done", "rating": 8,
+                "external_ids": ["isbn:0441172717"]}"#,
+        );
+        assert_eq!(status, 201);
+        let id = created["id"].as_i64().unwrap();
+        assert_eq!(created["status"], "Finished");
+
+        let (status, item) = authed(&repo, "GET", &format!("/api/items/{}", id), "");
+        assert_eq!((status, item["title"].as_str()), (200, Some("Dune")));
+        let (status, found) = authed(&repo, "GET", "/api/external-ids/isbn:9780441172719", "");
+        assert_eq!((status, found["id"].as_i64()), (200, Some(id)));
+
+        let (status, patched) = authed(
+            &repo,
+            "PATCH",
+            &format!("/api/items/{}", id),
+            r##"{"rating": null, "notes": "#scifi"}"##,
+        );
+        assert_eq!(status, 200);
+        assert_eq!(patched["rating"], Value::Null);
+        assert_eq!(patched["title"], "Dune");
+        assert_eq!(
+            repo.get(id).unwrap().unwrap().notes.as_deref(),
+            Some("#scifi")
+        );
+
+        authed(
+            &repo,
+            "POST",
+            "/api/items",
+            r#"{"title": "Emma", "category": "book"}"#,
+        );
+        let (status, page) = authed(
+            &repo,
+            "GET",
+            "/api/items?q=cat:book&sort=-title&limit=1",
+            "",
+        );
+        assert_eq!(status, 200);
+        assert_eq!(page["total"], 2);
+        assert_eq!(page["items"][0]["title"], "Emma");
+
+        let (status, _) = authed(&repo, "DELETE", &format!("/api/items/{}", id), "");
+        assert_eq!(status, 204);
+        let (status, _) = authed(&repo, "GET", &format!("/api/items/{}", id), "");
+        assert_eq!(status, 404);
+    }
+
+    #[test]
+    fn narrows_saved_searches() {
+        let repo = memory_repo();
+        for (title, category) in [
+            ("Dune", Category::Book),
+            ("Emma", Category::Book),
+            ("Alien", Category::Movie),
+        ] {
+            repo.add(&mut MediaItem::new(title, category)).unwrap();
+        }
+        let mut saved = SavedSearch {
+            id: None,
+            name: "Books".into(),
+            query: Query {
+                search: "cat:book".into(),
+                ..Query::default()
+            },
+        };
+        repo.save_search(&mut saved).unwrap();
+
+        let (status, searches) = authed(&repo, "GET", "/api/saved-searches", "");
+        assert_eq!(status, 200);
+        assert_eq!(searches[0]["name"], "Books");
+        let (status, page) = authed(&repo, "GET", "/api/items?saved=books&sort=-title", "");
+        assert_eq!(status, 200);
+        assert_eq!(page["total"], 2);
+        assert_eq!(page["items"][0]["title"], "Emma");
+        let (_, page) = authed(&repo, "GET", "/api/items?saved=Books&q=dune", "");
+        assert_eq!(page["total"], 1);
+        assert_eq!(page["items"][0]["title"], "Dune");
+        let (status, _) = authed(&repo, "GET", "/api/items?saved=Films", "");
+        assert_eq!(status, 404);
+    }
+
+    #[test]
+    fn reports_bad_input() {
+        let repo = memory_repo();
+        let status = |method: &str, url: &str, body: &str| authed(&repo, method, url, body).0;
+        assert_eq!(status("POST", "/api/items", r#"{"category": "book"}"#), 400);
+        assert_eq!(
+            status("POST", "/api/items", r#"{"title": "X", "rating": 11}"#),
+            400
+        );
+        assert_eq!(
+            status("POST", "/api/items", r#"{"title": "X", "colour": "red"}"#),
+            400
+        );
+        assert_eq!(status("GET", "/api/items?q=rating>=11", ""), 400);
+        assert_eq!(status("GET", "/api/items/abc", ""), 400);
+        assert_eq!(status("PUT", "/api/stats", ""), 405);
+        assert_eq!(status("GET", "/api/nothing", ""), 404);
+    }
+}
@@ -1,20 +1,225 @@
-use crate::models::{Category, MediaItem, Query, SortField, SortOrder, Status};
-use crate::repo::{Repository, Stats};
+use crate::barcode::CodeEntry;
+use crate::dedup::TitleIndex;
+use crate::duplicates_view::DuplicatesView;
+use crate::enrich_view::EnrichView;
+use crate::export_view::ExportView;
+use crate::goals_view::GoalsView;
+use crate::ids::{ExternalId, IdScheme};
+use crate::import::{self, ImportPreview};
+use crate::import_view::ImportView;
+use crate::inventory_view::InventoryView;
+use crate::loans_view::LoansView;
+use crate::models::{
+    Category, Condition, Format, Loan, MediaItem, OwnedCopy, Priority, Query, SavedSearch,
+    SortField, SortKey, SortOrder, Status,
+};
+use crate::picker_view::PickerView;
+use crate::repo::{RepoResult, Repository, Stats};
+use crate::scan;
+use crate::search::{self, ParseError};
+use crate::server_view::ServerView;
+use crate::site;
 use crate::sqlite_repo::SqliteRepo;
+use crate::stats_view::StatsView;
 use crate::util;
-use chrono::Local;
-use eframe::egui::{self, Button, Id, Key, Response, RichText, TextEdit};
+use crate::watch_view::WatchView;
+use crate::wishlist_view::WishlistView;
+use chrono::{Local, NaiveDate};
+use eframe::egui::{self, Button, Key, RichText, TextEdit};
 use egui_extras::{Column, TableBuilder};
+use std::collections::HashMap;
 use std::path::Path;
+use std::sync::Arc;
+
+const PAGE_SIZE: usize = 200;
+const MAX_CACHED_PAGES: usize = 16;
+const ROW_HEIGHT: f32 = 24.0;
+
+const SEARCH_HELP: &str = "Words match the title; \"quoted phrases\" match exactly.\n\
+Fields: title: notes: cat: status: format: owned: location: onloan: overdue: borrower:\n\
+rating: tag: created: updated:\n\
+Operators: cat:book  status:!finished  rating>=7  updated:<30d  created:>2024-01-01\n\
+Combine with OR, NOT (or -) and parentheses; terms are ANDed by default.\n\
+tag:scifi matches #scifi in the notes; owned:yes matches items with a copy;\n\
+borrower:sam matches items Sam has not returned yet.";
+
+/// Rows of the current query, fetched from the repository one page at a time
+/// as the table scrolls over them.
+#[derive(Default)]
+struct ItemPages {
+    total: usize,
+    pages: HashMap<usize, Vec<MediaItem>>,
+}
+
+impl ItemPages {
+    fn reset(&mut self, total: usize) {
+        self.total = total;
+        self.pages.clear();
+    }
+
+    fn get_mut(
+        &mut self,
+        repo: &dyn Repository,
+        query: &Query,
+        index: usize,
+    ) -> RepoResult<Option<&mut MediaItem>> {
+        let page = index / PAGE_SIZE;
+        if !self.pages.contains_key(&page) {
+            if self.pages.len() >= MAX_CACHED_PAGES {
+                // Keep the neighbourhood of the visible page, drop the rest.
+                self.pages.retain(|p, _| p.abs_diff(page) <= 1);
+            }
+            let items = repo.list(&query.page(page * PAGE_SIZE, PAGE_SIZE))?;
+            self.pages.insert(page, items);
+        }
+        Ok(self
+            .pages
+            .get_mut(&page)
+            .and_then(|items| items.get_mut(index % PAGE_SIZE)))
+    }
+}
 
 pub struct CatalogApp {
-    repo: Box<dyn Repository>,
-    items: Vec<MediaItem>,
+    repo: Arc<dyn Repository>,
+    items: ItemPages,
     query: Query,
+    /// What the table and exports show: `query` as of the last refresh,
+    /// keeping the last search that parsed.
+    fetched: Query,
     new_item_title: String,
     new_item_category: Category,
+    /// Existing titles similar to `new_item_title`, shown as a warning.
+    similar_titles: Vec<String>,
+    title_index: Option<TitleIndex>,
     error: Option<String>,
+    search_error: Option<ParseError>,
     stats: Stats,
+    smart_lists: Vec<(SavedSearch, Option<usize>)>,
+    new_smart_list_name: String,
+    editing: Option<ItemDraft>,
+    stats_view: StatsView,
+    inventory_view: InventoryView,
+    loans_view: LoansView,
+    goals_view: GoalsView,
+    duplicates_view: DuplicatesView,
+    picker_view: PickerView,
+    export_view: ExportView,
+    import_view: ImportView,
+    server_view: ServerView,
+    enrich_view: EnrichView,
+    watch_view: WatchView,
+    wishlist_view: WishlistView,
+    code_entry: CodeEntry,
+    /// Whether linked files exist, checked once per refresh.
+    file_exists: HashMap<String, bool>,
+    /// Loans not returned yet, by item, for the badge in the table.
+    open_loans: HashMap<i64, Vec<Loan>>,
+}
+
+/// Item open in the editor window, with text buffers for fields parsed on save.
+struct ItemDraft {
+    item: MediaItem,
+    finished_on: String,
+    id_scheme: IdScheme,
+    id_value: String,
+    target_price: String,
+    store: String,
+    copies: Vec<CopyDraft>,
+    /// Saved copies removed in the editor, deleted on save.
+    removed_copies: Vec<i64>,
+    loans: Vec<LoanDraft>,
+    /// Saved loans removed in the editor, deleted on save.
+    removed_loans: Vec<i64>,
+}
+
+impl ItemDraft {
+    fn new(item: &MediaItem, copies: Vec<OwnedCopy>, loans: Vec<Loan>) -> Self {
+        Self {
+            finished_on: item
+                .finished_at
+                .map(|t| t.format("%Y-%m-%d").to_string())
+                .unwrap_or_default(),
+            item: item.clone(),
+            id_scheme: IdScheme::Isbn,
+            id_value: String::new(),
+            target_price: item
+                .target_price
+                .map(|p| format!("{:.2}", p))
+                .unwrap_or_default(),
+            store: item.store.clone().unwrap_or_default(),
+            copies: copies.into_iter().map(CopyDraft::new).collect(),
+            removed_copies: vec![],
+            loans: loans.into_iter().map(LoanDraft::new).collect(),
+            removed_loans: vec![],
+        }
+    }
+}
+
+struct CopyDraft {
+    copy: OwnedCopy,
+    purchased_on: String,
+    price: String,
+}
+
+impl CopyDraft {
+    fn new(copy: OwnedCopy) -> Self {
+        Self {
+            purchased_on: copy.purchased_on.map(|d| d.to_string()).unwrap_or_default(),
+            price: copy.price.map(|p| format!("{:.2}", p)).unwrap_or_default(),
+            copy,
+        }
+    }
+
+    // The copy with the text fields parsed.
+    fn parsed(&self) -> Result<OwnedCopy, String> {
+        let mut copy = self.copy.clone();
+        copy.purchased_on = parse_date(&self.purchased_on)?;
+        copy.price = util::parse_price(&self.price)?;
+        Ok(copy)
+    }
+}
+
+struct LoanDraft {
+    loan: Loan,
+    lent_on: String,
+    due_on: String,
+    returned_on: String,
+}
+
+impl LoanDraft {
+    fn new(loan: Loan) -> Self {
+        let text = |d: Option<NaiveDate>| d.map(|d| d.to_string()).unwrap_or_default();
+        Self {
+            lent_on: loan.lent_on.to_string(),
+            due_on: text(loan.due_on),
+            returned_on: text(loan.returned_on),
+            loan,
+        }
+    }
+
+    // The loan with the text fields parsed and checked.
+    fn parsed(&self) -> Result<Loan, String> {
+        let mut loan = self.loan.clone();
+        loan.borrower = loan.borrower.trim().to_string();
+        if loan.borrower.is_empty() {
+            return Err("A loan needs a borrower".into());
+        }
+        loan.lent_on = parse_date(&self.lent_on)?
+            .ok_or_else(|| format!("Loan to {} needs a lent date", loan.borrower))?;
+        loan.due_on = parse_date(&self.due_on)?;
+        loan.returned_on = parse_date(&self.returned_on)?;
+        if [loan.due_on, loan.returned_on]
+            .into_iter()
+            .flatten()
+            .any(|d| d < loan.lent_on)
+        {
+            return Err(format!(
+                "Loan to {}: due and returned dates cannot be before {}",
+                loan.borrower, loan.lent_on
+            ));
+        }
+        Ok(loan)
+    }
 }
 
 impl CatalogApp {
@@ -22,53 +227,843 @@ impl CatalogApp {
         let repo = SqliteRepo::new(db_path);
         let _ = repo.init();
         let mut app = Self {
-            repo: Box::new(repo),
-            items: vec![],
+            repo: Arc::new(repo),
+            items: ItemPages::default(),
             query: Query {
-                sort_field: SortField::UpdatedAt,
-                sort_order: SortOrder::Desc,
+                sort: vec![SortKey::new(SortField::UpdatedAt, SortOrder::Desc)],
                 ..Default::default()
             },
             new_item_title: String::new(),
             new_item_category: Category::Movie,
+            similar_titles: vec![],
+            title_index: None,
             error: None,
+            search_error: None,
+            fetched: Query::default(),
             stats: Stats::default(),
+            smart_lists: vec![],
+            new_smart_list_name: String::new(),
+            editing: None,
+            stats_view: StatsView::default(),
+            inventory_view: InventoryView::default(),
+            loans_view: LoansView::default(),
+            goals_view: GoalsView::default(),
+            duplicates_view: DuplicatesView::default(),
+            picker_view: PickerView::default(),
+            export_view: ExportView::default(),
+            import_view: ImportView::default(),
+            server_view: ServerView::default(),
+            enrich_view: EnrichView::default(),
+            watch_view: WatchView::default(),
+            wishlist_view: WishlistView::default(),
+            code_entry: CodeEntry::default(),
+            file_exists: HashMap::new(),
+            open_loans: HashMap::new(),
         };
+        if let Err(e) = app.server_view.load(&app.repo) {
+            app.error = Some(e.to_string());
+        }
+        if let Err(e) = app.watch_view.load(&app.repo) {
+            app.error = Some(e.to_string());
+        }
         app.refresh();
         app
     }
 
     fn refresh(&mut self) {
-        match self.repo.list(&self.query) {
-            Ok(list) => self.items = list,
+        self.title_index = None;
+        self.file_exists.clear();
+        // An invalid search stays in the box for editing but is not run.
+        let search = match self.search_error {
+            Some(_) => std::mem::take(&mut self.fetched.search),
+            None => self.query.search.clone(),
+        };
+        self.fetched = Query {
+            search,
+            ..self.query.clone()
+        };
+        match self.repo.count(&self.fetched) {
+            Ok(total) => self.items.reset(total),
             Err(e) => self.error = Some(e.to_string()),
         }
         match self.repo.stats() {
             Ok(stats) => self.stats = stats,
             Err(e) => self.error = Some(e.to_string()),
         }
+        if let Err(e) = self.goals_view.reload(self.repo.as_ref()) {
+            self.error = Some(e.to_string());
+        }
+        if self.stats_view.open {
+            if let Err(e) = self.stats_view.reload(self.repo.as_ref()) {
+                self.error = Some(e.to_string());
+            }
+        }
+        if self.inventory_view.open {
+            if let Err(e) = self
+                .inventory_view
+                .reload(self.repo.as_ref(), &self.fetched)
+            {
+                self.error = Some(e.to_string());
+            }
+        }
+        if self.loans_view.open {
+            if let Err(e) = self.loans_view.reload(self.repo.as_ref(), &self.fetched) {
+                self.error = Some(e.to_string());
+            }
+        }
+        if self.wishlist_view.open {
+            if let Err(e) = self.wishlist_view.reload(self.repo.as_ref()) {
+                self.error = Some(e.to_string());
+            }
+        }
+        self.open_loans.clear();
+        match self.repo.open_loans() {
+            Ok(loans) => {
+                for loan in loans {
+                    self.open_loans.entry(loan.item_id).or_default().push(loan);
+                }
+            }
+            Err(e) => self.error = Some(e.to_string()),
+        }
+        match self.repo.list_saved_searches() {
+            Ok(saved) => {
+                self.smart_lists = saved
+                    .into_iter()
+                    .map(|s| {
+                        let count = self.repo.count(&s.query).ok();
+                        (s, count)
+                    })
+                    .collect()
+            }
+            Err(e) => self.error = Some(e.to_string()),
+        }
+    }
+
+    fn check_similar_titles(&mut self) {
+        self.similar_titles.clear();
+        let title = self.new_item_title.trim();
+        if title.is_empty() {
+            return;
+        }
+        let category = self.new_item_category;
+        if self.title_index.as_ref().map(|t| t.category) != Some(category) {
+            match self.repo.titles(category) {
+                Ok(titles) => self.title_index = Some(TitleIndex::new(category, titles)),
+                Err(e) => {
+                    self.error = Some(e.to_string());
+                    return;
+                }
+            }
+        }
+        if let Some(index) = &self.title_index {
+            self.similar_titles = index
+                .find(title)
+                .into_iter()
+                .take(3)
+                .map(String::from)
+                .collect();
+        }
+    }
+
+    /// Asks for a file, reads it with `read` and opens the import preview.
+    /// `categories` are the choices offered for items without a fitting category.
+    fn import_file(
+        &mut self,
+        source: &'static str,
+        extensions: &[&str],
+        read: import::Reader,
+        categories: &[import::CategoryChoice],
+    ) {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter(source, extensions)
+            .pick_file()
+        else {
+            return;
+        };
+        let preview = read(&path)
+            .map_err(|e| format!("Cannot import {}: {}", path.display(), e))
+            .and_then(|items| {
+                ImportPreview::new(source, items, self.repo.as_ref())
+                    .and_then(|p| p.with_categories(categories, self.repo.as_ref()))
+                    .map_err(|e| e.to_string())
+            });
+        match preview {
+            Ok(preview) => self.import_view.set_preview(preview),
+            Err(e) => self.error = Some(e),
+        }
+    }
+
+    /// Asks for a folder and adds or updates the items `scan` finds in it.
+    fn scan_folder(&mut self, scan: scan::Scanner) {
+        let Some(dir) = rfd::FileDialog::new().pick_folder() else {
+            return;
+        };
+        match scan(self.repo.as_ref(), &dir, &util::covers_dir()) {
+            Ok(report) => {
+                for error in &report.errors {
+                    log::warn!("{}", error);
+                }
+                self.error = Some(report.to_string());
+                self.refresh();
+            }
+            Err(e) => self.error = Some(format!("Scan failed: {}", e)),
+        }
+    }
+
+    fn apply_query(&mut self, query: Query) {
+        self.query = query;
+        self.search_error = search::parse(&self.query.search).err();
+        self.refresh();
+    }
+
+    fn item_editor(&mut self, ctx: &egui::Context) {
+        let Some(ItemDraft {
+            item: draft,
+            finished_on,
+            id_scheme,
+            id_value,
+            target_price,
+            store,
+            copies,
+            removed_copies,
+            loans,
+            removed_loans,
+        }) = self.editing.as_mut()
+        else {
+            return;
+        };
+        let mut open = true;
+        let mut save = false;
+        let mut cancel = false;
+        let mut enrich = false;
+        egui::Window::new("Edit item")
+            .id(egui::Id::new("item_editor"))
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                egui::Grid::new("item_editor_grid")
+                    .num_columns(2)
+                    .spacing([8.0, 6.0])
+                    .show(ui, |ui| {
+                        ui.label("Title:");
+                        ui.add(TextEdit::singleline(&mut draft.title).desired_width(240.0));
+                        ui.end_row();
+
+                        ui.label("Sort title:");
+                        let mut sort_title = draft.sort_title.clone().unwrap_or_default();
+                        if ui
+                            .add(
+                                TextEdit::singleline(&mut sort_title)
+                                    .hint_text("(same as title)")
+                                    .desired_width(240.0),
+                            )
+                            .changed()
+                        {
+                            draft.sort_title = if sort_title.trim().is_empty() {
+                                None
+                            } else {
+                                Some(sort_title)
+                            };
+                        }
+                        ui.end_row();
+
+                        ui.label("Category:");
+                        egui::ComboBox::from_id_source("item_editor_cat")
+                            .selected_text(draft.category.to_string())
+                            .show_ui(ui, |ui| {
+                                for c in Category::ALL {
+                                    ui.selectable_value(&mut draft.category, c, c.to_string());
+                                }
+                            });
+                        ui.end_row();
+
+                        ui.label("Format:");
+                        egui::ComboBox::from_id_source("item_editor_format")
+                            .selected_text(draft.format.map(|f| f.as_str()).unwrap_or("(not set)"))
+                            .show_ui(ui, |ui| {
+                                ui.selectable_value(&mut draft.format, None, "(not set)");
+                                for f in Format::ALL {
+                                    ui.selectable_value(&mut draft.format, Some(f), f.as_str());
+                                }
+                            });
+                        ui.end_row();
+
+                        ui.label("Status:");
+                        let mut status = draft.status;
+                        egui::ComboBox::from_id_source("item_editor_status")
+                            .selected_text(status.to_string())
+                            .show_ui(ui, |ui| {
+                                for s in Status::ALL {
+                                    ui.selectable_value(&mut status, s, s.to_string());
+                                }
+                            });
+                        if status != draft.status {
+                            draft.set_status(status);
+                            *finished_on = draft
+                                .finished_at
+                                .map(|t| t.format("%Y-%m-%d").to_string())
+                                .unwrap_or_default();
+                        }
+                        ui.end_row();
+
+                        if draft.status == Status::Wishlist {
+                            ui.label("Priority:");
+                            egui::ComboBox::from_id_source("item_editor_priority")
+                                .selected_text(draft.priority.map(|p| p.as_str()).unwrap_or(""))
+                                .show_ui(ui, |ui| {
+                                    ui.selectable_value(&mut draft.priority, None, "(not set)");
+                                    for p in Priority::ALL {
+                                        ui.selectable_value(
+                                            &mut draft.priority,
+                                            Some(p),
+                                            p.as_str(),
+                                        );
+                                    }
+                                });
+                            ui.end_row();
+
+                            ui.label("Target price:");
+                            ui.add(TextEdit::singleline(target_price).desired_width(60.0))
+                                .on_hover_text("Buy once it is this cheap");
+                            ui.end_row();
+
+                            ui.label("Store:");
+                            ui.add(
+                                TextEdit::singleline(store)
+                                    .hint_text("Store name or URL")
+                                    .desired_width(240.0),
+                            );
+                            ui.end_row();
+                        }
+
+                        ui.label("Progress:");
+                        ui.horizontal(|ui| {
+                            progress_value(ui, &mut draft.progress);
+                            ui.label("of");
+                            progress_value(ui, &mut draft.progress_total);
+                        })
+                        .response
+                        .on_hover_text("Episodes, chapters or pages; 0 means not recorded");
+                        ui.end_row();
+
+                        ui.label("File:");
+                        ui.horizontal(|ui| {
+                            match &draft.file_path {
+                                Some(path) => ui.small(path.as_str()),
+                                None => ui.small("(none)"),
+                            };
+                            if ui.small_button("Pick...").clicked() {
+                                if let Some(path) = rfd::FileDialog::new().pick_file() {
+                                    draft.file_path = Some(path.display().to_string());
+                                }
+                            }
+                            if let Some(path) = draft.file_path.clone() {
+                                if ui.small_button("Open").clicked() {
+                                    if let Err(e) = open_file(&path) {
+                                        self.error = Some(e);
+                                    }
+                                }
+                                if ui.small_button("Clear").clicked() {
+                                    draft.file_path = None;
+                                }
+                            }
+                        });
+                        ui.end_row();
+
+                        ui.label("IDs:");
+                        ui.vertical(|ui| {
+                            let mut remove = None;
+                            for (i, id) in draft.external_ids.iter().enumerate() {
+                                ui.horizontal(|ui| {
+                                    match id.url() {
+                                        Some(url) => ui.hyperlink_to(id.to_string(), url),
+                                        None => ui.label(id.to_string()),
+                                    };
+                                    if ui.small_button("✕").clicked() {
+                                        remove = Some(i);
+                                    }
+                                });
+                            }
+                            if let Some(i) = remove {
+                                draft.external_ids.remove(i);
+                            }
+                            ui.horizontal(|ui| {
+                                egui::ComboBox::from_id_source("item_editor_id_scheme")
+                                    .selected_text(id_scheme.as_str())
+                                    .show_ui(ui, |ui| {
+                                        for s in IdScheme::ALL {
+                                            ui.selectable_value(id_scheme, s, s.as_str());
+                                        }
+                                    });
+                                let response = ui.add(
+                                    TextEdit::singleline(id_value)
+                                        .hint_text("id or link")
+                                        .desired_width(120.0),
+                                );
+                                let enter = response.lost_focus()
+                                    && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                                if (ui.small_button("Add").clicked() || enter)
+                                    && !id_value.trim().is_empty()
+                                {
+                                    match ExternalId::new(*id_scheme, id_value) {
+                                        Ok(id) => {
+                                            draft.add_external_id(id);
+                                            id_value.clear();
+                                        }
+                                        Err(e) => self.error = Some(e.to_string()),
+                                    }
+                                }
+                            });
+                        });
+                        ui.end_row();
+
+                        if draft.status == Status::Finished {
+                            ui.label("Finished on:");
+                            ui.add(
+                                TextEdit::singleline(finished_on)
+                                    .hint_text("YYYY-MM-DD")
+                                    .desired_width(90.0),
+                            );
+                            ui.end_row();
+                        }
+                    });
+                ui.separator();
+                copies_ui(ui, draft, copies, removed_copies);
+                ui.separator();
+                loans_ui(ui, draft, copies, loans, removed_loans);
+                ui.separator();
+                ui.horizontal(|ui| {
+                    save = ui.button("Save").clicked();
+                    cancel = ui.button("Cancel").clicked();
+                    enrich = ui
+                        .button("Enrich...")
+                        .on_hover_text(
+                            "Fill in creators, year, genres and cover from a metadata source",
+                        )
+                        .clicked();
+                });
+            });
+        if enrich {
+            if let Err(e) = self.enrich_view.start(draft, self.repo.as_ref()) {
+                self.error = Some(e.to_string());
+            }
+        }
+
+        if save {
+            if draft.title.trim().is_empty() {
+                self.error = Some("Title cannot be empty".into());
+                return;
+            }
+            if draft.status == Status::Finished {
+                let unchanged = draft
+                    .finished_at
+                    .is_some_and(|t| t.format("%Y-%m-%d").to_string() == *finished_on);
+                if !unchanged {
+                    match util::parse_local_date(finished_on) {
+                        Some(t) => draft.finished_at = Some(t),
+                        None => {
+                            self.error =
+                                Some(format!("`{}` is not a date (YYYY-MM-DD)", finished_on));
+                            return;
+                        }
+                    }
+                }
+            }
+            if draft.status == Status::Wishlist {
+                match util::parse_price(target_price) {
+                    Ok(price) => draft.target_price = price,
+                    Err(e) => {
+                        self.error = Some(e);
+                        return;
+                    }
+                }
+                draft.store = Some(store.trim().to_string()).filter(|s| !s.is_empty());
+            }
+            let mut parsed = vec![];
+            for copy in copies.iter() {
+                match copy.parsed() {
+                    Ok(copy) => parsed.push(copy),
+                    Err(e) => {
+                        self.error = Some(e);
+                        return;
+                    }
+                }
+            }
+            let mut parsed_loans = vec![];
+            for loan in loans.iter() {
+                match loan.parsed() {
+                    Ok(mut loan) => {
+                        // The copy goes away with the removed ones below.
+                        if loan.copy_id.is_some_and(|id| removed_copies.contains(&id)) {
+                            loan.copy_id = None;
+                        }
+                        parsed_loans.push(loan)
+                    }
+                    Err(e) => {
+                        self.error = Some(e);
+                        return;
+                    }
+                }
+            }
+            draft.updated_at = Local::now();
+            if let Err(e) = self.repo.update(draft) {
+                // Keep the editor open so a clashing id can be fixed.
+                self.error = Some(e.to_string());
+                return;
+            }
+            let saved = removed_copies
+                .iter()
+                .try_for_each(|id| self.repo.delete_copy(*id))
+                .and_then(|()| {
+                    parsed
+                        .iter_mut()
+                        .try_for_each(|copy| self.repo.save_copy(copy).map(|_| ()))
+                })
+                .and_then(|()| {
+                    removed_loans
+                        .iter()
+                        .try_for_each(|id| self.repo.delete_loan(*id))
+                })
+                .and_then(|()| {
+                    parsed_loans
+                        .iter_mut()
+                        .try_for_each(|loan| self.repo.save_loan(loan).map(|_| ()))
+                });
+            if let Err(e) = saved {
+                self.error = Some(e.to_string());
+            }
+            self.editing = None;
+            self.refresh();
+        } else if cancel || !open {
+            self.editing = None;
+        }
+    }
+
+    fn smart_lists_ui(&mut self, ui: &mut egui::Ui) {
+        ui.heading("Smart lists");
+        let mut apply = None;
+        let mut delete = None;
+        for (saved, count) in &self.smart_lists {
+            let label = match count {
+                Some(n) => format!("{} ({})", saved.name, n),
+                None => format!("{} (invalid)", saved.name),
+            };
+            let response = ui.selectable_label(saved.query == self.query, label);
+            let response = if saved.query.search.is_empty() {
+                response
+            } else {
+                response.on_hover_text(&saved.query.search)
+            };
+            if response.clicked() {
+                apply = Some(saved.query.clone());
+            }
+            response.context_menu(|ui| {
+                if ui.button("Delete").clicked() {
+                    delete = saved.id;
+                    ui.close_menu();
+                }
+            });
+        }
+        if let Some(query) = apply {
+            self.apply_query(query);
+        }
+        if let Some(id) = delete {
+            if let Err(e) = self.repo.delete_saved_search(id) {
+                self.error = Some(e.to_string());
+            }
+            self.refresh();
+        }
+
+        ui.horizontal(|ui| {
+            ui.add(
+                TextEdit::singleline(&mut self.new_smart_list_name)
+                    .hint_text("Name")
+                    .desired_width(120.0),
+            );
+            if ui
+                .button("Save current")
+                .on_hover_text(
+                    "Save the current filters and sort (replaces a list with the same name)",
+                )
+                .clicked()
+            {
+                let name = self.new_smart_list_name.trim();
+                if name.is_empty() {
+                    self.error = Some("Smart list name cannot be empty".into());
+                } else if self.search_error.is_some() {
+                    self.error = Some("Fix the search before saving it".into());
+                } else {
+                    let mut saved = SavedSearch {
+                        id: None,
+                        name: name.to_string(),
+                        query: self.query.unpaged(),
+                    };
+                    if let Err(e) = self.repo.save_search(&mut saved) {
+                        self.error = Some(e.to_string());
+                    }
+                    self.new_smart_list_name.clear();
+                    self.refresh();
+                }
+            }
+        });
+        ui.horizontal(|ui| {
+            if ui.button("Export...").clicked() {
+                if let Some(path) = rfd::FileDialog::new()
+                    .add_filter("JSON", &["json"])
+                    .set_file_name("smart_lists.json")
+                    .save_file()
+                {
+                    let saved: Vec<SavedSearch> =
+                        self.smart_lists.iter().map(|(s, _)| s.clone()).collect();
+                    match util::export_saved_searches(&saved, &path) {
+                        Ok(()) => self.error = Some(format!("Exported: {}", path.display())),
+                        Err(e) => self.error = Some(format!("Export failed: {}", e)),
+                    }
+                }
+            }
+            if ui.button("Import...").clicked() {
+                if let Some(path) = rfd::FileDialog::new()
+                    .add_filter("JSON", &["json"])
+                    .pick_file()
+                {
+                    match util::import_saved_searches(&path) {
+                        Ok(imported) => {
+                            for mut saved in imported {
+                                if let Err(e) = self.repo.save_search(&mut saved) {
+                                    self.error = Some(e.to_string());
+                                }
+                            }
+                        }
+                        Err(e) => self.error = Some(format!("Import failed: {}", e)),
+                    }
+                    self.refresh();
+                }
+            }
+        });
     }
 }
 
 impl eframe::App for CatalogApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        if self.server_view.poll_changes(ctx) {
+            self.refresh();
+        }
+        if self.watch_view.poll_changes(ctx) {
+            self.refresh();
+        }
+        self.item_editor(ctx);
+        match self.editing.as_mut() {
+            Some(draft) => {
+                if let Err(e) = self
+                    .enrich_view
+                    .show(ctx, self.repo.as_ref(), &mut draft.item)
+                {
+                    self.error = Some(e.to_string());
+                }
+            }
+            None => self.enrich_view.open = false,
+        }
+        self.stats_view.show(ctx);
+        self.inventory_view.show(ctx);
+        match self.loans_view.show(ctx, self.repo.as_ref(), &self.fetched) {
+            Ok(true) => self.refresh(),
+            Ok(false) => {}
+            Err(e) => self.error = Some(e.to_string()),
+        }
+        if let Err(e) = self.goals_view.show(ctx, self.repo.as_ref()) {
+            self.error = Some(e.to_string());
+        }
+        if let Err(e) = self.server_view.show(ctx, &self.repo) {
+            self.error = Some(e.to_string());
+        }
+        if let Err(e) = self.watch_view.show(ctx, &self.repo) {
+            self.error = Some(e.to_string());
+        }
+        match self.duplicates_view.show(ctx, self.repo.as_ref()) {
+            Ok(true) => self.refresh(),
+            Ok(false) => {}
+            Err(e) => self.error = Some(e.to_string()),
+        }
+        match self
+            .picker_view
+            .show(ctx, self.repo.as_ref(), &self.fetched)
+        {
+            Ok(true) => self.refresh(),
+            Ok(false) => {}
+            Err(e) => self.error = Some(e.to_string()),
+        }
+        if let Err(e) = self
+            .export_view
+            .show(ctx, self.repo.as_ref(), &self.fetched)
+        {
+            self.error = Some(e.to_string());
+        }
+        match self.import_view.show(ctx, self.repo.as_ref()) {
+            Ok(true) => self.refresh(),
+            Ok(false) => {}
+            Err(e) => self.error = Some(e.to_string()),
+        }
+
         egui::TopBottomPanel::top("top").show(ctx, |ui| {
             ui.horizontal_wrapped(|ui| {
                 ui.heading("Media Catalog");
-                if ui.button("Export CSV (filtered)").clicked() {
-                    match util::export_csv(&self.items) {
-                        Ok(path) => self.error = Some(format!("Exported: {}", path.display())),
-                        Err(e) => self.error = Some(format!("Export failed: {}", e)),
+                ui.menu_button("Export (filtered)", |ui| {
+                    if ui.button("CSV, JSON, Excel...").clicked() {
+                        ui.close_menu();
+                        self.export_view.open = true;
+                        if let Err(e) = self.export_view.reload(self.repo.as_ref()) {
+                            self.error = Some(e.to_string());
+                        }
+                    }
+                    if ui.button("Static site...").clicked() {
+                        ui.close_menu();
+                        if let Some(dir) = rfd::FileDialog::new().pick_folder() {
+                            let exported = self
+                                .repo
+                                .list(&self.fetched.unpaged())
+                                .map_err(|e| e.into())
+                                .and_then(|items| site::export_site(&items, &dir));
+                            match exported {
+                                Ok(pages) => {
+                                    self.error = Some(format!(
+                                        "Exported {} pages to {}",
+                                        pages,
+                                        dir.display()
+                                    ))
+                                }
+                                Err(e) => self.error = Some(format!("Export failed: {}", e)),
+                            }
+                        }
+                    }
+                    if ui.button("Markdown...").clicked() {
+                        ui.close_menu();
+                        if let Some(path) = rfd::FileDialog::new()
+                            .add_filter("Markdown", &["md"])
+                            .set_file_name("catalog.md")
+                            .save_file()
+                        {
+                            let exported = self
+                                .repo
+                                .list(&self.fetched.unpaged())
+                                .map_err(|e| e.into())
+                                .and_then(|items| site::export_markdown(&items, &path));
+                            match exported {
+                                Ok(()) => {
+                                    self.error = Some(format!("Exported: {}", path.display()))
+                                }
+                                Err(e) => self.error = Some(format!("Export failed: {}", e)),
+                            }
+                        }
+                    }
+                });
+                ui.menu_button("Import", |ui| {
+                    if ui.button("Goodreads CSV...").clicked() {
+                        ui.close_menu();
+                        self.import_file("Goodreads", &["csv"], import::goodreads::read, &[]);
+                    }
+                    if ui.button("Letterboxd export...").clicked() {
+                        ui.close_menu();
+                        self.import_file(
+                            "Letterboxd",
+                            &["zip", "csv"],
+                            import::letterboxd::read,
+                            &[],
+                        );
+                    }
+                    if ui.button("IMDb ratings CSV...").clicked() {
+                        ui.close_menu();
+                        self.import_file("IMDb", &["csv"], import::imdb::read, &[]);
+                    }
+                    ui.separator();
+                    if ui.button("Scan music folder...").clicked() {
+                        ui.close_menu();
+                        self.scan_folder(scan::music::scan);
+                    }
+                    if ui.button("Scan e-book folder...").clicked() {
+                        ui.close_menu();
+                        self.scan_folder(scan::books::scan);
+                    }
+                    ui.separator();
+                    if ui.button("MyAnimeList XML...").clicked() {
+                        ui.close_menu();
+                        self.import_file(
+                            "MyAnimeList",
+                            &["xml", "gz"],
+                            import::anime::read_mal,
+                            &import::anime::CATEGORIES,
+                        );
+                    }
+                    if ui.button("AniList JSON...").clicked() {
+                        ui.close_menu();
+                        self.import_file(
+                            "AniList",
+                            &["json"],
+                            import::anime::read_anilist,
+                            &import::anime::CATEGORIES,
+                        );
+                    }
+                });
+                if ui.button("Statistics").clicked() {
+                    self.stats_view.open = !self.stats_view.open;
+                    if let Err(e) = self.stats_view.reload(self.repo.as_ref()) {
+                        self.error = Some(e.to_string());
+                    }
+                }
+                if ui.button("Inventory").clicked() {
+                    self.inventory_view.open = !self.inventory_view.open;
+                    if let Err(e) = self
+                        .inventory_view
+                        .reload(self.repo.as_ref(), &self.fetched)
+                    {
+                        self.error = Some(e.to_string());
+                    }
+                }
+                if ui.button("Wishlist").clicked() {
+                    self.wishlist_view.open = !self.wishlist_view.open;
+                    if let Err(e) = self.wishlist_view.reload(self.repo.as_ref()) {
+                        self.error = Some(e.to_string());
+                    }
+                }
+                if ui.button("Loans").clicked() {
+                    self.loans_view.open = !self.loans_view.open;
+                    if let Err(e) = self.loans_view.reload(self.repo.as_ref(), &self.fetched) {
+                        self.error = Some(e.to_string());
+                    }
+                }
+                if ui.button("What next?").clicked() {
+                    self.picker_view.open = true;
+                    if let Err(e) = self.picker_view.pick(self.repo.as_ref(), &self.fetched) {
+                        self.error = Some(e.to_string());
+                    }
+                }
+                if ui.button("Duplicates").clicked() {
+                    self.duplicates_view.open = !self.duplicates_view.open;
+                    if let Err(e) = self.duplicates_view.reload(self.repo.as_ref()) {
+                        self.error = Some(e.to_string());
                     }
                 }
                 ui.separator();
                 ui.label(
                     RichText::new(format!(
-                        "Total: {} | Finished: {} | Unfinished: {}",
-                        self.stats.total, self.stats.finished, self.stats.unfinished
+                        "Total: {} | Finished: {} | Unfinished: {} | Wishlist: {}",
+                        self.stats.total,
+                        self.stats.finished,
+                        self.stats.unfinished,
+                        self.stats.wishlist
                     ))
                     .small(),
                 );
+                self.goals_view.summary_ui(ui);
+                if ui.small_button("Goals").clicked() {
+                    self.goals_view.open = !self.goals_view.open;
+                }
+                if ui.small_button("Server").clicked() {
+                    self.server_view.open = !self.server_view.open;
+                }
+                if ui.small_button("Watch").clicked() {
+                    self.watch_view.open = !self.watch_view.open;
+                }
             });
         });
 
@@ -78,25 +1073,37 @@ impl eframe::App for CatalogApp {
             }
             ui.horizontal(|ui| {
                 ui.label("Add new:");
-                ui.add(
+                ui.toggle_value(&mut self.code_entry.on, "By code")
+                    .on_hover_text("Add physical items by barcode, typed or from a scanner");
+                if self.code_entry.on {
+                    category_combo(ui, &mut self.new_item_category)
+                        .on_hover_text("Used when the source does not say");
+                    match self
+                        .code_entry
+                        .show(ui, self.repo.as_ref(), self.new_item_category)
+                    {
+                        Ok(true) => {
+                            self.error = None;
+                            self.refresh();
+                        }
+                        Ok(false) => {}
+                        Err(e) => self.error = Some(e.to_string()),
+                    }
+                    return;
+                }
+                let title_response = ui.add(
                     TextEdit::singleline(&mut self.new_item_title)
                         .hint_text("Title")
                         .desired_width(200.0),
                 );
-                egui::ComboBox::from_label("Category")
-                    .selected_text(self.new_item_category.to_string())
-                    .show_ui(ui, |ui| {
-                        for c in Category::ALL {
-                            if ui
-                                .selectable_label(self.new_item_category == c, c.to_string())
-                                .clicked()
-                            {
-                                self.new_item_category = c;
-                            }
-                        }
-                    });
-                if ui.add(Button::new("+ Add")).clicked() || ui.input(|i| i.key_pressed(Key::Enter))
-                {
+                let mut check_similar = title_response.changed();
+                check_similar |= category_combo(ui, &mut self.new_item_category).changed();
+                if check_similar {
+                    self.check_similar_titles();
+                }
+                let submitted =
+                    title_response.lost_focus() && ui.input(|i| i.key_pressed(Key::Enter));
+                if ui.add(Button::new("+ Add")).clicked() || submitted {
                     let title = self.new_item_title.trim();
                     if title.is_empty() {
                         self.error = Some("Title cannot be empty".into());
@@ -106,9 +1113,20 @@ impl eframe::App for CatalogApp {
                             self.error = Some(e.to_string());
                         }
                         self.new_item_title.clear();
+                        self.similar_titles.clear();
                         self.refresh();
                     }
                 }
+                if !self.similar_titles.is_empty() {
+                    ui.colored_label(
+                        egui::Color32::YELLOW,
+                        format!(
+                            "⚠ Similar {} already in the catalog: {}",
+                            self.new_item_category,
+                            self.similar_titles.join(", ")
+                        ),
+                    );
+                }
             });
         });
 
@@ -118,8 +1136,49 @@ impl eframe::App for CatalogApp {
             .show(ctx, |ui| {
                 ui.heading("Filters");
                 ui.separator();
-                ui.label("Search title contains:");
-                ui.add(TextEdit::singleline(&mut self.query.title_substr).hint_text("e.g., Dune"));
+                ui.label("Search:").on_hover_text(SEARCH_HELP);
+                let search_response = ui.add(
+                    TextEdit::singleline(&mut self.query.search)
+                        .hint_text("e.g., cat:book rating>=7 Dune"),
+                );
+                let mut search_changed = search_response.changed();
+                let completions = search::completions(&self.query.search);
+                if !completions.is_empty() {
+                    ui.horizontal_wrapped(|ui| {
+                        for completion in completions.iter().take(8) {
+                            if ui.small_button(completion).clicked() {
+                                self.query.search =
+                                    search::apply_completion(&self.query.search, completion);
+                                search_response.request_focus();
+                                search_changed = true;
+                            }
+                        }
+                    });
+                }
+                if search_changed {
+                    self.search_error = search::parse(&self.query.search).err();
+                    if self.search_error.is_none() {
+                        self.refresh();
+                    }
+                }
+                if let Some(err) = &self.search_error {
+                    let text = &self.query.search;
+                    let start = err.span.start.min(text.len());
+                    let end = err.span.end.min(text.len());
+                    let pad = text[..start].chars().count();
+                    let width = text[start..end].chars().count().max(1);
+                    ui.colored_label(egui::Color32::LIGHT_RED, &err.message);
+                    ui.label(
+                        RichText::new(format!(
+                            "{}\n{}{}",
+                            text,
+                            " ".repeat(pad),
+                            "^".repeat(width)
+                        ))
+                        .monospace()
+                        .small(),
+                    );
+                }
                 ui.label("Category:");
                 egui::ComboBox::from_id_source("filter_cat")
                     .selected_text(
@@ -168,6 +1227,30 @@ impl eframe::App for CatalogApp {
                             }
                         }
                     });
+                ui.label("Owned:");
+                egui::ComboBox::from_id_source("filter_owned")
+                    .selected_text(match self.query.owned {
+                        None => "Any",
+                        Some(true) => "Owned",
+                        Some(false) => "Not owned",
+                    })
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut self.query.owned, None, "Any");
+                        ui.selectable_value(&mut self.query.owned, Some(true), "Owned");
+                        ui.selectable_value(&mut self.query.owned, Some(false), "Not owned");
+                    });
+                ui.label("On loan:");
+                egui::ComboBox::from_id_source("filter_on_loan")
+                    .selected_text(match self.query.on_loan {
+                        None => "Any",
+                        Some(true) => "On loan",
+                        Some(false) => "Not on loan",
+                    })
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut self.query.on_loan, None, "Any");
+                        ui.selectable_value(&mut self.query.on_loan, Some(true), "On loan");
+                        ui.selectable_value(&mut self.query.on_loan, Some(false), "Not on loan");
+                    });
                 ui.label("Min rating:");
                 let mut rating_str = self
                     .query
@@ -181,63 +1264,85 @@ impl eframe::App for CatalogApp {
                     self.query.min_rating = rating_str.parse::<u8>().ok();
                 }
                 ui.separator();
-                ui.label("Sort by:");
-                egui::ComboBox::from_id_source("sort_field")
-                    .selected_text(format!("{:?}", self.query.sort_field))
-                    .show_ui(ui, |ui| {
-                        for f in [
-                            SortField::Title,
-                            SortField::Category,
-                            SortField::Status,
-                            SortField::Rating,
-                            SortField::CreatedAt,
-                            SortField::UpdatedAt,
-                        ] {
-                            if ui
-                                .selectable_label(self.query.sort_field == f, format!("{:?}", f))
-                                .clicked()
-                            {
-                                self.query.sort_field = f;
-                            }
-                        }
-                    });
-                egui::ComboBox::from_id_source("sort_order")
-                    .selected_text(match self.query.sort_order {
-                        SortOrder::Asc => "Asc",
-                        SortOrder::Desc => "Desc",
-                    })
-                    .show_ui(ui, |ui| {
+                ui.label("Sort by:").on_hover_text(
+                    "Click a column header to sort by it; shift-click to add a secondary key.",
+                );
+                let mut sort_changed = false;
+                let mut remove_key = None;
+                let used: Vec<SortField> = self.query.sort.iter().map(|k| k.field).collect();
+                for (i, key) in self.query.sort.iter_mut().enumerate() {
+                    ui.horizontal(|ui| {
+                        ui.label(format!("{}.", i + 1));
+                        egui::ComboBox::from_id_source(("sort_field", i))
+                            .selected_text(key.field.as_str())
+                            .show_ui(ui, |ui| {
+                                // Fields sorted by another key are left out.
+                                let current = key.field;
+                                let free = SortField::ALL
+                                    .into_iter()
+                                    .filter(|f| *f == current || !used.contains(f));
+                                for f in free {
+                                    if ui.selectable_value(&mut key.field, f, f.as_str()).changed()
+                                    {
+                                        sort_changed = true;
+                                    }
+                                }
+                            });
                         if ui
-                            .selectable_label(
-                                matches!(self.query.sort_order, SortOrder::Asc),
-                                "Asc",
-                            )
+                            .small_button(key.order.arrow())
+                            .on_hover_text("Toggle direction")
                             .clicked()
                         {
-                            self.query.sort_order = SortOrder::Asc;
+                            key.order = key.order.toggled();
+                            sort_changed = true;
                         }
-                        if ui
-                            .selectable_label(
-                                matches!(self.query.sort_order, SortOrder::Desc),
-                                "Desc",
-                            )
-                            .clicked()
-                        {
-                            self.query.sort_order = SortOrder::Desc;
+                        if ui.small_button("✕").on_hover_text("Remove").clicked() {
+                            remove_key = Some(i);
                         }
                     });
+                }
+                if let Some(i) = remove_key {
+                    self.query.sort.remove(i);
+                    sort_changed = true;
+                }
+                let unused = SortField::ALL
+                    .into_iter()
+                    .find(|f| self.query.sort.iter().all(|k| k.field != *f));
+                if let Some(field) = unused {
+                    if ui.small_button("+ Add sort key").clicked() {
+                        self.query
+                            .sort
+                            .push(SortKey::new(field, field.default_order()));
+                        sort_changed = true;
+                    }
+                }
+                if ui
+                    .checkbox(&mut self.query.ignore_articles, "Ignore leading articles")
+                    .on_hover_text(
+                        "Sort \"The Matrix\" under M (also A, An, Der, Die, Das, Le, La, Les)",
+                    )
+                    .changed()
+                {
+                    sort_changed = true;
+                }
+                if sort_changed {
+                    self.refresh();
+                }
                 if ui.button("Apply").clicked() {
                     self.refresh();
                 }
                 if ui.button("Clear filters").clicked() {
                     self.query = Query {
-                        sort_field: self.query.sort_field,
-                        sort_order: self.query.sort_order,
+                        sort: std::mem::take(&mut self.query.sort),
                         ..Default::default()
                     };
+                    self.search_error = None;
                     self.refresh();
                 }
 
+                ui.separator();
+                self.smart_lists_ui(ui);
+
                 ui.separator();
                 ui.heading("By category");
                 for (cat, count) in &self.stats.by_category {
@@ -245,11 +1350,18 @@ impl eframe::App for CatalogApp {
                 }
             });
 
+        match self.wishlist_view.show(ctx, self.repo.as_ref()) {
+            Ok(true) => self.refresh(),
+            Ok(false) => {}
+            Err(e) => self.error = Some(e.to_string()),
+        }
+
         egui::CentralPanel::default().show(ctx, |ui| {
-            ui.heading("Items");
+            ui.heading(format!("Items ({})", self.items.total));
             ui.add_space(6.0);
 
             let mut need_refresh = false;
+            let mut sort_click = None;
 
             TableBuilder::new(ui)
                 .striped(true)
@@ -266,18 +1378,18 @@ impl eframe::App for CatalogApp {
                     header.col(|ui| {
                         ui.strong("Actions");
                     });
-                    header.col(|ui| {
-                        ui.strong("Title");
-                    });
-                    header.col(|ui| {
-                        ui.strong("Category");
-                    });
-                    header.col(|ui| {
-                        ui.strong("Status");
-                    });
-                    header.col(|ui| {
-                        ui.strong("Rating");
-                    });
+                    for field in [
+                        SortField::Title,
+                        SortField::Category,
+                        SortField::Status,
+                        SortField::Rating,
+                    ] {
+                        header.col(|ui| {
+                            if let Some(additive) = sortable_header(ui, &self.query, field) {
+                                sort_click = Some((field, additive));
+                            }
+                        });
+                    }
                     header.col(|ui| {
                         ui.strong("Notes");
                     });
@@ -285,177 +1397,464 @@ impl eframe::App for CatalogApp {
                         ui.strong("Cover");
                     });
                     header.col(|ui| {
-                        ui.strong("Updated");
+                        if let Some(additive) =
+                            sortable_header(ui, &self.query, SortField::UpdatedAt)
+                        {
+                            sort_click = Some((SortField::UpdatedAt, additive));
+                        }
                     });
                 })
-                .body(|mut body| {
-                    for item in &mut self.items {
-                        body.row(24.0, |mut row| {
-                            row.col(|ui| {
-                                if ui
-                                    .small_button("✓")
-                                    .on_hover_text("Mark finished")
+                .body(|body| {
+                    body.rows(ROW_HEIGHT, self.items.total, |mut row| {
+                        let item =
+                            match self
+                                .items
+                                .get_mut(self.repo.as_ref(), &self.fetched, row.index())
+                            {
+                                Ok(Some(item)) => item,
+                                Ok(None) => return,
+                                Err(e) => {
+                                    self.error = Some(e.to_string());
+                                    return;
+                                }
+                            };
+
+                        row.col(|ui| {
+                            if ui
+                                .small_button("✓")
+                                .on_hover_text("Mark finished")
+                                .clicked()
+                            {
+                                item.mark_finished();
+                                if let Err(e) = self.repo.update(item) {
+                                    self.error = Some(e.to_string());
+                                }
+                                need_refresh = true;
+                            }
+
+                            if ui.small_button("✎").on_hover_text("Edit").clicked() {
+                                let copies = match item.id.map(|id| self.repo.list_copies(id)) {
+                                    Some(Ok(copies)) => copies,
+                                    Some(Err(e)) => {
+                                        self.error = Some(e.to_string());
+                                        vec![]
+                                    }
+                                    None => vec![],
+                                };
+                                let loans = match item.id.map(|id| self.repo.list_loans(id)) {
+                                    Some(Ok(loans)) => loans,
+                                    Some(Err(e)) => {
+                                        self.error = Some(e.to_string());
+                                        vec![]
+                                    }
+                                    None => vec![],
+                                };
+                                self.editing = Some(ItemDraft::new(item, copies, loans));
+                            }
+
+                            if ui.small_button("🗑").on_hover_text("Delete").clicked() {
+                                if let Some(id) = item.id {
+                                    if let Err(e) = self.repo.delete(id) {
+                                        self.error = Some(e.to_string());
+                                    }
+                                }
+                                need_refresh = true;
+                            }
+                        });
+
+                        row.col(|ui| {
+                            ui.label(&item.title);
+                            if let Some(path) = &item.file_path {
+                                let exists = *self
+                                    .file_exists
+                                    .entry(path.clone())
+                                    .or_insert_with(|| Path::new(path).exists());
+                                if !exists {
+                                    ui.label(
+                                        RichText::new("⚠ file missing")
+                                            .small()
+                                            .color(ui.visuals().warn_fg_color),
+                                    )
+                                    .on_hover_text(path);
+                                } else if ui
+                                    .small_button("📂")
+                                    .on_hover_text(format!("Open {}", path))
                                     .clicked()
                                 {
-                                    item.status = Status::Finished;
-                                    item.updated_at = Local::now();
-                                    if let Err(e) = self.repo.update(item) {
-                                        self.error = Some(e.to_string());
+                                    if let Err(e) = open_file(path) {
+                                        self.error = Some(e);
                                     }
-                                    need_refresh = true;
                                 }
+                            }
+                            if let Some(loans) = item.id.and_then(|id| self.open_loans.get(&id)) {
+                                loan_badge(ui, loans);
+                            }
+                        });
+
+                        row.col(|ui| {
+                            ui.label(item.category.to_string());
+                        });
 
-                                let edit_id =
-                                    Id::new(format!("edit_{}", item.id.unwrap_or_default()));
-                                let edit_response: Response =
-                                    ui.small_button("✎").on_hover_text("Edit");
-                                if edit_response.clicked() {
-                                    ui.ctx().memory_mut(|m| m.toggle_popup(edit_id));
+                        row.col(|ui| {
+                            match item.progress_text() {
+                                Some(progress) => {
+                                    ui.label(format!("{} · {}", item.status, progress))
                                 }
+                                None => ui.label(item.status.to_string()),
+                            };
+                        });
 
-                                if ui.small_button("🗑").on_hover_text("Delete").clicked() {
-                                    if let Some(id) = item.id {
-                                        if let Err(e) = self.repo.delete(id) {
-                                            self.error = Some(e.to_string());
-                                        }
-                                    }
-                                    need_refresh = true;
+                        row.col(|ui| {
+                            let mut tmp = item.rating.unwrap_or(0).to_string();
+                            if ui
+                                .add(TextEdit::singleline(&mut tmp).desired_width(30.0))
+                                .lost_focus()
+                            {
+                                item.set_rating(tmp.parse::<u8>().ok());
+                                if let Err(e) = self.repo.update(item) {
+                                    self.error = Some(e.to_string());
                                 }
+                            }
+                        });
 
-                                egui::popup::popup_below_widget(
-                                    ui,
-                                    edit_id,
-                                    &edit_response,
-                                    |ui| {
-                                        ui.label(RichText::new("Edit item").strong());
-                                        ui.separator();
-                                        let mut title = item.title.clone();
-                                        ui.label("Title:");
-                                        ui.add(
-                                            TextEdit::singleline(&mut title).desired_width(240.0),
-                                        );
-                                        ui.label("Category:");
-                                        let mut cat = item.category;
-                                        egui::ComboBox::from_id_source(edit_id.with("cat"))
-                                            .selected_text(cat.to_string())
-                                            .show_ui(ui, |ui| {
-                                                for c in Category::ALL {
-                                                    if ui
-                                                        .selectable_label(cat == c, c.to_string())
-                                                        .clicked()
-                                                    {
-                                                        cat = c;
-                                                    }
-                                                }
-                                            });
-                                        ui.label("Status:");
-                                        let mut st = item.status;
-                                        egui::ComboBox::from_id_source(edit_id.with("status"))
-                                            .selected_text(st.to_string())
-                                            .show_ui(ui, |ui| {
-                                                for s in Status::ALL {
-                                                    if ui
-                                                        .selectable_label(st == s, s.to_string())
-                                                        .clicked()
-                                                    {
-                                                        st = s;
-                                                    }
-                                                }
-                                            });
-                                        if ui.button("Save").clicked() {
-                                            item.title = title;
-                                            item.category = cat;
-                                            item.status = st;
+                        row.col(|ui| {
+                            let mut text = item.notes.clone().unwrap_or_default();
+                            if ui
+                                .add(TextEdit::singleline(&mut text).desired_width(200.0))
+                                .lost_focus()
+                            {
+                                item.notes = if text.trim().is_empty() {
+                                    None
+                                } else {
+                                    Some(text)
+                                };
+                                item.updated_at = Local::now();
+                                if let Err(e) = self.repo.update(item) {
+                                    self.error = Some(e.to_string());
+                                }
+                            }
+                        });
+
+                        row.col(|ui| {
+                            let cover_display = item.cover_path.clone().unwrap_or_default();
+                            if ui.small_button("Pick...").clicked() {
+                                if let Some(path) = rfd::FileDialog::new()
+                                    .add_filter("Images", &["png", "jpg", "jpeg"])
+                                    .pick_file()
+                                {
+                                    // Copied into the covers folder, the only
+                                    // place the web view serves images from.
+                                    let media_type = match path.extension().and_then(|e| e.to_str())
+                                    {
+                                        Some(e) if e.eq_ignore_ascii_case("png") => "image/png",
+                                        _ => "image/jpeg",
+                                    };
+                                    let saved = std::fs::read(&path).and_then(|data| {
+                                        scan::save_cover(&util::covers_dir(), &data, media_type)
+                                    });
+                                    match saved {
+                                        Ok(cover) => {
+                                            item.cover_path = Some(cover);
                                             item.updated_at = Local::now();
                                             if let Err(e) = self.repo.update(item) {
                                                 self.error = Some(e.to_string());
                                             }
-                                            ui.ctx().memory_mut(|m| m.close_popup());
                                             need_refresh = true;
                                         }
-                                        if ui.button("Cancel").clicked() {
-                                            ui.ctx().memory_mut(|m| m.close_popup());
+                                        Err(e) => {
+                                            self.error =
+                                                Some(format!("Cannot copy the cover: {}", e))
                                         }
-                                    },
-                                );
-                            });
-
-                            row.col(|ui| {
-                                ui.label(&item.title);
-                            });
-
-                            row.col(|ui| {
-                                ui.label(item.category.to_string());
-                            });
-
-                            row.col(|ui| {
-                                ui.label(item.status.to_string());
-                            });
-
-                            row.col(|ui| {
-                                let mut tmp = item.rating.unwrap_or(0).to_string();
-                                if ui
-                                    .add(TextEdit::singleline(&mut tmp).desired_width(30.0))
-                                    .lost_focus()
-                                {
-                                    item.rating = tmp.parse::<u8>().ok();
-                                    item.updated_at = Local::now();
-                                    if let Err(e) = self.repo.update(item) {
-                                        self.error = Some(e.to_string());
                                     }
                                 }
-                            });
+                            }
+                            if cover_display.is_empty() {
+                                ui.small("(none)");
+                            } else {
+                                ui.small(cover_display);
+                            }
+                        });
 
-                            row.col(|ui| {
-                                let mut text = item.notes.clone().unwrap_or_default();
-                                if ui
-                                    .add(TextEdit::singleline(&mut text).desired_width(200.0))
-                                    .lost_focus()
-                                {
-                                    item.notes = if text.trim().is_empty() {
-                                        None
-                                    } else {
-                                        Some(text)
-                                    };
-                                    item.updated_at = Local::now();
-                                    if let Err(e) = self.repo.update(item) {
-                                        self.error = Some(e.to_string());
-                                    }
-                                }
-                            });
+                        row.col(|ui| {
+                            ui.small(item.updated_at.format("%Y-%m-%d %H:%M").to_string());
+                        });
+                    });
+                });
 
-                            row.col(|ui| {
-                                let cover_display = item.cover_path.clone().unwrap_or_default();
-                                if ui.small_button("Pick...").clicked() {
-                                    if let Some(path) = rfd::FileDialog::new()
-                                        .add_filter("Images", &["png", "jpg", "jpeg"])
-                                        .pick_file()
-                                    {
-                                        item.cover_path = Some(path.display().to_string());
-                                        item.updated_at = Local::now();
-                                        if let Err(e) = self.repo.update(item) {
-                                            self.error = Some(e.to_string());
-                                        }
-                                        need_refresh = true;
+            if let Some((field, additive)) = sort_click {
+                self.query.toggle_sort(field, additive);
+                need_refresh = true;
+            }
+            if need_refresh {
+                self.refresh();
+            }
+        });
+    }
+}
+
+/// Opens a linked file or folder with the system's default application.
+fn open_file(path: &str) -> Result<(), String> {
+    if !Path::new(path).exists() {
+        return Err(format!("File not found: {}", path));
+    }
+    open::that_detached(path).map_err(|e| format!("Cannot open {}: {}", path, e))
+}
+
+// Copies of the item being edited: one row each, and a button to add one.
+fn copies_ui(
+    ui: &mut egui::Ui,
+    item: &MediaItem,
+    copies: &mut Vec<CopyDraft>,
+    removed: &mut Vec<i64>,
+) {
+    ui.label("Copies:");
+    let mut remove = None;
+    if !copies.is_empty() {
+        egui::Grid::new("item_editor_copies")
+            .num_columns(6)
+            .spacing([6.0, 4.0])
+            .show(ui, |ui| {
+                for heading in ["Format", "Location", "Condition", "Purchased", "Price"] {
+                    ui.weak(heading);
+                }
+                ui.end_row();
+                for (i, draft) in copies.iter_mut().enumerate() {
+                    let copy = &mut draft.copy;
+                    ui.horizontal(|ui| {
+                        ui.add(TextEdit::singleline(&mut copy.format).desired_width(80.0));
+                        let formats = item.category.copy_formats();
+                        if !formats.is_empty() {
+                            ui.menu_button("▾", |ui| {
+                                for f in formats {
+                                    if ui.button(*f).clicked() {
+                                        copy.format = f.to_string();
+                                        ui.close_menu();
                                     }
                                 }
-                                if cover_display.is_empty() {
-                                    ui.small("(none)");
-                                } else {
-                                    ui.small(cover_display);
-                                }
                             });
+                        }
+                    });
+                    ui.add(
+                        TextEdit::singleline(&mut copy.location)
+                            .hint_text("Shelf, box, platform")
+                            .desired_width(100.0),
+                    );
+                    egui::ComboBox::from_id_source(("copy_condition", i))
+                        .width(80.0)
+                        .selected_text(copy.condition.map(|c| c.as_str()).unwrap_or(""))
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut copy.condition, None, "(not set)");
+                            for c in Condition::ALL {
+                                ui.selectable_value(&mut copy.condition, Some(c), c.as_str());
+                            }
+                        });
+                    ui.add(
+                        TextEdit::singleline(&mut draft.purchased_on)
+                            .hint_text("YYYY-MM-DD")
+                            .desired_width(80.0),
+                    );
+                    ui.add(TextEdit::singleline(&mut draft.price).desired_width(60.0));
+                    if ui.small_button("✕").on_hover_text("Remove copy").clicked() {
+                        remove = Some(i);
+                    }
+                    ui.end_row();
+                }
+            });
+    }
+    if let Some(i) = remove {
+        removed.extend(copies.remove(i).copy.id);
+    }
+    if let Some(id) = item.id {
+        if ui.small_button("+ Add copy").clicked() {
+            copies.push(CopyDraft::new(OwnedCopy::new(id)));
+        }
+    }
+}
 
-                            row.col(|ui| {
-                                ui.small(item.updated_at.format("%Y-%m-%d %H:%M").to_string());
-                            });
+// Loans of the item being edited, lent out as a whole or one copy at a time.
+fn loans_ui(
+    ui: &mut egui::Ui,
+    item: &MediaItem,
+    copies: &[CopyDraft],
+    loans: &mut Vec<LoanDraft>,
+    removed: &mut Vec<i64>,
+) {
+    ui.label("Loans:");
+    let today = Local::now().date_naive();
+    // Only saved copies can be lent.
+    let copy_label = |id: Option<i64>| match id {
+        None => "Whole item".to_string(),
+        Some(id) => copies
+            .iter()
+            .find(|c| c.copy.id == Some(id))
+            .map(|c| {
+                [c.copy.format.as_str(), c.copy.location.as_str()]
+                    .into_iter()
+                    .filter(|s| !s.is_empty())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            })
+            .filter(|s| !s.is_empty())
+            .unwrap_or_else(|| format!("Copy {}", id)),
+    };
+    let mut remove = None;
+    if !loans.is_empty() {
+        egui::Grid::new("item_editor_loans")
+            .num_columns(6)
+            .spacing([6.0, 4.0])
+            .show(ui, |ui| {
+                for heading in ["Borrower", "Copy", "Lent", "Due", "Returned"] {
+                    ui.weak(heading);
+                }
+                ui.end_row();
+                for (i, draft) in loans.iter_mut().enumerate() {
+                    let loan = &mut draft.loan;
+                    ui.add(TextEdit::singleline(&mut loan.borrower).desired_width(100.0));
+                    egui::ComboBox::from_id_source(("loan_copy", i))
+                        .width(100.0)
+                        .selected_text(copy_label(loan.copy_id))
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut loan.copy_id, None, copy_label(None));
+                            for id in copies.iter().filter_map(|c| c.copy.id) {
+                                ui.selectable_value(
+                                    &mut loan.copy_id,
+                                    Some(id),
+                                    copy_label(Some(id)),
+                                );
+                            }
                         });
+                    for date in [
+                        &mut draft.lent_on,
+                        &mut draft.due_on,
+                        &mut draft.returned_on,
+                    ] {
+                        ui.add(
+                            TextEdit::singleline(date)
+                                .hint_text("YYYY-MM-DD")
+                                .desired_width(80.0),
+                        );
                     }
-                });
+                    ui.horizontal(|ui| {
+                        if draft.returned_on.trim().is_empty() {
+                            if ui
+                                .small_button("↩")
+                                .on_hover_text("Returned today")
+                                .clicked()
+                            {
+                                draft.returned_on = today.to_string();
+                            }
+                            let overdue = parse_date(&draft.due_on)
+                                .ok()
+                                .flatten()
+                                .is_some_and(|due| due < today);
+                            if overdue {
+                                ui.label(
+                                    RichText::new("overdue")
+                                        .small()
+                                        .color(ui.visuals().error_fg_color),
+                                );
+                            }
+                        }
+                        if ui.small_button("✕").on_hover_text("Remove loan").clicked() {
+                            remove = Some(i);
+                        }
+                    });
+                    ui.end_row();
+                }
+            });
+    }
+    if let Some(i) = remove {
+        removed.extend(loans.remove(i).loan.id);
+    }
+    if let Some(id) = item.id {
+        if ui.small_button("+ Lend").clicked() {
+            loans.push(LoanDraft::new(Loan::new(id)));
+        }
+    }
+}
 
-            if need_refresh {
-                self.refresh();
+// "On loan" badge of a table row, red once a loan is overdue.
+fn loan_badge(ui: &mut egui::Ui, loans: &[Loan]) {
+    let today = Local::now().date_naive();
+    let overdue = loans.iter().any(|l| l.is_overdue(today));
+    let mut text = format!("📤 {}", loans[0].borrower);
+    if loans.len() > 1 {
+        text.push_str(&format!(" +{}", loans.len() - 1));
+    }
+    let color = if overdue {
+        ui.visuals().error_fg_color
+    } else {
+        ui.visuals().weak_text_color()
+    };
+    let hover = loans
+        .iter()
+        .map(|l| {
+            let mut line = format!("Lent to {} on {}", l.borrower, l.lent_on);
+            if let Some(due) = l.due_on {
+                line.push_str(&format!(", due {}", due));
             }
-        });
+            if l.is_overdue(today) {
+                line.push_str(" (overdue)");
+            }
+            line
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    ui.label(RichText::new(text).small().color(color))
+        .on_hover_text(hover);
+}
+
+// An optional YYYY-MM-DD date typed in the editor.
+fn parse_date(text: &str) -> Result<Option<NaiveDate>, String> {
+    match text.trim() {
+        "" => Ok(None),
+        date => NaiveDate::parse_from_str(date, "%Y-%m-%d")
+            .map(Some)
+            .map_err(|_| format!("`{}` is not a date (YYYY-MM-DD)", date)),
+    }
+}
+
+// Drag value for an optional count, where 0 stands for "not recorded".
+fn progress_value(ui: &mut egui::Ui, value: &mut Option<u32>) {
+    let mut n = value.unwrap_or(0);
+    if ui.add(egui::DragValue::new(&mut n)).changed() {
+        *value = (n > 0).then_some(n);
+    }
+}
+
+// Category picker of the add bar; the response reports a new choice as changed.
+fn category_combo(ui: &mut egui::Ui, category: &mut Category) -> egui::Response {
+    let mut changed = false;
+    let mut response = egui::ComboBox::from_label("Category")
+        .selected_text(category.to_string())
+        .show_ui(ui, |ui| {
+            for c in Category::ALL {
+                if ui.selectable_label(*category == c, c.to_string()).clicked() {
+                    *category = c;
+                    changed = true;
+                }
+            }
+        })
+        .response;
+    if changed {
+        response.mark_changed();
+    }
+    response
+}
+
+/// Column header that sorts by `field` when clicked. Returns `Some(additive)`
+/// on click, where `additive` is true if shift was held.
+fn sortable_header(ui: &mut egui::Ui, query: &Query, field: SortField) -> Option<bool> {
+    let mut text = field.as_str().to_string();
+    if let Some(i) = query.sort.iter().position(|k| k.field == field) {
+        text.push(' ');
+        text.push_str(query.sort[i].order.arrow());
+        if query.sort.len() > 1 {
+            text.push_str(&(i + 1).to_string());
+        }
     }
+    let response = ui
+        .add(egui::Label::new(RichText::new(text).strong()).sense(egui::Sense::click()))
+        .on_hover_text("Click to sort, shift-click to add a secondary sort key");
+    response.clicked().then(|| ui.input(|i| i.modifiers.shift))
 }
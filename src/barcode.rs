@@ -0,0 +1,231 @@
+use crate::enrich_view::DUMP_SETTING;
+use crate::ids::ExternalId;
+use crate::metadata::codes::CodeList;
+use crate::metadata::local::LocalDump;
+use crate::metadata::{Field, MetadataProvider};
+use crate::models::{Category, Format, MediaItem};
+use crate::repo::{RepoError, RepoResult, Repository};
+use eframe::egui::{self, Key, TextEdit};
+use std::path::Path;
+
+const CODES_SETTING: &str = "barcode.codes";
+
+/// "Add by code" mode of the add bar: a barcode typed or sent by a scanner
+/// (which types it and presses Enter) is looked up and added as a physical
+/// item straight away, so a shelf can be scanned one code after another.
+#[derive(Default)]
+pub struct CodeEntry {
+    pub on: bool,
+    code: String,
+    /// Code no source knew, waiting for a title.
+    unknown: Option<ExternalId>,
+    title: String,
+    /// What the last code did.
+    message: String,
+    focus: bool,
+    /// Sources with the file they were read from, in lookup order.
+    providers: Vec<(String, Box<dyn MetadataProvider>)>,
+}
+
+impl CodeEntry {
+    /// Shows the code field in the add bar; returns whether an item was added.
+    pub fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        repo: &dyn Repository,
+        category: Category,
+    ) -> RepoResult<bool> {
+        let mut added = false;
+        let response = ui.add(
+            TextEdit::singleline(&mut self.code)
+                .hint_text("Scan or type EAN, UPC or ISBN")
+                .desired_width(200.0),
+        );
+        if std::mem::take(&mut self.focus) {
+            response.request_focus();
+        }
+        if response.lost_focus() && ui.input(|i| i.key_pressed(Key::Enter)) {
+            let code = std::mem::take(&mut self.code);
+            self.focus = true;
+            if !code.trim().is_empty() {
+                added = self.submit(&code, repo, category)?;
+            }
+        }
+        if ui
+            .small_button("Codes...")
+            .on_hover_text("CSV file mapping codes to titles: code,title,category,creators,year")
+            .clicked()
+        {
+            if let Some(path) = rfd::FileDialog::new()
+                .add_filter("CSV", &["csv"])
+                .pick_file()
+            {
+                CodeList::open(&path)?;
+                repo.set_setting(CODES_SETTING, &path.to_string_lossy())?;
+            }
+        }
+        if let Some(id) = self.unknown.clone() {
+            ui.label(format!("Title for {}:", id));
+            let response = ui.add(TextEdit::singleline(&mut self.title).desired_width(160.0));
+            if response.lost_focus() && ui.input(|i| i.key_pressed(Key::Enter)) {
+                let title = std::mem::take(&mut self.title);
+                if !title.trim().is_empty() {
+                    let mut item = MediaItem::new(title.trim(), category);
+                    item.add_external_id(id);
+                    item.format = Some(Format::Physical);
+                    repo.add(&mut item)?;
+                    self.message = format!("Added “{}”", item.title);
+                    self.unknown = None;
+                    self.focus = true;
+                    added = true;
+                }
+            }
+        }
+        if !self.message.is_empty() {
+            ui.weak(self.message.as_str());
+        }
+        Ok(added)
+    }
+
+    // Looks `code` up and adds the item if a source knows it; otherwise asks
+    // for a title.
+    fn submit(
+        &mut self,
+        code: &str,
+        repo: &dyn Repository,
+        category: Category,
+    ) -> RepoResult<bool> {
+        self.unknown = None;
+        self.message.clear();
+        let id = ExternalId::from_barcode(code)?;
+        if let Some(existing) = repo.find_by_external_id(&id)? {
+            return Err(RepoError::Other(format!(
+                "{} is already in the catalog as “{}”",
+                id, existing.title
+            )));
+        }
+        self.load_providers(repo)?;
+        for (_, provider) in &self.providers {
+            let Some(m) = provider.fetch(&id)? else {
+                continue;
+            };
+            let mut item = MediaItem::new(m.title.as_str(), m.category.unwrap_or(category));
+            m.apply(
+                &mut item,
+                &[
+                    Field::Byline,
+                    Field::Genres,
+                    Field::Cover,
+                    Field::ExternalIds,
+                ],
+            );
+            item.add_external_id(id);
+            item.format = Some(Format::Physical);
+            repo.add(&mut item)?;
+            self.message = format!(
+                "Added “{}” ({}) from {}",
+                item.title,
+                item.category,
+                provider.name()
+            );
+            return Ok(true);
+        }
+        self.message = format!("No source knows {}", id);
+        self.unknown = Some(id);
+        Ok(false)
+    }
+
+    // (Re)loads the code list and dump named in the settings when they changed.
+    fn load_providers(&mut self, repo: &dyn Repository) -> RepoResult<()> {
+        let mut paths = vec![];
+        for setting in [CODES_SETTING, DUMP_SETTING] {
+            if let Some(path) = repo.get_setting(setting)? {
+                paths.push((setting, path));
+            }
+        }
+        let current: Vec<&str> = self.providers.iter().map(|(p, _)| p.as_str()).collect();
+        let wanted: Vec<&str> = paths.iter().map(|(_, p)| p.as_str()).collect();
+        if current == wanted {
+            return Ok(());
+        }
+        self.providers.clear();
+        for (setting, path) in paths {
+            let provider: Box<dyn MetadataProvider> = if setting == CODES_SETTING {
+                Box::new(CodeList::open(Path::new(&path))?)
+            } else {
+                Box::new(LocalDump::open(Path::new(&path))?)
+            };
+            self.providers.push((path, provider));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Query;
+    use crate::sqlite_repo::SqliteRepo;
+
+    #[test]
+    fn tells_isbns_from_product_codes() {
+        let code = |c: &str| ExternalId::from_barcode(c).map(|id| id.to_string());
+        assert_eq!(code("9780441172719").unwrap(), "isbn:9780441172719");
+        assert_eq!(code("0441172717").unwrap(), "isbn:9780441172719");
+        assert_eq!(code("0 12345 67890 5").unwrap(), "ean:0012345678905");
+        assert_eq!(code("4006381333931").unwrap(), "ean:4006381333931");
+        assert!(code("4006381333932").is_err());
+        assert!(code("hello").is_err());
+    }
+
+    #[test]
+    fn adds_known_codes_and_asks_for_unknown_ones() {
+        let repo = SqliteRepo::new(Path::new(":memory:"));
+        repo.init().unwrap();
+        let path = std::env::temp_dir().join(format!("codes-{}.csv", std::process::id()));
+        std::fs::write(
+            &path,
+            "code,title,category,creators,year\n\
+             9780441172719, Dune ,book,Frank Herbert,1965\n\
+             012345678905,Kind of Blue,music,Miles Davis;John Coltrane\n\
+             123,Bad code\n",
+        )
+        .unwrap();
+        repo.set_setting(CODES_SETTING, &path.to_string_lossy())
+            .unwrap();
+        let mut entry = CodeEntry::default();
+
+        assert!(entry.submit("0441172717", &repo, Category::Other).unwrap());
+        assert!(entry
+            .submit("0-12345-67890-5", &repo, Category::Other)
+            .unwrap());
+        assert!(entry
+            .message
+            .starts_with("Added “Kind of Blue” (Music) from codes-"));
+        let items = repo.list(&Query::default()).unwrap();
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].title, "Dune");
+        assert_eq!(items[0].category, Category::Book);
+        assert_eq!(items[0].format, Some(Format::Physical));
+        assert_eq!(items[0].notes.as_deref(), Some("by Frank Herbert · 1965"));
+        assert_eq!(
+            items[1].notes.as_deref(),
+            Some("by Miles Davis, John Coltrane")
+        );
+
+        let again = entry.submit("9780441172719", &repo, Category::Book);
+        assert!(again
+            .unwrap_err()
+            .to_string()
+            .contains("already in the catalog as “Dune”"));
+        assert!(!entry
+            .submit("4006381333931", &repo, Category::Game)
+            .unwrap());
+        assert_eq!(
+            entry.unknown.as_ref().unwrap().to_string(),
+            "ean:4006381333931"
+        );
+        assert!(entry.submit("12", &repo, Category::Game).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,186 @@
+use crate::api::ApiSettings;
+use crate::models::{MediaItem, Query, SavedSearch};
+use crate::recommend;
+use crate::repo::Repository;
+use crate::scan;
+use crate::server::ApiServer;
+use crate::util;
+use std::error::Error;
+use std::path::Path;
+use std::sync::Arc;
+
+const USAGE: &str = "\
+Usage: rust-media-catalog [COMMAND]
+
+Without a command the GUI is started.
+
+Commands:
+  list [--saved NAME] [--limit N] [SEARCH...]   Print matching items
+  next [--saved NAME] [--count N] [SEARCH...]   Suggest what to start next (Planned items
+                                                unless the search filters by status)
+  searches list                                 Print saved searches
+  searches export FILE                          Write saved searches to a JSON file
+  searches import FILE                          Add/replace saved searches from a JSON file
+  scan music DIR                                Add or update albums from the audio files in DIR
+  scan books DIR                                Add or update books from the EPUB/PDF files in DIR
+  serve [--port N]                              Serve the web view and JSON API on localhost
+  help                                          Show this message";
+
+const COMMANDS: [&str; 8] = [
+    "list", "next", "searches", "scan", "serve", "help", "--help", "-h",
+];
+
+/// Whether `arg` names a command; other arguments, such as flags added by a
+/// desktop launcher, start the GUI as usual.
+pub fn is_command(arg: &str) -> bool {
+    COMMANDS.contains(&arg)
+}
+
+pub fn run(args: &[String], repo: Arc<dyn Repository>) -> Result<(), Box<dyn Error>> {
+    let rest = &args[1..];
+    match args[0].as_str() {
+        "list" => list(rest, repo.as_ref()),
+        "next" => next(rest, repo.as_ref()),
+        "searches" => searches(rest, repo.as_ref()),
+        "scan" => scan(rest, repo.as_ref()),
+        "serve" => serve(rest, repo),
+        "help" | "--help" | "-h" => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        other => Err(format!("Unknown command `{}`\n\n{}", other, USAGE).into()),
+    }
+}
+
+fn list(args: &[String], repo: &dyn Repository) -> Result<(), Box<dyn Error>> {
+    let mut query = Query::default();
+    let mut search = vec![];
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--saved" => {
+                let name = args.next().ok_or("--saved needs a name")?;
+                query = find_saved(repo, name)?.query;
+            }
+            "--limit" => {
+                let n = args.next().ok_or("--limit needs a number")?;
+                query.limit = Some(n.parse()?);
+            }
+            _ => search.push(arg.as_str()),
+        }
+    }
+    query.narrow(&search.join(" "));
+
+    for item in repo.list(&query)? {
+        print_item(&item);
+    }
+    Ok(())
+}
+
+fn next(args: &[String], repo: &dyn Repository) -> Result<(), Box<dyn Error>> {
+    let mut query = Query::default();
+    let mut count = 1;
+    let mut search = vec![];
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--saved" => {
+                let name = args.next().ok_or("--saved needs a name")?;
+                query = find_saved(repo, name)?.query;
+            }
+            "--count" => {
+                let n = args.next().ok_or("--count needs a number")?;
+                count = n.parse()?;
+            }
+            _ => search.push(arg.as_str()),
+        }
+    }
+    query.narrow(&search.join(" "));
+
+    let suggestions = recommend::suggest(repo, &query, count, &mut fastrand::Rng::new())?;
+    if suggestions.is_empty() {
+        return Err("No matching items to suggest".into());
+    }
+    for suggestion in suggestions {
+        print_item(&suggestion.item);
+        for reason in &suggestion.reasons {
+            println!("\t- {}", reason);
+        }
+    }
+    Ok(())
+}
+
+fn searches(args: &[String], repo: &dyn Repository) -> Result<(), Box<dyn Error>> {
+    match (args.first().map(String::as_str), args.get(1)) {
+        (Some("list") | None, _) => {
+            for saved in repo.list_saved_searches()? {
+                let count = repo.count(&saved.query)?;
+                println!("{}\t{}\t{}", saved.name, count, saved.query.search);
+            }
+        }
+        (Some("export"), Some(path)) => {
+            let saved = repo.list_saved_searches()?;
+            util::export_saved_searches(&saved, Path::new(path))?;
+            println!("Exported {} saved searches to {}", saved.len(), path);
+        }
+        (Some("import"), Some(path)) => {
+            let imported = util::import_saved_searches(Path::new(path))?;
+            for mut saved in imported.iter().cloned() {
+                repo.save_search(&mut saved)?;
+            }
+            println!("Imported {} saved searches", imported.len());
+        }
+        _ => return Err(USAGE.into()),
+    }
+    Ok(())
+}
+
+fn scan(args: &[String], repo: &dyn Repository) -> Result<(), Box<dyn Error>> {
+    let report = match (args.first().map(String::as_str), args.get(1)) {
+        (Some("music"), Some(dir)) => scan::music::scan(repo, Path::new(dir), &util::covers_dir())?,
+        (Some("books"), Some(dir)) => scan::books::scan(repo, Path::new(dir), &util::covers_dir())?,
+        _ => return Err(USAGE.into()),
+    };
+    for error in &report.errors {
+        eprintln!("{}", error);
+    }
+    println!("{}", report);
+    Ok(())
+}
+
+fn serve(args: &[String], repo: Arc<dyn Repository>) -> Result<(), Box<dyn Error>> {
+    let mut settings = ApiSettings::load(repo.as_ref())?;
+    match (args.first().map(String::as_str), args.get(1)) {
+        (Some("--port"), Some(port)) => settings.port = port.parse()?,
+        (None, _) => {}
+        _ => return Err(USAGE.into()),
+    }
+    let server = ApiServer::start(repo, &settings)?;
+    println!(
+        "Web view: http://{}/?token={}",
+        server.addr(),
+        settings.token
+    );
+    println!("API:      http://{}/api", server.addr());
+    println!("Token: {}", settings.token);
+    server.wait();
+    Ok(())
+}
+
+fn find_saved(repo: &dyn Repository, name: &str) -> Result<SavedSearch, Box<dyn Error>> {
+    repo.list_saved_searches()?
+        .into_iter()
+        .find(|s| s.name.eq_ignore_ascii_case(name))
+        .ok_or_else(|| format!("No saved search named `{}`", name).into())
+}
+
+fn print_item(item: &MediaItem) {
+    println!(
+        "{}\t{}\t{}\t{}\t{}",
+        item.id.unwrap_or_default(),
+        item.title,
+        item.category,
+        item.status,
+        item.rating.map(|r| r.to_string()).unwrap_or_default(),
+    );
+}
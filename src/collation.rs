@@ -0,0 +1,113 @@
+use deunicode::deunicode;
+
+const ARTICLES: [&str; 9] = ["the", "a", "an", "der", "die", "das", "le", "la", "les"];
+
+/// Key that orders titles naturally under a plain byte comparison, so it can
+/// be stored and indexed: case- and accent-insensitive, and each run of
+/// digits is prefixed with its length so "Part 10" sorts after "Part 9".
+pub fn sort_key(title: &str, ignore_articles: bool) -> String {
+    let folded = fold(title, ignore_articles);
+    let mut key = String::with_capacity(folded.len() + 4);
+    let mut rest = folded.as_str();
+    while let Some(c) = rest.chars().next() {
+        if c.is_ascii_digit() {
+            let end = rest
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(rest.len());
+            let number = rest[..end].trim_start_matches('0');
+            // The length prefix is made of digits too, so a number still
+            // sorts against letters and punctuation as a digit would.
+            key.push_str(&format!("{:02}", number.len().min(99)));
+            key.push_str(number);
+            rest = &rest[end..];
+        } else {
+            key.push(c);
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    key
+}
+
+/// Lowercased ASCII transliteration, without a leading article if requested.
+pub fn fold(s: &str, ignore_articles: bool) -> String {
+    let folded = deunicode(s.trim()).to_lowercase();
+    if ignore_articles {
+        for article in ARTICLES {
+            if let Some(rest) = folded.strip_prefix(article) {
+                let rest = rest.trim_start();
+                if rest.len() < folded.len() - article.len() && !rest.is_empty() {
+                    return rest.to_string();
+                }
+            }
+        }
+    }
+    folded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted(titles: &[&str], ignore_articles: bool) -> Vec<String> {
+        let mut titles: Vec<String> = titles.iter().map(|t| t.to_string()).collect();
+        titles.sort_by_key(|t| sort_key(t, ignore_articles));
+        titles
+    }
+
+    #[test]
+    fn orders_numbers_by_value() {
+        assert_eq!(
+            sorted(
+                &["Part 10", "Part 9", "Part 1", "Part 010b", "Part 2"],
+                false
+            ),
+            vec!["Part 1", "Part 2", "Part 9", "Part 10", "Part 010b"]
+        );
+        assert_eq!(
+            sorted(&["Zelda", "1984", "300", "(500) Days"], false),
+            vec!["(500) Days", "300", "1984", "Zelda"]
+        );
+    }
+
+    #[test]
+    fn ignores_case_and_accents() {
+        assert_eq!(
+            sorted(&["eve", "Émile", "Ender", "apple"], false),
+            vec!["apple", "Émile", "Ender", "eve"]
+        );
+        assert_eq!(sort_key("ÉCOLE", false), sort_key("ecole", false));
+    }
+
+    #[test]
+    fn strips_a_leading_article_only_on_request() {
+        let titles = [
+            "The Matrix",
+            "A Bug's Life",
+            "Les Misérables",
+            "Theodore",
+            "Nemo",
+        ];
+        assert_eq!(
+            sorted(&titles, true),
+            vec![
+                "A Bug's Life",
+                "The Matrix",
+                "Les Misérables",
+                "Nemo",
+                "Theodore"
+            ]
+        );
+        assert_eq!(
+            sorted(&titles, false),
+            vec![
+                "A Bug's Life",
+                "Les Misérables",
+                "Nemo",
+                "The Matrix",
+                "Theodore"
+            ]
+        );
+        assert_eq!(fold("The", true), "the");
+        assert_eq!(fold("Them", true), "them");
+    }
+}
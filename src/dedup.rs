@@ -0,0 +1,330 @@
+use crate::collation;
+use crate::models::{Category, MediaItem, Status};
+use chrono::Local;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap};
+
+/// Normalized titles at least this similar (0..=1) are reported as likely duplicates.
+pub const THRESHOLD: f64 = 0.85;
+
+/// Title reduced to what matters for duplicate detection: lowercase ASCII
+/// without punctuation or a leading article, single-spaced.
+pub fn normalize_title(title: &str) -> String {
+    let cleaned: String = deunicode::deunicode(title)
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .collect();
+    let words = cleaned.split_whitespace().collect::<Vec<_>>().join(" ");
+    collation::fold(&words, true)
+}
+
+/// Similarity of two normalized titles. Titles with different numbers
+/// ("Part 1" and "Part 2") are never similar.
+pub fn similarity(a: &str, b: &str) -> f64 {
+    if a == b {
+        return 1.0;
+    }
+    if numbers(a) != numbers(b) {
+        return 0.0;
+    }
+    strsim::normalized_damerau_levenshtein(a, b)
+}
+
+fn numbers(s: &str) -> Vec<&str> {
+    s.split(|c: char| !c.is_ascii_digit())
+        .map(|n| n.trim_start_matches('0'))
+        .filter(|n| !n.is_empty())
+        .collect()
+}
+
+/// Normalized titles of one category, checked against while typing a new title.
+pub struct TitleIndex {
+    pub category: Category,
+    entries: Vec<(String, String)>,
+}
+
+impl TitleIndex {
+    pub fn new(category: Category, titles: Vec<String>) -> Self {
+        let entries = titles
+            .into_iter()
+            .map(|t| {
+                let key = normalize_title(&t);
+                (t, key)
+            })
+            .collect();
+        Self { category, entries }
+    }
+
+    pub fn insert(&mut self, title: &str) {
+        self.entries
+            .push((title.to_string(), normalize_title(title)));
+    }
+
+    /// Existing titles similar to `title`, most similar first.
+    pub fn find(&self, title: &str) -> Vec<&str> {
+        let key = normalize_title(title);
+        if key.is_empty() {
+            return vec![];
+        }
+        let mut hits: Vec<(f64, &str)> = self
+            .entries
+            .iter()
+            .map(|(t, k)| (similarity(&key, k), t.as_str()))
+            .filter(|(score, _)| *score >= THRESHOLD)
+            .collect();
+        hits.sort_by(|a, b| b.0.total_cmp(&a.0));
+        hits.into_iter().map(|(_, t)| t).collect()
+    }
+}
+
+/// Groups of likely duplicates, as indexes into `items`. Only items of the
+/// same category whose normalized titles share their first two characters
+/// are compared, which keeps the scan fast on large catalogs.
+pub fn clusters(items: &[MediaItem]) -> Vec<Vec<usize>> {
+    let keys: Vec<String> = items.iter().map(|i| normalize_title(&i.title)).collect();
+    let mut blocks: HashMap<(&str, String), Vec<usize>> = HashMap::new();
+    for (i, key) in keys.iter().enumerate() {
+        if !key.is_empty() {
+            let prefix = key.chars().take(2).collect();
+            blocks
+                .entry((items[i].category.as_str(), prefix))
+                .or_default()
+                .push(i);
+        }
+    }
+
+    let mut parent: Vec<usize> = (0..items.len()).collect();
+    for block in blocks.values() {
+        for (n, &a) in block.iter().enumerate() {
+            for &b in &block[n + 1..] {
+                if similarity(&keys[a], &keys[b]) >= THRESHOLD {
+                    let (ra, rb) = (root(&mut parent, a), root(&mut parent, b));
+                    parent[ra.max(rb)] = ra.min(rb);
+                }
+            }
+        }
+    }
+
+    let mut groups: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+    for i in 0..items.len() {
+        let r = root(&mut parent, i);
+        groups.entry(r).or_default().push(i);
+    }
+    groups.into_values().filter(|g| g.len() > 1).collect()
+}
+
+fn root(parent: &mut [usize], mut i: usize) -> usize {
+    while parent[i] != i {
+        parent[i] = parent[parent[i]];
+        i = parent[i];
+    }
+    i
+}
+
+/// Which duplicate each field of the merged item is taken from. The oldest
+/// item survives the merge and keeps its id; the others are deleted.
+pub struct MergePlan {
+    /// Oldest first.
+    pub items: Vec<MediaItem>,
+    pub title: usize,
+    pub category: usize,
+    pub status: usize,
+    pub rating: usize,
+    pub cover: usize,
+    /// `None` combines the notes of all items.
+    pub notes: Option<usize>,
+}
+
+impl MergePlan {
+    /// Proposes the best rating, the most advanced status and combined notes.
+    pub fn new(mut items: Vec<MediaItem>) -> Self {
+        items.sort_by_key(|i| (i.created_at, i.id));
+        let best = |key: &dyn Fn(&MediaItem) -> i64| {
+            (0..items.len())
+                .max_by_key(|&i| (key(&items[i]), Reverse(i)))
+                .unwrap_or(0)
+        };
+        let status = best(&|i| status_rank(i.status));
+        let rating = best(&|i| i.rating.map_or(-1, i64::from));
+        let cover = best(&|i| i64::from(i.cover_path.is_some()));
+        Self {
+            items,
+            title: 0,
+            category: 0,
+            status,
+            rating,
+            cover,
+            notes: None,
+        }
+    }
+
+    pub fn merged(&self) -> MediaItem {
+        let items = &self.items;
+        let mut out = items[0].clone();
+        out.title = items[self.title].title.clone();
+        out.sort_title = items[self.title].sort_title.clone();
+        out.category = items[self.category].category;
+        out.status = items[self.status].status;
+        out.rating = items[self.rating].rating;
+        out.cover_path = items[self.cover].cover_path.clone();
+        out.external_ids = vec![];
+        for id in items.iter().flat_map(|i| &i.external_ids) {
+            out.add_external_id(id.clone());
+        }
+        out.progress = items.iter().filter_map(|i| i.progress).max();
+        out.progress_total = items.iter().find_map(|i| i.progress_total);
+        out.file_path = items.iter().find_map(|i| i.file_path.clone());
+        out.format = items.iter().find_map(|i| i.format);
+        if out.status == Status::Wishlist {
+            out.priority = items.iter().filter_map(|i| i.priority).min();
+            out.target_price = items.iter().find_map(|i| i.target_price);
+            out.store = items.iter().find_map(|i| i.store.clone());
+        } else {
+            out.priority = None;
+            out.target_price = None;
+            out.store = None;
+        }
+        out.created_at = items
+            .iter()
+            .map(|i| i.created_at)
+            .min()
+            .unwrap_or(out.created_at);
+        out.started_at = match out.status {
+            Status::Planned | Status::Wishlist => None,
+            _ => items.iter().filter_map(|i| i.started_at).min(),
+        };
+        out.finished_at = match out.status {
+            Status::Finished => items
+                .iter()
+                .filter(|i| i.status == Status::Finished)
+                .filter_map(|i| i.finished_at)
+                .min(),
+            _ => None,
+        };
+
+        let mut notes: Vec<&str> = vec![];
+        let sources: Vec<&MediaItem> = match self.notes {
+            Some(i) => vec![&items[i]],
+            None => items.iter().collect(),
+        };
+        for note in sources.iter().filter_map(|i| i.notes.as_deref()) {
+            let note = note.trim();
+            if !note.is_empty() && !notes.contains(&note) {
+                notes.push(note);
+            }
+        }
+        let history = items[1..]
+            .iter()
+            .map(|i| format!("#{} \"{}\"", i.id.unwrap_or_default(), i.title))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let history = format!(
+            "Merged from {} on {}",
+            history,
+            Local::now().format("%Y-%m-%d")
+        );
+        notes.push(&history);
+        out.notes = Some(notes.join("\n\n"));
+        out.updated_at = Local::now();
+        out
+    }
+
+    /// Ids of the items folded into the survivor.
+    pub fn removed_ids(&self) -> Vec<i64> {
+        self.items[1..].iter().filter_map(|i| i.id).collect()
+    }
+}
+
+/// How far along `status` is: Wishlist < Planned < In Progress < Finished.
+pub fn status_rank(status: Status) -> i64 {
+    match status {
+        Status::Wishlist => -1,
+        Status::Planned => 0,
+        Status::InProgress => 1,
+        Status::Finished => 2,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::parse_local_date;
+
+    fn item(id: i64, title: &str, created: &str) -> MediaItem {
+        let mut item = MediaItem::new(title, Category::Book);
+        item.id = Some(id);
+        item.created_at = parse_local_date(created).unwrap();
+        item
+    }
+
+    #[test]
+    fn normalizes_case_accents_punctuation_and_articles() {
+        assert_eq!(
+            normalize_title("  The Lord of the Rings: Return!"),
+            "lord of the rings return"
+        );
+        assert_eq!(normalize_title("Les Misérables"), "miserables");
+        assert_eq!(normalize_title("?!"), "");
+    }
+
+    #[test]
+    fn titles_with_different_numbers_are_not_similar() {
+        let sim = |a: &str, b: &str| similarity(&normalize_title(a), &normalize_title(b));
+        assert_eq!(sim("The Hobbit", "Hobbit"), 1.0);
+        assert!(sim("Harry Poter", "Harry Potter") >= THRESHOLD);
+        assert_eq!(sim("Part 1", "Part 2"), 0.0);
+        assert!(sim("Part 01", "Part 1") >= THRESHOLD);
+        assert!(sim("Dune", "Emma") < THRESHOLD);
+
+        let index = TitleIndex::new(Category::Book, vec!["Harry Potter".into(), "Dune".into()]);
+        assert_eq!(index.find("harry poter"), vec!["Harry Potter"]);
+        assert!(index.find("...").is_empty());
+    }
+
+    #[test]
+    fn clusters_similar_titles_of_the_same_category() {
+        let mut items = vec![
+            item(1, "Dune", "2024-01-01"),
+            item(2, "Harry Potter", "2024-01-01"),
+            item(3, "dune", "2024-01-01"),
+            item(4, "Harry Poter", "2024-01-01"),
+            item(5, "Dune", "2024-01-01"),
+            item(6, "Emma", "2024-01-01"),
+        ];
+        items[4].category = Category::Movie;
+        assert_eq!(clusters(&items), vec![vec![0, 2], vec![1, 3]]);
+    }
+
+    #[test]
+    fn merge_keeps_the_oldest_item_and_the_best_fields() {
+        let mut newer = item(2, "Dune (2nd)", "2024-02-01");
+        newer.status = Status::Finished;
+        newer.finished_at = parse_local_date("2024-03-01");
+        newer.rating = Some(9);
+        newer.notes = Some("#scifi".into());
+        newer.progress = Some(300);
+        let mut older = item(1, "Dune", "2024-01-01");
+        older.rating = Some(7);
+        older.notes = Some("#scifi".into());
+        older.cover_path = Some("dune.png".into());
+        older.status = Status::Wishlist;
+        older.priority = Some(crate::models::Priority::High);
+
+        let plan = MergePlan::new(vec![newer, older]);
+        assert_eq!(plan.items[0].id, Some(1));
+        assert_eq!((plan.status, plan.rating, plan.cover), (1, 1, 0));
+        assert_eq!(plan.removed_ids(), vec![2]);
+
+        let merged = plan.merged();
+        assert_eq!(merged.id, Some(1));
+        assert_eq!(merged.title, "Dune");
+        assert_eq!(merged.status, Status::Finished);
+        assert_eq!(merged.rating, Some(9));
+        assert_eq!(merged.cover_path.as_deref(), Some("dune.png"));
+        assert_eq!(merged.progress, Some(300));
+        assert_eq!(merged.finished_at, parse_local_date("2024-03-01"));
+        assert_eq!(merged.priority, None);
+        let notes = merged.notes.unwrap();
+        assert!(notes.starts_with("#scifi\n\nMerged from #2 \"Dune (2nd)\" on "));
+    }
+}
@@ -0,0 +1,198 @@
+use crate::dedup::{self, MergePlan};
+use crate::models::{MediaItem, Query};
+use crate::repo::{RepoResult, Repository};
+use eframe::egui;
+
+/// Maintenance window listing suspected duplicates, with a merge editor.
+#[derive(Default)]
+pub struct DuplicatesView {
+    pub open: bool,
+    clusters: Vec<Vec<MediaItem>>,
+    merging: Option<MergePlan>,
+}
+
+impl DuplicatesView {
+    pub fn reload(&mut self, repo: &dyn Repository) -> RepoResult<()> {
+        let items = repo.list(&Query::default())?;
+        self.clusters = dedup::clusters(&items)
+            .into_iter()
+            .map(|group| group.into_iter().map(|i| items[i].clone()).collect())
+            .collect();
+        Ok(())
+    }
+
+    /// Returns true when items were merged and the catalog needs a refresh.
+    pub fn show(&mut self, ctx: &egui::Context, repo: &dyn Repository) -> RepoResult<bool> {
+        let mut open = self.open;
+        let mut result = Ok(false);
+        egui::Window::new("Duplicates")
+            .open(&mut open)
+            .default_width(520.0)
+            .show(ctx, |ui| {
+                result = if self.merging.is_some() {
+                    self.merge_ui(ui, repo)
+                } else {
+                    self.clusters_ui(ui, repo).map(|_| false)
+                };
+            });
+        self.open = open;
+        result
+    }
+
+    fn clusters_ui(&mut self, ui: &mut egui::Ui, repo: &dyn Repository) -> RepoResult<()> {
+        ui.horizontal(|ui| {
+            ui.label(format!("{} groups of similar titles", self.clusters.len()));
+            if ui.button("Rescan").clicked() {
+                return self.reload(repo);
+            }
+            Ok(())
+        })
+        .inner?;
+        ui.separator();
+        let mut merge = None;
+        let mut dismiss = None;
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            for (n, cluster) in self.clusters.iter().enumerate() {
+                ui.horizontal(|ui| {
+                    if ui.small_button("Merge...").clicked() {
+                        merge = Some(n);
+                    }
+                    if ui
+                        .small_button("Not duplicates")
+                        .on_hover_text("Hide this group until the next scan")
+                        .clicked()
+                    {
+                        dismiss = Some(n);
+                    }
+                    ui.weak(cluster[0].category.to_string());
+                    let titles = cluster
+                        .iter()
+                        .map(|i| format!("{} (#{})", i.title, i.id.unwrap_or_default()))
+                        .collect::<Vec<_>>()
+                        .join(" · ");
+                    ui.label(titles);
+                });
+            }
+        });
+        if let Some(n) = merge {
+            self.merging = Some(MergePlan::new(self.clusters[n].clone()));
+        } else if let Some(n) = dismiss {
+            self.clusters.remove(n);
+        }
+        Ok(())
+    }
+
+    fn merge_ui(&mut self, ui: &mut egui::Ui, repo: &dyn Repository) -> RepoResult<bool> {
+        let Some(plan) = self.merging.as_mut() else {
+            return Ok(false);
+        };
+        ui.label("Pick the value to keep for each field. The oldest item keeps its id.");
+        egui::ScrollArea::horizontal().show(ui, |ui| {
+            egui::Grid::new("merge_grid")
+                .striped(true)
+                .spacing([12.0, 6.0])
+                .show(ui, |ui| {
+                    ui.label("");
+                    for item in &plan.items {
+                        ui.strong(format!("#{}", item.id.unwrap_or_default()));
+                    }
+                    ui.end_row();
+
+                    ui.label("Title:");
+                    for (i, item) in plan.items.iter().enumerate() {
+                        ui.radio_value(&mut plan.title, i, &item.title);
+                    }
+                    ui.end_row();
+
+                    ui.label("Category:");
+                    for (i, item) in plan.items.iter().enumerate() {
+                        ui.radio_value(&mut plan.category, i, item.category.to_string());
+                    }
+                    ui.end_row();
+
+                    ui.label("Status:");
+                    for (i, item) in plan.items.iter().enumerate() {
+                        ui.radio_value(&mut plan.status, i, item.status.to_string());
+                    }
+                    ui.end_row();
+
+                    ui.label("Rating:");
+                    for (i, item) in plan.items.iter().enumerate() {
+                        let rating = item.rating.map(|r| r.to_string());
+                        ui.radio_value(&mut plan.rating, i, rating.as_deref().unwrap_or("—"));
+                    }
+                    ui.end_row();
+
+                    ui.label("Cover:");
+                    for (i, item) in plan.items.iter().enumerate() {
+                        let cover = item.cover_path.as_deref().unwrap_or("(none)");
+                        ui.radio_value(&mut plan.cover, i, truncate(cover, 24));
+                    }
+                    ui.end_row();
+
+                    ui.label("Notes:");
+                    for (i, item) in plan.items.iter().enumerate() {
+                        let notes = item.notes.as_deref().unwrap_or("(none)");
+                        ui.radio_value(&mut plan.notes, Some(i), truncate(notes, 24))
+                            .on_hover_text(notes);
+                    }
+                    ui.end_row();
+
+                    ui.label("");
+                    ui.radio_value(&mut plan.notes, None, "Combine all");
+                    ui.end_row();
+                });
+        });
+
+        let merged = plan.merged();
+        ui.separator();
+        ui.label(format!(
+            "Result: {} ({}, {}, rating {})",
+            merged.title,
+            merged.category,
+            merged.status,
+            merged
+                .rating
+                .map(|r| r.to_string())
+                .unwrap_or_else(|| "—".into())
+        ));
+        ui.weak(format!(
+            "Added {}{}",
+            merged.created_at.format("%Y-%m-%d"),
+            merged
+                .finished_at
+                .map(|t| format!(", finished {}", t.format("%Y-%m-%d")))
+                .unwrap_or_default()
+        ));
+        if let Some(notes) = &merged.notes {
+            ui.weak(notes);
+        }
+
+        let mut merged_ok = false;
+        ui.horizontal(|ui| {
+            if ui.button("Merge").clicked() {
+                merged_ok = true;
+            }
+            if ui.button("Back").clicked() {
+                self.merging = None;
+            }
+        });
+        if merged_ok {
+            if let Some(plan) = self.merging.take() {
+                repo.merge(&merged, &plan.removed_ids())?;
+                self.reload(repo)?;
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+}
+
+fn truncate(s: &str, max: usize) -> String {
+    let line = s.lines().next().unwrap_or_default();
+    if line.chars().count() > max || line.len() < s.trim_end().len() {
+        format!("{}…", line.chars().take(max).collect::<String>())
+    } else {
+        line.to_string()
+    }
+}
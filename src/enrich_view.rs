@@ -0,0 +1,217 @@
+use crate::metadata::local::LocalDump;
+use crate::metadata::{Field, Metadata, MetadataProvider};
+use crate::models::{Category, MediaItem};
+use crate::repo::{RepoError, RepoResult, Repository};
+use eframe::egui::{self, TextEdit};
+
+pub const DUMP_SETTING: &str = "metadata.local_dump";
+
+/// Window for filling in the item being edited from a metadata provider:
+/// search, pick a match, then choose which fields to take over.
+#[derive(Default)]
+pub struct EnrichView {
+    pub open: bool,
+    provider: Option<Box<dyn MetadataProvider>>,
+    query: String,
+    category: Option<Category>,
+    results: Vec<Metadata>,
+    selected: Option<usize>,
+    /// Changes the selected match would make, and whether each is ticked.
+    changes: Vec<(Field, String, bool)>,
+}
+
+impl EnrichView {
+    /// Opens the window for `item`, searching for its title.
+    pub fn start(&mut self, item: &MediaItem, repo: &dyn Repository) -> RepoResult<()> {
+        self.open = true;
+        self.query = item.title.clone();
+        self.category = Some(item.category);
+        if self.provider.is_none() {
+            if let Some(path) = repo.get_setting(DUMP_SETTING)? {
+                self.provider = Some(Box::new(LocalDump::open(path.as_ref())?));
+            }
+        }
+        self.search(item)
+    }
+
+    // Searches by title; the work with the item's external id, if the
+    // provider knows it, comes first.
+    fn search(&mut self, item: &MediaItem) -> RepoResult<()> {
+        self.results = vec![];
+        let Some(provider) = &self.provider else {
+            self.select(None, item);
+            return Ok(());
+        };
+        for id in &item.external_ids {
+            if let Some(m) = provider.fetch(id)? {
+                if !self.results.contains(&m) {
+                    self.results.push(m);
+                }
+            }
+        }
+        for m in provider.search(&self.query, self.category)? {
+            if !self.results.contains(&m) {
+                self.results.push(m);
+            }
+        }
+        self.select(
+            if self.results.is_empty() {
+                None
+            } else {
+                Some(0)
+            },
+            item,
+        );
+        Ok(())
+    }
+
+    fn select(&mut self, selected: Option<usize>, item: &MediaItem) {
+        self.selected = selected;
+        self.changes = selected
+            .map(|i| self.results[i].changes(item))
+            .unwrap_or_default()
+            .into_iter()
+            // Titles are usually as the user wants them.
+            .map(|(field, value)| (field, value, field != Field::Title))
+            .collect();
+    }
+
+    /// Shows the window; "Apply" fills in the ticked fields of `item`.
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        repo: &dyn Repository,
+        item: &mut MediaItem,
+    ) -> RepoResult<()> {
+        let mut open = self.open;
+        let mut result = Ok(());
+        egui::Window::new("Enrich")
+            .open(&mut open)
+            .default_width(460.0)
+            .show(ctx, |ui| {
+                result = self.enrich_ui(ui, repo, item);
+            });
+        self.open &= open;
+        result
+    }
+
+    fn enrich_ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        repo: &dyn Repository,
+        item: &mut MediaItem,
+    ) -> RepoResult<()> {
+        let mut search = false;
+        let mut dump = None;
+        ui.horizontal(|ui| {
+            ui.label("Source:");
+            match &self.provider {
+                Some(provider) => ui.label(provider.name()),
+                None => ui.weak("none"),
+            };
+            if ui
+                .small_button("Choose dump...")
+                .on_hover_text("JSON, JSON Lines or Open Library dump, optionally gzipped")
+                .clicked()
+            {
+                dump = rfd::FileDialog::new()
+                    .add_filter("Dump", &["json", "jsonl", "ndjson", "txt", "gz"])
+                    .pick_file();
+            }
+        });
+        if let Some(path) = dump {
+            let provider = LocalDump::open(&path)
+                .map_err(|e| RepoError::Other(format!("Cannot read {}: {}", path.display(), e)))?;
+            repo.set_setting(DUMP_SETTING, &path.to_string_lossy())?;
+            self.provider = Some(Box::new(provider));
+            search = true;
+        }
+        ui.horizontal(|ui| {
+            search |= ui
+                .add(TextEdit::singleline(&mut self.query).desired_width(240.0))
+                .lost_focus()
+                && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            let mut any = self.category.is_none();
+            if ui.checkbox(&mut any, "Any category").changed() {
+                self.category = if any { None } else { Some(item.category) };
+                search = true;
+            }
+            search |= ui.button("Search").clicked();
+        });
+        if search {
+            self.search(item)?;
+        }
+
+        ui.separator();
+        if self.provider.is_none() {
+            ui.weak("Choose a dump file to search.");
+            return Ok(());
+        }
+        if self.results.is_empty() {
+            ui.weak("No matches.");
+            return Ok(());
+        }
+        let mut selected = self.selected;
+        egui::ScrollArea::vertical()
+            .id_source("enrich_results")
+            .max_height(160.0)
+            .auto_shrink([false, true])
+            .show(ui, |ui| {
+                for (i, m) in self.results.iter().enumerate() {
+                    let byline = m.byline();
+                    let label = if byline.is_empty() {
+                        m.title.clone()
+                    } else {
+                        format!("{} – {}", m.title, byline)
+                    };
+                    let ids: Vec<String> = m.external_ids.iter().map(|id| id.to_string()).collect();
+                    ui.radio_value(&mut selected, Some(i), label)
+                        .on_hover_text(ids.join(" "));
+                }
+            });
+        if selected != self.selected {
+            self.select(selected, item);
+        }
+
+        ui.separator();
+        if self.changes.is_empty() {
+            ui.weak("The item already has everything this match offers.");
+            return Ok(());
+        }
+        egui::Grid::new("enrich_changes")
+            .num_columns(2)
+            .spacing([8.0, 4.0])
+            .show(ui, |ui| {
+                for (field, value, take) in self.changes.iter_mut() {
+                    ui.checkbox(take, field.as_str());
+                    ui.label(value.as_str());
+                    ui.end_row();
+                }
+            });
+        if ui.button("Apply").clicked() {
+            let fields: Vec<Field> = self
+                .changes
+                .iter()
+                .filter(|(_, _, take)| *take)
+                .map(|(field, _, _)| *field)
+                .collect();
+            let Some(m) = self.selected.map(|i| &self.results[i]) else {
+                return Ok(());
+            };
+            if fields.contains(&Field::ExternalIds) {
+                for id in m.new_ids(item) {
+                    if let Some(other) = repo.find_by_external_id(&id)?.filter(|o| o.id != item.id)
+                    {
+                        return Err(RepoError::Other(format!(
+                            "{} already belongs to “{}”",
+                            id, other.title
+                        )));
+                    }
+                }
+            }
+            m.apply(item, &fields);
+            self.open = false;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,523 @@
+use crate::models::MediaItem;
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Local};
+use rust_xlsxwriter::{Format, Workbook};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ExportFormat {
+    #[default]
+    Csv,
+    Tsv,
+    Json,
+    JsonLines,
+    Xlsx,
+}
+
+impl ExportFormat {
+    pub const ALL: [ExportFormat; 5] = [
+        ExportFormat::Csv,
+        ExportFormat::Tsv,
+        ExportFormat::Json,
+        ExportFormat::JsonLines,
+        ExportFormat::Xlsx,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "CSV",
+            ExportFormat::Tsv => "TSV",
+            ExportFormat::Json => "JSON",
+            ExportFormat::JsonLines => "JSON Lines",
+            ExportFormat::Xlsx => "Excel (XLSX)",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Tsv => "tsv",
+            ExportFormat::Json => "json",
+            ExportFormat::JsonLines => "jsonl",
+            ExportFormat::Xlsx => "xlsx",
+        }
+    }
+
+    /// Delimited text, where the delimiter and BOM options apply.
+    pub fn is_delimited(&self) -> bool {
+        matches!(self, ExportFormat::Csv | ExportFormat::Tsv)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DateFormat {
+    /// `2026-03-01T18:30:00+01:00`
+    Rfc3339,
+    /// A chrono `strftime` pattern such as `%d.%m.%Y`.
+    Custom(String),
+}
+
+impl Default for DateFormat {
+    fn default() -> Self {
+        DateFormat::Custom("%Y-%m-%d %H:%M:%S".into())
+    }
+}
+
+impl DateFormat {
+    pub fn format(&self, time: &DateTime<Local>) -> String {
+        match self {
+            DateFormat::Rfc3339 => time.to_rfc3339(),
+            DateFormat::Custom(pattern) => time.format(pattern).to_string(),
+        }
+    }
+
+    /// Rejects custom patterns chrono cannot format, which would otherwise panic.
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            DateFormat::Rfc3339 => Ok(()),
+            DateFormat::Custom(pattern) if pattern.trim().is_empty() => {
+                Err("The date format is empty".into())
+            }
+            DateFormat::Custom(pattern) => {
+                if StrftimeItems::new(pattern).any(|i| matches!(i, Item::Error)) {
+                    Err(format!("Invalid date format \"{}\"", pattern))
+                } else {
+                    Ok(())
+                }
+            }
+        }
+    }
+}
+
+/// Columns available when exporting catalog items.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ItemColumn {
+    Id,
+    Title,
+    SortTitle,
+    Category,
+    Status,
+    Rating,
+    Tags,
+    Notes,
+    CoverPath,
+    CreatedAt,
+    UpdatedAt,
+    StartedAt,
+    FinishedAt,
+    Progress,
+    ProgressTotal,
+    ExternalId,
+    FilePath,
+    Format,
+    Priority,
+    TargetPrice,
+    Store,
+}
+
+impl ItemColumn {
+    pub const ALL: [ItemColumn; 21] = [
+        ItemColumn::Id,
+        ItemColumn::Title,
+        ItemColumn::SortTitle,
+        ItemColumn::Category,
+        ItemColumn::Status,
+        ItemColumn::Rating,
+        ItemColumn::Tags,
+        ItemColumn::Notes,
+        ItemColumn::CoverPath,
+        ItemColumn::CreatedAt,
+        ItemColumn::UpdatedAt,
+        ItemColumn::StartedAt,
+        ItemColumn::FinishedAt,
+        ItemColumn::Progress,
+        ItemColumn::ProgressTotal,
+        ItemColumn::ExternalId,
+        ItemColumn::FilePath,
+        ItemColumn::Format,
+        ItemColumn::Priority,
+        ItemColumn::TargetPrice,
+        ItemColumn::Store,
+    ];
+
+    /// Header name in the exported file.
+    pub fn key(&self) -> &'static str {
+        match self {
+            ItemColumn::Id => "id",
+            ItemColumn::Title => "title",
+            ItemColumn::SortTitle => "sort_title",
+            ItemColumn::Category => "category",
+            ItemColumn::Status => "status",
+            ItemColumn::Rating => "rating",
+            ItemColumn::Tags => "tags",
+            ItemColumn::Notes => "notes",
+            ItemColumn::CoverPath => "cover_path",
+            ItemColumn::CreatedAt => "created_at",
+            ItemColumn::UpdatedAt => "updated_at",
+            ItemColumn::StartedAt => "started_at",
+            ItemColumn::FinishedAt => "finished_at",
+            ItemColumn::Progress => "progress",
+            ItemColumn::ProgressTotal => "progress_total",
+            ItemColumn::ExternalId => "external_id",
+            ItemColumn::FilePath => "file_path",
+            ItemColumn::Format => "format",
+            ItemColumn::Priority => "priority",
+            ItemColumn::TargetPrice => "target_price",
+            ItemColumn::Store => "store",
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ItemColumn::Id => "ID",
+            ItemColumn::Title => "Title",
+            ItemColumn::SortTitle => "Sort title",
+            ItemColumn::Category => "Category",
+            ItemColumn::Status => "Status",
+            ItemColumn::Rating => "Rating",
+            ItemColumn::Tags => "Tags",
+            ItemColumn::Notes => "Notes",
+            ItemColumn::CoverPath => "Cover path",
+            ItemColumn::CreatedAt => "Created",
+            ItemColumn::UpdatedAt => "Updated",
+            ItemColumn::StartedAt => "Started",
+            ItemColumn::FinishedAt => "Finished",
+            ItemColumn::Progress => "Progress",
+            ItemColumn::ProgressTotal => "Progress total",
+            ItemColumn::ExternalId => "External IDs",
+            ItemColumn::FilePath => "File path",
+            ItemColumn::Format => "Format",
+            ItemColumn::Priority => "Priority",
+            ItemColumn::TargetPrice => "Target price",
+            ItemColumn::Store => "Store",
+        }
+    }
+
+    fn cell(&self, item: &MediaItem) -> Cell {
+        let text = |s: &Option<String>| s.clone().map(Cell::Text).unwrap_or(Cell::Empty);
+        let date = |t: Option<DateTime<Local>>| t.map(Cell::Date).unwrap_or(Cell::Empty);
+        let int = |n: Option<u32>| n.map(|n| Cell::Int(n.into())).unwrap_or(Cell::Empty);
+        match self {
+            ItemColumn::Id => item.id.map(Cell::Int).unwrap_or(Cell::Empty),
+            ItemColumn::Title => Cell::Text(item.title.clone()),
+            ItemColumn::SortTitle => text(&item.sort_title),
+            ItemColumn::Category => Cell::Text(item.category.to_string()),
+            ItemColumn::Status => Cell::Text(item.status.to_string()),
+            ItemColumn::Rating => item
+                .rating
+                .map(|r| Cell::Int(r.into()))
+                .unwrap_or(Cell::Empty),
+            ItemColumn::Tags => Cell::Text(item.tags().join(" ")),
+            ItemColumn::Notes => text(&item.notes),
+            ItemColumn::CoverPath => text(&item.cover_path),
+            ItemColumn::CreatedAt => Cell::Date(item.created_at),
+            ItemColumn::UpdatedAt => Cell::Date(item.updated_at),
+            ItemColumn::StartedAt => date(item.started_at),
+            ItemColumn::FinishedAt => date(item.finished_at),
+            ItemColumn::Progress => int(item.progress),
+            ItemColumn::ProgressTotal => int(item.progress_total),
+            ItemColumn::ExternalId => text(
+                &Some(
+                    item.external_ids
+                        .iter()
+                        .map(|id| id.to_string())
+                        .collect::<Vec<_>>()
+                        .join(" "),
+                )
+                .filter(|ids| !ids.is_empty()),
+            ),
+            ItemColumn::FilePath => text(&item.file_path),
+            ItemColumn::Format => text(&item.format.map(|f| f.to_string())),
+            ItemColumn::Priority => text(&item.priority.map(|p| p.to_string())),
+            ItemColumn::TargetPrice => item.target_price.map(Cell::Number).unwrap_or(Cell::Empty),
+            ItemColumn::Store => text(&item.store),
+        }
+    }
+}
+
+/// How an export is written. Saved as JSON in presets.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExportOptions {
+    pub format: ExportFormat,
+    /// Item columns in output order; only used by [`item_table`].
+    pub columns: Vec<ItemColumn>,
+    /// Field delimiter for CSV; TSV always uses a tab.
+    pub delimiter: char,
+    /// Start CSV/TSV files with a UTF-8 byte order mark, so Excel detects the encoding.
+    pub bom: bool,
+    /// Used for text formats; XLSX stores real dates.
+    pub date_format: DateFormat,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        Self {
+            format: ExportFormat::Csv,
+            columns: vec![
+                ItemColumn::Id,
+                ItemColumn::Title,
+                ItemColumn::Category,
+                ItemColumn::Status,
+                ItemColumn::Rating,
+                ItemColumn::Notes,
+                ItemColumn::CoverPath,
+                ItemColumn::CreatedAt,
+                ItemColumn::UpdatedAt,
+            ],
+            delimiter: ',',
+            bom: false,
+            date_format: DateFormat::default(),
+        }
+    }
+}
+
+/// Named export options, stored in the database.
+#[derive(Debug, Clone)]
+pub struct ExportPreset {
+    pub id: Option<i64>,
+    pub name: String,
+    pub options: ExportOptions,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Cell {
+    Empty,
+    Text(String),
+    Int(i64),
+    Number(f64),
+    Date(DateTime<Local>),
+}
+
+/// Rows to export with their header. Reports build one of these and hand it
+/// to [`write_table`], so they get every format for free.
+#[derive(Debug, Clone, Default)]
+pub struct Table {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<Cell>>,
+}
+
+pub fn item_table(items: &[MediaItem], columns: &[ItemColumn]) -> Table {
+    Table {
+        columns: columns.iter().map(|c| c.key().to_string()).collect(),
+        rows: items
+            .iter()
+            .map(|item| columns.iter().map(|c| c.cell(item)).collect())
+            .collect(),
+    }
+}
+
+pub fn write_table(
+    table: &Table,
+    options: &ExportOptions,
+    path: &Path,
+) -> Result<(), Box<dyn Error>> {
+    options.date_format.validate()?;
+    match options.format {
+        ExportFormat::Csv | ExportFormat::Tsv => write_delimited(table, options, path),
+        ExportFormat::Json => {
+            let rows: Vec<Value> = table
+                .rows
+                .iter()
+                .map(|row| json_row(table, row, &options.date_format))
+                .collect();
+            let mut out = BufWriter::new(File::create(path)?);
+            serde_json::to_writer_pretty(&mut out, &rows)?;
+            out.flush()?;
+            Ok(())
+        }
+        ExportFormat::JsonLines => {
+            let mut out = BufWriter::new(File::create(path)?);
+            for row in &table.rows {
+                serde_json::to_writer(&mut out, &json_row(table, row, &options.date_format))?;
+                out.write_all(b"\n")?;
+            }
+            out.flush()?;
+            Ok(())
+        }
+        ExportFormat::Xlsx => write_xlsx(table, path),
+    }
+}
+
+fn write_delimited(
+    table: &Table,
+    options: &ExportOptions,
+    path: &Path,
+) -> Result<(), Box<dyn Error>> {
+    let delimiter = match options.format {
+        ExportFormat::Tsv => b'\t',
+        _ => u8::try_from(options.delimiter)
+            .ok()
+            .filter(|d| d.is_ascii() && *d != b'"' && *d != b'\n' && *d != b'\r')
+            .ok_or_else(|| format!("Cannot use {:?} as a delimiter", options.delimiter))?,
+    };
+    let mut file = File::create(path)?;
+    if options.bom {
+        file.write_all("\u{feff}".as_bytes())?;
+    }
+    let mut wtr = csv::WriterBuilder::new()
+        .delimiter(delimiter)
+        .from_writer(file);
+    wtr.write_record(&table.columns)?;
+    for row in &table.rows {
+        wtr.write_record(row.iter().map(|cell| match cell {
+            Cell::Empty => String::new(),
+            Cell::Text(s) => s.clone(),
+            Cell::Int(n) => n.to_string(),
+            Cell::Number(n) => n.to_string(),
+            Cell::Date(t) => options.date_format.format(t),
+        }))?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+fn json_row(table: &Table, row: &[Cell], date_format: &DateFormat) -> Value {
+    let mut object = Map::new();
+    for (column, cell) in table.columns.iter().zip(row) {
+        let value = match cell {
+            Cell::Empty => Value::Null,
+            Cell::Text(s) => Value::from(s.as_str()),
+            Cell::Int(n) => Value::from(*n),
+            Cell::Number(n) => Value::from(*n),
+            Cell::Date(t) => Value::from(date_format.format(t)),
+        };
+        object.insert(column.clone(), value);
+    }
+    Value::Object(object)
+}
+
+fn write_xlsx(table: &Table, path: &Path) -> Result<(), Box<dyn Error>> {
+    let mut workbook = Workbook::new();
+    let sheet = workbook.add_worksheet();
+    let header = Format::new().set_bold();
+    let date = Format::new().set_num_format("yyyy-mm-dd hh:mm");
+    for (col, name) in table.columns.iter().enumerate() {
+        sheet.write_string_with_format(0, col as u16, name, &header)?;
+    }
+    for (i, row) in table.rows.iter().enumerate() {
+        let r = i as u32 + 1;
+        for (col, cell) in row.iter().enumerate() {
+            let col = col as u16;
+            match cell {
+                Cell::Empty => {}
+                Cell::Text(s) => {
+                    sheet.write_string(r, col, s)?;
+                }
+                Cell::Int(n) => {
+                    sheet.write_number(r, col, *n as f64)?;
+                }
+                Cell::Number(n) => {
+                    sheet.write_number(r, col, *n)?;
+                }
+                Cell::Date(t) => {
+                    sheet.write_datetime_with_format(r, col, t.naive_local(), &date)?;
+                }
+            }
+        }
+    }
+    sheet.set_freeze_panes(1, 0)?;
+    sheet.autofit();
+    workbook.save(path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ids::ExternalId;
+    use crate::models::Category;
+    use crate::util::parse_local_date;
+    use std::path::PathBuf;
+
+    fn temp_file(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("export-{}-{}", std::process::id(), name))
+    }
+
+    fn table() -> Table {
+        let mut item = MediaItem::new("Dune, \"the\" novel", Category::Book);
+        item.id = Some(7);
+        item.created_at = parse_local_date("2024-03-01").unwrap();
+        item.notes = Some("#scifi".into());
+        item.add_external_id(ExternalId::parse("isbn:0441172717").unwrap());
+        item_table(
+            &[item],
+            &[
+                ItemColumn::Id,
+                ItemColumn::Title,
+                ItemColumn::Rating,
+                ItemColumn::Tags,
+                ItemColumn::ExternalId,
+                ItemColumn::CreatedAt,
+            ],
+        )
+    }
+
+    fn export(options: &ExportOptions, name: &str) -> Vec<u8> {
+        let path = temp_file(name);
+        write_table(&table(), options, &path).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn writes_delimited_text_with_options() {
+        let options = ExportOptions {
+            delimiter: ';',
+            bom: true,
+            date_format: DateFormat::Custom("%d.%m.%Y".into()),
+            ..Default::default()
+        };
+        assert_eq!(
+            String::from_utf8(export(&options, "semicolon.csv")).unwrap(),
+            "\u{feff}id;title;rating;tags;external_id;created_at\n\
+             7;\"Dune, \"\"the\"\" novel\";;scifi;isbn:9780441172719;01.03.2024\n"
+        );
+        let tsv = ExportOptions {
+            format: ExportFormat::Tsv,
+            delimiter: ',',
+            ..Default::default()
+        };
+        let text = String::from_utf8(export(&tsv, "tabs.tsv")).unwrap();
+        assert!(text.starts_with("id\ttitle\trating"));
+    }
+
+    #[test]
+    fn writes_json_with_nulls_and_formatted_dates() {
+        let options = ExportOptions {
+            format: ExportFormat::JsonLines,
+            date_format: DateFormat::Custom("%Y".into()),
+            ..Default::default()
+        };
+        let line: Value = serde_json::from_slice(&export(&options, "rows.jsonl")).unwrap();
+        assert_eq!(line["id"], 7);
+        assert_eq!(line["rating"], Value::Null);
+        assert_eq!(line["created_at"], "2024");
+        let keys: Vec<&String> = line.as_object().unwrap().keys().collect();
+        assert_eq!(keys[..2], ["id", "title"]);
+    }
+
+    #[test]
+    fn rejects_bad_delimiters_and_date_formats() {
+        let path = temp_file("bad.csv");
+        let quote = ExportOptions {
+            delimiter: '"',
+            ..Default::default()
+        };
+        assert!(write_table(&table(), &quote, &path).is_err());
+        let bad_date = ExportOptions {
+            date_format: DateFormat::Custom("%Q".into()),
+            ..Default::default()
+        };
+        assert!(write_table(&table(), &bad_date, &path).is_err());
+        assert!(DateFormat::Custom(" ".into()).validate().is_err());
+        assert!(DateFormat::Rfc3339.validate().is_ok());
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,287 @@
+use crate::export::{self, DateFormat, ExportFormat, ExportOptions, ExportPreset, ItemColumn};
+use crate::models::Query;
+use crate::repo::{RepoResult, Repository};
+use chrono::Local;
+use eframe::egui::{self, TextEdit};
+
+const LAST_OPTIONS_KEY: &str = "export.last";
+const DELIMITERS: [(char, &str); 4] = [
+    (',', "Comma"),
+    (';', "Semicolon"),
+    ('|', "Pipe"),
+    ('\t', "Tab"),
+];
+
+/// Export dialog for the filtered list: format, columns, encoding and presets.
+#[derive(Default)]
+pub struct ExportView {
+    pub open: bool,
+    loaded: bool,
+    options: ExportOptions,
+    presets: Vec<ExportPreset>,
+    preset_name: String,
+    custom_date: String,
+    message: Option<String>,
+}
+
+impl ExportView {
+    /// Loads the presets and the options used for the last export.
+    pub fn reload(&mut self, repo: &dyn Repository) -> RepoResult<()> {
+        self.presets = repo.list_export_presets()?;
+        if !self.loaded {
+            if let Some(json) = repo.get_setting(LAST_OPTIONS_KEY)? {
+                self.set_options(serde_json::from_str(&json)?);
+            } else {
+                self.set_options(ExportOptions::default());
+            }
+            self.loaded = true;
+        }
+        Ok(())
+    }
+
+    fn set_options(&mut self, options: ExportOptions) {
+        self.custom_date = match &options.date_format {
+            DateFormat::Custom(pattern) => pattern.clone(),
+            DateFormat::Rfc3339 => match DateFormat::default() {
+                DateFormat::Custom(pattern) => pattern,
+                DateFormat::Rfc3339 => String::new(),
+            },
+        };
+        self.options = options;
+    }
+
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        repo: &dyn Repository,
+        query: &Query,
+    ) -> RepoResult<()> {
+        let mut open = self.open;
+        let mut result = Ok(());
+        egui::Window::new("Export")
+            .open(&mut open)
+            .default_width(380.0)
+            .show(ctx, |ui| {
+                result = self.export_ui(ui, repo, query);
+            });
+        self.open = open;
+        result
+    }
+
+    fn export_ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        repo: &dyn Repository,
+        query: &Query,
+    ) -> RepoResult<()> {
+        if !self.loaded {
+            self.reload(repo)?;
+        }
+        self.presets_ui(ui, repo)?;
+        ui.separator();
+
+        egui::Grid::new("export_options")
+            .num_columns(2)
+            .spacing([12.0, 6.0])
+            .show(ui, |ui| {
+                ui.label("Format:");
+                egui::ComboBox::from_id_source("export_format")
+                    .selected_text(self.options.format.as_str())
+                    .show_ui(ui, |ui| {
+                        for format in ExportFormat::ALL {
+                            ui.selectable_value(&mut self.options.format, format, format.as_str());
+                        }
+                    });
+                ui.end_row();
+
+                if self.options.format == ExportFormat::Csv {
+                    ui.label("Delimiter:");
+                    ui.horizontal(|ui| {
+                        for (delimiter, name) in DELIMITERS {
+                            ui.radio_value(&mut self.options.delimiter, delimiter, name);
+                        }
+                    });
+                    ui.end_row();
+                }
+                if self.options.format.is_delimited() {
+                    ui.label("Encoding:");
+                    ui.checkbox(&mut self.options.bom, "UTF-8 with BOM")
+                        .on_hover_text("Lets Excel detect the encoding when opening the file");
+                    ui.end_row();
+                }
+                if self.options.format != ExportFormat::Xlsx {
+                    ui.label("Dates:");
+                    ui.vertical(|ui| self.date_format_ui(ui));
+                    ui.end_row();
+                }
+            });
+
+        ui.separator();
+        ui.label("Columns:");
+        self.columns_ui(ui);
+
+        ui.separator();
+        if ui
+            .add_enabled(
+                !self.options.columns.is_empty(),
+                egui::Button::new("Export filtered items..."),
+            )
+            .clicked()
+        {
+            self.export(repo, query)?;
+        }
+        if let Some(message) = &self.message {
+            ui.label(message);
+        }
+        Ok(())
+    }
+
+    fn presets_ui(&mut self, ui: &mut egui::Ui, repo: &dyn Repository) -> RepoResult<()> {
+        let mut chosen = None;
+        ui.horizontal(|ui| {
+            ui.label("Preset:");
+            egui::ComboBox::from_id_source("export_preset")
+                .selected_text("Load...")
+                .show_ui(ui, |ui| {
+                    for (i, preset) in self.presets.iter().enumerate() {
+                        if ui.selectable_label(false, &preset.name).clicked() {
+                            chosen = Some(i);
+                        }
+                    }
+                });
+            ui.add(
+                TextEdit::singleline(&mut self.preset_name)
+                    .hint_text("Preset name")
+                    .desired_width(120.0),
+            );
+        });
+        if let Some(i) = chosen {
+            let preset = self.presets[i].clone();
+            self.preset_name = preset.name;
+            self.set_options(preset.options);
+        }
+
+        let name = self.preset_name.trim().to_string();
+        let existing = self.presets.iter().find(|p| p.name == name).cloned();
+        ui.horizontal(|ui| -> RepoResult<()> {
+            let label = if existing.is_some() {
+                "Update preset"
+            } else {
+                "Save preset"
+            };
+            if ui
+                .add_enabled(!name.is_empty(), egui::Button::new(label))
+                .clicked()
+            {
+                repo.save_export_preset(&mut ExportPreset {
+                    id: None,
+                    name: name.clone(),
+                    options: self.options.clone(),
+                })?;
+                self.presets = repo.list_export_presets()?;
+            }
+            if let Some(id) = existing.and_then(|p| p.id) {
+                if ui.button("Delete preset").clicked() {
+                    repo.delete_export_preset(id)?;
+                    self.presets = repo.list_export_presets()?;
+                }
+            }
+            Ok(())
+        })
+        .inner
+    }
+
+    fn date_format_ui(&mut self, ui: &mut egui::Ui) {
+        let rfc3339 = self.options.date_format == DateFormat::Rfc3339;
+        if ui.radio(rfc3339, "RFC 3339").clicked() {
+            self.options.date_format = DateFormat::Rfc3339;
+        }
+        ui.horizontal(|ui| {
+            if ui.radio(!rfc3339, "Custom:").clicked() {
+                self.options.date_format = DateFormat::Custom(self.custom_date.clone());
+            }
+            let edit = ui
+                .add(TextEdit::singleline(&mut self.custom_date).desired_width(140.0))
+                .on_hover_text("strftime pattern, e.g. %d.%m.%Y or %Y-%m-%d %H:%M");
+            if edit.changed() {
+                self.options.date_format = DateFormat::Custom(self.custom_date.clone());
+            }
+        });
+        match self.options.date_format.validate() {
+            Ok(()) => ui.weak(format!(
+                "e.g. {}",
+                self.options.date_format.format(&Local::now())
+            )),
+            Err(e) => ui.colored_label(ui.visuals().error_fg_color, e),
+        };
+    }
+
+    fn columns_ui(&mut self, ui: &mut egui::Ui) {
+        let columns = &mut self.options.columns;
+        let mut toggled = None;
+        let mut moved = None;
+        let count = columns.len();
+        for (i, column) in columns.iter().enumerate() {
+            ui.horizontal(|ui| {
+                if !ui.checkbox(&mut true, column.as_str()).changed() {
+                    if ui
+                        .add_enabled(i > 0, egui::Button::new("⬆").small())
+                        .clicked()
+                    {
+                        moved = Some((i, i - 1));
+                    }
+                    if ui
+                        .add_enabled(i + 1 < count, egui::Button::new("⬇").small())
+                        .clicked()
+                    {
+                        moved = Some((i, i + 1));
+                    }
+                } else {
+                    toggled = Some(*column);
+                }
+            });
+        }
+        for column in ItemColumn::ALL {
+            if !columns.contains(&column) && ui.checkbox(&mut false, column.as_str()).changed() {
+                toggled = Some(column);
+            }
+        }
+        if let Some((from, to)) = moved {
+            columns.swap(from, to);
+        }
+        if let Some(column) = toggled {
+            match columns.iter().position(|c| *c == column) {
+                Some(i) => {
+                    columns.remove(i);
+                }
+                None => columns.push(column),
+            }
+        }
+    }
+
+    fn export(&mut self, repo: &dyn Repository, query: &Query) -> RepoResult<()> {
+        if let Err(e) = self.options.date_format.validate() {
+            self.message = Some(e);
+            return Ok(());
+        }
+        let format = self.options.format;
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter(format.as_str(), &[format.extension()])
+            .set_file_name(format!(
+                "export_{}.{}",
+                Local::now().format("%Y%m%d_%H%M%S"),
+                format.extension()
+            ))
+            .save_file()
+        else {
+            return Ok(());
+        };
+        let items = repo.list(&query.unpaged())?;
+        let table = export::item_table(&items, &self.options.columns);
+        self.message = Some(match export::write_table(&table, &self.options, &path) {
+            Ok(()) => format!("Exported {} items to {}", items.len(), path.display()),
+            Err(e) => format!("Export failed: {}", e),
+        });
+        repo.set_setting(LAST_OPTIONS_KEY, &serde_json::to_string(&self.options)?)
+    }
+}
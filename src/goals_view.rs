@@ -0,0 +1,233 @@
+use crate::models::{Category, Goal, Pace};
+use crate::repo::{RepoError, RepoResult, Repository};
+use chrono::{Datelike, Local, NaiveDate};
+use eframe::egui::{self, Color32, RichText, TextEdit};
+
+/// Goals window plus the compact progress shown in the top panel.
+pub struct GoalsView {
+    pub open: bool,
+    goals: Vec<(Goal, usize)>,
+    draft: Goal,
+    target: String,
+    start: String,
+    end: String,
+}
+
+impl Default for GoalsView {
+    fn default() -> Self {
+        let (start, end) = Goal::year_range(Goal::current_year());
+        Self {
+            open: false,
+            goals: vec![],
+            draft: Goal::new("", 12, start, end),
+            target: "12".into(),
+            start: start.to_string(),
+            end: end.to_string(),
+        }
+    }
+}
+
+impl GoalsView {
+    pub fn reload(&mut self, repo: &dyn Repository) -> RepoResult<()> {
+        self.goals = repo
+            .list_goals()?
+            .into_iter()
+            .map(|g| repo.goal_progress(&g).map(|done| (g, done)))
+            .collect::<RepoResult<_>>()?;
+        Ok(())
+    }
+
+    /// One short progress label per goal that is currently running.
+    pub fn summary_ui(&mut self, ui: &mut egui::Ui) {
+        let today = Local::now().date_naive();
+        for (goal, done) in &self.goals {
+            if today < goal.start || today > goal.end {
+                continue;
+            }
+            let pace = goal.pace(*done, today);
+            let response = ui.add(
+                egui::Label::new(
+                    RichText::new(format!("{}: {}/{}", goal.name, done, goal.target))
+                        .small()
+                        .color(pace_color(pace)),
+                )
+                .sense(egui::Sense::click()),
+            );
+            if response
+                .on_hover_text(pace_text(goal, *done, today))
+                .clicked()
+            {
+                self.open = true;
+            }
+        }
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context, repo: &dyn Repository) -> RepoResult<()> {
+        let mut open = self.open;
+        let mut result = Ok(());
+        egui::Window::new("Goals")
+            .open(&mut open)
+            .default_width(420.0)
+            .show(ctx, |ui| {
+                result = self.goals_ui(ui, repo);
+            });
+        self.open = open;
+        result
+    }
+
+    fn goals_ui(&mut self, ui: &mut egui::Ui, repo: &dyn Repository) -> RepoResult<()> {
+        let today = Local::now().date_naive();
+        let mut delete = None;
+        if self.goals.is_empty() {
+            ui.weak("No goals yet.");
+        }
+        for (goal, done) in &self.goals {
+            ui.horizontal(|ui| {
+                ui.strong(&goal.name);
+                ui.weak(format!("{} – {}", goal.start, goal.end));
+                if ui.small_button("🗑").on_hover_text("Delete goal").clicked() {
+                    delete = goal.id;
+                }
+            });
+            let fraction = *done as f32 / goal.target.max(1) as f32;
+            ui.add(
+                egui::ProgressBar::new(fraction.min(1.0)).text(format!("{}/{}", done, goal.target)),
+            );
+            ui.colored_label(
+                pace_color(goal.pace(*done, today)),
+                pace_text(goal, *done, today),
+            );
+            ui.add_space(4.0);
+        }
+        if let Some(id) = delete {
+            repo.delete_goal(id)?;
+            self.reload(repo)?;
+        }
+
+        ui.separator();
+        ui.strong("New goal");
+        egui::Grid::new("new_goal_grid")
+            .num_columns(2)
+            .spacing([8.0, 6.0])
+            .show(ui, |ui| {
+                ui.label("Name:");
+                ui.add(TextEdit::singleline(&mut self.draft.name).hint_text("Read 24 books"));
+                ui.end_row();
+
+                ui.label("Target:");
+                ui.add(TextEdit::singleline(&mut self.target).desired_width(60.0));
+                ui.end_row();
+
+                ui.label("Category:");
+                egui::ComboBox::from_id_source("new_goal_cat")
+                    .selected_text(
+                        self.draft
+                            .category
+                            .map(|c| c.to_string())
+                            .unwrap_or_else(|| "Any".into()),
+                    )
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut self.draft.category, None, "Any");
+                        for c in Category::ALL {
+                            ui.selectable_value(&mut self.draft.category, Some(c), c.to_string());
+                        }
+                    });
+                ui.end_row();
+
+                ui.label("From:");
+                ui.add(TextEdit::singleline(&mut self.start).desired_width(90.0));
+                ui.end_row();
+
+                ui.label("To:");
+                ui.add(TextEdit::singleline(&mut self.end).desired_width(90.0));
+                ui.end_row();
+            });
+        ui.horizontal(|ui| {
+            let year = Goal::current_year();
+            let mut range = None;
+            if ui.small_button(year.to_string()).clicked() {
+                range = Some(Goal::year_range(year));
+            }
+            for q in 1..=4 {
+                if ui.small_button(format!("Q{}", q)).clicked() {
+                    range = Some(Goal::quarter_range(year, q));
+                }
+            }
+            if let Some((start, end)) = range {
+                self.start = start.to_string();
+                self.end = end.to_string();
+            }
+        });
+        ui.checkbox(
+            &mut self.draft.backlog_only,
+            "Only items already in the backlog",
+        )
+        .on_hover_text("Count only items added before the goal starts");
+
+        if ui.button("Add goal").clicked() {
+            match self.parse_draft() {
+                Ok(mut goal) => {
+                    repo.save_goal(&mut goal)?;
+                    self.draft.name.clear();
+                    self.reload(repo)?;
+                }
+                Err(msg) => return Err(RepoError::Other(msg)),
+            }
+        }
+        Ok(())
+    }
+
+    fn parse_draft(&self) -> Result<Goal, String> {
+        let name = self.draft.name.trim();
+        if name.is_empty() {
+            return Err("Goal name cannot be empty".into());
+        }
+        let target: u32 = match self.target.trim().parse() {
+            Ok(n) if n > 0 => n,
+            _ => return Err("Goal target must be a positive number".into()),
+        };
+        let parse_date = |s: &str| {
+            NaiveDate::parse_from_str(s.trim(), "%Y-%m-%d")
+                .map_err(|_| format!("`{}` is not a date (YYYY-MM-DD)", s.trim()))
+        };
+        let start = parse_date(&self.start)?;
+        let end = parse_date(&self.end)?;
+        if end < start {
+            return Err("Goal ends before it starts".into());
+        }
+        Ok(Goal {
+            name: name.to_string(),
+            target,
+            start,
+            end,
+            ..self.draft.clone()
+        })
+    }
+}
+
+fn pace_color(pace: Pace) -> Color32 {
+    match pace {
+        Pace::Ahead | Pace::Reached => Color32::LIGHT_GREEN,
+        Pace::OnTrack => Color32::LIGHT_BLUE,
+        Pace::Behind | Pace::Missed => Color32::LIGHT_RED,
+        Pace::NotStarted => Color32::GRAY,
+    }
+}
+
+fn pace_text(goal: &Goal, done: usize, today: NaiveDate) -> String {
+    let pace = goal.pace(done, today);
+    match pace {
+        Pace::Ahead | Pace::OnTrack | Pace::Behind => {
+            let days_left = (goal.end - today).num_days() + 1;
+            format!(
+                "{} (expected {:.1} by now, {} to go in {} days)",
+                pace.as_str(),
+                goal.expected_by(today),
+                goal.target as usize - done,
+                days_left
+            )
+        }
+        Pace::NotStarted => format!("starts {}", goal.start.format("%b %-d, %Y")),
+        Pace::Reached | Pace::Missed => format!("{} ({})", pace.as_str(), goal.end.year()),
+    }
+}
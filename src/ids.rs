@@ -0,0 +1,432 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use thiserror::Error;
+
+/// Catalog or service an external id belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum IdScheme {
+    Isbn,
+    Imdb,
+    Tmdb,
+    Igdb,
+    MusicBrainz,
+    OpenLibrary,
+    Goodreads,
+    Letterboxd,
+    Mal,
+    AniList,
+    /// EAN-13 product barcode; UPC-A codes get a leading zero.
+    Ean,
+}
+
+impl IdScheme {
+    pub const ALL: [IdScheme; 11] = [
+        IdScheme::Isbn,
+        IdScheme::Imdb,
+        IdScheme::Tmdb,
+        IdScheme::Igdb,
+        IdScheme::MusicBrainz,
+        IdScheme::OpenLibrary,
+        IdScheme::Goodreads,
+        IdScheme::Letterboxd,
+        IdScheme::Mal,
+        IdScheme::AniList,
+        IdScheme::Ean,
+    ];
+
+    /// Prefix in the `scheme:value` form, as stored.
+    pub fn as_key(&self) -> &'static str {
+        match self {
+            IdScheme::Isbn => "isbn",
+            IdScheme::Imdb => "imdb",
+            IdScheme::Tmdb => "tmdb",
+            IdScheme::Igdb => "igdb",
+            IdScheme::MusicBrainz => "musicbrainz",
+            IdScheme::OpenLibrary => "openlibrary",
+            IdScheme::Goodreads => "goodreads",
+            IdScheme::Letterboxd => "letterboxd",
+            IdScheme::Mal => "mal",
+            IdScheme::AniList => "anilist",
+            IdScheme::Ean => "ean",
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IdScheme::Isbn => "ISBN",
+            IdScheme::Imdb => "IMDb",
+            IdScheme::Tmdb => "TMDB",
+            IdScheme::Igdb => "IGDB",
+            IdScheme::MusicBrainz => "MusicBrainz",
+            IdScheme::OpenLibrary => "Open Library",
+            IdScheme::Goodreads => "Goodreads",
+            IdScheme::Letterboxd => "Letterboxd",
+            IdScheme::Mal => "MyAnimeList",
+            IdScheme::AniList => "AniList",
+            IdScheme::Ean => "EAN/UPC",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<IdScheme> {
+        IdScheme::ALL
+            .into_iter()
+            .find(|s| s.as_key().eq_ignore_ascii_case(key.trim()))
+    }
+}
+
+impl fmt::Display for IdScheme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum IdError {
+    #[error("Unknown id scheme `{0}`")]
+    UnknownScheme(String),
+    #[error("`{value}` is not a valid {scheme} id")]
+    Invalid { scheme: IdScheme, value: String },
+}
+
+/// Stable id of an item in another catalog, such as an ISBN or IMDb id.
+/// Values are normalized, so equal ids compare equal: ISBN-10s become
+/// ISBN-13s and ids may be given as links to the service.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct ExternalId {
+    pub scheme: IdScheme,
+    pub value: String,
+}
+
+impl ExternalId {
+    pub fn new(scheme: IdScheme, value: &str) -> Result<Self, IdError> {
+        normalize(scheme, value.trim())
+            .map(|value| Self { scheme, value })
+            .ok_or_else(|| IdError::Invalid {
+                scheme,
+                value: value.trim().to_string(),
+            })
+    }
+
+    /// Parses the `scheme:value` form, e.g. `isbn:9780441172719` or `mal:anime:1`.
+    pub fn parse(s: &str) -> Result<Self, IdError> {
+        let (scheme, value) = s.split_once(':').unwrap_or((s, ""));
+        let scheme = IdScheme::from_key(scheme)
+            .ok_or_else(|| IdError::UnknownScheme(scheme.trim().to_string()))?;
+        ExternalId::new(scheme, value)
+    }
+
+    /// A scanned or typed barcode: an ISBN for books (EAN-13 starting with
+    /// 978 or 979, or an ISBN-10), otherwise an EAN-13 or UPC-A product code.
+    pub fn from_barcode(code: &str) -> Result<Self, IdError> {
+        let ean = ExternalId::new(IdScheme::Ean, code);
+        match &ean {
+            Ok(id) if id.value.starts_with("978") || id.value.starts_with("979") => {
+                ExternalId::new(IdScheme::Isbn, &id.value)
+            }
+            Ok(_) => ean,
+            Err(_) => ExternalId::new(IdScheme::Isbn, code).map_err(|_| IdError::Invalid {
+                scheme: IdScheme::Ean,
+                value: code.trim().to_string(),
+            }),
+        }
+    }
+
+    /// Page for the id on the service's website, where there is one.
+    pub fn url(&self) -> Option<String> {
+        let v = &self.value;
+        Some(match self.scheme {
+            IdScheme::Isbn => format!("https://openlibrary.org/isbn/{}", v),
+            IdScheme::Imdb => format!("https://www.imdb.com/title/{}/", v),
+            IdScheme::Tmdb => match v.strip_prefix("tv:") {
+                Some(id) => format!("https://www.themoviedb.org/tv/{}", id),
+                None => format!("https://www.themoviedb.org/movie/{}", v),
+            },
+            IdScheme::Igdb if !v.chars().all(|c| c.is_ascii_digit()) => {
+                format!("https://www.igdb.com/games/{}", v)
+            }
+            IdScheme::MusicBrainz => format!("https://musicbrainz.org/release/{}", v),
+            IdScheme::OpenLibrary => {
+                let kind = match v.chars().last() {
+                    Some('W') => "works",
+                    Some('A') => "authors",
+                    _ => "books",
+                };
+                format!("https://openlibrary.org/{}/{}", kind, v)
+            }
+            IdScheme::Goodreads => format!("https://www.goodreads.com/book/show/{}", v),
+            IdScheme::Mal => format!("https://myanimelist.net/{}", v.replace(':', "/")),
+            IdScheme::AniList => format!("https://anilist.co/{}", v.replace(':', "/")),
+            // Older Letterboxd imports made ids up from title and year.
+            IdScheme::Igdb | IdScheme::Letterboxd | IdScheme::Ean => return None,
+        })
+    }
+}
+
+impl fmt::Display for ExternalId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.scheme.as_key(), self.value)
+    }
+}
+
+impl From<ExternalId> for String {
+    fn from(id: ExternalId) -> Self {
+        id.to_string()
+    }
+}
+
+impl TryFrom<String> for ExternalId {
+    type Error = IdError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        ExternalId::parse(&s)
+    }
+}
+
+fn normalize(scheme: IdScheme, value: &str) -> Option<String> {
+    if value.is_empty() {
+        return None;
+    }
+    let lower = value.to_lowercase();
+    // The id part of a link such as https://www.imdb.com/title/tt0133093/.
+    let last = lower
+        .trim_end_matches('/')
+        .rsplit('/')
+        .next()
+        .unwrap_or_default();
+    match scheme {
+        IdScheme::Isbn => isbn(value),
+        IdScheme::Imdb => lower.match_indices("tt").find_map(|(i, _)| {
+            let digits = leading_digits(&lower[i + 2..]);
+            (digits.len() >= 7).then(|| format!("tt{}", digits))
+        }),
+        IdScheme::Tmdb => {
+            let (tv, rest) = after_kind(&lower, &["tv", "movie"])
+                .map(|(kind, rest)| (kind == "tv", rest))
+                .unwrap_or((false, last));
+            let id = leading_digits(rest);
+            (!id.is_empty()).then(|| {
+                if tv {
+                    format!("tv:{}", id)
+                } else {
+                    id.to_string()
+                }
+            })
+        }
+        IdScheme::Igdb => {
+            let valid = last.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+            (valid && !last.is_empty()).then(|| last.to_string())
+        }
+        // Short links (https://boxd.it/2bbS) are case-sensitive.
+        IdScheme::Letterboxd => {
+            let last = value.trim_end_matches('/').rsplit('/').next()?;
+            let valid = last.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+            (valid && !last.is_empty()).then(|| last.to_string())
+        }
+        IdScheme::MusicBrainz => {
+            let valid = last.len() == 36
+                && last.char_indices().all(|(i, c)| match i {
+                    8 | 13 | 18 | 23 => c == '-',
+                    _ => c.is_ascii_hexdigit(),
+                });
+            valid.then(|| last.to_string())
+        }
+        IdScheme::OpenLibrary => {
+            let id = last.to_uppercase();
+            let digits = id
+                .strip_prefix("OL")
+                .map(leading_digits)
+                .unwrap_or_default();
+            let valid = !digits.is_empty() && matches!(&id[2 + digits.len()..], "W" | "M" | "A");
+            valid.then_some(id)
+        }
+        IdScheme::Goodreads => {
+            let id = leading_digits(last);
+            (!id.is_empty()).then(|| id.to_string())
+        }
+        IdScheme::Mal | IdScheme::AniList => {
+            let (kind, rest) = after_kind(&lower, &["anime", "manga"])?;
+            let id = leading_digits(rest);
+            (!id.is_empty()).then(|| format!("{}:{}", kind, id))
+        }
+        IdScheme::Ean => ean(value),
+    }
+}
+
+// The first of `kinds` that `s` starts with ("tv:1396") or has as a path
+// segment (".../tv/1396-name"), and what follows it.
+fn after_kind<'a>(s: &'a str, kinds: &[&'static str]) -> Option<(&'static str, &'a str)> {
+    kinds.iter().find_map(|&kind| {
+        if let Some(rest) = s
+            .strip_prefix(kind)
+            .and_then(|r| r.strip_prefix([':', '/']))
+        {
+            return Some((kind, rest));
+        }
+        let i = s.find(&format!("/{}/", kind))?;
+        Some((kind, &s[i + kind.len() + 2..]))
+    })
+}
+
+fn leading_digits(s: &str) -> &str {
+    let end = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    &s[..end]
+}
+
+/// ISBN-10 or ISBN-13 with a valid check digit, as ISBN-13 without hyphens.
+fn isbn(text: &str) -> Option<String> {
+    let digits: String = text
+        .chars()
+        .filter(|c| !matches!(c, '-' | ' '))
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    let value = |c: char| c.to_digit(10);
+    match digits.len() {
+        10 => {
+            let sum = digits.chars().enumerate().try_fold(0, |sum, (i, c)| {
+                let v = if i == 9 && c == 'X' { 10 } else { value(c)? };
+                Some(sum + v * (10 - i as u32))
+            })?;
+            if sum % 11 != 0 {
+                return None;
+            }
+            let isbn13 = format!("978{}", &digits[..9]);
+            Some(format!("{}{}", isbn13, isbn13_check(&isbn13)?))
+        }
+        13 => {
+            let check = value(digits.chars().last()?)?;
+            (isbn13_check(&digits[..12])? == check).then_some(digits)
+        }
+        _ => None,
+    }
+}
+
+/// EAN-13 or UPC-A with a valid check digit, as EAN-13.
+fn ean(text: &str) -> Option<String> {
+    let digits: String = text.chars().filter(|c| !matches!(c, '-' | ' ')).collect();
+    let digits = match digits.len() {
+        12 => format!("0{}", digits),
+        13 => digits,
+        _ => return None,
+    };
+    let check = digits.chars().last()?.to_digit(10)?;
+    (isbn13_check(&digits[..12])? == check).then_some(digits)
+}
+
+// ISBN-13 is an EAN-13, so this is also the EAN check digit.
+fn isbn13_check(first12: &str) -> Option<u32> {
+    let sum = first12.chars().enumerate().try_fold(0, |sum, (i, c)| {
+        Some(sum + c.to_digit(10)? * if i % 2 == 0 { 1 } else { 3 })
+    })?;
+    Some((10 - sum % 10) % 10)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn id(s: &str) -> Result<String, IdError> {
+        ExternalId::parse(s).map(|id| id.to_string())
+    }
+
+    #[test]
+    fn checks_isbn_digits_and_stores_isbn_13() {
+        assert_eq!(id("isbn:0-441-17271-7").unwrap(), "isbn:9780441172719");
+        assert_eq!(id("isbn:978 0441 172719").unwrap(), "isbn:9780441172719");
+        assert_eq!(id("isbn:080442957x").unwrap(), "isbn:9780804429573");
+        assert_eq!(id("ISBN:9791032305690").unwrap(), "isbn:9791032305690");
+        for bad in [
+            "0441172718",
+            "9780441172710",
+            "044117271",
+            "08044295X7",
+            "97804411727a9",
+        ] {
+            assert_eq!(
+                id(&format!("isbn:{}", bad)),
+                Err(IdError::Invalid {
+                    scheme: IdScheme::Isbn,
+                    value: bad.to_string()
+                })
+            );
+        }
+        assert_eq!(
+            ExternalId::parse("isbn:0441172717").unwrap(),
+            ExternalId::parse("isbn:9780441172719").unwrap()
+        );
+    }
+
+    #[test]
+    fn normalizes_ids_and_links() {
+        for (given, stored) in [
+            (
+                "imdb:https://www.imdb.com/title/tt0133093/?ref_=nv",
+                "imdb:tt0133093",
+            ),
+            (
+                "tmdb:https://www.themoviedb.org/tv/1396-breaking-bad",
+                "tmdb:tv:1396",
+            ),
+            ("tmdb:603", "tmdb:603"),
+            ("openlibrary:/works/ol27448w", "openlibrary:OL27448W"),
+            (
+                "goodreads:https://www.goodreads.com/book/show/8855321-leviathan",
+                "goodreads:8855321",
+            ),
+            (
+                "mal:https://myanimelist.net/anime/5114/Fullmetal",
+                "mal:anime:5114",
+            ),
+            ("anilist:manga/30013", "anilist:manga:30013"),
+            ("letterboxd:https://boxd.it/2bbS", "letterboxd:2bbS"),
+            (
+                "musicbrainz:https://musicbrainz.org/release/B84EE12A-09EF-421B-82DE-0441A926375B",
+                "musicbrainz:b84ee12a-09ef-421b-82de-0441a926375b",
+            ),
+        ] {
+            assert_eq!(id(given).unwrap(), stored, "{}", given);
+        }
+        for bad in [
+            "imdb:tt123",
+            "mal:5114",
+            "openlibrary:OL1X",
+            "musicbrainz:abc",
+            "tmdb:",
+        ] {
+            assert!(id(bad).is_err(), "{}", bad);
+        }
+        assert_eq!(id("asin:B000"), Err(IdError::UnknownScheme("asin".into())));
+    }
+
+    #[test]
+    fn links_to_service_pages() {
+        let url = |s: &str| ExternalId::parse(s).unwrap().url();
+        assert_eq!(
+            url("tmdb:tv:1396").as_deref(),
+            Some("https://www.themoviedb.org/tv/1396")
+        );
+        assert_eq!(
+            url("mal:anime:5114").as_deref(),
+            Some("https://myanimelist.net/anime/5114")
+        );
+        assert_eq!(
+            url("openlibrary:OL26320A").as_deref(),
+            Some("https://openlibrary.org/authors/OL26320A")
+        );
+        assert_eq!(url("igdb:1942"), None);
+        assert_eq!(
+            url("igdb:the-witcher-3").as_deref(),
+            Some("https://www.igdb.com/games/the-witcher-3")
+        );
+    }
+
+    #[test]
+    fn round_trips_through_serde() {
+        let ids = vec![ExternalId::parse("isbn:0441172717").unwrap()];
+        let json = serde_json::to_string(&ids).unwrap();
+        assert_eq!(json, r#"["isbn:9780441172719"]"#);
+        let back: Vec<ExternalId> = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, ids);
+        assert!(serde_json::from_str::<ExternalId>(r#""isbn:123""#).is_err());
+    }
+}
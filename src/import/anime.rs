@@ -0,0 +1,412 @@
+use super::{notes, read_text, tag, CategoryChoice};
+use crate::ids::{ExternalId, IdScheme};
+use crate::models::{Category, MediaItem, Status};
+use crate::util::parse_local_date;
+use chrono::{DateTime, Local};
+use flate2::read::GzDecoder;
+use serde_json::Value;
+use std::error::Error;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+/// The catalog has no anime or manga category; these pick one on import.
+pub const CATEGORIES: [CategoryChoice; 2] = [
+    CategoryChoice {
+        label: "Anime",
+        tag: "anime",
+        setting: "import.category.anime",
+        category: Category::Movie,
+    },
+    CategoryChoice {
+        label: "Manga",
+        tag: "manga",
+        setting: "import.category.manga",
+        category: Category::Book,
+    },
+];
+
+// Everything both formats say about one list entry.
+#[derive(Default)]
+struct Entry {
+    source: &'static str,
+    kind: &'static str,
+    external_id: Option<ExternalId>,
+    title: String,
+    status: String,
+    score: Option<f64>,
+    progress: Option<u32>,
+    total: Option<u32>,
+    started: Option<DateTime<Local>>,
+    finished: Option<DateTime<Local>>,
+    rewatched: bool,
+    comments: String,
+    tags: Vec<String>,
+}
+
+/// Reads a MyAnimeList anime or manga list export (`.xml` or `.xml.gz`).
+pub fn read_mal(path: &Path) -> Result<Vec<MediaItem>, Box<dyn Error>> {
+    let gzipped = path
+        .extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("gz"));
+    let text = if gzipped {
+        let mut text = String::new();
+        GzDecoder::new(File::open(path)?).read_to_string(&mut text)?;
+        text
+    } else {
+        read_text(path)?
+    };
+    let doc = roxmltree::Document::parse(&text)?;
+    let mut items = vec![];
+    for node in doc.root_element().children().filter(|n| n.is_element()) {
+        let field = |name: &str| {
+            node.children()
+                .find(|c| c.has_tag_name(name))
+                .and_then(|c| c.text())
+                .map(|t| t.trim().to_string())
+                .unwrap_or_default()
+        };
+        let count = |name: &str| field(name).parse::<u32>().ok().filter(|n| *n > 0);
+        let entry = match node.tag_name().name() {
+            "anime" => Entry {
+                kind: "anime",
+                external_id: mal_id("anime", &field("series_animedb_id")),
+                title: field("series_title"),
+                progress: count("my_watched_episodes"),
+                total: count("series_episodes"),
+                rewatched: count("my_times_watched").is_some() || field("my_rewatching") == "1",
+                ..Entry::default()
+            },
+            "manga" => Entry {
+                kind: "manga",
+                external_id: mal_id("manga", &field("manga_mangadb_id")),
+                title: field("manga_title"),
+                progress: count("my_read_chapters"),
+                total: count("manga_chapters"),
+                rewatched: count("my_times_read").is_some() || field("my_rereading") == "1",
+                ..Entry::default()
+            },
+            _ => continue,
+        };
+        items.push(to_item(Entry {
+            source: "mal",
+            status: mal_status(&field("my_status")).to_string(),
+            score: field("my_score").parse().ok(),
+            // Unknown dates are written as 0000-00-00.
+            started: parse_local_date(&field("my_start_date")),
+            finished: parse_local_date(&field("my_finish_date")),
+            comments: field("my_comments"),
+            tags: field("my_tags")
+                .split(',')
+                .filter(|t| !t.trim().is_empty())
+                .map(tag)
+                .collect(),
+            ..entry
+        }));
+    }
+    Ok(items)
+}
+
+/// Reads an AniList list as JSON: the `MediaListCollection` answer of the
+/// GraphQL API, or any JSON containing its list entries.
+pub fn read_anilist(path: &Path) -> Result<Vec<MediaItem>, Box<dyn Error>> {
+    let json: Value = serde_json::from_str(&read_text(path)?)?;
+    let mut entries = vec![];
+    collect_entries(&json, &mut entries);
+    // Scores follow the user's scoring system, which only the user's
+    // `mediaListOptions` tell; a 3 can be a smiley, stars or a tenth.
+    let scored = entries
+        .iter()
+        .any(|e| e["score"].as_f64().is_some_and(|s| s > 0.0));
+    let scale = match find_key(&json, "scoreFormat").and_then(Value::as_str) {
+        Some(format) => {
+            score_scale(format).ok_or_else(|| format!("Unknown AniList score format {}", format))?
+        }
+        None if scored => {
+            return Err(
+                "The file does not say which scoring system the scores use. \
+                        Add `user { mediaListOptions { scoreFormat } }` to the \
+                        MediaListCollection query, or \"scoreFormat\": \"POINT_10\" \
+                        (or POINT_100, POINT_10_DECIMAL, POINT_5, POINT_3) to the file."
+                    .into(),
+            )
+        }
+        None => 1.0,
+    };
+    let mut items = vec![];
+    for e in entries {
+        let media = &e["media"];
+        let title = ["userPreferred", "romaji", "english", "native"]
+            .iter()
+            .find_map(|k| media["title"][*k].as_str().filter(|t| !t.trim().is_empty()))
+            .unwrap_or_default();
+        if title.is_empty() {
+            continue;
+        }
+        let manga = media["type"].as_str() == Some("MANGA");
+        let kind = if manga { "manga" } else { "anime" };
+        let count = |v: &Value| v.as_u64().map(|n| n as u32).filter(|n| *n > 0);
+        // Prefer the MyAnimeList id so both importers recognise the same entry.
+        let external_id = match (media["idMal"].as_u64(), media["id"].as_u64()) {
+            (Some(mal), _) => mal_id(kind, &mal.to_string()),
+            (None, Some(id)) => {
+                ExternalId::new(IdScheme::AniList, &format!("{}:{}", kind, id)).ok()
+            }
+            (None, None) => None,
+        };
+        items.push(to_item(Entry {
+            source: "anilist",
+            kind,
+            external_id,
+            title: title.to_string(),
+            status: anilist_status(e["status"].as_str().unwrap_or_default()).to_string(),
+            score: e["score"].as_f64().map(|s| s * scale),
+            progress: count(&e["progress"]),
+            total: count(if manga {
+                &media["chapters"]
+            } else {
+                &media["episodes"]
+            }),
+            started: fuzzy_date(&e["startedAt"]),
+            finished: fuzzy_date(&e["completedAt"]),
+            rewatched: e["repeat"].as_u64().is_some_and(|n| n > 0)
+                || e["status"].as_str() == Some("REPEATING"),
+            comments: e["notes"].as_str().unwrap_or_default().to_string(),
+            tags: vec![],
+        }));
+    }
+    Ok(items)
+}
+
+// List entries are objects with a `status` and a `media` object, wherever
+// they are nested.
+fn collect_entries<'a>(value: &'a Value, out: &mut Vec<&'a Value>) {
+    match value {
+        Value::Object(map) if map.contains_key("status") && map["media"].is_object() => {
+            out.push(value)
+        }
+        Value::Object(map) => map.values().for_each(|v| collect_entries(v, out)),
+        Value::Array(list) => list.iter().for_each(|v| collect_entries(v, out)),
+        _ => {}
+    }
+}
+
+// First value under `key` anywhere in `value`.
+fn find_key<'a>(value: &'a Value, key: &str) -> Option<&'a Value> {
+    match value {
+        Value::Object(map) => map
+            .get(key)
+            .or_else(|| map.values().find_map(|v| find_key(v, key))),
+        Value::Array(list) => list.iter().find_map(|v| find_key(v, key)),
+        _ => None,
+    }
+}
+
+// Factor from an AniList `ScoreFormat` to the catalog's 1 to 10.
+fn score_scale(format: &str) -> Option<f64> {
+    Some(match format {
+        "POINT_100" => 0.1,
+        "POINT_10_DECIMAL" | "POINT_10" => 1.0,
+        "POINT_5" => 2.0,
+        "POINT_3" => 10.0 / 3.0,
+        _ => return None,
+    })
+}
+
+// MAL statuses are names in current exports and numbers in old ones.
+fn mal_status(status: &str) -> &'static str {
+    match status.to_lowercase().as_str() {
+        "1" | "watching" | "reading" => "watching",
+        "2" | "completed" => "completed",
+        "3" | "on-hold" => "on-hold",
+        "4" | "dropped" => "dropped",
+        _ => "planned",
+    }
+}
+
+fn anilist_status(status: &str) -> &'static str {
+    match status {
+        "CURRENT" | "REPEATING" => "watching",
+        "COMPLETED" => "completed",
+        "PAUSED" => "on-hold",
+        "DROPPED" => "dropped",
+        _ => "planned",
+    }
+}
+
+fn mal_id(kind: &str, id: &str) -> Option<ExternalId> {
+    ExternalId::new(IdScheme::Mal, &format!("{}:{}", kind, id)).ok()
+}
+
+// Items start in `Other`; the preview moves them to the chosen category.
+fn to_item(entry: Entry) -> MediaItem {
+    let mut item = MediaItem::new(entry.title.trim(), Category::Other);
+    item.external_ids.extend(entry.external_id);
+    let (started, finished) = (entry.started, entry.finished);
+    if let Some(created) = started.or(finished) {
+        item.created_at = created;
+    }
+    let mut tags = vec![tag(entry.source), tag(entry.kind)];
+    // On hold and dropped entries were started but not finished; the tag
+    // keeps them apart from ones being watched.
+    match entry.status.as_str() {
+        "completed" => {
+            item.status = Status::Finished;
+            item.started_at = started;
+            item.finished_at = finished.or(Some(item.created_at));
+        }
+        "watching" | "on-hold" | "dropped" => {
+            item.status = Status::InProgress;
+            item.started_at = started.or(Some(item.created_at));
+            if entry.status != "watching" {
+                tags.push(tag(&entry.status));
+            }
+        }
+        _ => item.status = Status::Planned,
+    }
+    item.rating = entry
+        .score
+        .filter(|s| *s > 0.0)
+        .map(|s| s.round().clamp(1.0, 10.0) as u8);
+    item.progress = entry.progress;
+    item.progress_total = entry.total;
+    if entry.rewatched {
+        tags.push(tag("rewatch"));
+    }
+    for t in entry.tags {
+        if !tags.contains(&t) {
+            tags.push(t);
+        }
+    }
+    item.notes = notes(&[entry.comments, tags.join(" ")]);
+    item
+}
+
+// AniList dates are `{ "year": 2021, "month": 4, "day": null }`.
+fn fuzzy_date(v: &Value) -> Option<DateTime<Local>> {
+    let part = |k: &str| v[k].as_u64();
+    parse_local_date(&format!(
+        "{}-{:02}-{:02}",
+        part("year")?,
+        part("month").unwrap_or(1),
+        part("day").unwrap_or(1)
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn temp_file(name: &str, contents: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("anime-{}-{}", std::process::id(), name));
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    fn anilist(name: &str, json: &str) -> Result<Vec<MediaItem>, String> {
+        let path = temp_file(name, json);
+        let items = read_anilist(&path).map_err(|e| e.to_string());
+        std::fs::remove_file(&path).unwrap();
+        items
+    }
+
+    fn list(format: &str, score: f64) -> String {
+        format!(
+            r#"{{"data": {{"MediaListCollection": {{
+                "user": {{"mediaListOptions": {{"scoreFormat": "{}"}}}},
+                "lists": [{{"entries": [{{
+                    "status": "COMPLETED", "score": {}, "repeat": 1,
+                    "completedAt": {{"year": 2021, "month": 4, "day": null}},
+                    "media": {{"id": 1, "idMal": null, "type": "ANIME", "episodes": 26,
+                               "title": {{"userPreferred": "Cowboy Bebop"}}}}
+                }}]}}]
+            }}}}}}"#,
+            format, score
+        )
+    }
+
+    #[test]
+    fn scales_anilist_scores_by_the_scoring_system() {
+        for (format, score, rating) in [
+            ("POINT_100", 85.0, 9),
+            ("POINT_10_DECIMAL", 7.5, 8),
+            ("POINT_10", 7.0, 7),
+            ("POINT_5", 3.0, 6),
+            ("POINT_3", 2.0, 7),
+            ("POINT_3", 3.0, 10),
+        ] {
+            let items = anilist("scored.json", &list(format, score)).unwrap();
+            assert_eq!(items[0].rating, Some(rating), "{} {}", format, score);
+        }
+        let item = &anilist("entry.json", &list("POINT_10", 0.0)).unwrap()[0];
+        assert_eq!(item.rating, None);
+        assert_eq!(item.status, Status::Finished);
+        assert_eq!(item.external_ids[0].to_string(), "anilist:anime:1");
+        assert_eq!(item.finished_at, parse_local_date("2021-04-01"));
+        assert_eq!(item.progress_total, Some(26));
+        assert_eq!(item.tags(), ["anilist", "anime", "rewatch"]);
+    }
+
+    #[test]
+    fn asks_for_the_scoring_system_when_scores_need_it() {
+        let unknown = list("POINT_100", 3.0).replace(r#""scoreFormat": "POINT_100""#, "");
+        let unknown = unknown.replace(r#""mediaListOptions": {}"#, r#""name": "x""#);
+        assert!(anilist("unknown.json", &unknown)
+            .unwrap_err()
+            .contains("scoreFormat"));
+        let unscored = unknown.replace(r#""score": 3"#, r#""score": 0"#);
+        assert_eq!(anilist("unscored.json", &unscored).unwrap().len(), 1);
+        assert!(anilist("odd.json", &list("STARS", 3.0)).is_err());
+    }
+
+    #[test]
+    fn reads_mal_anime_and_manga_lists() {
+        let path = temp_file(
+            "list.xml",
+            "<myanimelist>
+              <myinfo><user_name>me</user_name></myinfo>
+              <anime>
+                <series_animedb_id>5114</series_animedb_id>
+                <series_title>Fullmetal Alchemist: Brotherhood</series_title>
+                <series_episodes>64</series_episodes>
+                <my_watched_episodes>20</my_watched_episodes>
+                <my_start_date>2023-01-10</my_start_date>
+                <my_finish_date>0000-00-00</my_finish_date>
+                <my_score>0</my_score>
+                <my_status>On-Hold</my_status>
+                <my_tags>shonen, classic</my_tags>
+              </anime>
+              <manga>
+                <manga_mangadb_id>2</manga_mangadb_id>
+                <manga_title>Berserk</manga_title>
+                <my_read_chapters>0</my_read_chapters>
+                <my_score>9</my_score>
+                <my_status>2</my_status>
+                <my_finish_date>2022-08-01</my_finish_date>
+              </manga>
+            </myanimelist>",
+        );
+        let items = read_mal(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(items.len(), 2);
+
+        let anime = &items[0];
+        assert_eq!(anime.external_ids[0].to_string(), "mal:anime:5114");
+        assert_eq!(anime.status, Status::InProgress);
+        assert_eq!(anime.started_at, parse_local_date("2023-01-10"));
+        assert_eq!((anime.progress, anime.progress_total), (Some(20), Some(64)));
+        assert_eq!(anime.rating, None);
+        assert_eq!(
+            anime.tags(),
+            ["mal", "anime", "on-hold", "shonen", "classic"]
+        );
+
+        let manga = &items[1];
+        assert_eq!(manga.category, Category::Other);
+        assert_eq!(manga.status, Status::Finished);
+        assert_eq!(manga.rating, Some(9));
+        assert_eq!(manga.finished_at, parse_local_date("2022-08-01"));
+        assert_eq!(manga.progress, None);
+    }
+}
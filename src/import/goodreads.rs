@@ -0,0 +1,201 @@
+use super::{notes, tag};
+use crate::ids::{ExternalId, IdScheme};
+use crate::models::{Category, MediaItem, Status};
+use crate::util::parse_local_date;
+use chrono::{DateTime, Local};
+use serde::Deserialize;
+use std::error::Error;
+use std::path::Path;
+
+const EXCLUSIVE_SHELVES: [&str; 3] = ["read", "currently-reading", "to-read"];
+
+// Columns of the "Export Library" CSV; the others are ignored.
+#[derive(Deserialize)]
+struct Record {
+    #[serde(rename = "Book Id", default)]
+    book_id: String,
+    #[serde(rename = "Title")]
+    title: String,
+    #[serde(rename = "Author", default)]
+    author: String,
+    #[serde(rename = "My Rating", default)]
+    rating: Option<u8>,
+    #[serde(rename = "Date Read", default)]
+    date_read: String,
+    #[serde(rename = "Date Added", default)]
+    date_added: String,
+    #[serde(rename = "Bookshelves", default)]
+    shelves: String,
+    #[serde(rename = "Exclusive Shelf", default)]
+    exclusive_shelf: String,
+    #[serde(rename = "My Review", default)]
+    review: String,
+    #[serde(rename = "Private Notes", default)]
+    private_notes: String,
+    #[serde(rename = "ISBN", default)]
+    isbn: String,
+    #[serde(rename = "ISBN13", default)]
+    isbn13: String,
+}
+
+/// Reads a Goodreads library export into book items.
+pub fn read(path: &Path) -> Result<Vec<MediaItem>, Box<dyn Error>> {
+    let mut rdr = csv::Reader::from_path(path)?;
+    let mut items = vec![];
+    for (line, record) in rdr.deserialize::<Record>().enumerate() {
+        let record = record.map_err(|e| format!("Row {}: {}", line + 1, e))?;
+        if !record.title.trim().is_empty() {
+            items.push(to_item(record));
+        }
+    }
+    Ok(items)
+}
+
+fn to_item(record: Record) -> MediaItem {
+    let (title, series) = split_series(record.title.trim());
+    let mut item = MediaItem::new(title, Category::Book);
+    let shelf = record.exclusive_shelf.trim();
+    let added = date(&record.date_added);
+    let read = date(&record.date_read);
+    if let Some(added) = added {
+        item.created_at = added;
+    }
+    match shelf {
+        "read" => {
+            item.status = Status::Finished;
+            item.finished_at = read.or(added).or(Some(item.created_at));
+        }
+        "currently-reading" => {
+            item.status = Status::InProgress;
+            item.started_at = added.or(Some(item.created_at));
+        }
+        _ => item.status = Status::Planned,
+    }
+    let ids = [
+        (IdScheme::Goodreads, &record.book_id),
+        (IdScheme::Isbn, &record.isbn13),
+        (IdScheme::Isbn, &record.isbn),
+    ];
+    for (scheme, value) in ids {
+        // ISBNs are written as ="0441172717" so spreadsheets keep the zeros.
+        if let Ok(id) = ExternalId::new(scheme, value.trim_matches(['=', '"'])) {
+            item.add_external_id(id);
+        }
+    }
+    // Goodreads uses 0 for "not rated".
+    item.rating = record.rating.filter(|r| (1..=5).contains(r)).map(|r| r * 2);
+
+    let mut tags = vec![tag("goodreads")];
+    for name in record.shelves.split(',').chain([shelf]) {
+        let name = name.trim();
+        if name.is_empty() || EXCLUSIVE_SHELVES.contains(&name) {
+            continue;
+        }
+        let tag = tag(name);
+        if !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+    let author = record.author.trim();
+    let byline = match (author.is_empty(), series) {
+        (false, Some(series)) => format!("by {} · {}", author, series),
+        (false, None) => format!("by {}", author),
+        (true, series) => series.unwrap_or_default(),
+    };
+    item.notes = notes(&[
+        byline,
+        review_text(&record.review),
+        record.private_notes,
+        tags.join(" "),
+    ]);
+    item
+}
+
+// Goodreads appends the series to the title: "Leviathan Wakes (The Expanse, #1)".
+// The series comes back as "The Expanse, book 1" so it does not read as a tag.
+fn split_series(title: &str) -> (&str, Option<String>) {
+    if let Some(rest) = title.strip_suffix(')') {
+        if let Some((title, series)) = rest.rsplit_once(" (") {
+            if series.contains('#') && !title.trim().is_empty() {
+                return (title.trim(), Some(series.replace('#', "book ")));
+            }
+        }
+    }
+    (title, None)
+}
+
+// Goodreads dates look like 2021/03/14.
+fn date(s: &str) -> Option<DateTime<Local>> {
+    parse_local_date(&s.trim().replace('/', "-"))
+}
+
+// Reviews are exported as HTML fragments with <br/> line breaks.
+fn review_text(review: &str) -> String {
+    review
+        .replace("<br/>", "\n")
+        .replace("<br />", "\n")
+        .replace("<br>", "\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXPORT: &str = "\
+Book Id,Title,Author,ISBN,ISBN13,My Rating,Date Read,Date Added,Bookshelves,Exclusive Shelf,My Review,Private Notes
+44037,\"Leviathan Wakes (The Expanse, #1)\",James S.A. Corey,\"=\"\"0316129089\"\"\",\"=\"\"9780316129084\"\"\",4,2021/03/14,2020/12/01,\"sci-fi, read\",read,Great<br/>fun,
+2,Dune,Frank Herbert,\"=\"\"\"\"\",\"=\"\"\"\"\",0,,2022/05/02,\"Space Opera\",currently-reading,,Lent to Sam
+3,Piranesi,Susanna Clarke,,,0,,,,to-read,,
+4,,Nobody,,,0,,,,to-read,,
+";
+
+    fn read_export() -> Vec<MediaItem> {
+        let path = std::env::temp_dir().join(format!("goodreads-{}.csv", std::process::id()));
+        std::fs::write(&path, EXPORT).unwrap();
+        let items = read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        items
+    }
+
+    #[test]
+    fn reads_shelves_ratings_and_ids() {
+        let items = read_export();
+        assert_eq!(items.len(), 3);
+
+        let read = &items[0];
+        assert_eq!(read.title, "Leviathan Wakes");
+        assert_eq!(read.status, Status::Finished);
+        assert_eq!(read.rating, Some(8));
+        assert_eq!(read.finished_at, parse_local_date("2021-03-14"));
+        assert_eq!(read.created_at, parse_local_date("2020-12-01").unwrap());
+        let ids: Vec<String> = read.external_ids.iter().map(|i| i.to_string()).collect();
+        assert_eq!(ids, ["goodreads:44037", "isbn:9780316129084"]);
+        assert_eq!(
+            read.notes.as_deref(),
+            Some("by James S.A. Corey · The Expanse, book 1\n\nGreat\nfun\n\n#goodreads #sci-fi")
+        );
+
+        let reading = &items[1];
+        assert_eq!(reading.status, Status::InProgress);
+        assert_eq!(reading.rating, None);
+        assert_eq!(reading.started_at, parse_local_date("2022-05-02"));
+        assert_eq!(reading.external_ids.len(), 1);
+        assert_eq!(reading.tags(), ["goodreads", "space-opera"]);
+
+        assert_eq!(items[2].status, Status::Planned);
+        assert_eq!(items[2].finished_at, None);
+    }
+
+    #[test]
+    fn splits_series_only_when_numbered() {
+        assert_eq!(
+            split_series("Leviathan Wakes (The Expanse, #1)"),
+            ("Leviathan Wakes", Some("The Expanse, book 1".to_string()))
+        );
+        assert_eq!(
+            split_series("Dune (Deluxe Edition)"),
+            ("Dune (Deluxe Edition)", None)
+        );
+        assert_eq!(split_series("(#1)"), ("(#1)", None));
+    }
+}
@@ -0,0 +1,131 @@
+use super::{notes, read_text, tag};
+use crate::ids::{ExternalId, IdScheme};
+use crate::models::{Category, MediaItem, Status};
+use crate::util::parse_local_date;
+use serde::Deserialize;
+use std::error::Error;
+use std::path::Path;
+
+// Columns of the ratings and watchlist exports; the others are ignored.
+#[derive(Deserialize)]
+struct Record {
+    #[serde(rename = "Const")]
+    id: String,
+    #[serde(rename = "Title")]
+    title: String,
+    #[serde(rename = "Your Rating", default)]
+    rating: Option<u8>,
+    #[serde(rename = "Date Rated", default)]
+    date_rated: String,
+    /// Watchlist only: when the title was added.
+    #[serde(rename = "Created", default)]
+    created: String,
+    #[serde(rename = "Title Type", default)]
+    title_type: String,
+    #[serde(rename = "Year", default)]
+    year: String,
+    #[serde(rename = "Genres", default)]
+    genres: String,
+    #[serde(rename = "Directors", default)]
+    directors: String,
+}
+
+/// Reads an IMDb ratings or watchlist CSV. Rated titles are finished on the
+/// day they were rated; unrated (watchlist) titles are planned.
+pub fn read(path: &Path) -> Result<Vec<MediaItem>, Box<dyn Error>> {
+    let text = read_text(path)?;
+    let mut rdr = csv::Reader::from_reader(text.as_bytes());
+    let mut items = vec![];
+    for (line, record) in rdr.deserialize::<Record>().enumerate() {
+        let record = record.map_err(|e| format!("Row {}: {}", line + 1, e))?;
+        if !record.title.trim().is_empty() && !record.id.trim().is_empty() {
+            items.push(to_item(record));
+        }
+    }
+    Ok(items)
+}
+
+fn to_item(record: Record) -> MediaItem {
+    let title_type = record.title_type.trim();
+    // "Video Game" in current exports, "videoGame" in older ones.
+    let kind = title_type.to_lowercase().replace(' ', "");
+    let category = match kind.as_str() {
+        "videogame" => Category::Game,
+        _ => Category::Movie,
+    };
+    let mut item = MediaItem::new(record.title.trim(), category);
+    item.external_ids
+        .extend(ExternalId::new(IdScheme::Imdb, &record.id));
+    let rated = parse_local_date(&record.date_rated);
+    if let Some(added) = parse_local_date(&record.created).or(rated) {
+        item.created_at = added;
+    }
+    item.rating = record.rating.filter(|r| (1..=10).contains(r));
+    if item.rating.is_some() {
+        item.status = Status::Finished;
+        item.finished_at = rated.or(Some(item.created_at));
+    }
+
+    let mut tags = vec![tag("imdb")];
+    if !matches!(kind.as_str(), "" | "movie" | "videogame") {
+        tags.push(tag(title_type));
+    }
+    tags.extend(
+        record
+            .genres
+            .split(',')
+            .filter(|g| !g.trim().is_empty())
+            .map(tag),
+    );
+    let directors = record.directors.trim();
+    item.notes = notes(&[
+        match (directors.is_empty(), record.year.trim()) {
+            (false, "") => format!("Directed by {}", directors),
+            (false, year) => format!("Directed by {} · {}", directors, year),
+            (true, year) => year.to_string(),
+        },
+        tags.join(" "),
+    ]);
+    item
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_ratings_and_watchlist_rows() {
+        let path = std::env::temp_dir().join(format!("imdb-{}.csv", std::process::id()));
+        // Latin-1 "é", as in some older exports.
+        let mut csv =
+            b"Const,Your Rating,Date Rated,Title,Title Type,Year,Genres,Directors,Created\n\
+tt0133093,9,2021-06-01,The Matrix,movie,1999,\"Action, Sci-Fi\",Lana Wachowski,\n\
+tt0903747,,,Breaking Bad,TV Series,2008,Drama,,2022-01-02\n\
+tt1234567,7,2020-01-01,Some Game,Video Game,2015,,,\n\
+,8,2020-01-01,No Id,movie,2000,,,\n"
+                .to_vec();
+        csv.extend(b"tt7654321,,,Am\xe9lie,movie,2001,,,\n");
+        std::fs::write(&path, &csv).unwrap();
+        let items = read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(items.len(), 4);
+
+        let matrix = &items[0];
+        assert_eq!(matrix.external_ids[0].to_string(), "imdb:tt0133093");
+        assert_eq!(matrix.status, Status::Finished);
+        assert_eq!(matrix.rating, Some(9));
+        assert_eq!(matrix.finished_at, parse_local_date("2021-06-01"));
+        assert_eq!(
+            matrix.notes.as_deref(),
+            Some("Directed by Lana Wachowski · 1999\n\n#imdb #action #sci-fi")
+        );
+
+        let show = &items[1];
+        assert_eq!(show.status, Status::Planned);
+        assert_eq!(show.created_at, parse_local_date("2022-01-02").unwrap());
+        assert_eq!(show.tags(), ["imdb", "tv-series", "drama"]);
+
+        assert_eq!(items[2].category, Category::Game);
+        assert_eq!(items[3].title, "Amélie");
+    }
+}
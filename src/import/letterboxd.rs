@@ -0,0 +1,248 @@
+use super::{notes, tag};
+use crate::ids::{ExternalId, IdScheme};
+use crate::models::{Category, MediaItem, Status};
+use crate::util::parse_local_date;
+use chrono::{DateTime, Local};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+// Files of the account export, in the order they are applied: later files
+// win, so the current rating from ratings.csv overrides older diary ratings.
+const EXPORT_FILES: [&str; 5] = [
+    "watchlist.csv",
+    "watched.csv",
+    "diary.csv",
+    "reviews.csv",
+    "ratings.csv",
+];
+
+// Union of the columns of the export's CSV files; each file has a subset.
+#[derive(Deserialize)]
+struct Record {
+    #[serde(rename = "Date", default)]
+    date: String,
+    #[serde(rename = "Name")]
+    name: String,
+    #[serde(rename = "Year", default)]
+    year: String,
+    #[serde(rename = "Letterboxd URI", default)]
+    uri: String,
+    #[serde(rename = "Rating", default)]
+    rating: Option<f32>,
+    #[serde(rename = "Rewatch", default)]
+    rewatch: String,
+    #[serde(rename = "Tags", default)]
+    tags: String,
+    #[serde(rename = "Watched Date", default)]
+    watched_date: String,
+    #[serde(rename = "Review", default)]
+    review: String,
+}
+
+// Everything the export says about one film, across files and diary entries.
+#[derive(Default)]
+struct Film {
+    name: String,
+    year: String,
+    id: Option<ExternalId>,
+    watched: bool,
+    added: Option<DateTime<Local>>,
+    watch_dates: Vec<DateTime<Local>>,
+    rewatch: bool,
+    rating: Option<u8>,
+    reviews: Vec<String>,
+    tags: Vec<String>,
+}
+
+/// Reads a Letterboxd export: the zip from Settings → Import & Export, or one
+/// of its CSV files (watched, diary, ratings, reviews or watchlist).
+pub fn read(path: &Path) -> Result<Vec<MediaItem>, Box<dyn Error>> {
+    let mut films = Films::default();
+    let is_zip = path
+        .extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("zip"));
+    if is_zip {
+        let mut archive = zip::ZipArchive::new(File::open(path)?)?;
+        for name in EXPORT_FILES {
+            if let Ok(file) = archive.by_name(name) {
+                films.add_csv(file, name)?;
+            }
+        }
+    } else {
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        films.add_csv(File::open(path)?, &name)?;
+    }
+    Ok(films.list.into_iter().map(to_item).collect())
+}
+
+#[derive(Default)]
+struct Films {
+    list: Vec<Film>,
+    index: HashMap<(String, String), usize>,
+}
+
+impl Films {
+    fn add_csv(&mut self, reader: impl Read, file_name: &str) -> Result<(), Box<dyn Error>> {
+        let watchlist = file_name.contains("watchlist");
+        // In ratings.csv the date is when the film was rated, not watched.
+        let ratings = file_name.contains("ratings");
+        // Diary and review rows link to the entry rather than the film.
+        let film_uri = !file_name.contains("diary") && !file_name.contains("reviews");
+        let mut rdr = csv::Reader::from_reader(reader);
+        for (line, record) in rdr.deserialize::<Record>().enumerate() {
+            let record = record.map_err(|e| format!("{} row {}: {}", file_name, line + 1, e))?;
+            if record.name.trim().is_empty() {
+                continue;
+            }
+            let key = (
+                record.name.trim().to_string(),
+                record.year.trim().to_string(),
+            );
+            let i = *self.index.entry(key.clone()).or_insert_with(|| {
+                self.list.push(Film {
+                    name: key.0,
+                    year: key.1,
+                    ..Film::default()
+                });
+                self.list.len() - 1
+            });
+            let film = &mut self.list[i];
+            if film_uri && film.id.is_none() {
+                film.id = ExternalId::new(IdScheme::Letterboxd, &record.uri).ok();
+            }
+            let date = parse_local_date(&record.date);
+            if date.is_some() && (film.added.is_none() || date < film.added) {
+                film.added = date;
+            }
+            if watchlist {
+                continue;
+            }
+            film.watched = true;
+            let watched = parse_local_date(&record.watched_date)
+                .or(date.filter(|_| !ratings || film.watch_dates.is_empty()));
+            if let Some(watched) = watched {
+                if !film.watch_dates.contains(&watched) {
+                    film.watch_dates.push(watched);
+                }
+            }
+            film.rewatch |= record.rewatch.eq_ignore_ascii_case("yes");
+            // Half stars from 0.5 to 5, i.e. 1 to 10 on the catalog scale.
+            if let Some(rating) = record.rating.filter(|r| *r > 0.0) {
+                film.rating = Some((rating * 2.0).round().clamp(0.0, 10.0) as u8);
+            }
+            let review = record.review.trim();
+            if !review.is_empty() && !film.reviews.iter().any(|r| r == review) {
+                film.reviews.push(review.to_string());
+            }
+            for name in record.tags.split(',').filter(|t| !t.trim().is_empty()) {
+                let tag = tag(name);
+                if !film.tags.contains(&tag) {
+                    film.tags.push(tag);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn to_item(film: Film) -> MediaItem {
+    let mut item = MediaItem::new(film.name.as_str(), Category::Movie);
+    item.external_ids.extend(film.id);
+    if let Some(added) = film.added.or(film.watch_dates.iter().min().copied()) {
+        item.created_at = added;
+    }
+    if film.watched {
+        item.status = Status::Finished;
+        item.finished_at = film.watch_dates.iter().max().copied().or(film.added);
+    }
+    item.rating = film.rating;
+
+    let mut tags = vec![tag("letterboxd")];
+    if film.rewatch || film.watch_dates.len() > 1 {
+        tags.push(tag("rewatch"));
+    }
+    tags.extend(film.tags);
+    let mut parts = vec![];
+    if !film.year.is_empty() {
+        parts.push(format!("Released {}", film.year));
+    }
+    parts.extend(film.reviews);
+    parts.push(tags.join(" "));
+    item.notes = notes(&parts);
+    item
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn films(files: &[(&str, &str)]) -> Vec<MediaItem> {
+        let mut films = Films::default();
+        for (name, csv) in files {
+            films.add_csv(csv.as_bytes(), name).unwrap();
+        }
+        films.list.into_iter().map(to_item).collect()
+    }
+
+    #[test]
+    fn identifies_films_by_their_uri_across_files() {
+        let items = films(&[
+            (
+                "watchlist.csv",
+                "Date,Name,Year,Letterboxd URI\n2023-01-05,Heat,1995,https://boxd.it/2b0c\n",
+            ),
+            (
+                "watched.csv",
+                "Date,Name,Year,Letterboxd URI\n\
+                 2023-02-01,The Matrix,1999,https://boxd.it/2bbS\n",
+            ),
+            (
+                "diary.csv",
+                "Date,Name,Year,Letterboxd URI,Rating,Rewatch,Tags,Watched Date\n\
+                 2023-02-01,The Matrix,1999,https://boxd.it/6Zx1a,4,,\"cyberpunk, 4k\",2023-01-31\n\
+                 2024-06-01,The Matrix,1999,https://boxd.it/9Qw2b,4.5,Yes,,2024-05-30\n",
+            ),
+            (
+                "ratings.csv",
+                "Date,Name,Year,Letterboxd URI,Rating\n\
+                 2024-06-02,The Matrix,1999,https://boxd.it/2bbS,5\n",
+            ),
+        ]);
+        assert_eq!(items.len(), 2);
+
+        let heat = &items[0];
+        assert_eq!(heat.status, Status::Planned);
+        assert_eq!(heat.external_ids[0].to_string(), "letterboxd:2b0c");
+        assert_eq!(heat.created_at, parse_local_date("2023-01-05").unwrap());
+
+        let matrix = &items[1];
+        assert_eq!(matrix.status, Status::Finished);
+        assert_eq!(matrix.external_ids.len(), 1);
+        assert_eq!(matrix.external_ids[0].to_string(), "letterboxd:2bbS");
+        assert_eq!(matrix.rating, Some(10));
+        assert_eq!(matrix.finished_at, parse_local_date("2024-05-30"));
+        assert_eq!(matrix.tags(), ["letterboxd", "rewatch", "cyberpunk", "4k"]);
+    }
+
+    #[test]
+    fn diary_entries_alone_have_no_film_id() {
+        let items = films(&[(
+            "diary.csv",
+            "Date,Name,Year,Letterboxd URI,Rating,Watched Date\n\
+             2023-02-01,Alien,1979,https://boxd.it/6Zx1a,3.5,2023-01-31\n",
+        )]);
+        assert!(items[0].external_ids.is_empty());
+        assert_eq!(items[0].rating, Some(7));
+        assert_eq!(
+            items[0].notes.as_deref(),
+            Some("Released 1979\n\n#letterboxd")
+        );
+    }
+}
@@ -0,0 +1,305 @@
+pub mod anime;
+pub mod goodreads;
+pub mod imdb;
+pub mod letterboxd;
+
+use crate::dedup::{status_rank, TitleIndex};
+use crate::models::{Category, MediaItem};
+use crate::repo::{RepoResult, Repository};
+use crate::search;
+use chrono::Local;
+use std::error::Error;
+use std::path::Path;
+
+/// Reads one exported file into new items.
+pub type Reader = fn(&Path) -> Result<Vec<MediaItem>, Box<dyn Error>>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RowKind {
+    New,
+    /// Imported before (same external id); `item` is the stored item with
+    /// the newer rating, status and tags applied.
+    Update,
+    /// Imported before and nothing changed since.
+    Unchanged,
+}
+
+pub struct ImportRow {
+    pub item: MediaItem,
+    pub kind: RowKind,
+    /// Similar title already in the catalog or earlier in the same file.
+    pub duplicate_of: Option<String>,
+    pub include: bool,
+}
+
+/// Kind of item a file has no fitting category for, such as anime. Items
+/// tagged `tag` go to a category the user picks, remembered under `setting`.
+#[derive(Debug, Clone, Copy)]
+pub struct CategoryChoice {
+    pub label: &'static str,
+    pub tag: &'static str,
+    pub setting: &'static str,
+    pub category: Category,
+}
+
+/// Items read from another service's export, shown for review with likely
+/// duplicates flagged before anything is saved.
+pub struct ImportPreview {
+    pub source: &'static str,
+    pub rows: Vec<ImportRow>,
+    /// Choices that apply to at least one imported item.
+    pub categories: Vec<CategoryChoice>,
+    imported: Vec<MediaItem>,
+}
+
+impl ImportPreview {
+    /// Checks `items` against the catalog. Items with an external id that was
+    /// imported before update that item, so importing a file twice is harmless;
+    /// other likely duplicates start unticked.
+    pub fn new(
+        source: &'static str,
+        items: Vec<MediaItem>,
+        repo: &dyn Repository,
+    ) -> RepoResult<Self> {
+        Ok(Self {
+            source,
+            rows: rows(&items, repo)?,
+            categories: vec![],
+            imported: items,
+        })
+    }
+
+    /// Applies the saved (or default) category of each choice to its items.
+    pub fn with_categories(
+        mut self,
+        choices: &[CategoryChoice],
+        repo: &dyn Repository,
+    ) -> RepoResult<Self> {
+        for choice in choices {
+            if !self
+                .imported
+                .iter()
+                .any(|i| i.tags().iter().any(|t| t == choice.tag))
+            {
+                continue;
+            }
+            let mut choice = *choice;
+            if let Some(saved) = repo.get_setting(choice.setting)? {
+                choice.category = search::parse_category(&saved).unwrap_or(choice.category);
+            }
+            self.categories.push(choice);
+        }
+        self.recategorize(repo)?;
+        Ok(self)
+    }
+
+    /// Moves the items of choice `i` to `category`, checks them for
+    /// duplicates again and remembers the choice for the next import.
+    pub fn set_category(
+        &mut self,
+        i: usize,
+        category: Category,
+        repo: &dyn Repository,
+    ) -> RepoResult<()> {
+        self.categories[i].category = category;
+        repo.set_setting(self.categories[i].setting, category.as_str())?;
+        self.recategorize(repo)
+    }
+
+    fn recategorize(&mut self, repo: &dyn Repository) -> RepoResult<()> {
+        for item in &mut self.imported {
+            let tags = item.tags();
+            if let Some(choice) = self
+                .categories
+                .iter()
+                .find(|c| tags.iter().any(|t| t == c.tag))
+            {
+                item.category = choice.category;
+            }
+        }
+        self.rows = rows(&self.imported, repo)?;
+        Ok(())
+    }
+
+    pub fn included(&self) -> usize {
+        self.rows.iter().filter(|r| r.include).count()
+    }
+
+    pub fn count(&self, kind: RowKind) -> usize {
+        self.rows.iter().filter(|r| r.kind == kind).count()
+    }
+
+    pub fn duplicates(&self) -> usize {
+        self.rows
+            .iter()
+            .filter(|r| r.duplicate_of.is_some())
+            .count()
+    }
+
+    /// Saves the ticked rows and returns how many were saved. Saved rows are
+    /// unticked, so retrying after an error skips them.
+    pub fn apply(&mut self, repo: &dyn Repository) -> RepoResult<usize> {
+        let mut saved = 0;
+        for row in self.rows.iter_mut().filter(|r| r.include) {
+            match row.kind {
+                RowKind::New => {
+                    repo.add(&mut row.item)?;
+                }
+                RowKind::Update | RowKind::Unchanged => repo.update(&row.item)?,
+            }
+            row.include = false;
+            saved += 1;
+        }
+        Ok(saved)
+    }
+}
+
+// Import rows for `items`: updates of earlier imports, or new items checked
+// for similar titles in the catalog and earlier in the file.
+fn rows(items: &[MediaItem], repo: &dyn Repository) -> RepoResult<Vec<ImportRow>> {
+    let mut indexes: Vec<TitleIndex> = vec![];
+    let mut rows = Vec::with_capacity(items.len());
+    for item in items {
+        let mut existing = None;
+        for id in &item.external_ids {
+            existing = repo.find_by_external_id(id)?;
+            if existing.is_some() {
+                break;
+            }
+        }
+        if let Some(existing) = existing {
+            let (item, kind) = match updated(&existing, item) {
+                Some(updated) => (updated, RowKind::Update),
+                None => (existing, RowKind::Unchanged),
+            };
+            rows.push(ImportRow {
+                item,
+                kind,
+                duplicate_of: None,
+                include: kind == RowKind::Update,
+            });
+            continue;
+        }
+        let index = match indexes.iter().position(|i| i.category == item.category) {
+            Some(i) => &mut indexes[i],
+            None => {
+                indexes.push(TitleIndex::new(item.category, repo.titles(item.category)?));
+                indexes.last_mut().unwrap()
+            }
+        };
+        let duplicate_of = index.find(&item.title).first().map(|t| t.to_string());
+        index.insert(&item.title);
+        rows.push(ImportRow {
+            include: duplicate_of.is_none(),
+            kind: RowKind::New,
+            duplicate_of,
+            item: item.clone(),
+        });
+    }
+    Ok(rows)
+}
+
+// `existing` with what a new import says about it: a new rating, a status
+// further along, more progress and any new tags (such as #rewatch).
+// Title, category and edited notes are left alone. `None` if nothing changes.
+fn updated(existing: &MediaItem, imported: &MediaItem) -> Option<MediaItem> {
+    let mut out = existing.clone();
+    if imported.rating.is_some() {
+        out.rating = imported.rating;
+    }
+    if status_rank(imported.status) > status_rank(out.status) {
+        out.status = imported.status;
+        out.started_at = out.started_at.or(imported.started_at);
+        out.finished_at = imported.finished_at;
+    }
+    if imported.progress > out.progress {
+        out.progress = imported.progress;
+    }
+    if imported.progress_total.is_some() {
+        out.progress_total = imported.progress_total;
+    }
+    for id in &imported.external_ids {
+        out.add_external_id(id.clone());
+    }
+    let tags = out.tags();
+    let new_tags: Vec<String> = imported
+        .tags()
+        .into_iter()
+        .filter(|t| !tags.contains(t))
+        .map(|t| format!("#{}", t))
+        .collect();
+    if !new_tags.is_empty() {
+        out.notes = notes(&[out.notes.unwrap_or_default(), new_tags.join(" ")]);
+    }
+    let changed = out.rating != existing.rating
+        || out.status != existing.status
+        || out.finished_at != existing.finished_at
+        || out.progress != existing.progress
+        || out.progress_total != existing.progress_total
+        || out.external_ids != existing.external_ids
+        || out.notes != existing.notes;
+    if !changed {
+        return None;
+    }
+    out.updated_at = Local::now();
+    Some(out)
+}
+
+/// `#tag` form of a shelf or list name.
+pub fn tag(name: &str) -> String {
+    format!("#{}", slug(name))
+}
+
+/// Lowercase `name` with runs of other characters than letters, digits and
+/// `_` replaced by `-`.
+pub fn slug(name: &str) -> String {
+    let slug: String = name
+        .trim()
+        .to_lowercase()
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '_' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    slug.split('-')
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// File contents as text: UTF-8 (without a byte order mark) or, failing that,
+/// Latin-1, which some older exports use.
+pub fn read_text(path: &Path) -> std::io::Result<String> {
+    let bytes = std::fs::read(path)?;
+    Ok(match String::from_utf8(bytes) {
+        Ok(text) => text.trim_start_matches('\u{feff}').to_string(),
+        Err(e) => e.into_bytes().iter().map(|&b| b as char).collect(),
+    })
+}
+
+/// Joins the non-empty parts with blank lines, or `None` if all are empty.
+pub fn notes(parts: &[String]) -> Option<String> {
+    let parts: Vec<&str> = parts
+        .iter()
+        .map(|p| p.trim())
+        .filter(|p| !p.is_empty())
+        .collect();
+    (!parts.is_empty()).then(|| parts.join("\n\n"))
+}
+
+/// Appends the `#tags` that `item` does not have yet to its notes.
+pub fn add_tags(item: &mut MediaItem, tags: &[String]) {
+    let existing = item.tags();
+    let new_tags: Vec<&str> = tags
+        .iter()
+        .map(String::as_str)
+        .filter(|t| !existing.iter().any(|old| t.strip_prefix('#') == Some(old)))
+        .collect();
+    if !new_tags.is_empty() {
+        item.notes = notes(&[item.notes.take().unwrap_or_default(), new_tags.join(" ")]);
+    }
+}
@@ -0,0 +1,150 @@
+use crate::import::{ImportPreview, RowKind};
+use crate::models::Category;
+use crate::repo::{RepoResult, Repository};
+use eframe::egui::{self, RichText};
+
+/// Preview of a pending import: which rows to add and which look like duplicates.
+#[derive(Default)]
+pub struct ImportView {
+    pub open: bool,
+    preview: Option<ImportPreview>,
+    message: Option<String>,
+}
+
+impl ImportView {
+    pub fn set_preview(&mut self, preview: ImportPreview) {
+        self.preview = Some(preview);
+        self.message = None;
+        self.open = true;
+    }
+
+    /// Returns true when items were imported and the catalog needs a refresh.
+    pub fn show(&mut self, ctx: &egui::Context, repo: &dyn Repository) -> RepoResult<bool> {
+        let mut open = self.open;
+        let mut result = Ok(false);
+        let title = match &self.preview {
+            Some(preview) => format!("Import from {}", preview.source),
+            None => "Import".to_string(),
+        };
+        egui::Window::new(title)
+            .id(egui::Id::new("import_window"))
+            .open(&mut open)
+            .default_width(640.0)
+            .default_height(480.0)
+            .show(ctx, |ui| {
+                result = self.import_ui(ui, repo);
+            });
+        self.open = open;
+        if !self.open {
+            self.preview = None;
+        }
+        result
+    }
+
+    fn import_ui(&mut self, ui: &mut egui::Ui, repo: &dyn Repository) -> RepoResult<bool> {
+        if let Some(message) = &self.message {
+            ui.label(message);
+        }
+        let Some(preview) = self.preview.as_mut() else {
+            return Ok(false);
+        };
+        ui.label(format!(
+            "{} rows: {} new ({} possible duplicates), {} updates, {} already imported.",
+            preview.rows.len(),
+            preview.count(RowKind::New),
+            preview.duplicates(),
+            preview.count(RowKind::Update),
+            preview.count(RowKind::Unchanged),
+        ));
+        for i in 0..preview.categories.len() {
+            let choice = preview.categories[i];
+            let mut category = choice.category;
+            ui.horizontal(|ui| {
+                ui.label(format!("Import {} as", choice.label));
+                egui::ComboBox::from_id_source(("import_category", choice.setting))
+                    .selected_text(category.as_str())
+                    .show_ui(ui, |ui| {
+                        for c in Category::ALL {
+                            ui.selectable_value(&mut category, c, c.as_str());
+                        }
+                    });
+            });
+            if category != choice.category {
+                preview.set_category(i, category, repo)?;
+            }
+        }
+        ui.weak("Possible duplicates are unticked; tick them to import anyway.");
+        let mut import = false;
+        ui.horizontal(|ui| {
+            if ui.button("Select all").clicked() {
+                preview
+                    .rows
+                    .iter_mut()
+                    .for_each(|r| r.include = r.kind != RowKind::Unchanged);
+            }
+            if ui.button("Skip duplicates").clicked() {
+                preview.rows.iter_mut().for_each(|r| {
+                    r.include = r.duplicate_of.is_none() && r.kind != RowKind::Unchanged
+                });
+            }
+            if ui.button("Select none").clicked() {
+                preview.rows.iter_mut().for_each(|r| r.include = false);
+            }
+            import = ui
+                .add_enabled(
+                    preview.included() > 0,
+                    egui::Button::new(format!("Save {} items", preview.included())),
+                )
+                .clicked();
+        });
+        ui.separator();
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            egui::Grid::new("import_rows")
+                .num_columns(7)
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.strong("");
+                    ui.strong("Title");
+                    ui.strong("Status");
+                    ui.strong("Rating");
+                    ui.strong("Progress");
+                    ui.strong("Finished");
+                    ui.strong("");
+                    ui.end_row();
+                    for row in preview.rows.iter_mut() {
+                        ui.add_enabled(
+                            row.kind != RowKind::Unchanged,
+                            egui::Checkbox::without_text(&mut row.include),
+                        );
+                        ui.label(&row.item.title);
+                        ui.label(row.item.status.as_str());
+                        ui.label(row.item.rating.map(|r| r.to_string()).unwrap_or_default());
+                        ui.label(row.item.progress_text().unwrap_or_default());
+                        ui.label(
+                            row.item
+                                .finished_at
+                                .map(|t| t.format("%Y-%m-%d").to_string())
+                                .unwrap_or_default(),
+                        );
+                        match (&row.duplicate_of, row.kind) {
+                            (Some(title), _) => ui.label(
+                                RichText::new(format!("Similar to \"{}\"", title))
+                                    .color(ui.visuals().warn_fg_color),
+                            ),
+                            (None, RowKind::New) => ui.label(""),
+                            (None, RowKind::Update) => ui.label("Updates earlier import"),
+                            (None, RowKind::Unchanged) => ui.weak("Already imported"),
+                        };
+                        ui.end_row();
+                    }
+                });
+        });
+        if import {
+            let added = preview.apply(repo)?;
+            self.preview = None;
+            self.message = Some(format!("Saved {} items.", added));
+            return Ok(true);
+        }
+        Ok(false)
+    }
+}
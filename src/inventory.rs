@@ -0,0 +1,186 @@
+use crate::export::{Cell, Table};
+use crate::models::{MediaItem, OwnedCopy};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Copies counted and valued together, e.g. all copies on one shelf.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Group {
+    pub name: String,
+    pub copies: usize,
+    pub value: f64,
+}
+
+/// What the owned copies of some items are worth: totals, and the split by
+/// category, format and location. Copies without a price count as 0.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Inventory {
+    pub copies: usize,
+    pub items: usize,
+    pub value: f64,
+    /// Copies without a price.
+    pub unpriced: usize,
+    pub by_category: Vec<Group>,
+    pub by_format: Vec<Group>,
+    pub by_location: Vec<Group>,
+}
+
+impl Inventory {
+    pub fn new(copies: &[(MediaItem, OwnedCopy)]) -> Self {
+        let items: BTreeSet<i64> = copies.iter().map(|(_, c)| c.item_id).collect();
+        Self {
+            copies: copies.len(),
+            items: items.len(),
+            value: copies.iter().filter_map(|(_, c)| c.price).sum(),
+            unpriced: copies.iter().filter(|(_, c)| c.price.is_none()).count(),
+            by_category: groups(copies, |item, _| item.category.to_string()),
+            by_format: groups(copies, |_, copy| copy.format.clone()),
+            by_location: groups(copies, |_, copy| copy.location.clone()),
+        }
+    }
+}
+
+// Most valuable first; copies with an empty key are grouped as "(none)".
+fn groups(
+    copies: &[(MediaItem, OwnedCopy)],
+    key: impl Fn(&MediaItem, &OwnedCopy) -> String,
+) -> Vec<Group> {
+    let mut groups: BTreeMap<String, Group> = BTreeMap::new();
+    for (item, copy) in copies {
+        let mut name = key(item, copy).trim().to_string();
+        if name.is_empty() {
+            name = "(none)".into();
+        }
+        let group = groups.entry(name.clone()).or_insert_with(|| Group {
+            name,
+            ..Group::default()
+        });
+        group.copies += 1;
+        group.value += copy.price.unwrap_or(0.0);
+    }
+    let mut out: Vec<Group> = groups.into_values().collect();
+    out.sort_by(|a, b| b.value.total_cmp(&a.value).then(b.copies.cmp(&a.copies)));
+    out
+}
+
+/// One row per copy, for export.
+pub fn table(copies: &[(MediaItem, OwnedCopy)]) -> Table {
+    let text = |s: &str| {
+        let s = s.trim();
+        if s.is_empty() {
+            Cell::Empty
+        } else {
+            Cell::Text(s.to_string())
+        }
+    };
+    Table {
+        columns: [
+            "item_id",
+            "title",
+            "category",
+            "format",
+            "location",
+            "condition",
+            "purchased_on",
+            "price",
+        ]
+        .map(String::from)
+        .to_vec(),
+        rows: copies
+            .iter()
+            .map(|(item, copy)| {
+                vec![
+                    Cell::Int(copy.item_id),
+                    Cell::Text(item.title.clone()),
+                    Cell::Text(item.category.to_string()),
+                    text(&copy.format),
+                    text(&copy.location),
+                    copy.condition
+                        .map(|c| Cell::Text(c.to_string()))
+                        .unwrap_or(Cell::Empty),
+                    copy.purchased_on
+                        .map(|d| Cell::Text(d.to_string()))
+                        .unwrap_or(Cell::Empty),
+                    copy.price.map(Cell::Number).unwrap_or(Cell::Empty),
+                ]
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Category, Condition};
+
+    fn copy(item: &MediaItem, format: &str, location: &str, price: Option<f64>) -> OwnedCopy {
+        OwnedCopy {
+            format: format.into(),
+            location: location.into(),
+            price,
+            ..OwnedCopy::new(item.id.unwrap())
+        }
+    }
+
+    fn copies() -> Vec<(MediaItem, OwnedCopy)> {
+        let mut dune = MediaItem::new("Dune", Category::Book);
+        dune.id = Some(1);
+        let mut heat = MediaItem::new("Heat", Category::Movie);
+        heat.id = Some(2);
+        vec![
+            (
+                dune.clone(),
+                copy(&dune, "Hardcover", "Shelf 1", Some(30.0)),
+            ),
+            (dune.clone(), copy(&dune, "Paperback", " ", None)),
+            (heat.clone(), copy(&heat, "Blu-ray", "Shelf 1", Some(12.5))),
+        ]
+    }
+
+    #[test]
+    fn totals_and_groups_by_value() {
+        let inventory = Inventory::new(&copies());
+        assert_eq!(
+            (inventory.copies, inventory.items, inventory.unpriced),
+            (3, 2, 1)
+        );
+        assert_eq!(inventory.value, 42.5);
+        let names = |groups: &[Group]| -> Vec<(String, usize, f64)> {
+            groups
+                .iter()
+                .map(|g| (g.name.clone(), g.copies, g.value))
+                .collect()
+        };
+        assert_eq!(
+            names(&inventory.by_category),
+            [("Book".into(), 2, 30.0), ("Movie".into(), 1, 12.5)]
+        );
+        assert_eq!(
+            names(&inventory.by_location),
+            [("Shelf 1".into(), 2, 42.5), ("(none)".into(), 1, 0.0)]
+        );
+        assert_eq!(inventory.by_format[2].name, "Paperback");
+        assert_eq!(Inventory::new(&[]), Inventory::default());
+    }
+
+    #[test]
+    fn exports_one_row_per_copy() {
+        let mut copies = copies();
+        copies[0].1.condition = Some(Condition::LikeNew);
+        copies[0].1.purchased_on = chrono::NaiveDate::from_ymd_opt(2023, 4, 1);
+        let table = table(&copies);
+        assert_eq!(table.columns[3..5], ["format", "location"]);
+        assert_eq!(table.rows.len(), 3);
+        assert_eq!(
+            table.rows[0][5..],
+            [
+                Cell::Text(Condition::LikeNew.to_string()),
+                Cell::Text("2023-04-01".into()),
+                Cell::Number(30.0)
+            ]
+        );
+        assert_eq!(
+            table.rows[1][4..],
+            [Cell::Empty, Cell::Empty, Cell::Empty, Cell::Empty]
+        );
+    }
+}
@@ -0,0 +1,118 @@
+use crate::export::{self, ExportFormat, ExportOptions};
+use crate::inventory::{self, Group, Inventory};
+use crate::models::{MediaItem, OwnedCopy, Query};
+use crate::repo::{RepoResult, Repository};
+use chrono::Local;
+use eframe::egui;
+
+/// Inventory report for the filtered items: number and value of their
+/// copies, split by category, format and location, with an export of every
+/// copy.
+#[derive(Default)]
+pub struct InventoryView {
+    pub open: bool,
+    copies: Vec<(MediaItem, OwnedCopy)>,
+    data: Inventory,
+    format: ExportFormat,
+    message: Option<String>,
+}
+
+impl InventoryView {
+    pub fn reload(&mut self, repo: &dyn Repository, query: &Query) -> RepoResult<()> {
+        self.copies = repo.inventory(query)?;
+        self.data = Inventory::new(&self.copies);
+        Ok(())
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context) {
+        let mut open = self.open;
+        egui::Window::new("Inventory")
+            .open(&mut open)
+            .default_width(420.0)
+            .show(ctx, |ui| {
+                ui.weak("Copies of the items matching the current filters.");
+                let data = &self.data;
+                ui.label(format!(
+                    "{} copies of {} items, worth {:.2}",
+                    data.copies, data.items, data.value
+                ));
+                if data.unpriced > 0 {
+                    ui.weak(format!("{} copies have no price.", data.unpriced));
+                }
+                ui.separator();
+                egui::ScrollArea::vertical()
+                    .max_height(360.0)
+                    .show(ui, |ui| {
+                        groups_ui(ui, "By category", &data.by_category);
+                        groups_ui(ui, "By format", &data.by_format);
+                        groups_ui(ui, "By location", &data.by_location);
+                    });
+                ui.separator();
+                ui.horizontal(|ui| {
+                    egui::ComboBox::from_id_source("inventory_format")
+                        .selected_text(self.format.as_str())
+                        .show_ui(ui, |ui| {
+                            for f in ExportFormat::ALL {
+                                ui.selectable_value(&mut self.format, f, f.as_str());
+                            }
+                        });
+                    if ui
+                        .add_enabled(!self.copies.is_empty(), egui::Button::new("Export..."))
+                        .clicked()
+                    {
+                        self.export();
+                    }
+                });
+                if let Some(message) = &self.message {
+                    ui.label(message.as_str());
+                }
+            });
+        self.open = open;
+    }
+
+    fn export(&mut self) {
+        let format = self.format;
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter(format.as_str(), &[format.extension()])
+            .set_file_name(format!(
+                "inventory_{}.{}",
+                Local::now().format("%Y%m%d"),
+                format.extension()
+            ))
+            .save_file()
+        else {
+            return;
+        };
+        let options = ExportOptions {
+            format,
+            ..ExportOptions::default()
+        };
+        let table = inventory::table(&self.copies);
+        self.message = Some(match export::write_table(&table, &options, &path) {
+            Ok(()) => format!(
+                "Exported {} copies to {}",
+                self.copies.len(),
+                path.display()
+            ),
+            Err(e) => format!("Export failed: {}", e),
+        });
+    }
+}
+
+fn groups_ui(ui: &mut egui::Ui, heading: &str, groups: &[Group]) {
+    egui::CollapsingHeader::new(heading)
+        .default_open(true)
+        .show(ui, |ui| {
+            egui::Grid::new(heading)
+                .num_columns(3)
+                .striped(true)
+                .show(ui, |ui| {
+                    for g in groups {
+                        ui.label(g.name.as_str());
+                        ui.label(g.copies.to_string());
+                        ui.label(format!("{:.2}", g.value));
+                        ui.end_row();
+                    }
+                });
+        });
+}
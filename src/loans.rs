@@ -0,0 +1,121 @@
+use crate::export::{Cell, Table};
+use crate::models::{Loan, MediaItem};
+use chrono::NaiveDate;
+
+/// Where a loan stands on `today`.
+pub fn state(loan: &Loan, today: NaiveDate) -> &'static str {
+    if !loan.is_open() {
+        "Returned"
+    } else if loan.is_overdue(today) {
+        "Overdue"
+    } else {
+        "On loan"
+    }
+}
+
+/// Days the item was out: until it came back, or until `today`.
+pub fn days_out(loan: &Loan, today: NaiveDate) -> i64 {
+    (loan.returned_on.unwrap_or(today) - loan.lent_on).num_days()
+}
+
+/// One row per loan, for export.
+pub fn table(loans: &[(MediaItem, Loan)], today: NaiveDate) -> Table {
+    let date = |d: Option<NaiveDate>| d.map(|d| Cell::Text(d.to_string())).unwrap_or(Cell::Empty);
+    Table {
+        columns: [
+            "item_id",
+            "title",
+            "category",
+            "copy_id",
+            "borrower",
+            "lent_on",
+            "due_on",
+            "returned_on",
+            "days_out",
+            "state",
+        ]
+        .map(String::from)
+        .to_vec(),
+        rows: loans
+            .iter()
+            .map(|(item, loan)| {
+                vec![
+                    Cell::Int(loan.item_id),
+                    Cell::Text(item.title.clone()),
+                    Cell::Text(item.category.to_string()),
+                    loan.copy_id.map(Cell::Int).unwrap_or(Cell::Empty),
+                    Cell::Text(loan.borrower.clone()),
+                    date(Some(loan.lent_on)),
+                    date(loan.due_on),
+                    date(loan.returned_on),
+                    Cell::Int(days_out(loan, today)),
+                    Cell::Text(state(loan, today).into()),
+                ]
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Category;
+
+    fn day(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    fn loan(lent: &str, due: Option<&str>, returned: Option<&str>) -> Loan {
+        Loan {
+            borrower: "Sam".into(),
+            lent_on: day(lent),
+            due_on: due.map(day),
+            returned_on: returned.map(day),
+            ..Loan::new(1)
+        }
+    }
+
+    #[test]
+    fn tells_open_overdue_and_returned_loans_apart() {
+        let today = day("2024-03-10");
+        let open = loan("2024-03-01", Some("2024-03-10"), None);
+        assert_eq!(
+            (state(&open, today), days_out(&open, today)),
+            ("On loan", 9)
+        );
+        let late = loan("2024-02-01", Some("2024-03-09"), None);
+        assert_eq!(
+            (state(&late, today), days_out(&late, today)),
+            ("Overdue", 38)
+        );
+        let back = loan("2024-02-01", Some("2024-02-05"), Some("2024-02-11"));
+        assert_eq!(
+            (state(&back, today), days_out(&back, today)),
+            ("Returned", 10)
+        );
+        let undated = loan("2020-01-01", None, None);
+        assert_eq!(state(&undated, today), "On loan");
+    }
+
+    #[test]
+    fn exports_one_row_per_loan() {
+        let mut item = MediaItem::new("Dune", Category::Book);
+        item.id = Some(1);
+        let mut lent = loan("2024-03-01", None, None);
+        lent.copy_id = Some(4);
+        let table = table(&[(item, lent)], day("2024-03-10"));
+        assert_eq!(table.columns.len(), table.rows[0].len());
+        assert_eq!(
+            table.rows[0][3..],
+            [
+                Cell::Int(4),
+                Cell::Text("Sam".into()),
+                Cell::Text("2024-03-01".into()),
+                Cell::Empty,
+                Cell::Empty,
+                Cell::Int(9),
+                Cell::Text("On loan".into())
+            ]
+        );
+    }
+}
@@ -0,0 +1,147 @@
+use crate::export::{self, ExportFormat, ExportOptions};
+use crate::loans;
+use crate::models::{Loan, MediaItem, Query};
+use crate::repo::{RepoResult, Repository};
+use chrono::Local;
+use eframe::egui::{self, RichText};
+
+/// Loans report for the filtered items: who has what and since when, with
+/// overdue loans highlighted and an export of the list.
+#[derive(Default)]
+pub struct LoansView {
+    pub open: bool,
+    include_returned: bool,
+    loans: Vec<(MediaItem, Loan)>,
+    format: ExportFormat,
+    message: Option<String>,
+}
+
+impl LoansView {
+    pub fn reload(&mut self, repo: &dyn Repository, query: &Query) -> RepoResult<()> {
+        self.loans = repo.loans(query)?;
+        if !self.include_returned {
+            self.loans.retain(|(_, loan)| loan.is_open());
+        }
+        Ok(())
+    }
+
+    /// Returns true when a loan was marked returned and the catalog needs a
+    /// refresh.
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        repo: &dyn Repository,
+        query: &Query,
+    ) -> RepoResult<bool> {
+        let mut open = self.open;
+        let mut returned = None;
+        let mut reload = false;
+        let today = Local::now().date_naive();
+        egui::Window::new("Loans")
+            .open(&mut open)
+            .default_width(560.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.weak("Loans of the items matching the current filters.");
+                    reload = ui
+                        .checkbox(&mut self.include_returned, "Include returned")
+                        .changed();
+                });
+                let overdue = self
+                    .loans
+                    .iter()
+                    .filter(|(_, l)| l.is_overdue(today))
+                    .count();
+                ui.label(format!("{} loans, {} overdue", self.loans.len(), overdue));
+                ui.separator();
+                egui::ScrollArea::vertical()
+                    .max_height(360.0)
+                    .show(ui, |ui| {
+                        egui::Grid::new("loans_grid")
+                            .num_columns(6)
+                            .striped(true)
+                            .show(ui, |ui| {
+                                for heading in ["Title", "Borrower", "Lent", "Due", "State"] {
+                                    ui.weak(heading);
+                                }
+                                ui.end_row();
+                                for (i, (item, loan)) in self.loans.iter().enumerate() {
+                                    ui.label(item.title.as_str());
+                                    ui.label(loan.borrower.as_str());
+                                    ui.label(loan.lent_on.to_string());
+                                    ui.label(
+                                        loan.due_on.map(|d| d.to_string()).unwrap_or_default(),
+                                    );
+                                    let state = loans::state(loan, today);
+                                    if loan.is_overdue(today) {
+                                        ui.label(
+                                            RichText::new(state).color(ui.visuals().error_fg_color),
+                                        );
+                                    } else {
+                                        ui.label(state);
+                                    }
+                                    if loan.is_open() && ui.small_button("Returned").clicked() {
+                                        returned = Some(i);
+                                    }
+                                    ui.end_row();
+                                }
+                            });
+                    });
+                ui.separator();
+                ui.horizontal(|ui| {
+                    egui::ComboBox::from_id_source("loans_format")
+                        .selected_text(self.format.as_str())
+                        .show_ui(ui, |ui| {
+                            for f in ExportFormat::ALL {
+                                ui.selectable_value(&mut self.format, f, f.as_str());
+                            }
+                        });
+                    if ui
+                        .add_enabled(!self.loans.is_empty(), egui::Button::new("Export..."))
+                        .clicked()
+                    {
+                        self.export();
+                    }
+                });
+                if let Some(message) = &self.message {
+                    ui.label(message.as_str());
+                }
+            });
+        self.open = open;
+        if let Some(i) = returned {
+            let mut loan = self.loans[i].1.clone();
+            loan.returned_on = Some(today);
+            repo.save_loan(&mut loan)?;
+            self.reload(repo, query)?;
+            return Ok(true);
+        }
+        if reload {
+            self.reload(repo, query)?;
+        }
+        Ok(false)
+    }
+
+    fn export(&mut self) {
+        let format = self.format;
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter(format.as_str(), &[format.extension()])
+            .set_file_name(format!(
+                "loans_{}.{}",
+                Local::now().format("%Y%m%d"),
+                format.extension()
+            ))
+            .save_file()
+        else {
+            return;
+        };
+        let options = ExportOptions {
+            format,
+            ..ExportOptions::default()
+        };
+        let table = loans::table(&self.loans, Local::now().date_naive());
+        self.message = Some(match export::write_table(&table, &options, &path) {
+            Ok(()) => format!("Exported {} loans to {}", self.loans.len(), path.display()),
+            Err(e) => format!("Export failed: {}", e),
+        });
+    }
+}
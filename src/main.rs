@@ -1,11 +1,44 @@
+mod api;
 mod app;
+mod barcode;
+mod cli;
+mod collation;
+mod dedup;
+mod duplicates_view;
+mod enrich_view;
+mod export;
+mod export_view;
+mod goals_view;
+mod ids;
+mod import;
+mod import_view;
+mod inventory;
+mod inventory_view;
+mod loans;
+mod loans_view;
+mod metadata;
 mod models;
+mod picker_view;
+mod recommend;
 mod repo;
+mod scan;
+mod search;
+mod server;
+mod server_view;
+mod site;
 mod sqlite_repo;
+mod stats_view;
 mod util;
+mod watch_view;
+mod watcher;
+mod web;
+mod wishlist_view;
 
 use app::CatalogApp;
+use repo::Repository;
 use simplelog::{ColorChoice, Config, LevelFilter, TermLogger, TerminalMode};
+use sqlite_repo::SqliteRepo;
+use std::sync::Arc;
 
 fn main() -> eframe::Result<()> {
     // Basic logger (won't crash the app if it fails)
@@ -19,6 +52,21 @@ fn main() -> eframe::Result<()> {
     // Create/open local DB file next to the binary
     let db_path = util::default_db_path();
 
+    // A command as first argument selects a command-line action instead of the GUI
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().is_some_and(|a| cli::is_command(a)) {
+        let repo: Arc<dyn Repository> = Arc::new(SqliteRepo::new(&db_path));
+        let result = repo
+            .init()
+            .map_err(|e| e.into())
+            .and_then(|_| cli::run(&args, repo));
+        if let Err(e) = result {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
     let options = eframe::NativeOptions::default();
     eframe::run_native(
         "Media Catalog (Local)",
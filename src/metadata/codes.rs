@@ -0,0 +1,102 @@
+use super::{Metadata, MetadataProvider};
+use crate::dedup::{normalize_title, similarity};
+use crate::ids::ExternalId;
+use crate::models::Category;
+use crate::repo::{RepoError, RepoResult};
+use crate::search::parse_category;
+use serde::Deserialize;
+use std::path::Path;
+
+const MIN_SIMILARITY: f64 = 0.6;
+
+// One line of the mapping file; only `code` and `title` are required.
+// Optional columns are `Option`s so short rows read as missing values.
+#[derive(Deserialize)]
+struct Row {
+    #[serde(alias = "ean", alias = "upc", alias = "isbn", alias = "barcode")]
+    code: String,
+    title: String,
+    #[serde(default)]
+    category: Option<String>,
+    #[serde(default, alias = "creator", alias = "author", alias = "artist")]
+    creators: Option<String>,
+    #[serde(default)]
+    year: Option<i32>,
+}
+
+/// Provider reading a CSV file that maps barcodes to titles, with the
+/// columns `code,title,category,creators,year`. Several creators are
+/// separated by `;`. Lines with an invalid code are skipped.
+pub struct CodeList {
+    name: String,
+    records: Vec<Metadata>,
+}
+
+impl CodeList {
+    pub fn open(path: &Path) -> RepoResult<Self> {
+        let mut rdr = csv::ReaderBuilder::new()
+            .flexible(true)
+            .trim(csv::Trim::All)
+            .from_path(path)
+            .map_err(|e| RepoError::Other(e.to_string()))?;
+        let mut records = vec![];
+        for (line, row) in rdr.deserialize::<Row>().enumerate() {
+            let row = row.map_err(|e| RepoError::Other(format!("line {}: {}", line + 2, e)))?;
+            let id = match ExternalId::from_barcode(&row.code) {
+                Ok(id) => id,
+                Err(e) => {
+                    log::warn!("{}, line {}: {}", path.display(), line + 2, e);
+                    continue;
+                }
+            };
+            records.push(Metadata {
+                external_ids: vec![id],
+                title: row.title,
+                category: row
+                    .category
+                    .filter(|c| !c.is_empty())
+                    .and_then(|c| parse_category(&c)),
+                creators: row
+                    .creators
+                    .unwrap_or_default()
+                    .split(';')
+                    .map(str::trim)
+                    .filter(|c| !c.is_empty())
+                    .map(str::to_string)
+                    .collect(),
+                year: row.year,
+                ..Metadata::default()
+            });
+        }
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| "code list".into());
+        Ok(Self { name, records })
+    }
+}
+
+impl MetadataProvider for CodeList {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn search(&self, title: &str, category: Option<Category>) -> RepoResult<Vec<Metadata>> {
+        let wanted = normalize_title(title);
+        Ok(self
+            .records
+            .iter()
+            .filter(|m| category.is_none() || m.category.is_none() || m.category == category)
+            .filter(|m| similarity(&wanted, &normalize_title(&m.title)) >= MIN_SIMILARITY)
+            .cloned()
+            .collect())
+    }
+
+    fn fetch(&self, id: &ExternalId) -> RepoResult<Option<Metadata>> {
+        Ok(self
+            .records
+            .iter()
+            .find(|m| m.external_ids.contains(id))
+            .cloned())
+    }
+}
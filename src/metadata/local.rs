@@ -0,0 +1,368 @@
+use super::{Metadata, MetadataProvider};
+use crate::dedup::{normalize_title, similarity};
+use crate::ids::{ExternalId, IdScheme};
+use crate::models::Category;
+use crate::repo::RepoResult;
+use flate2::read::GzDecoder;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+
+const MAX_RESULTS: usize = 20;
+const MAX_GENRES: usize = 5;
+// Titles less similar than this are not offered, unless one contains the other.
+const MIN_SIMILARITY: f64 = 0.6;
+
+/// Provider reading a dump file into memory: a JSON array or JSON Lines of
+/// records such as TMDB movies, or an Open Library dump (tab-separated, with
+/// the record as JSON in the last column). `.gz` files are decompressed.
+///
+/// Records are read leniently: `title` or `name`, authors or directors as
+/// names or `{"name": …}` objects, genres or subjects, a year from any of the
+/// usual date fields. Open Library author keys are resolved when the dump
+/// also holds the authors. Covers are only used if they are local files,
+/// relative to the dump.
+pub struct LocalDump {
+    name: String,
+    records: Vec<(String, Metadata)>,
+}
+
+impl LocalDump {
+    pub fn open(path: &Path) -> RepoResult<Self> {
+        let file = File::open(path)?;
+        let reader: Box<dyn Read> = if path.extension().is_some_and(|e| e == "gz") {
+            Box::new(GzDecoder::new(file))
+        } else {
+            Box::new(file)
+        };
+        let mut reader = BufReader::new(reader);
+        let values: Vec<Value> = if reader.fill_buf()?.trim_ascii_start().starts_with(b"[") {
+            serde_json::from_reader(reader)?
+        } else {
+            let mut values = vec![];
+            for line in reader.lines() {
+                let line = line?;
+                // Open Library: type, key, revision, last modified, JSON.
+                let json = line.rsplit('\t').next().unwrap_or_default().trim();
+                if !json.is_empty() {
+                    values.push(serde_json::from_str(json)?);
+                }
+            }
+            values
+        };
+
+        let authors: HashMap<&str, &str> = values
+            .iter()
+            .filter(|v| str_field(v, &["type.key"]).as_deref() == Some("/type/author"))
+            .filter_map(|v| Some((v.get("key")?.as_str()?, v.get("name")?.as_str()?)))
+            .collect();
+        let dir = path.parent().unwrap_or(Path::new("")).to_path_buf();
+        let records = values
+            .iter()
+            .filter_map(|v| record(v, &authors, &dir))
+            .map(|m| (normalize_title(&m.title), m))
+            .collect();
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| "local dump".into());
+        Ok(Self { name, records })
+    }
+}
+
+impl MetadataProvider for LocalDump {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn search(&self, title: &str, category: Option<Category>) -> RepoResult<Vec<Metadata>> {
+        let wanted = normalize_title(title);
+        if wanted.is_empty() {
+            return Ok(vec![]);
+        }
+        let mut matches: Vec<(f64, &Metadata)> = self
+            .records
+            .iter()
+            .filter(|(_, m)| category.is_none() || m.category.is_none() || m.category == category)
+            .filter_map(|(normalized, m)| {
+                let mut score = similarity(&wanted, normalized);
+                if score < MIN_SIMILARITY
+                    && (normalized.contains(&wanted) || wanted.contains(normalized.as_str()))
+                {
+                    score = MIN_SIMILARITY;
+                }
+                (score >= MIN_SIMILARITY).then_some((score, m))
+            })
+            .collect();
+        matches.sort_by(|a, b| b.0.total_cmp(&a.0).then(a.1.year.cmp(&b.1.year)));
+        Ok(matches
+            .into_iter()
+            .take(MAX_RESULTS)
+            .map(|(_, m)| m.clone())
+            .collect())
+    }
+
+    fn fetch(&self, id: &ExternalId) -> RepoResult<Option<Metadata>> {
+        Ok(self
+            .records
+            .iter()
+            .find(|(_, m)| m.external_ids.contains(id))
+            .map(|(_, m)| m.clone()))
+    }
+}
+
+fn record(v: &Value, authors: &HashMap<&str, &str>, dir: &Path) -> Option<Metadata> {
+    let kind = str_field(v, &["type.key"]);
+    if kind
+        .as_deref()
+        .is_some_and(|k| k != "/type/work" && k != "/type/edition")
+    {
+        return None;
+    }
+    let title = str_field(v, &["title", "name", "original_title", "original_name"])?;
+    let key = str_field(v, &["key"]).filter(|k| k.starts_with('/'));
+    let tmdb = [
+        "poster_path",
+        "release_date",
+        "first_air_date",
+        "popularity",
+    ]
+    .iter()
+    .any(|f| v.get(f).is_some());
+    let tv =
+        v.get("first_air_date").is_some() || str_field(v, &["media_type"]).as_deref() == Some("tv");
+    // The provider's own id first, then ids in other catalogs it mentions.
+    let mut external_ids: Vec<ExternalId> = vec![];
+    let primary = match (&key, str_field(v, &["id"])) {
+        (Some(key), _) => ExternalId::new(IdScheme::OpenLibrary, key).ok(),
+        (None, Some(id)) if tmdb && tv => {
+            ExternalId::new(IdScheme::Tmdb, &format!("tv:{}", id)).ok()
+        }
+        (None, Some(id)) if tmdb => ExternalId::new(IdScheme::Tmdb, &id).ok(),
+        (None, _) => None,
+    };
+    external_ids.extend(primary);
+    for (field, scheme) in [
+        ("imdb_id", IdScheme::Imdb),
+        ("isbn_13", IdScheme::Isbn),
+        ("isbn_10", IdScheme::Isbn),
+        ("isbn", IdScheme::Isbn),
+    ] {
+        for value in names(v, &[field], authors) {
+            if let Ok(id) = ExternalId::new(scheme, &value) {
+                if !external_ids.contains(&id) {
+                    external_ids.push(id);
+                }
+            }
+        }
+    }
+    for value in names(v, &["ean", "upc", "barcode"], authors) {
+        if let Ok(id) = ExternalId::from_barcode(&value) {
+            if !external_ids.contains(&id) {
+                external_ids.push(id);
+            }
+        }
+    }
+    for value in names(v, &["external_ids", "external_id"], authors) {
+        if let Ok(id) = ExternalId::parse(&value) {
+            if !external_ids.contains(&id) {
+                external_ids.push(id);
+            }
+        }
+    }
+    let category = match str_field(v, &["category"]) {
+        Some(c) => Category::ALL
+            .into_iter()
+            .find(|cat| cat.as_str().eq_ignore_ascii_case(&c)),
+        None if key.is_some() => Some(Category::Book),
+        None if tmdb => Some(Category::Movie),
+        None => None,
+    };
+    let creators = names(
+        v,
+        &[
+            "authors",
+            "author_name",
+            "author",
+            "creators",
+            "creator",
+            "directors",
+            "director",
+            "artists",
+            "artist",
+        ],
+        authors,
+    );
+    let year = [
+        "year",
+        "first_publish_year",
+        "first_publish_date",
+        "publish_date",
+        "release_date",
+        "first_air_date",
+        "date",
+    ]
+    .iter()
+    .find_map(|f| v.get(f).and_then(parse_year));
+    let mut genres = names(v, &["genres", "subjects", "subject", "tags"], authors);
+    genres.truncate(MAX_GENRES);
+    let cover = str_field(v, &["cover", "cover_path", "poster_path", "image"])
+        .and_then(|c| local_file(dir, &c));
+    Some(Metadata {
+        external_ids,
+        title,
+        category,
+        creators,
+        year,
+        genres,
+        cover,
+    })
+}
+
+// The first of `fields` holding a string or number; `a.b` looks into objects.
+fn str_field(v: &Value, fields: &[&str]) -> Option<String> {
+    fields.iter().find_map(|f| {
+        let value = f.split('.').try_fold(v, |v, part| v.get(part))?;
+        match value {
+            Value::String(s) if !s.trim().is_empty() => Some(s.trim().to_string()),
+            Value::Number(n) => Some(n.to_string()),
+            _ => None,
+        }
+    })
+}
+
+// Names from the first of `fields` present: a string, a list of strings,
+// `{"name": …}` objects or Open Library `{"author": {"key": …}}` references.
+fn names(v: &Value, fields: &[&str], authors: &HashMap<&str, &str>) -> Vec<String> {
+    let Some(value) = fields.iter().find_map(|f| v.get(f)) else {
+        return vec![];
+    };
+    let name = |v: &Value| -> Option<String> {
+        match v {
+            Value::String(s) => Some(s.clone()),
+            Value::Object(_) => v
+                .get("name")
+                .and_then(Value::as_str)
+                .or_else(|| {
+                    let key = v.pointer("/author/key").or_else(|| v.get("key"))?;
+                    authors.get(key.as_str()?).copied()
+                })
+                .map(str::to_string),
+            _ => None,
+        }
+    };
+    let mut out: Vec<String> = vec![];
+    let all: Vec<String> = match value {
+        Value::Array(values) => values.iter().filter_map(name).collect(),
+        // "Tolkien, J. R. R." stays one name; lists use ';'.
+        value => name(value)
+            .map(|s| s.split(';').map(str::to_string).collect())
+            .unwrap_or_default(),
+    };
+    for n in all {
+        let n = n.trim().to_string();
+        if !n.is_empty() && !out.contains(&n) {
+            out.push(n);
+        }
+    }
+    out
+}
+
+// A year from a number or the first four-digit run in a date string.
+fn parse_year(v: &Value) -> Option<i32> {
+    let y = match v {
+        Value::Number(n) => n.as_i64()? as i32,
+        Value::String(s) => s
+            .split(|c: char| !c.is_ascii_digit())
+            .find(|part| part.len() == 4)?
+            .parse()
+            .ok()?,
+        _ => return None,
+    };
+    (1000..=2999).contains(&y).then_some(y)
+}
+
+fn local_file(dir: &Path, path: &str) -> Option<String> {
+    if path.starts_with("http://") || path.starts_with("https://") {
+        return None;
+    }
+    // TMDB poster paths start with '/' but are relative to the image server.
+    [PathBuf::from(path), dir.join(path.trim_start_matches('/'))]
+        .into_iter()
+        .find(|p| p.is_file())
+        .map(|p| p.to_string_lossy().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dump(name: &str, contents: &str) -> LocalDump {
+        let dir = std::env::temp_dir().join(format!("dump-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("poster.jpg"), "jpeg").unwrap();
+        let path = dir.join(name);
+        std::fs::write(&path, contents).unwrap();
+        let dump = LocalDump::open(&path).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        dump
+    }
+
+    #[test]
+    fn reads_open_library_dumps_with_authors() {
+        let dump = dump(
+            "ol_dump.txt",
+            "/type/author\t/authors/OL26320A\t4\t2021-01-01\t\
+             {\"key\": \"/authors/OL26320A\", \"name\": \"J.R.R. Tolkien\", \"type\": {\"key\": \"/type/author\"}}\n\
+             /type/work\t/works/OL27448W\t9\t2021-01-01\t\
+             {\"key\": \"/works/OL27448W\", \"title\": \"The Lord of the Rings\", \
+             \"type\": {\"key\": \"/type/work\"}, \"first_publish_date\": \"October 1954\", \
+             \"authors\": [{\"author\": {\"key\": \"/authors/OL26320A\"}}], \
+             \"subjects\": [\"Fantasy\", \"Quests\"]}\n",
+        );
+        assert_eq!(dump.name(), "ol_dump.txt");
+        let found = dump.search("lord of the rings", None).unwrap();
+        assert_eq!(found.len(), 1);
+        let work = &found[0];
+        assert_eq!(work.external_ids[0].to_string(), "openlibrary:OL27448W");
+        assert_eq!(work.category, Some(Category::Book));
+        assert_eq!(work.creators, ["J.R.R. Tolkien"]);
+        assert_eq!(work.year, Some(1954));
+        assert_eq!(work.genres, ["Fantasy", "Quests"]);
+        assert!(dump.search("The Hobbit", None).unwrap().is_empty());
+        assert!(dump
+            .search("Lord of the Rings", Some(Category::Movie))
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn reads_tmdb_arrays_and_fetches_by_any_id() {
+        let dump = dump(
+            "movies.json",
+            r#"[
+                {"id": 603, "title": "The Matrix", "release_date": "1999-03-30",
+                 "imdb_id": "tt0133093", "poster_path": "/poster.jpg",
+                 "genres": [{"id": 28, "name": "Action"}], "directors": "Lana Wachowski; Lilly Wachowski"},
+                {"id": 604, "title": "The Matrix Reloaded", "release_date": "2003-05-15",
+                 "poster_path": "https://image.tmdb.org/x.jpg"},
+                {"id": 1396, "name": "Breaking Bad", "first_air_date": "2008-01-20"}
+            ]"#,
+        );
+        let found = dump.search("Matrix", Some(Category::Movie)).unwrap();
+        assert_eq!(found[0].title, "The Matrix");
+        assert_eq!(found[0].creators, ["Lana Wachowski", "Lilly Wachowski"]);
+        assert_eq!(found[0].genres, ["Action"]);
+        assert!(found[0].cover.as_deref().unwrap().ends_with("poster.jpg"));
+        assert_eq!(found[1].cover, None);
+
+        let imdb = ExternalId::parse("imdb:tt0133093").unwrap();
+        assert_eq!(dump.fetch(&imdb).unwrap().unwrap().year, Some(1999));
+        let tv = ExternalId::parse("tmdb:tv:1396").unwrap();
+        assert_eq!(dump.fetch(&tv).unwrap().unwrap().title, "Breaking Bad");
+        let missing = ExternalId::parse("tmdb:1").unwrap();
+        assert!(dump.fetch(&missing).unwrap().is_none());
+    }
+}
@@ -0,0 +1,220 @@
+pub mod codes;
+pub mod local;
+
+use crate::ids::ExternalId;
+use crate::import::{add_tags, notes, tag};
+use crate::models::{Category, MediaItem};
+use crate::repo::RepoResult;
+use chrono::Local;
+
+/// What a provider knows about one work.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Metadata {
+    /// The provider's id first, e.g. `openlibrary:OL45804W` or `tmdb:603`,
+    /// then any ids in other catalogs, such as ISBNs.
+    pub external_ids: Vec<ExternalId>,
+    pub title: String,
+    pub category: Option<Category>,
+    /// Authors, directors or artists.
+    pub creators: Vec<String>,
+    pub year: Option<i32>,
+    pub genres: Vec<String>,
+    /// Local image file.
+    pub cover: Option<String>,
+}
+
+/// Source of details for items, such as a local database dump or an online
+/// service.
+pub trait MetadataProvider: Send + Sync {
+    fn name(&self) -> &str;
+    /// Works whose title matches `title`, best match first, limited to
+    /// `category` if given.
+    fn search(&self, title: &str, category: Option<Category>) -> RepoResult<Vec<Metadata>>;
+    /// The work with `id`, if the provider knows it.
+    fn fetch(&self, id: &ExternalId) -> RepoResult<Option<Metadata>>;
+}
+
+/// Part of an item that metadata can fill in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Title,
+    /// "by Author · year" at the start of the notes.
+    Byline,
+    /// Genres as `#tags`.
+    Genres,
+    Cover,
+    ExternalIds,
+}
+
+impl Field {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Field::Title => "Title",
+            Field::Byline => "Creators and year",
+            Field::Genres => "Genres",
+            Field::Cover => "Cover",
+            Field::ExternalIds => "External ids",
+        }
+    }
+}
+
+impl Metadata {
+    /// "by A, B · 1999", "by A", "1999" or empty.
+    pub fn byline(&self) -> String {
+        let creators =
+            (!self.creators.is_empty()).then(|| format!("by {}", self.creators.join(", ")));
+        [creators, self.year.map(|y| y.to_string())]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>()
+            .join(" · ")
+    }
+
+    fn new_tags(&self, item: &MediaItem) -> Vec<String> {
+        let existing = item.tags();
+        self.genres
+            .iter()
+            .map(|g| tag(g))
+            .filter(|t| !existing.iter().any(|old| t.strip_prefix('#') == Some(old)))
+            .collect()
+    }
+
+    /// Ids `item` does not have yet.
+    pub fn new_ids(&self, item: &MediaItem) -> Vec<ExternalId> {
+        self.external_ids
+            .iter()
+            .filter(|id| !item.external_ids.contains(id))
+            .cloned()
+            .collect()
+    }
+
+    /// The fields that would change `item`, each with the value it would get.
+    pub fn changes(&self, item: &MediaItem) -> Vec<(Field, String)> {
+        let mut out = vec![];
+        if !self.title.is_empty() && self.title != item.title {
+            out.push((Field::Title, self.title.clone()));
+        }
+        let byline = self.byline();
+        if !byline.is_empty() && first_paragraph(item) != Some(byline.as_str()) {
+            out.push((Field::Byline, byline));
+        }
+        let tags = self.new_tags(item);
+        if !tags.is_empty() {
+            out.push((Field::Genres, tags.join(" ")));
+        }
+        if let Some(cover) = &self.cover {
+            if item.cover_path.as_ref() != Some(cover) {
+                out.push((Field::Cover, cover.clone()));
+            }
+        }
+        let ids = self.new_ids(item);
+        if !ids.is_empty() {
+            let ids: Vec<String> = ids.iter().map(|id| id.to_string()).collect();
+            out.push((Field::ExternalIds, ids.join(" ")));
+        }
+        out
+    }
+
+    /// Fills in `fields` of `item`. A byline replaces one already at the start
+    /// of the notes; other notes are kept.
+    pub fn apply(&self, item: &mut MediaItem, fields: &[Field]) {
+        for field in fields {
+            match field {
+                Field::Title => item.title = self.title.clone(),
+                Field::Byline => {
+                    let mut rest = item.notes.take().unwrap_or_default();
+                    if let Some(first) = first_paragraph_of(&rest) {
+                        if is_byline(first) {
+                            rest = rest[first.len()..].to_string();
+                        }
+                    }
+                    item.notes = notes(&[self.byline(), rest]);
+                }
+                Field::Genres => add_tags(item, &self.new_tags(item)),
+                Field::Cover => item.cover_path = self.cover.clone(),
+                Field::ExternalIds => {
+                    for id in self.new_ids(item) {
+                        item.add_external_id(id);
+                    }
+                }
+            }
+        }
+        item.updated_at = Local::now();
+    }
+}
+
+fn first_paragraph(item: &MediaItem) -> Option<&str> {
+    item.notes.as_deref().and_then(first_paragraph_of)
+}
+
+fn first_paragraph_of(notes: &str) -> Option<&str> {
+    notes.split("\n\n").next().filter(|p| !p.trim().is_empty())
+}
+
+// "by …" lines and bare years, as written by scans and earlier enrichment.
+fn is_byline(paragraph: &str) -> bool {
+    let first = paragraph.split(" · ").next().unwrap_or_default();
+    first.starts_with("by ") || (first.len() == 4 && first.chars().all(|c| c.is_ascii_digit()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matrix() -> Metadata {
+        Metadata {
+            external_ids: vec![ExternalId::parse("tmdb:603").unwrap()],
+            title: "The Matrix".into(),
+            category: Some(Category::Movie),
+            creators: vec!["Lana Wachowski".into(), "Lilly Wachowski".into()],
+            year: Some(1999),
+            genres: vec!["Science Fiction".into(), "Action".into()],
+            cover: None,
+        }
+    }
+
+    #[test]
+    fn lists_only_fields_that_would_change() {
+        let mut item = MediaItem::new("Matrix", Category::Movie);
+        item.notes = Some("1998\n\nSeen twice. #action".into());
+        let fields: Vec<Field> = matrix().changes(&item).into_iter().map(|c| c.0).collect();
+        assert_eq!(
+            fields,
+            [
+                Field::Title,
+                Field::Byline,
+                Field::Genres,
+                Field::ExternalIds
+            ]
+        );
+        assert_eq!(
+            matrix().changes(&item)[2],
+            (Field::Genres, "#science-fiction".to_string())
+        );
+
+        matrix().apply(&mut item, &fields);
+        assert!(matrix().changes(&item).is_empty());
+    }
+
+    #[test]
+    fn replaces_an_earlier_byline_and_keeps_other_notes() {
+        let mut item = MediaItem::new("The Matrix", Category::Movie);
+        item.notes = Some("by Someone · 1998\n\nSeen twice. #action".into());
+        matrix().apply(&mut item, &[Field::Byline, Field::Genres]);
+        assert_eq!(
+            item.notes.as_deref(),
+            Some("by Lana Wachowski, Lilly Wachowski · 1999\n\nSeen twice. #action\n\n#science-fiction")
+        );
+
+        let mut item = MediaItem::new("The Matrix", Category::Movie);
+        item.notes = Some("Seen twice.".into());
+        matrix().apply(&mut item, &[Field::Byline]);
+        assert_eq!(
+            item.notes.as_deref(),
+            Some("by Lana Wachowski, Lilly Wachowski · 1999\n\nSeen twice.")
+        );
+        let untitled = Metadata::default();
+        assert_eq!(untitled.byline(), "");
+        assert!(untitled.changes(&item).is_empty());
+    }
+}
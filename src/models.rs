@@ -1,5 +1,6 @@
-use chrono::{DateTime, Local};
-use serde::{Deserialize, Serialize};
+use crate::ids::ExternalId;
+use chrono::{DateTime, Datelike, Local, NaiveDate};
+use serde::{Deserialize, Deserializer, Serialize};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Category {
@@ -28,6 +29,17 @@ impl Category {
             Category::Other => "Other",
         }
     }
+
+    /// Common copy formats, offered when adding a copy.
+    pub fn copy_formats(&self) -> &'static [&'static str] {
+        match self {
+            Category::Book => &["Hardcover", "Paperback", "E-book", "Audiobook"],
+            Category::Movie => &["Blu-ray", "4K Blu-ray", "DVD", "Digital"],
+            Category::Game => &["Steam", "GOG", "PlayStation", "Xbox", "Switch", "Cartridge"],
+            Category::Music => &["Vinyl", "CD", "Cassette", "Digital"],
+            Category::Other => &[],
+        }
+    }
 }
 
 impl std::fmt::Display for Category {
@@ -38,19 +50,38 @@ impl std::fmt::Display for Category {
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Status {
+    /// Owned, or at hand, but not started.
     Planned,
     InProgress,
     Finished,
+    /// Wanted but not owned yet.
+    Wishlist,
 }
 
 impl Status {
-    pub const ALL: [Status; 3] = [Status::Planned, Status::InProgress, Status::Finished];
+    pub const ALL: [Status; 4] = [
+        Status::Wishlist,
+        Status::Planned,
+        Status::InProgress,
+        Status::Finished,
+    ];
 
     pub fn as_str(&self) -> &'static str {
         match self {
             Status::Planned => "Planned",
             Status::InProgress => "In Progress",
             Status::Finished => "Finished",
+            Status::Wishlist => "Wishlist",
+        }
+    }
+
+    /// Lowercase name without spaces, for URLs and HTML attributes.
+    pub fn as_key(&self) -> &'static str {
+        match self {
+            Status::Planned => "planned",
+            Status::InProgress => "inprogress",
+            Status::Finished => "finished",
+            Status::Wishlist => "wishlist",
         }
     }
 }
@@ -61,10 +92,63 @@ impl std::fmt::Display for Status {
     }
 }
 
+/// How much a wishlist item is wanted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Priority {
+    High,
+    Normal,
+    Low,
+}
+
+impl Priority {
+    pub const ALL: [Priority; 3] = [Priority::High, Priority::Normal, Priority::Low];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Priority::High => "High",
+            Priority::Normal => "Normal",
+            Priority::Low => "Low",
+        }
+    }
+}
+
+impl std::fmt::Display for Priority {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// How a copy of the item is owned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Format {
+    /// Book, disc, cartridge or other object on a shelf.
+    Physical,
+    Digital,
+}
+
+impl Format {
+    pub const ALL: [Format; 2] = [Format::Physical, Format::Digital];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Format::Physical => "Physical",
+            Format::Digital => "Digital",
+        }
+    }
+}
+
+impl std::fmt::Display for Format {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MediaItem {
     pub id: Option<i64>,
     pub title: String,
+    /// Overrides `title` when sorting, e.g. "Lord of the Rings 1" for "The Fellowship of the Ring".
+    pub sort_title: Option<String>,
     pub category: Category,
     pub status: Status,
     pub rating: Option<u8>,
@@ -72,6 +156,31 @@ pub struct MediaItem {
     pub cover_path: Option<String>,
     pub created_at: DateTime<Local>,
     pub updated_at: DateTime<Local>,
+    /// When the item last moved to In Progress.
+    pub started_at: Option<DateTime<Local>>,
+    /// When the item was finished; set while the status is Finished.
+    pub finished_at: Option<DateTime<Local>>,
+    /// Ids in other catalogs, e.g. `isbn:9780441172719` or `imdb:tt0133093`.
+    /// An id belongs to one item at most.
+    #[serde(default)]
+    pub external_ids: Vec<ExternalId>,
+    /// Episodes, chapters or pages done so far.
+    pub progress: Option<u32>,
+    /// Total episodes, chapters or pages, if known.
+    pub progress_total: Option<u32>,
+    /// Local file or folder with the item itself, e.g. an e-book.
+    pub file_path: Option<String>,
+    #[serde(default)]
+    pub format: Option<Format>,
+    /// How much the item is wanted, while on the wishlist.
+    #[serde(default)]
+    pub priority: Option<Priority>,
+    /// Price to wait for before buying.
+    #[serde(default)]
+    pub target_price: Option<f64>,
+    /// Where to buy it: a store name or a URL.
+    #[serde(default)]
+    pub store: Option<String>,
 }
 
 impl MediaItem {
@@ -80,6 +189,7 @@ impl MediaItem {
         Self {
             id: None,
             title: title.into(),
+            sort_title: None,
             category,
             status: Status::Planned,
             rating: None,
@@ -87,67 +197,633 @@ impl MediaItem {
             cover_path: None,
             created_at: now,
             updated_at: now,
+            started_at: None,
+            finished_at: None,
+            external_ids: vec![],
+            progress: None,
+            progress_total: None,
+            file_path: None,
+            format: None,
+            priority: None,
+            target_price: None,
+            store: None,
         }
     }
 
+    /// Changes the status and records when the item was started or finished.
+    /// Leaving the wishlist, e.g. once bought, clears the wishlist details.
+    pub fn set_status(&mut self, status: Status) {
+        if status == self.status {
+            return;
+        }
+        let now = Local::now();
+        match status {
+            Status::Planned | Status::Wishlist => {
+                self.started_at = None;
+                self.finished_at = None;
+            }
+            Status::InProgress => {
+                self.started_at.get_or_insert(now);
+                self.finished_at = None;
+            }
+            Status::Finished => self.finished_at = Some(now),
+        }
+        if self.status == Status::Wishlist {
+            self.priority = None;
+            self.target_price = None;
+            self.store = None;
+        }
+        self.status = status;
+        self.updated_at = now;
+    }
+
+    /// Adds `id` unless the item has it already. Returns whether it was added.
+    pub fn add_external_id(&mut self, id: ExternalId) -> bool {
+        if self.external_ids.contains(&id) {
+            return false;
+        }
+        self.external_ids.push(id);
+        true
+    }
+
     pub fn mark_finished(&mut self) {
-        self.status = Status::Finished;
-        self.updated_at = Local::now();
+        self.set_status(Status::Finished);
     }
 
     pub fn set_rating(&mut self, rating: Option<u8>) {
         self.rating = rating;
         self.updated_at = Local::now();
     }
+
+    /// "5/26", "5" or "?/26", or `None` when no progress is recorded.
+    pub fn progress_text(&self) -> Option<String> {
+        match (self.progress, self.progress_total) {
+            (None, None) => None,
+            (Some(done), None) => Some(done.to_string()),
+            (done, Some(total)) => Some(format!(
+                "{}/{}",
+                done.map_or("?".to_string(), |d| d.to_string()),
+                total
+            )),
+        }
+    }
+
+    /// Lowercased `#hashtags` in the notes, without the `#`.
+    pub fn tags(&self) -> Vec<String> {
+        let mut tags: Vec<String> = vec![];
+        for word in self.notes.as_deref().unwrap_or_default().split_whitespace() {
+            let Some(tag) = word.strip_prefix('#') else {
+                continue;
+            };
+            let tag: String = tag
+                .chars()
+                .take_while(|c| c.is_alphanumeric() || *c == '-' || *c == '_')
+                .collect::<String>()
+                .to_lowercase();
+            if !tag.is_empty() && !tags.contains(&tag) {
+                tags.push(tag);
+            }
+        }
+        tags
+    }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
 pub enum SortField {
+    #[default]
     Title,
     Category,
     Status,
     Rating,
     CreatedAt,
     UpdatedAt,
+    /// Wishlist priority, highest first when ascending.
+    Priority,
 }
 
-impl Default for SortField {
-    fn default() -> Self {
-        SortField::Title
+impl SortField {
+    pub const ALL: [SortField; 7] = [
+        SortField::Title,
+        SortField::Category,
+        SortField::Status,
+        SortField::Rating,
+        SortField::Priority,
+        SortField::CreatedAt,
+        SortField::UpdatedAt,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SortField::Title => "Title",
+            SortField::Category => "Category",
+            SortField::Status => "Status",
+            SortField::Rating => "Rating",
+            SortField::CreatedAt => "Created",
+            SortField::UpdatedAt => "Updated",
+            SortField::Priority => "Priority",
+        }
+    }
+
+    /// Direction used when a field is first chosen: highest/newest first
+    /// for ratings and dates, alphabetical otherwise.
+    pub fn default_order(&self) -> SortOrder {
+        match self {
+            SortField::Rating | SortField::CreatedAt | SortField::UpdatedAt => SortOrder::Desc,
+            _ => SortOrder::Asc,
+        }
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
 pub enum SortOrder {
+    #[default]
     Asc,
     Desc,
 }
 
-impl Default for SortOrder {
-    fn default() -> Self {
-        SortOrder::Asc
+impl SortOrder {
+    pub fn toggled(&self) -> SortOrder {
+        match self {
+            SortOrder::Asc => SortOrder::Desc,
+            SortOrder::Desc => SortOrder::Asc,
+        }
+    }
+
+    pub fn arrow(&self) -> &'static str {
+        match self {
+            SortOrder::Asc => "⬆",
+            SortOrder::Desc => "⬇",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SortKey {
+    pub field: SortField,
+    pub order: SortOrder,
+}
+
+impl SortKey {
+    pub fn new(field: SortField, order: SortOrder) -> Self {
+        Self { field, order }
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Query {
-    pub title_substr: String,
+    /// Search box text in the `search` module syntax.
+    pub search: String,
     pub category: Option<Category>,
     pub status: Option<Status>,
     pub min_rating: Option<u8>,
-    pub sort_field: SortField,
-    pub sort_order: SortOrder,
+    /// Only items with at least one copy (`true`) or without any (`false`).
+    pub owned: Option<bool>,
+    /// Only items lent and not returned (`true`) or not lent out (`false`).
+    pub on_loan: Option<bool>,
+    /// Sort keys in priority order; ties on all of them fall back to the item id.
+    pub sort: Vec<SortKey>,
+    /// Sort titles without a leading "The", "A", "Der", "Le", ...
+    pub ignore_articles: bool,
+    /// Maximum number of rows to return; `None` returns every match.
+    #[serde(skip)]
+    pub limit: Option<usize>,
+    /// Number of matching rows to skip before the first returned one.
+    #[serde(skip)]
+    pub offset: usize,
 }
 
-impl Default for Query {
-    fn default() -> Self {
+impl Query {
+    /// Reads a saved query. Queries saved before multi-key sorting hold a
+    /// single `sort_field`/`sort_order` pair instead of a `sort` list.
+    pub fn from_json_value(mut value: serde_json::Value) -> serde_json::Result<Query> {
+        if let Some(obj) = value.as_object_mut() {
+            if let (Some(field), Some(order)) = (obj.remove("sort_field"), obj.remove("sort_order"))
+            {
+                obj.entry("sort")
+                    .or_insert(serde_json::json!([{ "field": field, "order": order }]));
+            }
+        }
+        let mut query: Query = serde_json::from_value(value)?;
+        query.dedup_sort();
+        Ok(query)
+    }
+
+    /// Adds `terms` to the search, narrowing it rather than replacing it.
+    pub fn narrow(&mut self, terms: &str) {
+        if terms.trim().is_empty() {
+            return;
+        }
+        self.search = if self.search.trim().is_empty() {
+            terms.to_string()
+        } else {
+            format!("({}) {}", self.search, terms)
+        };
+    }
+
+    /// Same filters and sort, restricted to one page of results.
+    pub fn page(&self, offset: usize, limit: usize) -> Self {
         Self {
-            title_substr: String::new(),
+            limit: Some(limit),
+            offset,
+            ..self.clone()
+        }
+    }
+
+    /// Click on a sortable column: a plain click makes `field` the only key
+    /// (or flips it if it already is), `additive` appends it as a further
+    /// key (or flips it if it is already one of the keys).
+    pub fn toggle_sort(&mut self, field: SortField, additive: bool) {
+        let existing = self.sort.iter().position(|k| k.field == field);
+        match (additive, existing) {
+            (true, Some(i)) => self.sort[i].order = self.sort[i].order.toggled(),
+            (true, None) => self.sort.push(SortKey::new(field, field.default_order())),
+            (false, Some(0)) if self.sort.len() == 1 => {
+                self.sort[0].order = self.sort[0].order.toggled()
+            }
+            (false, _) => self.sort = vec![SortKey::new(field, field.default_order())],
+        }
+        self.dedup_sort();
+    }
+
+    /// Drops sort keys whose field already sorts earlier in the list; they
+    /// could never break a tie.
+    pub fn dedup_sort(&mut self) {
+        let mut seen = vec![];
+        self.sort.retain(|k| {
+            let first = !seen.contains(&k.field);
+            seen.push(k.field);
+            first
+        });
+    }
+
+    /// Same filters and sort, without any limit/offset.
+    pub fn unpaged(&self) -> Self {
+        Self {
+            limit: None,
+            offset: 0,
+            ..self.clone()
+        }
+    }
+}
+
+/// A named query ("smart list"), including its sort settings.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedSearch {
+    #[serde(skip)]
+    pub id: Option<i64>,
+    pub name: String,
+    #[serde(deserialize_with = "saved_query")]
+    pub query: Query,
+}
+
+// Query of a saved search, possibly written before multi-key sorting.
+fn saved_query<'de, D: Deserializer<'de>>(d: D) -> Result<Query, D::Error> {
+    Query::from_json_value(serde_json::Value::deserialize(d)?).map_err(serde::de::Error::custom)
+}
+
+/// A consumption goal, e.g. "read 24 books in 2026" or "finish 10 games
+/// from the backlog by Q2". Items count towards it when they are finished
+/// between `start` and `end` (inclusive).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Goal {
+    pub id: Option<i64>,
+    pub name: String,
+    pub category: Option<Category>,
+    pub target: u32,
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+    /// Only count items that were already in the catalog when the goal started.
+    pub backlog_only: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Condition {
+    New,
+    LikeNew,
+    Good,
+    Fair,
+    Poor,
+}
+
+impl Condition {
+    pub const ALL: [Condition; 5] = [
+        Condition::New,
+        Condition::LikeNew,
+        Condition::Good,
+        Condition::Fair,
+        Condition::Poor,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Condition::New => "New",
+            Condition::LikeNew => "Like new",
+            Condition::Good => "Good",
+            Condition::Fair => "Fair",
+            Condition::Poor => "Poor",
+        }
+    }
+}
+
+impl std::fmt::Display for Condition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// One copy of an item that is owned, e.g. a hardcover on the living room
+/// shelf or a game in a Steam library. An item may have several.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OwnedCopy {
+    pub id: Option<i64>,
+    pub item_id: i64,
+    /// Free text such as "Hardcover", "Blu-ray", "Steam" or "Vinyl".
+    pub format: String,
+    /// Shelf, box or platform.
+    pub location: String,
+    pub condition: Option<Condition>,
+    pub purchased_on: Option<NaiveDate>,
+    pub price: Option<f64>,
+}
+
+impl OwnedCopy {
+    pub fn new(item_id: i64) -> Self {
+        Self {
+            id: None,
+            item_id,
+            format: String::new(),
+            location: String::new(),
+            condition: None,
+            purchased_on: None,
+            price: None,
+        }
+    }
+}
+
+/// An item, or one copy of it, lent to someone.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Loan {
+    pub id: Option<i64>,
+    pub item_id: i64,
+    /// The copy lent, if the item has several.
+    pub copy_id: Option<i64>,
+    pub borrower: String,
+    pub lent_on: NaiveDate,
+    pub due_on: Option<NaiveDate>,
+    pub returned_on: Option<NaiveDate>,
+}
+
+impl Loan {
+    pub fn new(item_id: i64) -> Self {
+        Self {
+            id: None,
+            item_id,
+            copy_id: None,
+            borrower: String::new(),
+            lent_on: Local::now().date_naive(),
+            due_on: None,
+            returned_on: None,
+        }
+    }
+
+    /// Not returned yet.
+    pub fn is_open(&self) -> bool {
+        self.returned_on.is_none()
+    }
+
+    /// Not returned and past its due date on `today`.
+    pub fn is_overdue(&self, today: NaiveDate) -> bool {
+        self.is_open() && self.due_on.is_some_and(|due| due < today)
+    }
+}
+
+/// A folder watched for new files, which are added to the catalog as
+/// `category` items with `status`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WatchFolder {
+    pub id: Option<i64>,
+    pub path: String,
+    pub category: Category,
+    pub status: Status,
+    pub enabled: bool,
+}
+
+impl WatchFolder {
+    pub fn new(path: impl Into<String>, category: Category) -> Self {
+        Self {
+            id: None,
+            path: path.into(),
+            category,
+            status: Status::Planned,
+            enabled: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pace {
+    Ahead,
+    OnTrack,
+    Behind,
+    Reached,
+    Missed,
+    NotStarted,
+}
+
+impl Pace {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Pace::Ahead => "ahead of schedule",
+            Pace::OnTrack => "on track",
+            Pace::Behind => "behind schedule",
+            Pace::Reached => "reached",
+            Pace::Missed => "missed",
+            Pace::NotStarted => "not started",
+        }
+    }
+}
+
+impl Goal {
+    pub fn new(name: impl Into<String>, target: u32, start: NaiveDate, end: NaiveDate) -> Self {
+        Self {
+            id: None,
+            name: name.into(),
             category: None,
-            status: None,
-            min_rating: None,
-            sort_field: SortField::default(),
-            sort_order: SortOrder::default(),
+            target,
+            start,
+            end,
+            backlog_only: false,
+        }
+    }
+
+    /// Whole calendar year `year`.
+    pub fn year_range(year: i32) -> (NaiveDate, NaiveDate) {
+        (
+            NaiveDate::from_ymd_opt(year, 1, 1).unwrap(),
+            NaiveDate::from_ymd_opt(year, 12, 31).unwrap(),
+        )
+    }
+
+    /// Quarter `quarter` (1-4) of `year`.
+    pub fn quarter_range(year: i32, quarter: u32) -> (NaiveDate, NaiveDate) {
+        let start = NaiveDate::from_ymd_opt(year, (quarter - 1) * 3 + 1, 1).unwrap();
+        let end = if quarter == 4 {
+            NaiveDate::from_ymd_opt(year, 12, 31).unwrap()
+        } else {
+            NaiveDate::from_ymd_opt(year, quarter * 3 + 1, 1)
+                .unwrap()
+                .pred_opt()
+                .unwrap()
+        };
+        (start, end)
+    }
+
+    /// How many items should be done by `today` to finish evenly over the range.
+    pub fn expected_by(&self, today: NaiveDate) -> f64 {
+        let total = (self.end - self.start).num_days() + 1;
+        let elapsed = ((today - self.start).num_days() + 1).clamp(0, total);
+        self.target as f64 * elapsed as f64 / total as f64
+    }
+
+    pub fn pace(&self, done: usize, today: NaiveDate) -> Pace {
+        if done as u32 >= self.target {
+            return Pace::Reached;
+        }
+        if today < self.start {
+            return Pace::NotStarted;
+        }
+        if today > self.end {
+            return Pace::Missed;
+        }
+        let expected = self.expected_by(today);
+        // Within one item of the even pace counts as on track.
+        if done as f64 >= expected + 1.0 {
+            Pace::Ahead
+        } else if done as f64 + 1.0 > expected {
+            Pace::OnTrack
+        } else {
+            Pace::Behind
         }
     }
+
+    pub fn current_year() -> i32 {
+        Local::now().year()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields(query: &Query) -> Vec<(SortField, SortOrder)> {
+        query.sort.iter().map(|k| (k.field, k.order)).collect()
+    }
+
+    #[test]
+    fn toggle_sort_keeps_one_key_per_field() {
+        let mut query = Query::default();
+        query.toggle_sort(SortField::Title, false);
+        query.toggle_sort(SortField::Rating, true);
+        query.toggle_sort(SortField::Rating, true);
+        assert_eq!(
+            fields(&query),
+            vec![
+                (SortField::Title, SortOrder::Asc),
+                (SortField::Rating, SortOrder::Asc)
+            ]
+        );
+        query.toggle_sort(SortField::Rating, false);
+        assert_eq!(fields(&query), vec![(SortField::Rating, SortOrder::Desc)]);
+    }
+
+    #[test]
+    fn dedup_sort_keeps_the_first_key() {
+        let mut query = Query {
+            sort: vec![
+                SortKey::new(SortField::Title, SortOrder::Desc),
+                SortKey::new(SortField::Rating, SortOrder::Asc),
+                SortKey::new(SortField::Title, SortOrder::Asc),
+            ],
+            ..Default::default()
+        };
+        query.dedup_sort();
+        assert_eq!(
+            fields(&query),
+            vec![
+                (SortField::Title, SortOrder::Desc),
+                (SortField::Rating, SortOrder::Asc)
+            ]
+        );
+    }
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn quarter_ranges_cover_whole_months() {
+        assert_eq!(
+            Goal::quarter_range(2024, 1),
+            (date(2024, 1, 1), date(2024, 3, 31))
+        );
+        assert_eq!(
+            Goal::quarter_range(2024, 2),
+            (date(2024, 4, 1), date(2024, 6, 30))
+        );
+        assert_eq!(
+            Goal::quarter_range(2023, 3),
+            (date(2023, 7, 1), date(2023, 9, 30))
+        );
+        assert_eq!(
+            Goal::quarter_range(2024, 4),
+            (date(2024, 10, 1), date(2024, 12, 31))
+        );
+        assert_eq!(
+            Goal::year_range(2024),
+            (date(2024, 1, 1), date(2024, 12, 31))
+        );
+    }
+
+    #[test]
+    fn pace_compares_progress_with_an_even_spread() {
+        // 10 items over 10 days: one a day is on track.
+        let goal = Goal::new("Ten", 10, date(2024, 1, 1), date(2024, 1, 10));
+        assert_eq!(goal.expected_by(date(2024, 1, 5)), 5.0);
+        assert_eq!(goal.expected_by(date(2023, 12, 1)), 0.0);
+        assert_eq!(goal.expected_by(date(2024, 2, 1)), 10.0);
+
+        let on = date(2024, 1, 5);
+        assert_eq!(goal.pace(6, on), Pace::Ahead);
+        assert_eq!(goal.pace(5, on), Pace::OnTrack);
+        assert_eq!(goal.pace(4, on), Pace::Behind);
+        assert_eq!(goal.pace(10, on), Pace::Reached);
+        assert_eq!(goal.pace(0, date(2023, 12, 31)), Pace::NotStarted);
+        assert_eq!(goal.pace(9, date(2024, 1, 11)), Pace::Missed);
+        assert_eq!(goal.pace(10, date(2024, 1, 11)), Pace::Reached);
+    }
+
+    #[test]
+    fn leaving_the_wishlist_clears_its_details() {
+        let mut item = MediaItem::new("Dune", Category::Book);
+        item.set_status(Status::InProgress);
+        item.set_status(Status::Wishlist);
+        assert_eq!((item.started_at, item.finished_at), (None, None));
+        item.priority = Some(Priority::High);
+        item.target_price = Some(12.5);
+        item.store = Some("https://example.com/dune".into());
+
+        let mut bought = item.clone();
+        bought.set_status(Status::Wishlist);
+        assert_eq!(bought.priority, Some(Priority::High));
+        bought.set_status(Status::Planned);
+        assert_eq!(
+            (bought.priority, bought.target_price, bought.store),
+            (None, None, None)
+        );
+
+        item.set_status(Status::Finished);
+        assert!(item.finished_at.is_some());
+        assert_eq!((item.priority, item.store), (None, None));
+    }
 }
@@ -0,0 +1,112 @@
+use crate::models::{Query, Status};
+use crate::recommend::{self, Suggestion};
+use crate::repo::{RepoResult, Repository};
+use eframe::egui;
+
+/// "What should I do next?" window with weighted suggestions from the backlog.
+pub struct PickerView {
+    pub open: bool,
+    count: usize,
+    use_filters: bool,
+    suggestions: Vec<Suggestion>,
+    rng: fastrand::Rng,
+}
+
+impl Default for PickerView {
+    fn default() -> Self {
+        Self {
+            open: false,
+            count: 3,
+            use_filters: false,
+            suggestions: vec![],
+            rng: fastrand::Rng::new(),
+        }
+    }
+}
+
+impl PickerView {
+    pub fn pick(&mut self, repo: &dyn Repository, query: &Query) -> RepoResult<()> {
+        let query = if self.use_filters {
+            query.clone()
+        } else {
+            Query::default()
+        };
+        self.suggestions = recommend::suggest(repo, &query, self.count, &mut self.rng)?;
+        Ok(())
+    }
+
+    /// Returns true when an item was started and the catalog needs a refresh.
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        repo: &dyn Repository,
+        query: &Query,
+    ) -> RepoResult<bool> {
+        let mut open = self.open;
+        let mut result = Ok(false);
+        egui::Window::new("What next?")
+            .open(&mut open)
+            .default_width(420.0)
+            .show(ctx, |ui| {
+                result = self.picker_ui(ui, repo, query);
+            });
+        self.open = open;
+        result
+    }
+
+    fn picker_ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        repo: &dyn Repository,
+        query: &Query,
+    ) -> RepoResult<bool> {
+        let mut pick = false;
+        ui.horizontal(|ui| {
+            pick = ui.button("🎲 Pick again").clicked();
+            ui.add(egui::DragValue::new(&mut self.count).clamp_range(1..=10))
+                .on_hover_text("Number of suggestions");
+            pick |= ui
+                .checkbox(&mut self.use_filters, "Use current filters")
+                .on_hover_text("Without a status filter only Planned items are suggested")
+                .changed();
+        });
+        if pick {
+            self.pick(repo, query)?;
+        }
+        ui.separator();
+        if self.suggestions.is_empty() {
+            ui.weak("Nothing to suggest: no matching items.");
+        }
+        let mut started = None;
+        for (i, suggestion) in self.suggestions.iter().enumerate() {
+            let item = &suggestion.item;
+            ui.horizontal(|ui| {
+                ui.strong(&item.title);
+                ui.weak(format!(
+                    "{} · {:.1}% chance",
+                    item.category,
+                    suggestion.chance * 100.0
+                ));
+                if item.status != Status::InProgress
+                    && ui
+                        .small_button("Start")
+                        .on_hover_text("Mark as In Progress")
+                        .clicked()
+                {
+                    started = Some(i);
+                }
+            });
+            for reason in &suggestion.reasons {
+                ui.label(format!("  • {}", reason));
+            }
+            ui.add_space(4.0);
+        }
+        if let Some(i) = started {
+            let mut suggestion = self.suggestions.remove(i);
+            suggestion.item.set_status(Status::InProgress);
+            repo.update(&suggestion.item)?;
+            return Ok(true);
+        }
+        Ok(false)
+    }
+}
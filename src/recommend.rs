@@ -0,0 +1,356 @@
+use crate::dedup;
+use crate::models::{MediaItem, Query, Status};
+use crate::repo::{RepoResult, Repository};
+use crate::search::{self, Field};
+use std::collections::HashMap;
+
+// Ratings a category or tag needs before it counts as a preference.
+const MIN_RATINGS: usize = 3;
+const NEXT_IN_SERIES_BOOST: f64 = 3.0;
+const OUT_OF_ORDER_PENALTY: f64 = 0.25;
+// Words between a series name and its number, e.g. "Dune Book 2".
+const SERIES_WORDS: [&str; 8] = [
+    "part", "book", "vol", "volume", "season", "episode", "chapter", "no",
+];
+const ROMAN: [&str; 10] = ["i", "ii", "iii", "iv", "v", "vi", "vii", "viii", "ix", "x"];
+
+/// A suggested item and why it was picked.
+pub struct Suggestion {
+    pub item: MediaItem,
+    pub reasons: Vec<String>,
+    /// Chance this item had of being the first pick, 0..=1.
+    pub chance: f64,
+}
+
+/// Picks up to `count` distinct items matching `query` at random, weighted
+/// towards well-rated categories and tags, the next part of a series in
+/// progress, and items that have waited longest. Unless the query filters
+/// by status, only Planned items are considered.
+pub fn suggest(
+    repo: &dyn Repository,
+    query: &Query,
+    count: usize,
+    rng: &mut fastrand::Rng,
+) -> RepoResult<Vec<Suggestion>> {
+    let mut query = query.unpaged();
+    let filters_status = search::parse(&query.search).is_ok_and(|e| e.mentions(Field::Status));
+    if query.status.is_none() && !filters_status {
+        query.status = Some(Status::Planned);
+    }
+    let candidates = repo.list(&query)?;
+    if candidates.is_empty() {
+        return Ok(vec![]);
+    }
+    let catalog = repo.list(&Query::default())?;
+    let taste = Taste::new(&catalog);
+    let series = Series::new(&catalog);
+
+    // 1.0 for the oldest candidate down to almost 0 for the newest.
+    let n = candidates.len();
+    let mut by_age: Vec<usize> = (0..n).collect();
+    by_age.sort_by_key(|&i| (candidates[i].created_at, candidates[i].id));
+    let mut age = vec![0.0; n];
+    for (rank, &i) in by_age.iter().enumerate() {
+        age[i] = 1.0 - rank as f64 / n as f64;
+    }
+
+    let mut pool: Vec<(f64, Suggestion)> = candidates
+        .into_iter()
+        .zip(age)
+        .map(|(item, age)| {
+            let mut reasons = vec![];
+            let mut weight = taste.category_weight(&item, &mut reasons)
+                * taste.tag_weight(&item, &mut reasons)
+                * series.weight(&item, &mut reasons)
+                * (1.0 + age);
+            if n >= 4 && age > 0.75 {
+                reasons.push(format!(
+                    "Waiting since {}, among the oldest quarter of the backlog",
+                    item.created_at.format("%Y-%m-%d")
+                ));
+            }
+            if reasons.is_empty() {
+                reasons.push(format!("Random pick from {} candidates", n));
+            }
+            weight = weight.max(f64::MIN_POSITIVE);
+            let suggestion = Suggestion {
+                item,
+                reasons,
+                chance: 0.0,
+            };
+            (weight, suggestion)
+        })
+        .collect();
+    let total: f64 = pool.iter().map(|(w, _)| w).sum();
+    for (w, s) in &mut pool {
+        s.chance = *w / total;
+    }
+
+    let mut picks = vec![];
+    while picks.len() < count && !pool.is_empty() {
+        let remaining: f64 = pool.iter().map(|(w, _)| w).sum();
+        let mut r = rng.f64() * remaining;
+        let i = pool
+            .iter()
+            .position(|(w, _)| {
+                r -= w;
+                r < 0.0
+            })
+            .unwrap_or(pool.len() - 1);
+        picks.push(pool.swap_remove(i).1);
+    }
+    Ok(picks)
+}
+
+// Weight multiplier for a rating `diff` points above (or below) the overall
+// average: +2 doubles the chance, -2 halves it.
+fn preference(diff: f64) -> f64 {
+    2f64.powf(diff / 2.0)
+}
+
+/// Average ratings per category and per tag.
+struct Taste {
+    overall: f64,
+    categories: HashMap<&'static str, (f64, usize)>,
+    tags: HashMap<String, (f64, usize)>,
+}
+
+impl Taste {
+    fn new(items: &[MediaItem]) -> Self {
+        let mut sum = 0.0;
+        let mut rated = 0;
+        let mut categories: HashMap<&'static str, (f64, usize)> = HashMap::new();
+        let mut tags: HashMap<String, (f64, usize)> = HashMap::new();
+        for item in items {
+            let Some(rating) = item.rating else {
+                continue;
+            };
+            let rating = rating as f64;
+            sum += rating;
+            rated += 1;
+            let entry = categories.entry(item.category.as_str()).or_default();
+            entry.0 += rating;
+            entry.1 += 1;
+            for tag in item.tags() {
+                let entry = tags.entry(tag).or_default();
+                entry.0 += rating;
+                entry.1 += 1;
+            }
+        }
+        let average = |(sum, n): &mut (f64, usize)| *sum /= *n as f64;
+        categories.values_mut().for_each(average);
+        tags.values_mut().for_each(average);
+        Self {
+            overall: if rated > 0 { sum / rated as f64 } else { 0.0 },
+            categories,
+            tags,
+        }
+    }
+
+    fn category_weight(&self, item: &MediaItem, reasons: &mut Vec<String>) -> f64 {
+        match self.categories.get(item.category.as_str()) {
+            Some(&(avg, n)) if n >= MIN_RATINGS => {
+                let diff = avg - self.overall;
+                if diff >= 0.5 {
+                    reasons.push(format!(
+                        "You rate {} items {:.1} on average (overall {:.1})",
+                        item.category, avg, self.overall
+                    ));
+                }
+                preference(diff)
+            }
+            _ => 1.0,
+        }
+    }
+
+    fn tag_weight(&self, item: &MediaItem, reasons: &mut Vec<String>) -> f64 {
+        let mut weight = 1.0;
+        let mut favourite: Option<(String, f64)> = None;
+        for tag in item.tags() {
+            let Some(&(avg, n)) = self.tags.get(&tag) else {
+                continue;
+            };
+            if n < MIN_RATINGS {
+                continue;
+            }
+            let diff = avg - self.overall;
+            weight *= preference(diff);
+            if diff >= 0.5 && favourite.as_ref().is_none_or(|(_, best)| avg > *best) {
+                favourite = Some((tag, avg));
+            }
+        }
+        if let Some((tag, avg)) = favourite {
+            reasons.push(format!(
+                "Tagged #{}, which you rate {:.1} on average",
+                tag, avg
+            ));
+        }
+        weight.clamp(0.25, 4.0)
+    }
+}
+
+/// Items grouped by series name, each with its number in the series.
+struct Series<'a> {
+    parts: HashMap<String, Vec<(u32, &'a MediaItem)>>,
+}
+
+impl<'a> Series<'a> {
+    fn new(items: &'a [MediaItem]) -> Self {
+        let mut parts: HashMap<String, Vec<(u32, &MediaItem)>> = HashMap::new();
+        for item in items {
+            // An unnumbered title counts as the first part ("Dune", "Dune 2").
+            let (name, number) =
+                series_key(&item.title).unwrap_or_else(|| (dedup::normalize_title(&item.title), 1));
+            parts.entry(name).or_default().push((number, item));
+        }
+        Self { parts }
+    }
+
+    fn weight(&self, item: &MediaItem, reasons: &mut Vec<String>) -> f64 {
+        let Some((name, number)) = series_key(&item.title) else {
+            return 1.0;
+        };
+        let Some(parts) = self.parts.get(&name) else {
+            return 1.0;
+        };
+        if number < 2 {
+            return 1.0;
+        }
+        let previous = parts
+            .iter()
+            .find(|(n, i)| *n == number - 1 && i.status == Status::Finished);
+        if let Some((_, prev)) = previous {
+            reasons.push(format!(
+                "Next in series after \"{}\", which you finished",
+                prev.title
+            ));
+            return NEXT_IN_SERIES_BOOST;
+        }
+        let earlier = parts
+            .iter()
+            .filter(|(n, i)| *n < number && i.status != Status::Finished)
+            .min_by_key(|(n, _)| *n);
+        if let Some((_, first)) = earlier {
+            reasons.push(format!("\"{}\" comes earlier in the series", first.title));
+            return OUT_OF_ORDER_PENALTY;
+        }
+        1.0
+    }
+}
+
+/// Series name and number for titles ending in a number, e.g.
+/// "Dune: Book 2" gives ("dune", 2) and "Rocky IV" gives ("rocky", 4).
+fn series_key(title: &str) -> Option<(String, u32)> {
+    let normalized = dedup::normalize_title(title);
+    let mut words: Vec<&str> = normalized.split_whitespace().collect();
+    let last = words.pop()?;
+    let number = match last.parse::<u32>() {
+        Ok(n) => n,
+        Err(_) => ROMAN.iter().position(|r| *r == last)? as u32 + 1,
+    };
+    while words.last().is_some_and(|w| SERIES_WORDS.contains(w)) {
+        words.pop();
+    }
+    if words.is_empty() {
+        return None;
+    }
+    Some((words.join(" "), number))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Category;
+    use crate::sqlite_repo::SqliteRepo;
+    use std::path::Path;
+
+    fn repo_with(items: &[(&str, Status)]) -> SqliteRepo {
+        let repo = SqliteRepo::new(Path::new(":memory:"));
+        repo.init().unwrap();
+        for (title, status) in items {
+            let mut item = MediaItem::new(*title, Category::Book);
+            item.status = *status;
+            repo.add(&mut item).unwrap();
+        }
+        repo
+    }
+
+    fn suggested_titles(repo: &SqliteRepo, search: &str) -> Vec<String> {
+        let query = Query {
+            search: search.into(),
+            ..Default::default()
+        };
+        let mut titles: Vec<String> = suggest(repo, &query, 10, &mut fastrand::Rng::with_seed(1))
+            .unwrap()
+            .into_iter()
+            .map(|s| s.item.title)
+            .collect();
+        titles.sort();
+        titles
+    }
+
+    #[test]
+    fn reads_series_numbers() {
+        assert_eq!(series_key("Dune: Book 2"), Some(("dune".into(), 2)));
+        assert_eq!(series_key("Rocky IV"), Some(("rocky".into(), 4)));
+        assert_eq!(series_key("The Hobbit"), None);
+        assert_eq!(series_key("1984"), None);
+    }
+
+    #[test]
+    fn only_planned_items_unless_the_search_filters_by_status() {
+        let repo = repo_with(&[
+            ("Dune", Status::Planned),
+            ("Emma", Status::Finished),
+            ("status: unknown", Status::InProgress),
+        ]);
+        assert_eq!(suggested_titles(&repo, ""), vec!["Dune"]);
+        assert_eq!(
+            suggested_titles(&repo, "\"status: unknown\""),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            suggested_titles(&repo, "-status:planned"),
+            vec!["Emma", "status: unknown"]
+        );
+        assert_eq!(
+            suggested_titles(&repo, "status!planned"),
+            vec!["Emma", "status: unknown"]
+        );
+        assert_eq!(
+            suggested_titles(&repo, "status:done OR dune"),
+            vec!["Dune", "Emma"]
+        );
+    }
+
+    #[test]
+    fn favours_the_next_part_of_a_series() {
+        let repo = repo_with(&[
+            ("Dune", Status::Finished),
+            ("Dune 2", Status::Planned),
+            ("Foundation 2", Status::Planned),
+            ("Foundation", Status::Planned),
+        ]);
+        let picks = suggest(
+            &repo,
+            &Query::default(),
+            3,
+            &mut fastrand::Rng::with_seed(7),
+        )
+        .unwrap();
+        let chance = |title: &str| {
+            picks
+                .iter()
+                .find(|s| s.item.title == title)
+                .map(|s| (s.chance, s.reasons.clone()))
+                .unwrap()
+        };
+        let (dune, reasons) = chance("Dune 2");
+        assert!(reasons[0].starts_with("Next in series after \"Dune\""));
+        let (foundation, reasons) = chance("Foundation 2");
+        assert!(reasons.contains(&"\"Foundation\" comes earlier in the series".to_string()));
+        assert!(dune > 4.0 * foundation);
+        let total: f64 = picks.iter().map(|s| s.chance).sum();
+        assert!((total - 1.0).abs() < 1e-9);
+    }
+}
@@ -1,10 +1,22 @@
-use crate::models::{MediaItem, Query};
+use crate::export::ExportPreset;
+use crate::ids::{ExternalId, IdError};
+use crate::models::{Category, Goal, Loan, MediaItem, OwnedCopy, Query, SavedSearch, WatchFolder};
+use crate::scan::ScannedFile;
+use crate::search::ParseError;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
 pub enum RepoError {
     #[error("SQLite error: {0}")]
     Sqlite(#[from] rusqlite::Error),
+    #[error("Invalid search: {0}")]
+    Search(#[from] ParseError),
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("{0}")]
+    Id(#[from] IdError),
+    #[error("{0} already belongs to another item")]
+    DuplicateId(ExternalId),
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
     #[error("Other: {0}")]
@@ -19,8 +31,68 @@ pub trait Repository: Send + Sync {
     fn update(&self, item: &MediaItem) -> RepoResult<()>;
     fn delete(&self, id: i64) -> RepoResult<()>;
     fn get(&self, id: i64) -> RepoResult<Option<MediaItem>>;
+    /// The item that has `id`, if any.
+    fn find_by_external_id(&self, id: &ExternalId) -> RepoResult<Option<MediaItem>>;
     fn list(&self, query: &Query) -> RepoResult<Vec<MediaItem>>;
+    fn count(&self, query: &Query) -> RepoResult<usize>;
+    /// All titles in `category`, for duplicate checks.
+    fn titles(&self, category: Category) -> RepoResult<Vec<String>>;
+    /// Saves `merged` and deletes the `duplicates` folded into it, atomically.
+    fn merge(&self, merged: &MediaItem, duplicates: &[i64]) -> RepoResult<()>;
     fn stats(&self) -> RepoResult<Stats>;
+    fn detailed_stats(&self) -> RepoResult<DetailedStats>;
+
+    fn list_saved_searches(&self) -> RepoResult<Vec<SavedSearch>>;
+    /// Inserts the search, or replaces the query of an existing one with the same name.
+    fn save_search(&self, search: &mut SavedSearch) -> RepoResult<i64>;
+    fn delete_saved_search(&self, id: i64) -> RepoResult<()>;
+
+    fn list_export_presets(&self) -> RepoResult<Vec<ExportPreset>>;
+    /// Inserts the preset, or replaces the options of an existing one with the same name.
+    fn save_export_preset(&self, preset: &mut ExportPreset) -> RepoResult<i64>;
+    fn delete_export_preset(&self, id: i64) -> RepoResult<()>;
+
+    fn list_goals(&self) -> RepoResult<Vec<Goal>>;
+    /// Inserts the goal if it has no id yet, otherwise updates it.
+    fn save_goal(&self, goal: &mut Goal) -> RepoResult<i64>;
+    fn delete_goal(&self, id: i64) -> RepoResult<()>;
+    /// Number of items finished so far that count towards `goal`.
+    fn goal_progress(&self, goal: &Goal) -> RepoResult<usize>;
+
+    fn get_setting(&self, key: &str) -> RepoResult<Option<String>>;
+    fn set_setting(&self, key: &str, value: &str) -> RepoResult<()>;
+
+    /// Files recorded by earlier scans of `dir` or its subfolders.
+    fn scanned_files(&self, dir: &str) -> RepoResult<Vec<ScannedFile>>;
+    /// Inserts the file, or replaces the record with the same path.
+    fn save_scanned_file(&self, file: &ScannedFile) -> RepoResult<()>;
+    fn delete_scanned_file(&self, path: &str) -> RepoResult<()>;
+
+    /// Copies of item `item_id`, in the order they were added.
+    fn list_copies(&self, item_id: i64) -> RepoResult<Vec<OwnedCopy>>;
+    /// Copies of the items matching `query`, each with its item, in the
+    /// query's order.
+    fn inventory(&self, query: &Query) -> RepoResult<Vec<(MediaItem, OwnedCopy)>>;
+    /// Inserts the copy if it has no id yet, otherwise updates it.
+    fn save_copy(&self, copy: &mut OwnedCopy) -> RepoResult<i64>;
+    /// Saves `item` and inserts or updates `copy`, atomically.
+    fn save_purchase(&self, item: &MediaItem, copy: &mut OwnedCopy) -> RepoResult<i64>;
+    fn delete_copy(&self, id: i64) -> RepoResult<()>;
+
+    /// Loans of item `item_id`, most recent first.
+    fn list_loans(&self, item_id: i64) -> RepoResult<Vec<Loan>>;
+    /// Loans not returned yet, of any item.
+    fn open_loans(&self) -> RepoResult<Vec<Loan>>;
+    /// Loans of the items matching `query`, each with its item, most recent first.
+    fn loans(&self, query: &Query) -> RepoResult<Vec<(MediaItem, Loan)>>;
+    /// Inserts the loan if it has no id yet, otherwise updates it.
+    fn save_loan(&self, loan: &mut Loan) -> RepoResult<i64>;
+    fn delete_loan(&self, id: i64) -> RepoResult<()>;
+
+    fn list_watch_folders(&self) -> RepoResult<Vec<WatchFolder>>;
+    /// Inserts the folder if it has no id yet, otherwise updates it.
+    fn save_watch_folder(&self, folder: &mut WatchFolder) -> RepoResult<i64>;
+    fn delete_watch_folder(&self, id: i64) -> RepoResult<()>;
 }
 
 #[derive(Debug, Clone, Default)]
@@ -28,5 +100,22 @@ pub struct Stats {
     pub total: usize,
     pub by_category: Vec<(String, usize)>,
     pub finished: usize,
+    /// Owned items not finished yet; `total` is this plus `finished` and `wishlist`.
     pub unfinished: usize,
+    pub wishlist: usize,
+}
+
+/// Aggregates behind the statistics dashboard. Months are `YYYY-MM` in local
+/// time, oldest first, with no gaps between the first and last month.
+#[derive(Debug, Clone, Default)]
+pub struct DetailedStats {
+    pub finished_per_month: Vec<(String, usize)>,
+    pub finished_per_year: Vec<(i32, usize)>,
+    /// Number of rated items for each rating 0..=10.
+    pub rating_histogram: [usize; 11],
+    pub avg_rating_by_category: Vec<(String, f64)>,
+    /// Days from start (or creation, if never started) to finish, per finished item.
+    pub days_to_finish: Vec<i64>,
+    /// Unfinished items at the end of each month, leaving out the wishlist.
+    pub backlog_per_month: Vec<(String, i64)>,
 }
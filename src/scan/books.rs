@@ -0,0 +1,480 @@
+use super::{relink, save_cover, walk, FileState, ScanReport};
+use crate::ids::{ExternalId, IdScheme};
+use crate::import::{add_tags, notes, tag};
+use crate::models::{Category, MediaItem};
+use crate::repo::{RepoResult, Repository};
+use chrono::Local;
+use roxmltree::{Document, Node, ParsingOptions};
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+use zip::ZipArchive;
+
+const BOOK_EXTENSIONS: [&str; 2] = ["epub", "pdf"];
+
+// What an e-book file says about itself.
+#[derive(Default)]
+struct Book {
+    title: Option<String>,
+    creators: Vec<String>,
+    series: Option<String>,
+    year: Option<String>,
+    isbn: Option<ExternalId>,
+    subjects: Vec<String>,
+    cover: Option<(Vec<u8>, String)>,
+    format: &'static str,
+}
+
+/// Scans `dir` for EPUB and PDF files and creates or updates one Book item
+/// per file, linked to the file. Files with the ISBN of a catalog item are
+/// linked to that item instead. EPUB covers are copied to `covers`. As with
+/// music, unchanged files are not read again.
+pub fn scan(repo: &dyn Repository, dir: &Path, covers: &Path) -> RepoResult<ScanReport> {
+    let mut report = ScanReport::default();
+    for (mut file, state) in walk(repo, dir, &BOOK_EXTENSIONS, &mut report)? {
+        match state {
+            FileState::Unchanged => {}
+            FileState::Moved => {
+                if let Some(id) = file.item_id {
+                    if relink(repo, id, &file.path)? {
+                        report.updated += 1;
+                    }
+                }
+            }
+            FileState::Changed => {
+                let path = Path::new(&file.path);
+                let book = match read_book(path) {
+                    Ok(book) => book,
+                    Err(e) => {
+                        report.errors.push(format!("{}: {}", path.display(), e));
+                        continue;
+                    }
+                };
+                report.read += 1;
+                file.group = file.path.clone();
+                let mut existing = match file.item_id {
+                    Some(id) => repo.get(id)?,
+                    None => None,
+                };
+                if existing.is_none() {
+                    if let Some(isbn) = &book.isbn {
+                        existing = repo.find_by_external_id(isbn)?;
+                    }
+                }
+                file.item_id = match existing {
+                    Some(item) => {
+                        if let Some(updated) = book.update(&item, &file.path, covers)? {
+                            repo.update(&updated)?;
+                            report.updated += 1;
+                        }
+                        item.id
+                    }
+                    None => {
+                        let mut item = book.to_item(path, covers)?;
+                        Some(report.add(repo, &mut item)?)
+                    }
+                };
+            }
+        }
+        repo.save_scanned_file(&file)?;
+    }
+    Ok(report)
+}
+
+fn read_book(path: &Path) -> Result<Book, Box<dyn Error>> {
+    let is_pdf = path
+        .extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("pdf"));
+    if is_pdf {
+        read_pdf(path)
+    } else {
+        read_epub(path)
+    }
+}
+
+// EPUB metadata lives in the package document (OPF) that
+// META-INF/container.xml points to.
+fn read_epub(path: &Path) -> Result<Book, Box<dyn Error>> {
+    let mut archive = ZipArchive::new(File::open(path)?)?;
+    let container = String::from_utf8(read_entry(&mut archive, "META-INF/container.xml")?)?;
+    let container = parse_xml(&container)?;
+    let opf_path = container
+        .descendants()
+        .find(|n| n.has_tag_name("rootfile"))
+        .and_then(|n| n.attribute("full-path"))
+        .ok_or("no package document in META-INF/container.xml")?
+        .to_string();
+    let opf = String::from_utf8(read_entry(&mut archive, &opf_path)?)?;
+    let opf = parse_xml(&opf)?;
+    let metadata = opf
+        .descendants()
+        .find(|n| n.has_tag_name("metadata"))
+        .ok_or("no metadata in the package document")?;
+    let elements = |name: &'static str| {
+        metadata
+            .children()
+            .filter(move |n| n.is_element() && n.tag_name().name() == name)
+    };
+    let texts = |name: &'static str| {
+        elements(name)
+            .filter_map(|n| n.text())
+            .map(|t| t.trim().to_string())
+            .filter(|t| !t.is_empty())
+    };
+    // EPUB 2 metadata uses <meta name content>, EPUB 3 <meta property>text</meta>.
+    let meta = |name: &str| {
+        elements("meta")
+            .find(|n| n.attribute("name") == Some(name))
+            .and_then(|n| n.attribute("content"))
+            .map(|c| c.trim().to_string())
+    };
+
+    let mut book = Book {
+        format: "epub",
+        title: texts("title").next(),
+        creators: texts("creator").collect(),
+        year: texts("date").next().map(|d| d.chars().take(4).collect()),
+        subjects: texts("subject").collect(),
+        ..Book::default()
+    };
+    book.isbn = elements("identifier").find_map(|n| {
+        let scheme = n
+            .attributes()
+            .find(|a| a.name() == "scheme")
+            .map(|a| a.value());
+        let text = n.text()?.trim();
+        let lower = text.to_lowercase();
+        let text = lower
+            .strip_prefix("urn:isbn:")
+            .or_else(|| lower.strip_prefix("isbn:"))
+            .or(scheme
+                .filter(|s| s.eq_ignore_ascii_case("isbn"))
+                .map(|_| lower.as_str()))?;
+        ExternalId::new(IdScheme::Isbn, text).ok()
+    });
+    // Calibre writes the series as meta tags; EPUB 3 as a collection.
+    let series = meta("calibre:series").map(|s| (s, meta("calibre:series_index")));
+    let series = series.or_else(|| {
+        let collection =
+            elements("meta").find(|n| n.attribute("property") == Some("belongs-to-collection"))?;
+        let name = collection.text()?.trim().to_string();
+        let position = collection.attribute("id").and_then(|id| {
+            let refines = format!("#{}", id);
+            elements("meta")
+                .find(|n| {
+                    n.attribute("refines") == Some(refines.as_str())
+                        && n.attribute("property") == Some("group-position")
+                })
+                .and_then(|n| n.text())
+                .map(|t| t.trim().to_string())
+        });
+        Some((name, position))
+    });
+    book.series = series.map(|(name, index)| match index {
+        // Calibre writes whole numbers as "1.0".
+        Some(index) => format!("{}, book {}", name, index.trim_end_matches(".0")),
+        None => name,
+    });
+
+    let manifest = opf.descendants().find(|n| n.has_tag_name("manifest"));
+    let items = || {
+        manifest
+            .into_iter()
+            .flat_map(|m| m.children().filter(Node::is_element))
+    };
+    let cover = meta("cover")
+        .and_then(|id| items().find(|n| n.attribute("id") == Some(id.as_str())))
+        .or_else(|| {
+            items().find(|n| {
+                n.attribute("properties")
+                    .is_some_and(|p| p.split_whitespace().any(|p| p == "cover-image"))
+            })
+        });
+    if let Some(cover) = cover {
+        if let Some(href) = cover.attribute("href") {
+            // Manifest paths are relative to the package document.
+            let base = opf_path.rsplit_once('/').map(|(dir, _)| dir).unwrap_or("");
+            let href = href.replace("%20", " ");
+            let entry = if base.is_empty() {
+                href
+            } else {
+                format!("{}/{}", base, href)
+            };
+            if let Ok(data) = read_entry(&mut archive, &entry) {
+                let media_type = cover.attribute("media-type").unwrap_or("image/jpeg");
+                book.cover = Some((data, media_type.to_string()));
+            }
+        }
+    }
+    Ok(book)
+}
+
+fn read_entry(archive: &mut ZipArchive<File>, name: &str) -> io::Result<Vec<u8>> {
+    let mut entry = archive
+        .by_name(name)
+        .map_err(|e| io::Error::new(io::ErrorKind::NotFound, format!("{}: {}", name, e)))?;
+    let mut data = vec![];
+    entry.read_to_end(&mut data)?;
+    Ok(data)
+}
+
+// Some package documents still declare a DTD.
+fn parse_xml(text: &str) -> Result<Document<'_>, roxmltree::Error> {
+    let options = ParsingOptions {
+        allow_dtd: true,
+        ..ParsingOptions::default()
+    };
+    Document::parse_with_options(text.trim_start_matches('\u{feff}'), options)
+}
+
+// PDFs only have the document information dictionary: title, author,
+// subject, keywords and dates.
+fn read_pdf(path: &Path) -> Result<Book, Box<dyn Error>> {
+    let doc = lopdf::Document::load(path)?;
+    let mut book = Book {
+        format: "pdf",
+        ..Book::default()
+    };
+    let Some(info) = doc
+        .trailer
+        .get(b"Info")
+        .and_then(|o| doc.dereference(o))
+        .and_then(|(_, o)| o.as_dict())
+        .ok()
+    else {
+        return Ok(book);
+    };
+    let field = |key: &[u8]| {
+        info.get(key)
+            .ok()
+            .and_then(|o| lopdf::decode_text_string(o).ok())
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+    };
+    book.title = field(b"Title");
+    book.creators = field(b"Author")
+        .map(|a| a.split(';').map(|a| a.trim().to_string()).collect())
+        .unwrap_or_default();
+    // Dates look like D:20040715120000+02'00'.
+    book.year = field(b"CreationDate")
+        .map(|d| d.trim_start_matches("D:").chars().take(4).collect())
+        .filter(|y: &String| y.len() == 4 && y.chars().all(|c| c.is_ascii_digit()));
+    book.subjects = field(b"Keywords")
+        .map(|k| {
+            k.split([',', ';'])
+                .map(|k| k.trim().to_string())
+                .filter(|k| !k.is_empty())
+                .collect()
+        })
+        .unwrap_or_default();
+    Ok(book)
+}
+
+impl Book {
+    fn tags(&self) -> Vec<String> {
+        std::iter::once(tag(self.format))
+            .chain(self.subjects.iter().map(|s| tag(s)))
+            .collect()
+    }
+
+    fn to_item(&self, path: &Path, covers: &Path) -> io::Result<MediaItem> {
+        // Untitled files (and PDFs titled after the word processor file) fall
+        // back to the file name.
+        let title = self.title.clone().unwrap_or_else(|| {
+            path.file_stem()
+                .map(|s| s.to_string_lossy().replace('_', " "))
+                .unwrap_or_default()
+        });
+        let mut item = MediaItem::new(title, Category::Book);
+        let byline = [
+            (!self.creators.is_empty()).then(|| format!("by {}", self.creators.join(", "))),
+            self.series.clone(),
+            self.year.clone(),
+        ]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .join(" · ");
+        item.notes = notes(&[byline, self.tags().join(" ")]);
+        item.external_ids.extend(self.isbn.clone());
+        item.cover_path = self.save_cover(covers)?;
+        item.file_path = Some(path.to_string_lossy().to_string());
+        Ok(item)
+    }
+
+    // `existing` linked to `path` if its file is gone, with a cover if it has
+    // none and any new tags. Title and notes are left alone.
+    fn update(
+        &self,
+        existing: &MediaItem,
+        path: &str,
+        covers: &Path,
+    ) -> io::Result<Option<MediaItem>> {
+        let mut out = existing.clone();
+        if !out
+            .file_path
+            .as_deref()
+            .is_some_and(|p| Path::new(p).exists())
+        {
+            out.file_path = Some(path.to_string());
+        }
+        if out.cover_path.is_none() {
+            out.cover_path = self.save_cover(covers)?;
+        }
+        add_tags(&mut out, &self.tags());
+        if out.file_path == existing.file_path
+            && out.cover_path == existing.cover_path
+            && out.notes == existing.notes
+        {
+            return Ok(None);
+        }
+        out.updated_at = Local::now();
+        Ok(Some(out))
+    }
+
+    fn save_cover(&self, covers: &Path) -> io::Result<Option<String>> {
+        self.cover
+            .as_ref()
+            .map(|(data, media_type)| save_cover(covers, data, media_type))
+            .transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sqlite_repo::SqliteRepo;
+    use lopdf::{dictionary, Object, StringFormat};
+    use std::io::Write;
+    use std::path::PathBuf;
+    use zip::write::SimpleFileOptions;
+
+    const OPF: &str = r#"<?xml version="1.0"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="2.0">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/"
+            xmlns:opf="http://www.idpf.org/2007/opf">
+    <dc:title>Leviathan Wakes</dc:title>
+    <dc:creator>James S. A. Corey</dc:creator>
+    <dc:date>2011-06-02T00:00:00+00:00</dc:date>
+    <dc:subject>Science Fiction</dc:subject>
+    <dc:identifier opf:scheme="calibre">1234</dc:identifier>
+    <dc:identifier opf:scheme="ISBN">0-316-12908-9</dc:identifier>
+    <meta name="calibre:series" content="The Expanse"/>
+    <meta name="calibre:series_index" content="1.0"/>
+    <meta name="cover" content="cover-img"/>
+  </metadata>
+  <manifest>
+    <item id="cover-img" href="images/cover%20art.png" media-type="image/png"/>
+  </manifest>
+</package>"#;
+
+    fn write_epub(path: &Path) {
+        let mut zip = zip::ZipWriter::new(File::create(path).unwrap());
+        let options = SimpleFileOptions::default();
+        let files: [(&str, &[u8]); 3] = [
+            (
+                "META-INF/container.xml",
+                br#"<container><rootfiles><rootfile full-path="OEBPS/content.opf"/></rootfiles></container>"#,
+            ),
+            ("OEBPS/content.opf", OPF.as_bytes()),
+            ("OEBPS/images/cover art.png", b"not really a png"),
+        ];
+        for (name, data) in files {
+            zip.start_file(name, options).unwrap();
+            zip.write_all(data).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    fn write_pdf(path: &Path) {
+        let mut doc = lopdf::Document::with_version("1.5");
+        let text = |s: &str| Object::String(s.as_bytes().to_vec(), StringFormat::Literal);
+        let pages = doc.add_object(dictionary! {
+            "Type" => "Pages",
+            "Kids" => Vec::<Object>::new(),
+            "Count" => 0,
+        });
+        let catalog = doc.add_object(dictionary! { "Type" => "Catalog", "Pages" => pages });
+        let info = doc.add_object(dictionary! {
+            "Title" => text("Structure and Interpretation"),
+            "Author" => text("Harold Abelson; Gerald Sussman"),
+            "CreationDate" => text("D:19960101120000Z"),
+            "Keywords" => text("lisp, computing"),
+        });
+        doc.trailer.set("Root", catalog);
+        doc.trailer.set("Info", info);
+        doc.save(path).unwrap();
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("books-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn reads_epub_and_pdf_metadata() {
+        let dir = temp_dir("read");
+        let (epub, pdf) = (dir.join("expanse.epub"), dir.join("sicp.pdf"));
+        write_epub(&epub);
+        write_pdf(&pdf);
+
+        let book = read_book(&epub).unwrap();
+        assert_eq!(book.title.as_deref(), Some("Leviathan Wakes"));
+        assert_eq!(book.creators, ["James S. A. Corey"]);
+        assert_eq!(book.series.as_deref(), Some("The Expanse, book 1"));
+        assert_eq!(book.year.as_deref(), Some("2011"));
+        assert_eq!(book.isbn.unwrap().to_string(), "isbn:9780316129084");
+        let (data, media_type) = book.cover.unwrap();
+        assert_eq!(
+            (data.as_slice(), media_type.as_str()),
+            (&b"not really a png"[..], "image/png")
+        );
+
+        let book = read_book(&pdf).unwrap();
+        assert_eq!(book.title.as_deref(), Some("Structure and Interpretation"));
+        assert_eq!(book.creators, ["Harold Abelson", "Gerald Sussman"]);
+        assert_eq!(book.year.as_deref(), Some("1996"));
+        assert_eq!(book.subjects, ["lisp", "computing"]);
+        assert!(book.isbn.is_none());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn links_files_to_items_with_their_isbn() {
+        let repo = SqliteRepo::new(Path::new(":memory:"));
+        repo.init().unwrap();
+        let mut owned = MediaItem::new("Leviathan Wakes (paperback)", Category::Book);
+        owned.add_external_id(ExternalId::parse("isbn:9780316129084").unwrap());
+        let owned_id = repo.add(&mut owned).unwrap();
+        let dir = temp_dir("scan");
+        let covers = dir.join("covers");
+        let books = dir.join("books");
+        std::fs::create_dir_all(&books).unwrap();
+        write_epub(&books.join("expanse.epub"));
+        write_pdf(&books.join("sicp.pdf"));
+        std::fs::write(books.join("broken.epub"), "not a zip").unwrap();
+
+        let report = scan(&repo, &books, &covers).unwrap();
+        assert_eq!((report.files, report.read, report.updated), (3, 2, 1));
+        assert_eq!(report.errors.len(), 1);
+        assert_eq!(report.added.len(), 1);
+        let linked = repo.get(owned_id).unwrap().unwrap();
+        assert_eq!(linked.title, "Leviathan Wakes (paperback)");
+        assert!(linked
+            .file_path
+            .as_deref()
+            .unwrap()
+            .ends_with("expanse.epub"));
+        assert!(linked.cover_path.as_deref().unwrap().ends_with(".png"));
+        assert_eq!(linked.tags(), ["epub", "science-fiction"]);
+
+        let pdf = repo.get(report.added[0].0).unwrap().unwrap();
+        assert_eq!(
+            pdf.notes.as_deref(),
+            Some("by Harold Abelson, Gerald Sussman · 1996\n\n#pdf #lisp #computing")
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
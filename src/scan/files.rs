@@ -0,0 +1,69 @@
+use super::{relink, walk, FileState, ScanReport};
+use crate::import::notes;
+use crate::models::{Category, MediaItem};
+use crate::repo::{RepoResult, Repository};
+use std::path::Path;
+
+const VIDEO_EXTENSIONS: [&str; 9] = [
+    "mkv", "mp4", "m4v", "avi", "mov", "webm", "wmv", "mpg", "mpeg",
+];
+const GAME_EXTENSIONS: [&str; 12] = [
+    "iso", "chd", "cue", "zip", "7z", "nsp", "xci", "nds", "gba", "sfc", "nes", "z64",
+];
+
+/// Scans `dir` and adds one `category` item per new file, named after the
+/// file and linked to it. Movies and games only count video files and game
+/// images; any file counts for other categories.
+pub fn scan(repo: &dyn Repository, dir: &Path, category: Category) -> RepoResult<ScanReport> {
+    let extensions: &[&str] = match category {
+        Category::Movie => &VIDEO_EXTENSIONS,
+        Category::Game => &GAME_EXTENSIONS,
+        _ => &[],
+    };
+    let mut report = ScanReport::default();
+    for (mut file, state) in walk(repo, dir, extensions, &mut report)? {
+        match (state, file.item_id) {
+            (FileState::Unchanged, _) => {}
+            (FileState::Moved, Some(id)) => {
+                if relink(repo, id, &file.path)? {
+                    report.updated += 1;
+                }
+            }
+            // Changed contents keep their item; the name is all there is to read.
+            (FileState::Changed, Some(_)) => report.read += 1,
+            (FileState::Moved | FileState::Changed, None) => {
+                report.read += 1;
+                let path = Path::new(&file.path);
+                let (title, year) = title_from_name(path);
+                let mut item = MediaItem::new(title, category);
+                item.notes = notes(&[year.map(|y| format!("Released {}", y)).unwrap_or_default()]);
+                item.file_path = Some(file.path.clone());
+                file.group = file.path.clone();
+                file.item_id = Some(report.add(repo, &mut item)?);
+            }
+        }
+        repo.save_scanned_file(&file)?;
+    }
+    Ok(report)
+}
+
+// "The.Matrix.1999.1080p.BluRay.mkv" -> ("The Matrix", Some("1999")):
+// dots and underscores become spaces and the name is cut at a year.
+fn title_from_name(path: &Path) -> (String, Option<String>) {
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().replace(['.', '_'], " "))
+        .unwrap_or_default();
+    let words: Vec<&str> = stem.split_whitespace().collect();
+    let year = words.iter().enumerate().skip(1).find_map(|(i, w)| {
+        let w = w.trim_matches(|c| matches!(c, '(' | ')' | '[' | ']'));
+        let is_year = w.len() == 4
+            && w.chars().all(|c| c.is_ascii_digit())
+            && (w.starts_with("19") || w.starts_with("20"));
+        is_year.then(|| (i, w.to_string()))
+    });
+    match year {
+        Some((i, year)) => (words[..i].join(" "), Some(year)),
+        None => (words.join(" "), None),
+    }
+}
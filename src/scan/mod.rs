@@ -0,0 +1,312 @@
+pub mod books;
+pub mod files;
+pub mod music;
+
+use crate::models::{Category, MediaItem};
+use crate::repo::{RepoResult, Repository};
+use chrono::Local;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+use walkdir::WalkDir;
+
+/// Scans a folder into the catalog, copying covers to the second path.
+pub type Scanner = fn(&dyn Repository, &Path, &Path) -> RepoResult<ScanReport>;
+
+/// A file seen by a folder scan. The next scan only reads it again when its
+/// size or modification time changed and the contents hash differs.
+#[derive(Debug, Clone)]
+pub struct ScannedFile {
+    pub path: String,
+    /// Modification time, seconds since the epoch.
+    pub modified: i64,
+    pub size: i64,
+    /// SHA-256 of the contents, hex.
+    pub hash: String,
+    /// Files with the same group belong to one item, e.g. the tracks of an album.
+    pub group: String,
+    pub item_id: Option<i64>,
+}
+
+#[derive(Debug, Default)]
+pub struct ScanReport {
+    pub files: usize,
+    /// New or changed files that were read.
+    pub read: usize,
+    /// Id and title of each item added.
+    pub added: Vec<(i64, String)>,
+    pub updated: usize,
+    /// Files recorded by an earlier scan that are gone.
+    pub removed: usize,
+    pub errors: Vec<String>,
+}
+
+impl fmt::Display for ScanReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Scanned {} files ({} new or changed, {} removed): {} items added, {} updated",
+            self.files,
+            self.read,
+            self.removed,
+            self.added.len(),
+            self.updated
+        )?;
+        if !self.errors.is_empty() {
+            write!(f, ", {} unreadable", self.errors.len())?;
+        }
+        Ok(())
+    }
+}
+
+impl ScanReport {
+    fn add(&mut self, repo: &dyn Repository, item: &mut MediaItem) -> RepoResult<i64> {
+        let id = repo.add(item)?;
+        self.added.push((id, item.title.clone()));
+        Ok(id)
+    }
+}
+
+/// Scans `dir` with the scanner for `category`: albums for Music, e-books
+/// for Book, one item per file otherwise.
+pub fn scan_folder(
+    repo: &dyn Repository,
+    dir: &Path,
+    category: Category,
+    covers: &Path,
+) -> RepoResult<ScanReport> {
+    match category {
+        Category::Music => music::scan(repo, dir, covers),
+        Category::Book => books::scan(repo, dir, covers),
+        category => files::scan(repo, dir, category),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FileState {
+    Unchanged,
+    /// New, or the contents changed since the last scan.
+    Changed,
+    /// New path with the contents of a file that is gone; keeps its item.
+    Moved,
+}
+
+// Files under `dir` with one of `extensions` (any, if empty), compared with
+// the last scan. Hidden files and unfinished downloads are skipped. Records
+// of files that are gone are deleted. Files that cannot be read are reported
+// and left out.
+fn walk(
+    repo: &dyn Repository,
+    dir: &Path,
+    extensions: &[&str],
+    report: &mut ScanReport,
+) -> RepoResult<Vec<(ScannedFile, FileState)>> {
+    let mut known: HashMap<String, ScannedFile> = repo
+        .scanned_files(&dir.to_string_lossy())?
+        .into_iter()
+        .map(|f| (f.path.clone(), f))
+        .collect();
+    let mut found = vec![];
+    for entry in WalkDir::new(dir).follow_links(true).sort_by_file_name() {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                report.errors.push(e.to_string());
+                continue;
+            }
+        };
+        let path = entry.path();
+        if !entry.file_type().is_file() || skipped(path) || !has_extension(path, extensions) {
+            continue;
+        }
+        report.files += 1;
+        let previous = known.remove(path.to_string_lossy().as_ref());
+        match check_file(path, previous) {
+            Ok(file) => found.push(file),
+            Err(e) => report.errors.push(format!("{}: {}", path.display(), e)),
+        }
+    }
+    // What is left was not found again: moved, or gone.
+    for (file, state) in found.iter_mut() {
+        if *state != FileState::Changed || file.item_id.is_some() {
+            continue;
+        }
+        let moved = known
+            .iter()
+            .find(|(_, old)| old.hash == file.hash)
+            .map(|(path, _)| path.clone());
+        if let Some(old) = moved.and_then(|path| known.remove(&path)) {
+            repo.delete_scanned_file(&old.path)?;
+            file.group = old.group;
+            file.item_id = old.item_id;
+            *state = FileState::Moved;
+        }
+    }
+    for path in known.into_keys() {
+        repo.delete_scanned_file(&path)?;
+        report.removed += 1;
+    }
+    Ok(found)
+}
+
+// Compares `path` with what the last scan recorded: size and modification
+// time first, then the contents hash.
+fn check_file(path: &Path, previous: Option<ScannedFile>) -> io::Result<(ScannedFile, FileState)> {
+    let meta = std::fs::metadata(path)?;
+    let modified = meta
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+    let size = meta.len() as i64;
+    if let Some(previous) = &previous {
+        if previous.modified == modified && previous.size == size {
+            return Ok((previous.clone(), FileState::Unchanged));
+        }
+    }
+    let hash = file_hash(path)?;
+    Ok(match previous {
+        Some(previous) if previous.hash == hash => {
+            // Touched but not changed.
+            let file = ScannedFile {
+                modified,
+                size,
+                ..previous
+            };
+            (file, FileState::Unchanged)
+        }
+        previous => {
+            let file = ScannedFile {
+                path: path.to_string_lossy().to_string(),
+                modified,
+                size,
+                hash,
+                group: previous
+                    .as_ref()
+                    .map(|p| p.group.clone())
+                    .unwrap_or_default(),
+                item_id: previous.and_then(|p| p.item_id),
+            };
+            (file, FileState::Changed)
+        }
+    })
+}
+
+fn file_hash(path: &Path) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0; 64 * 1024];
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+fn has_extension(path: &Path, extensions: &[&str]) -> bool {
+    extensions.is_empty()
+        || path
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| extensions.iter().any(|x| e.eq_ignore_ascii_case(x)))
+}
+
+fn skipped(path: &Path) -> bool {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    name.starts_with('.')
+        || [".part", ".crdownload", ".download", ".tmp"]
+            .iter()
+            .any(|suffix| name.ends_with(suffix))
+}
+
+// Points item `id` at `path` when the file it links to is gone, e.g. after
+// the file was moved. Returns whether the item changed.
+fn relink(repo: &dyn Repository, id: i64, path: &str) -> RepoResult<bool> {
+    let Some(mut item) = repo.get(id)? else {
+        return Ok(false);
+    };
+    if item
+        .file_path
+        .as_deref()
+        .is_some_and(|p| Path::new(p).exists())
+    {
+        return Ok(false);
+    }
+    item.file_path = Some(path.to_string());
+    item.updated_at = Local::now();
+    repo.update(&item)?;
+    Ok(true)
+}
+
+// Writes cover image `data` to `covers` and returns its path. Covers are
+// named after their contents, so items sharing artwork share one file and
+// rescans do not write copies.
+pub fn save_cover(covers: &Path, data: &[u8], media_type: &str) -> io::Result<String> {
+    let ext = match media_type {
+        "image/png" => "png",
+        "image/gif" => "gif",
+        "image/webp" => "webp",
+        _ => "jpg",
+    };
+    let hash = format!("{:x}", Sha256::digest(data));
+    let path = covers.join(format!("{}.{}", &hash[..16], ext));
+    if !path.exists() {
+        std::fs::create_dir_all(covers)?;
+        std::fs::write(&path, data)?;
+    }
+    Ok(path.to_string_lossy().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sqlite_repo::SqliteRepo;
+
+    #[test]
+    fn keeps_items_of_moved_files_and_forgets_deleted_ones() {
+        let repo = SqliteRepo::new(Path::new(":memory:"));
+        repo.init().unwrap();
+        let root = std::env::temp_dir().join(format!("scan-walk-{}", std::process::id()));
+        let (dir, sibling) = (root.join("shows"), root.join("shows2"));
+        std::fs::create_dir_all(dir.join("old")).unwrap();
+        std::fs::create_dir_all(&sibling).unwrap();
+        std::fs::write(dir.join("old/one.txt"), "one").unwrap();
+        std::fs::write(dir.join("two.txt"), "two").unwrap();
+        std::fs::write(dir.join(".hidden"), "hidden").unwrap();
+        std::fs::write(dir.join("three.txt.part"), "partial").unwrap();
+        std::fs::write(sibling.join("other.txt"), "other").unwrap();
+        let scan = |dir: &Path| files::scan(&repo, dir, Category::Other).unwrap();
+
+        assert_eq!(scan(&sibling).added.len(), 1);
+        let first = scan(&dir);
+        assert_eq!((first.files, first.added.len()), (2, 2));
+        let again = scan(&dir);
+        assert_eq!((again.read, again.removed), (0, 0));
+
+        std::fs::create_dir_all(dir.join("new")).unwrap();
+        std::fs::rename(dir.join("old/one.txt"), dir.join("new/one.txt")).unwrap();
+        std::fs::remove_file(dir.join("two.txt")).unwrap();
+        let moved = scan(&dir);
+        assert_eq!(moved.added.len(), 0);
+        assert_eq!((moved.updated, moved.removed), (1, 1));
+        let item = repo.get(first.added[0].0).unwrap().unwrap();
+        assert_eq!(
+            item.file_path.as_deref(),
+            Some(dir.join("new/one.txt").to_string_lossy().as_ref())
+        );
+        let sibling_files = repo.scanned_files(&sibling.to_string_lossy()).unwrap();
+        assert_eq!(sibling_files.len(), 1);
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
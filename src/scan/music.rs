@@ -0,0 +1,361 @@
+use super::{relink, save_cover, walk, FileState, ScanReport, ScannedFile};
+use crate::ids::{ExternalId, IdScheme};
+use crate::import::{add_tags, notes, tag};
+use crate::models::{Category, MediaItem};
+use crate::repo::{RepoResult, Repository};
+use chrono::Local;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs::File;
+use std::io;
+use std::path::{Path, PathBuf};
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::{MetadataOptions, MetadataRevision, StandardTagKey, StandardVisualKey};
+use symphonia::core::probe::Hint;
+
+const AUDIO_EXTENSIONS: [&str; 8] = ["mp3", "flac", "ogg", "oga", "opus", "m4a", "m4b", "mp4"];
+
+// Tags of one audio file.
+#[derive(Default)]
+struct Track {
+    album: Option<String>,
+    album_artist: Option<String>,
+    artist: Option<String>,
+    date: Option<String>,
+    musicbrainz: Option<String>,
+    genres: Vec<String>,
+    cover: Option<Cover>,
+}
+
+struct Cover {
+    data: Vec<u8>,
+    media_type: String,
+}
+
+// What the new or changed tracks of one album say about it.
+#[derive(Default)]
+struct Album {
+    title: String,
+    folder: String,
+    album_artist: Option<String>,
+    artists: Vec<String>,
+    year: Option<String>,
+    musicbrainz: Option<ExternalId>,
+    genres: Vec<String>,
+    cover: Option<Cover>,
+}
+
+/// Scans `dir` for audio files and creates or updates one Music item per
+/// album, linked to the album's folder. Embedded cover art is copied to
+/// `covers`. Files that did not change since the last scan are not read;
+/// albums whose item was deleted are not created again unless one of their
+/// files changes.
+pub fn scan(repo: &dyn Repository, dir: &Path, covers: &Path) -> RepoResult<ScanReport> {
+    let mut report = ScanReport::default();
+    let mut files = vec![];
+    let mut albums: BTreeMap<String, Album> = BTreeMap::new();
+    for (mut file, state) in walk(repo, dir, &AUDIO_EXTENSIONS, &mut report)? {
+        let path = PathBuf::from(&file.path);
+        match state {
+            FileState::Unchanged => {}
+            FileState::Moved => {
+                if let Some(id) = file.item_id {
+                    if relink(repo, id, &folder(&path).to_string_lossy())? {
+                        report.updated += 1;
+                    }
+                }
+            }
+            FileState::Changed => match read_track(&path) {
+                Ok(track) => {
+                    report.read += 1;
+                    let group = group(&path, &track);
+                    if file.group != group {
+                        file.group = group.clone();
+                        file.item_id = None;
+                    }
+                    albums
+                        .entry(group)
+                        .or_insert_with(|| Album {
+                            title: album_title(&path, &track),
+                            folder: folder(&path).to_string_lossy().to_string(),
+                            ..Album::default()
+                        })
+                        .add(track);
+                }
+                Err(e) => {
+                    report.errors.push(format!("{}: {}", path.display(), e));
+                    continue;
+                }
+            },
+        }
+        files.push(file);
+    }
+
+    for (group, album) in albums {
+        let mut tracks: Vec<&mut ScannedFile> =
+            files.iter_mut().filter(|f| f.group == group).collect();
+        let mut existing = None;
+        for id in tracks.iter().filter_map(|f| f.item_id) {
+            existing = repo.get(id)?;
+            if existing.is_some() {
+                break;
+            }
+        }
+        // The same release in another folder, or one added by hand.
+        if existing.is_none() {
+            if let Some(id) = &album.musicbrainz {
+                existing = repo.find_by_external_id(id)?;
+            }
+        }
+        let id = match existing {
+            Some(item) => {
+                if let Some(updated) = album.update(&item, covers)? {
+                    repo.update(&updated)?;
+                    report.updated += 1;
+                }
+                item.id
+            }
+            None => {
+                let mut item = album.to_item(covers)?;
+                Some(report.add(repo, &mut item)?)
+            }
+        };
+        for track in tracks.iter_mut() {
+            track.item_id = id;
+        }
+    }
+    // Saved last, so a scan that stopped early reads the files again.
+    for file in &files {
+        repo.save_scanned_file(file)?;
+    }
+    Ok(report)
+}
+
+fn read_track(path: &Path) -> Result<Track, Box<dyn Error>> {
+    let stream = MediaSourceStream::new(Box::new(File::open(path)?), Default::default());
+    let mut hint = Hint::new();
+    if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
+        hint.with_extension(ext);
+    }
+    let mut probed = symphonia::default::get_probe().format(
+        &hint,
+        stream,
+        &FormatOptions::default(),
+        &MetadataOptions::default(),
+    )?;
+    let mut track = Track::default();
+    // ID3v2 tags come before the audio stream and are read by the probe;
+    // Vorbis comments and MP4 atoms belong to the container.
+    if let Some(metadata) = probed.metadata.get() {
+        if let Some(revision) = metadata.current() {
+            track.add(revision);
+        }
+    }
+    if let Some(revision) = probed.format.metadata().current() {
+        track.add(revision);
+    }
+    Ok(track)
+}
+
+impl Track {
+    // Keeps values already read, so ID3 tags win over container tags.
+    fn add(&mut self, revision: &MetadataRevision) {
+        for t in revision.tags() {
+            let value = t.value.to_string().trim().to_string();
+            if value.is_empty() {
+                continue;
+            }
+            let field = match t.std_key {
+                Some(StandardTagKey::Album) => &mut self.album,
+                Some(StandardTagKey::AlbumArtist) => &mut self.album_artist,
+                Some(StandardTagKey::Artist) => &mut self.artist,
+                Some(StandardTagKey::Date) | Some(StandardTagKey::ReleaseDate) => &mut self.date,
+                Some(StandardTagKey::MusicBrainzAlbumId) => &mut self.musicbrainz,
+                Some(StandardTagKey::Genre) => {
+                    // Some taggers put several genres in one field.
+                    for genre in value.split([';', '/', '\0']).map(str::trim) {
+                        if !genre.is_empty() && !self.genres.iter().any(|g| g == genre) {
+                            self.genres.push(genre.to_string());
+                        }
+                    }
+                    continue;
+                }
+                _ => continue,
+            };
+            field.get_or_insert(value);
+        }
+        if self.cover.is_none() {
+            let visuals = revision.visuals();
+            let front = visuals
+                .iter()
+                .find(|v| v.usage == Some(StandardVisualKey::FrontCover))
+                .or(visuals.first());
+            self.cover = front.map(|v| Cover {
+                data: v.data.to_vec(),
+                media_type: v.media_type.clone(),
+            });
+        }
+    }
+}
+
+// Tracks group by album artist and album. Without an album artist the
+// folder stands in, so a compilation's tracks are not split up by artist.
+fn group(path: &Path, track: &Track) -> String {
+    let owner = match &track.album_artist {
+        Some(artist) => artist.to_lowercase(),
+        None => folder(path).to_string_lossy().to_string(),
+    };
+    format!("{}\n{}", owner, album_title(path, track).to_lowercase())
+}
+
+// The album tag, or the folder name for untagged files.
+fn album_title(path: &Path, track: &Track) -> String {
+    track.album.clone().unwrap_or_else(|| {
+        folder(path)
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| "Unknown album".to_string())
+    })
+}
+
+fn folder(path: &Path) -> PathBuf {
+    path.parent().map(Path::to_path_buf).unwrap_or_default()
+}
+
+impl Album {
+    fn add(&mut self, track: Track) {
+        if self.album_artist.is_none() {
+            self.album_artist = track.album_artist;
+        }
+        if let Some(artist) = track.artist {
+            if !self.artists.contains(&artist) {
+                self.artists.push(artist);
+            }
+        }
+        if self.year.is_none() {
+            self.year = track.date.map(|d| d.chars().take(4).collect());
+        }
+        if self.musicbrainz.is_none() {
+            self.musicbrainz = track
+                .musicbrainz
+                .and_then(|id| ExternalId::new(IdScheme::MusicBrainz, &id).ok());
+        }
+        for genre in track.genres {
+            if !self.genres.contains(&genre) {
+                self.genres.push(genre);
+            }
+        }
+        if self.cover.is_none() {
+            self.cover = track.cover;
+        }
+    }
+
+    fn artist(&self) -> Option<&str> {
+        match (&self.album_artist, self.artists.as_slice()) {
+            (Some(artist), _) => Some(artist),
+            (None, [artist]) => Some(artist),
+            (None, []) => None,
+            (None, _) => Some("Various Artists"),
+        }
+    }
+
+    fn tags(&self) -> Vec<String> {
+        self.genres.iter().map(|g| tag(g)).collect()
+    }
+
+    fn to_item(&self, covers: &Path) -> io::Result<MediaItem> {
+        let mut item = MediaItem::new(self.title.as_str(), Category::Music);
+        let byline = [
+            self.artist().map(|a| format!("by {}", a)),
+            self.year.clone(),
+        ]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .join(" · ");
+        item.notes = notes(&[byline, self.tags().join(" ")]);
+        item.cover_path = self.save_cover(covers)?;
+        item.file_path = Some(self.folder.clone());
+        item.external_ids.extend(self.musicbrainz.clone());
+        Ok(item)
+    }
+
+    // `existing` with a cover and folder if it has none and any new genre
+    // tags; notes and title may have been edited and are otherwise left alone.
+    fn update(&self, existing: &MediaItem, covers: &Path) -> io::Result<Option<MediaItem>> {
+        let mut out = existing.clone();
+        if out.cover_path.is_none() {
+            out.cover_path = self.save_cover(covers)?;
+        }
+        out.file_path.get_or_insert_with(|| self.folder.clone());
+        add_tags(&mut out, &self.tags());
+        if let Some(id) = &self.musicbrainz {
+            out.add_external_id(id.clone());
+        }
+        if out.cover_path == existing.cover_path
+            && out.file_path == existing.file_path
+            && out.notes == existing.notes
+            && out.external_ids == existing.external_ids
+        {
+            return Ok(None);
+        }
+        out.updated_at = Local::now();
+        Ok(Some(out))
+    }
+
+    fn save_cover(&self, covers: &Path) -> io::Result<Option<String>> {
+        self.cover
+            .as_ref()
+            .map(|c| save_cover(covers, &c.data, &c.media_type))
+            .transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(album: Option<&str>, album_artist: Option<&str>, artist: &str) -> Track {
+        Track {
+            album: album.map(String::from),
+            album_artist: album_artist.map(String::from),
+            artist: Some(artist.to_string()),
+            date: Some("1997-05-21".into()),
+            genres: vec!["Alt Rock".into()],
+            ..Track::default()
+        }
+    }
+
+    #[test]
+    fn groups_tracks_by_album_artist_or_folder() {
+        let path = Path::new("music/OK Computer/01.mp3");
+        let tagged = track(Some("OK Computer"), Some("Radiohead"), "Radiohead");
+        assert_eq!(group(path, &tagged), "radiohead\nok computer");
+        let compilation = track(Some("Hits"), None, "Various");
+        let other_folder = Path::new("music/Hits 2/01.mp3");
+        assert_ne!(
+            group(Path::new("music/Hits/01.mp3"), &compilation),
+            group(other_folder, &compilation)
+        );
+        assert_eq!(album_title(path, &Track::default()), "OK Computer");
+    }
+
+    #[test]
+    fn names_albums_with_several_artists_various_artists() {
+        let mut album = Album {
+            title: "Hits".into(),
+            folder: "music/Hits".into(),
+            ..Album::default()
+        };
+        album.add(track(None, None, "Blur"));
+        album.add(track(None, None, "Pulp"));
+        let item = album.to_item(Path::new("covers")).unwrap();
+        assert_eq!(
+            item.notes.as_deref(),
+            Some("by Various Artists · 1997\n\n#alt-rock")
+        );
+        assert_eq!(item.file_path.as_deref(), Some("music/Hits"));
+        assert!(album.update(&item, Path::new("covers")).unwrap().is_none());
+    }
+}
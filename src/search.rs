@@ -0,0 +1,774 @@
+use crate::models::{Category, Format, Priority, Status};
+use chrono::{NaiveDate, TimeDelta};
+use std::ops::Range;
+use thiserror::Error;
+
+pub type Span = Range<usize>;
+
+#[derive(Debug, Clone, Error, PartialEq)]
+#[error("{message} (at {}..{})", span.start, span.end)]
+pub struct ParseError {
+    pub message: String,
+    pub span: Span,
+}
+
+impl ParseError {
+    fn new(message: impl Into<String>, span: Span) -> Self {
+        Self {
+            message: message.into(),
+            span,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    And(Vec<Expr>),
+    Or(Vec<Expr>),
+    Not(Box<Expr>),
+    /// Bare word or quoted phrase, matched against the title.
+    Text(String),
+    Field(Field, CmpOp, Value),
+}
+
+impl Expr {
+    /// Whether a `field` term appears anywhere, negated or not.
+    pub fn mentions(&self, field: Field) -> bool {
+        match self {
+            Expr::And(terms) | Expr::Or(terms) => terms.iter().any(|t| t.mentions(field)),
+            Expr::Not(inner) => inner.mentions(field),
+            Expr::Text(_) => false,
+            Expr::Field(f, _, _) => *f == field,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Title,
+    Notes,
+    Category,
+    Status,
+    Format,
+    /// Whether the item has a copy.
+    Owned,
+    /// Where a copy is kept.
+    Location,
+    /// Whether the item is lent out and not returned.
+    OnLoan,
+    /// Lent out and past the due date.
+    Overdue,
+    /// Who has the item now.
+    Borrower,
+    Rating,
+    Tag,
+    Created,
+    Updated,
+}
+
+impl Field {
+    pub const KEYS: [(&'static str, Field); 14] = [
+        ("title", Field::Title),
+        ("notes", Field::Notes),
+        ("cat", Field::Category),
+        ("status", Field::Status),
+        ("format", Field::Format),
+        ("owned", Field::Owned),
+        ("location", Field::Location),
+        ("onloan", Field::OnLoan),
+        ("overdue", Field::Overdue),
+        ("borrower", Field::Borrower),
+        ("rating", Field::Rating),
+        ("tag", Field::Tag),
+        ("created", Field::Created),
+        ("updated", Field::Updated),
+    ];
+
+    fn from_key(key: &str) -> Option<Field> {
+        let key = key.to_ascii_lowercase();
+        let field = match key.as_str() {
+            "category" => Field::Category,
+            "note" => Field::Notes,
+            "added" => Field::Created,
+            _ => Self::KEYS.iter().find(|(k, _)| *k == key)?.1,
+        };
+        Some(field)
+    }
+
+    fn is_ordered(&self) -> bool {
+        matches!(self, Field::Rating | Field::Created | Field::Updated)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CmpOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl CmpOp {
+    pub fn as_sql(&self) -> &'static str {
+        match self {
+            CmpOp::Eq => "=",
+            CmpOp::Ne => "<>",
+            CmpOp::Lt => "<",
+            CmpOp::Le => "<=",
+            CmpOp::Gt => ">",
+            CmpOp::Ge => ">=",
+        }
+    }
+
+    /// The operator with its operands swapped (`a < b` == `b > a`).
+    pub fn flipped(&self) -> CmpOp {
+        match self {
+            CmpOp::Lt => CmpOp::Gt,
+            CmpOp::Le => CmpOp::Ge,
+            CmpOp::Gt => CmpOp::Lt,
+            CmpOp::Ge => CmpOp::Le,
+            op => *op,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Text(String),
+    Category(Category),
+    Status(Status),
+    Format(Format),
+    Flag(bool),
+    Number(i64),
+    /// Calendar day in local time, e.g. `2024-01-31`.
+    Date(NaiveDate),
+    /// Relative age such as `30d`; `updated:<30d` means "less than 30 days ago".
+    Age(TimeDelta),
+}
+
+/// Parses the search box syntax, e.g.
+/// `cat:book status:!finished rating>=7 tag:scifi "exact phrase" updated:<30d`.
+/// Terms are ANDed; `OR`, `NOT`/`-` and parentheses are supported.
+pub fn parse(input: &str) -> Result<Expr, ParseError> {
+    let tokens = lex(input)?;
+    let mut parser = Parser {
+        tokens,
+        pos: 0,
+        input_len: input.len(),
+    };
+    let expr = parser.parse_or()?;
+    if let Some(tok) = parser.peek() {
+        return Err(ParseError::new("Unexpected `)`", tok.span.clone()));
+    }
+    Ok(expr)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum TokenKind {
+    LParen,
+    RParen,
+    Or,
+    And,
+    Not,
+    Word(String),
+    Phrase(String),
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    kind: TokenKind,
+    span: Span,
+}
+
+fn is_op_char(c: char) -> bool {
+    matches!(c, ':' | '<' | '>' | '=' | '!')
+}
+
+fn lex(input: &str) -> Result<Vec<Token>, ParseError> {
+    let mut tokens = vec![];
+    let mut chars = input.char_indices().peekable();
+
+    while let Some(&(start, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+        let kind = match c {
+            '(' => {
+                chars.next();
+                TokenKind::LParen
+            }
+            ')' => {
+                chars.next();
+                TokenKind::RParen
+            }
+            '"' => {
+                chars.next();
+                TokenKind::Phrase(read_quoted(input, start, &mut chars)?)
+            }
+            // `-` negates the term right after it; on its own it is a word.
+            '-' if input[start + 1..].starts_with(|c: char| !c.is_whitespace() && c != ')') => {
+                chars.next();
+                TokenKind::Not
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some(&(i, c)) = chars.peek() {
+                    if c.is_whitespace() || c == '(' || c == ')' {
+                        break;
+                    }
+                    if c == '"' {
+                        // Quoted field value, e.g. `title:"star wars"`.
+                        if word.ends_with(is_op_char) {
+                            chars.next();
+                            word.push_str(&read_quoted(input, i, &mut chars)?);
+                            continue;
+                        }
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+                match word.as_str() {
+                    "OR" | "|" => TokenKind::Or,
+                    "AND" | "&" => TokenKind::And,
+                    "NOT" => TokenKind::Not,
+                    _ => TokenKind::Word(word),
+                }
+            }
+        };
+        let end = chars.peek().map(|&(i, _)| i).unwrap_or(input.len());
+        tokens.push(Token {
+            kind,
+            span: start..end,
+        });
+    }
+    Ok(tokens)
+}
+
+fn read_quoted(
+    input: &str,
+    quote_at: usize,
+    chars: &mut std::iter::Peekable<std::str::CharIndices<'_>>,
+) -> Result<String, ParseError> {
+    let mut text = String::new();
+    for (_, c) in chars.by_ref() {
+        if c == '"' {
+            return Ok(text);
+        }
+        text.push(c);
+    }
+    Err(ParseError::new("Unterminated quote", quote_at..input.len()))
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    input_len: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let tok = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        tok
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, ParseError> {
+        let mut alts = vec![self.parse_and()?];
+        while matches!(self.peek(), Some(t) if t.kind == TokenKind::Or) {
+            let op = self.next().unwrap();
+            if alts.last() == Some(&Expr::And(vec![])) {
+                return Err(ParseError::new("Expected a term before `OR`", op.span));
+            }
+            if matches!(
+                self.peek(),
+                None | Some(Token {
+                    kind: TokenKind::RParen,
+                    ..
+                })
+            ) {
+                return Err(ParseError::new("Expected a term after `OR`", op.span));
+            }
+            alts.push(self.parse_and()?);
+        }
+        Ok(if alts.len() == 1 {
+            alts.pop().unwrap()
+        } else {
+            Expr::Or(alts)
+        })
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, ParseError> {
+        let mut terms = vec![];
+        loop {
+            match self.peek().map(|t| &t.kind) {
+                None | Some(TokenKind::RParen) | Some(TokenKind::Or) => break,
+                Some(TokenKind::And) => {
+                    let op = self.next().unwrap();
+                    if terms.is_empty() {
+                        return Err(ParseError::new("`AND` needs a term before it", op.span));
+                    }
+                }
+                _ => terms.push(self.parse_unary()?),
+            }
+        }
+        Ok(if terms.len() == 1 {
+            terms.pop().unwrap()
+        } else {
+            Expr::And(terms)
+        })
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, ParseError> {
+        let tok = self.next().unwrap();
+        match tok.kind {
+            TokenKind::Not => match self.peek().map(|t| &t.kind) {
+                None | Some(TokenKind::RParen) | Some(TokenKind::Or) | Some(TokenKind::And) => {
+                    Err(ParseError::new("Expected a term after `NOT`", tok.span))
+                }
+                _ => Ok(Expr::Not(Box::new(self.parse_unary()?))),
+            },
+            TokenKind::LParen => {
+                let inner = self.parse_or()?;
+                match self.next() {
+                    Some(Token {
+                        kind: TokenKind::RParen,
+                        ..
+                    }) => Ok(inner),
+                    _ => Err(ParseError::new(
+                        "Unclosed `(`",
+                        tok.span.start..self.input_len,
+                    )),
+                }
+            }
+            TokenKind::Phrase(text) => Ok(Expr::Text(text)),
+            TokenKind::Word(word) => parse_term(&word, tok.span),
+            // `)`, `OR` and `AND` are consumed by the callers.
+            _ => unreachable!(),
+        }
+    }
+}
+
+fn parse_term(word: &str, span: Span) -> Result<Expr, ParseError> {
+    let Some(op_at) = word.find(is_op_char) else {
+        return Ok(Expr::Text(word.to_string()));
+    };
+    let key = &word[..op_at];
+    // Not a field, e.g. `Mamma Mia!` or `Mission:`: match it in the title.
+    let Some(field) = Field::from_key(key) else {
+        return Ok(Expr::Text(word.to_string()));
+    };
+
+    let rest = &word[op_at..];
+    let rest = rest.strip_prefix(':').unwrap_or(rest);
+    let (op, value) = [
+        ("!=", CmpOp::Ne),
+        (">=", CmpOp::Ge),
+        ("<=", CmpOp::Le),
+        ("!", CmpOp::Ne),
+        (">", CmpOp::Gt),
+        ("<", CmpOp::Lt),
+        ("=", CmpOp::Eq),
+    ]
+    .iter()
+    .find_map(|(p, op)| rest.strip_prefix(p).map(|v| (*op, v)))
+    .unwrap_or((CmpOp::Eq, rest));
+
+    let value_span = span.start + word.len() - value.len()..span.end;
+    if value.is_empty() {
+        return Err(ParseError::new(
+            format!("Missing value for `{}`", key),
+            span,
+        ));
+    }
+    if !field.is_ordered() && !matches!(op, CmpOp::Eq | CmpOp::Ne) {
+        return Err(ParseError::new(
+            format!("`{}` only supports `:` and `:!`", key),
+            span,
+        ));
+    }
+
+    let value =
+        match field {
+            Field::Title | Field::Notes | Field::Location | Field::Borrower => {
+                Value::Text(value.to_string())
+            }
+            Field::Owned | Field::OnLoan | Field::Overdue => {
+                Value::Flag(match value.to_ascii_lowercase().as_str() {
+                    "yes" | "true" | "y" => true,
+                    "no" | "false" | "n" => false,
+                    _ => return Err(ParseError::new("Expected `yes` or `no`", value_span)),
+                })
+            }
+            Field::Tag => Value::Text(value.trim_start_matches('#').to_string()),
+            Field::Category => Value::Category(match matching_categories(value)[..] {
+                [category] => category,
+                [] => {
+                    return Err(ParseError::new(
+                        format!("Unknown category `{}`", value),
+                        value_span,
+                    ))
+                }
+                ref several => {
+                    let names: Vec<String> = several
+                        .iter()
+                        .map(|c| c.as_str().to_ascii_lowercase())
+                        .collect();
+                    return Err(ParseError::new(
+                        format!("`{}` could be {}", value, names.join(" or ")),
+                        value_span,
+                    ));
+                }
+            }),
+            Field::Status => Value::Status(parse_status(value).ok_or_else(|| {
+                ParseError::new(format!("Unknown status `{}`", value), value_span)
+            })?),
+            // Other names match the free-text format of copies, e.g. `format:vinyl`.
+            Field::Format => match parse_format(value) {
+                Some(format) => Value::Format(format),
+                None => Value::Text(value.to_string()),
+            },
+            Field::Rating => match value.parse::<i64>() {
+                Ok(n) if (0..=10).contains(&n) => Value::Number(n),
+                _ => {
+                    return Err(ParseError::new(
+                        "Rating must be a number from 0 to 10",
+                        value_span,
+                    ))
+                }
+            },
+            Field::Created | Field::Updated => {
+                parse_when(value).map_err(|message| ParseError::new(message, value_span))?
+            }
+        };
+    Ok(Expr::Field(field, op, value))
+}
+
+/// Category by its name or an unambiguous prefix of it, case-insensitively.
+pub fn parse_category(s: &str) -> Option<Category> {
+    match matching_categories(s)[..] {
+        [category] => Some(category),
+        _ => None,
+    }
+}
+
+// The category named `s`, or every category whose name starts with it.
+fn matching_categories(s: &str) -> Vec<Category> {
+    let s = s.trim().to_ascii_lowercase();
+    if s == "film" {
+        return vec![Category::Movie];
+    }
+    if let Some(exact) = Category::ALL
+        .into_iter()
+        .find(|c| c.as_str().eq_ignore_ascii_case(&s))
+    {
+        return vec![exact];
+    }
+    Category::ALL
+        .into_iter()
+        .filter(|c| c.as_str().to_ascii_lowercase().starts_with(&s))
+        .collect()
+}
+
+/// Format by name, case-insensitively.
+pub fn parse_format(s: &str) -> Option<Format> {
+    Format::ALL
+        .into_iter()
+        .find(|f| f.as_str().eq_ignore_ascii_case(s.trim()))
+}
+
+/// Wishlist priority by name, case-insensitively.
+pub fn parse_priority(s: &str) -> Option<Priority> {
+    Priority::ALL
+        .into_iter()
+        .find(|p| p.as_str().eq_ignore_ascii_case(s.trim()))
+}
+
+/// Status by name or a common synonym such as "done" or "todo".
+pub fn parse_status(s: &str) -> Option<Status> {
+    let s: String = s
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .collect::<String>()
+        .to_ascii_lowercase();
+    match s.as_str() {
+        "done" | "finished" | "complete" | "completed" => Some(Status::Finished),
+        "started" | "inprogress" | "progress" | "current" => Some(Status::InProgress),
+        "planned" | "todo" | "backlog" => Some(Status::Planned),
+        "wishlist" | "wish" | "wanted" | "wanttobuy" => Some(Status::Wishlist),
+        _ => None,
+    }
+}
+
+fn parse_when(s: &str) -> Result<Value, &'static str> {
+    const EXPECTED: &str = "Expected a date (2024-01-31) or an age (30d, 6w, 3m, 1y)";
+    if let Ok(date) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        return Ok(Value::Date(date));
+    }
+    let unit = s.chars().last().ok_or(EXPECTED)?;
+    let n: i64 = s[..s.len() - unit.len_utf8()]
+        .parse()
+        .map_err(|_| EXPECTED)?;
+    let age = match unit {
+        'd' => TimeDelta::try_days(n),
+        'w' => TimeDelta::try_weeks(n),
+        'm' => n.checked_mul(30).and_then(TimeDelta::try_days),
+        'y' => n.checked_mul(365).and_then(TimeDelta::try_days),
+        _ => return Err(EXPECTED),
+    };
+    age.map(Value::Age).ok_or("Age is too large")
+}
+
+// Where the last term of `input` starts, after a leading `-` (NOT) but not
+// after a hyphen inside a word such as "Spider-Man".
+fn last_term_start(input: &str) -> usize {
+    let start = input
+        .rfind(|c: char| c.is_whitespace() || c == '(')
+        .map(|i| i + 1)
+        .unwrap_or(0);
+    if input[start..].starts_with('-') {
+        start + 1
+    } else {
+        start
+    }
+}
+
+/// Suggestions for the last (partially typed) term of `input`. Each
+/// suggestion is the full replacement text for that term.
+pub fn completions(input: &str) -> Vec<String> {
+    let term = &input[last_term_start(input)..];
+    if term.is_empty() || term.contains('"') {
+        return vec![];
+    }
+
+    let Some(colon) = term.find(':') else {
+        let lower = term.to_ascii_lowercase();
+        return Field::KEYS
+            .iter()
+            .filter(|(k, _)| k.starts_with(&lower) && *k != lower)
+            .map(|(k, _)| format!("{}:", k))
+            .collect();
+    };
+
+    let key = &term[..colon];
+    let value = &term[colon + 1..];
+    let (neg, value) = match value.strip_prefix('!') {
+        Some(v) => ("!", v),
+        None => ("", value),
+    };
+    let value = value.to_ascii_lowercase();
+    let values: Vec<String> = match Field::from_key(key) {
+        Some(Field::Category) => Category::ALL
+            .iter()
+            .map(|c| c.as_str().to_ascii_lowercase())
+            .collect(),
+        Some(Field::Status) => Status::ALL.iter().map(|s| s.as_key().into()).collect(),
+        Some(Field::Format) => vec!["physical".into(), "digital".into()],
+        Some(Field::Owned | Field::OnLoan | Field::Overdue) => vec!["yes".into(), "no".into()],
+        Some(Field::Rating) => vec![">=5".into(), ">=7".into(), ">=9".into()],
+        Some(Field::Created | Field::Updated) => {
+            vec!["<7d".into(), "<30d".into(), "<1y".into(), ">1y".into()]
+        }
+        _ => vec![],
+    };
+    values
+        .into_iter()
+        .filter(|v| v.starts_with(&value) && *v != value)
+        .map(|v| format!("{}:{}{}", key, neg, v))
+        .collect()
+}
+
+/// Replaces the last term of `input` with `completion`.
+pub fn apply_completion(input: &str, completion: &str) -> String {
+    let start = last_term_start(input);
+    let mut out = input[..start].to_string();
+    out.push_str(completion);
+    if !completion.ends_with(':') {
+        out.push(' ');
+    }
+    out
+}
+
+/// Escapes `*`, `?` and `[` for a `GLOB` pattern.
+pub fn glob_escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '*' | '?' | '[' => {
+                out.push('[');
+                out.push(c);
+                out.push(']');
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Escapes `%`, `_` and `\` for a `LIKE ... ESCAPE '\'` pattern.
+pub fn like_escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(input: &str) -> Vec<TokenKind> {
+        lex(input).unwrap().into_iter().map(|t| t.kind).collect()
+    }
+
+    fn word(s: &str) -> TokenKind {
+        TokenKind::Word(s.into())
+    }
+
+    #[test]
+    fn lexes_operators_phrases_and_quoted_values() {
+        assert_eq!(
+            kinds(r#"(cat:book OR -tag:x) "exact phrase" title:"star wars""#),
+            vec![
+                TokenKind::LParen,
+                word("cat:book"),
+                TokenKind::Or,
+                TokenKind::Not,
+                word("tag:x"),
+                TokenKind::RParen,
+                TokenKind::Phrase("exact phrase".into()),
+                word("title:star wars"),
+            ]
+        );
+    }
+
+    #[test]
+    fn lexes_dash_as_not_only_before_a_term() {
+        assert_eq!(kinds("-dune"), vec![TokenKind::Not, word("dune")]);
+        assert_eq!(kinds("a - b"), vec![word("a"), word("-"), word("b")]);
+        assert_eq!(
+            kinds("(x -)"),
+            vec![TokenKind::LParen, word("x"), word("-"), TokenKind::RParen]
+        );
+        assert_eq!(kinds("Spider-Man"), vec![word("Spider-Man")]);
+    }
+
+    #[test]
+    fn token_spans_cover_the_input() {
+        let spans: Vec<Span> = lex(r#"ab "c d" e"#)
+            .unwrap()
+            .into_iter()
+            .map(|t| t.span)
+            .collect();
+        assert_eq!(spans, vec![0..2, 3..8, 9..10]);
+    }
+
+    #[test]
+    fn parses_fields_and_boolean_structure() {
+        assert_eq!(
+            parse("cat:book rating>=7 OR NOT status:!finished").unwrap(),
+            Expr::Or(vec![
+                Expr::And(vec![
+                    Expr::Field(Field::Category, CmpOp::Eq, Value::Category(Category::Book)),
+                    Expr::Field(Field::Rating, CmpOp::Ge, Value::Number(7)),
+                ]),
+                Expr::Not(Box::new(Expr::Field(
+                    Field::Status,
+                    CmpOp::Ne,
+                    Value::Status(Status::Finished)
+                ))),
+            ])
+        );
+        assert_eq!(
+            parse("updated:2024-03-31").unwrap(),
+            Expr::Field(
+                Field::Updated,
+                CmpOp::Eq,
+                Value::Date(NaiveDate::from_ymd_opt(2024, 3, 31).unwrap())
+            )
+        );
+    }
+
+    #[test]
+    fn words_that_are_not_fields_match_the_title() {
+        assert_eq!(
+            parse("Mamma Mia!").unwrap(),
+            Expr::And(vec![Expr::Text("Mamma".into()), Expr::Text("Mia!".into())])
+        );
+        assert_eq!(parse("Mission:").unwrap(), Expr::Text("Mission:".into()));
+        assert_eq!(parse("<3").unwrap(), Expr::Text("<3".into()));
+        assert_eq!(parse("a - b").unwrap(), parse("a \"-\" b").unwrap());
+    }
+
+    #[test]
+    fn errors_point_at_the_offending_text() {
+        let err = |input: &str| parse(input).unwrap_err();
+        assert_eq!(err("rating>=11").span, 8..10);
+        assert_eq!(err("cat:bogus").span, 4..9);
+        assert_eq!(err("cat:m").message, "`m` could be movie or music");
+        assert_eq!(err("owned:maybe").span, 6..11);
+        assert_eq!(err("x status:").span, 2..9);
+        assert_eq!(err("title>b").span, 0..7);
+        assert_eq!(err("dune )").span, 5..6);
+        assert_eq!(err("(dune").span, 0..5);
+        assert_eq!(err(r#"say "hi"#).span, 4..7);
+        assert_eq!(err("dune OR").span, 5..7);
+        assert_eq!(err("NOT").span, 0..3);
+    }
+
+    #[test]
+    fn rejects_ages_too_large_for_a_duration() {
+        for input in [
+            "updated:<200000000000d",
+            "created:>9223372036854775807w",
+            "updated:400000000000000000m",
+            "updated:<30000000000000000y",
+        ] {
+            let err = parse(input).unwrap_err();
+            assert_eq!(err.message, "Age is too large", "{}", input);
+            assert_eq!(err.span.end, input.len());
+        }
+        assert_eq!(parse("updated:<200000000000d").unwrap_err().span, 9..22);
+        assert_eq!(
+            parse("updated:<2y").unwrap(),
+            Expr::Field(
+                Field::Updated,
+                CmpOp::Lt,
+                Value::Age(TimeDelta::try_days(730).unwrap())
+            )
+        );
+        assert!(parse("updated:99999999999999999999d")
+            .unwrap_err()
+            .message
+            .starts_with("Expected a date"));
+    }
+
+    #[test]
+    fn categories_need_an_exact_name_or_a_unique_prefix() {
+        assert_eq!(parse_category("mo"), Some(Category::Movie));
+        assert_eq!(parse_category("MUSIC"), Some(Category::Music));
+        assert_eq!(parse_category("film"), Some(Category::Movie));
+        assert_eq!(parse_category("m"), None);
+        assert_eq!(parse_category("x"), None);
+    }
+
+    #[test]
+    fn completes_the_last_term_only() {
+        assert_eq!(completions("dune sta"), vec!["status:"]);
+        assert!(completions("cat:b").contains(&"cat:book".to_string()));
+        assert_eq!(completions("-owned:y"), vec!["owned:yes"]);
+        assert!(completions("Spider-sta").is_empty());
+        assert_eq!(apply_completion("x -sta", "status:"), "x -status:");
+    }
+}
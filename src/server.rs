@@ -0,0 +1,254 @@
+use crate::api::{self, ApiRequest, ApiSettings};
+use crate::repo::{RepoError, RepoResult, Repository};
+use crate::util;
+use crate::web::{self, WebAccess};
+use std::io::Read;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use tiny_http::{Header, Response, Server};
+
+const MAX_BODY: u64 = 1024 * 1024;
+
+/// The JSON API under `/api` and the HTML view everywhere else, served from a
+/// background thread on localhost. Stops when dropped.
+pub struct ApiServer {
+    server: Arc<Server>,
+    thread: Option<JoinHandle<()>>,
+    addr: SocketAddr,
+    writes: Arc<AtomicU64>,
+}
+
+impl ApiServer {
+    pub fn start(repo: Arc<dyn Repository>, settings: &ApiSettings) -> RepoResult<Self> {
+        let port = settings.port;
+        let token = settings.token.clone();
+        let web_edits = settings.web_edits;
+        let server = Server::http(("127.0.0.1", port))
+            .map_err(|e| RepoError::Other(format!("Cannot listen on port {}: {}", port, e)))?;
+        let addr = server
+            .server_addr()
+            .to_ip()
+            .ok_or_else(|| RepoError::Other("Server is not on a TCP socket".into()))?;
+        let server = Arc::new(server);
+        let writes = Arc::new(AtomicU64::new(0));
+        let thread = {
+            let server = Arc::clone(&server);
+            let writes = Arc::clone(&writes);
+            std::thread::spawn(move || {
+                let covers = util::covers_dir();
+                let access = WebAccess {
+                    token: &token,
+                    addr,
+                    allow_edits: web_edits,
+                    covers: &covers,
+                };
+                for request in server.incoming_requests() {
+                    serve(repo.as_ref(), &access, request, &writes);
+                }
+            })
+        };
+        Ok(Self {
+            server,
+            thread: Some(thread),
+            addr,
+            writes,
+        })
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Number of successful requests that changed the catalog so far.
+    pub fn writes(&self) -> u64 {
+        self.writes.load(Ordering::Relaxed)
+    }
+
+    /// Blocks until the server stops.
+    pub fn wait(mut self) {
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for ApiServer {
+    fn drop(&mut self) {
+        self.server.unblock();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn serve(
+    repo: &dyn Repository,
+    access: &WebAccess,
+    mut request: tiny_http::Request,
+    writes: &AtomicU64,
+) {
+    let mut body = String::new();
+    let read = request.as_reader().take(MAX_BODY).read_to_string(&mut body);
+    let (authorization, host, origin, cookie) = (
+        header(&request, "Authorization"),
+        header(&request, "Host"),
+        header(&request, "Origin"),
+        header(&request, "Cookie"),
+    );
+    let method = request.method().as_str().to_uppercase();
+    let req = ApiRequest {
+        method: &method,
+        url: request.url(),
+        authorization: authorization.as_deref(),
+        host: host.as_deref(),
+        origin: origin.as_deref(),
+        cookie: cookie.as_deref(),
+        body: &body,
+    };
+    let path = req.url.split('?').next().unwrap_or_default();
+    let response = match read {
+        Ok(_) if path == "/api" || path.starts_with("/api/") => {
+            api::handle(repo, access.token, &req)
+        }
+        Ok(_) => web::handle(repo, &req, access),
+        Err(e) => api::ApiResponse::error(400, format!("Unreadable body: {}", e)),
+    };
+    if response.is_write(&method) {
+        writes.fetch_add(1, Ordering::Relaxed);
+    }
+    log::info!("{} {} -> {}", method, request.url(), response.status);
+    let content_type =
+        Header::from_bytes("Content-Type", response.content_type).expect("static header is valid");
+    let mut reply = Response::from_data(response.body)
+        .with_status_code(response.status)
+        .with_header(content_type);
+    for (name, value) in &response.headers {
+        if let Ok(header) = Header::from_bytes(*name, value.as_bytes()) {
+            reply.add_header(header);
+        }
+    }
+    let _ = request.respond(reply);
+}
+
+fn header(request: &tiny_http::Request, name: &'static str) -> Option<String> {
+    request
+        .headers()
+        .iter()
+        .find(|h| h.field.equiv(name))
+        .map(|h| h.value.as_str().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Category, MediaItem, Query};
+    use crate::sqlite_repo::SqliteRepo;
+    use std::io::Write;
+    use std::net::TcpStream;
+    use std::path::Path;
+
+    const TOKEN: &str = "secret-token";
+
+    fn start() -> (ApiServer, Arc<SqliteRepo>) {
+        let repo = Arc::new(SqliteRepo::new(Path::new(":memory:")));
+        repo.init().unwrap();
+        repo.add(&mut MediaItem::new("Dune", Category::Book))
+            .unwrap();
+        let settings = ApiSettings {
+            enabled: true,
+            port: 0,
+            token: TOKEN.into(),
+            web_edits: false,
+        };
+        let server = ApiServer::start(repo.clone(), &settings).unwrap();
+        (server, repo)
+    }
+
+    // Sends one request over a fresh connection; returns the status and the
+    // raw head and body.
+    fn send(
+        server: &ApiServer,
+        method: &str,
+        url: &str,
+        headers: &[&str],
+        body: &str,
+    ) -> (u16, String) {
+        let mut stream = TcpStream::connect(server.addr()).unwrap();
+        let mut head = format!(
+            "{} {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\nContent-Length: {}\r\n",
+            method,
+            url,
+            server.addr(),
+            body.len()
+        );
+        for header in headers {
+            head.push_str(header);
+            head.push_str("\r\n");
+        }
+        head.push_str("\r\n");
+        stream.write_all(head.as_bytes()).unwrap();
+        // The server may answer before reading all of an oversized body.
+        let _ = stream.write_all(body.as_bytes());
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let status = response
+            .split(' ')
+            .nth(1)
+            .and_then(|s| s.parse().ok())
+            .unwrap_or_default();
+        (status, response)
+    }
+
+    #[test]
+    fn serves_the_api_and_web_pages_over_http() {
+        let (server, repo) = start();
+        let bearer = "Authorization: Bearer secret-token";
+
+        assert_eq!(send(&server, "GET", "/api/items", &[], "").0, 401);
+        let (status, response) = send(&server, "GET", "/api/items", &[bearer], "");
+        assert_eq!(status, 200);
+        assert!(response.contains("\"Dune\""), "{}", response);
+
+        let (status, _) = send(
+            &server,
+            "POST",
+            "/api/items",
+            &[bearer, "Content-Type: application/json"],
+            r#"{"title": "Emma"}"#,
+        );
+        assert_eq!(status, 201);
+        let oversized = format!(
+            r#"{{"title": "Big", "notes": "{}"}}"#,
+            "x".repeat(MAX_BODY as usize)
+        );
+        let (status, _) = send(&server, "POST", "/api/items", &[bearer], &oversized);
+        assert_eq!(status, 400);
+        assert_eq!(repo.count(&Query::default()).unwrap(), 2);
+        assert_eq!(server.writes(), 1);
+
+        // A token in the link signs the browser in; the API ignores it.
+        let (status, response) = send(&server, "GET", "/?token=secret-token", &[], "");
+        assert_eq!(status, 303);
+        assert!(
+            response.contains("Set-Cookie: catalog_token="),
+            "{}",
+            response
+        );
+        assert_eq!(
+            send(&server, "GET", "/api?token=secret-token", &[], "").0,
+            401
+        );
+        let (status, response) = send(
+            &server,
+            "GET",
+            "/",
+            &["Cookie: catalog_token=secret-token"],
+            "",
+        );
+        assert_eq!(status, 200);
+        assert!(response.contains("text/html"), "{}", response);
+        assert!(response.contains("Dune"), "{}", response);
+    }
+}
@@ -0,0 +1,160 @@
+use crate::api::{self, ApiSettings};
+use crate::repo::{RepoResult, Repository};
+use crate::server::ApiServer;
+use eframe::egui::{self, RichText, TextEdit};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Window for turning the local HTTP server (JSON API and web view) on and off.
+#[derive(Default)]
+pub struct ServerView {
+    pub open: bool,
+    settings: Option<ApiSettings>,
+    port: String,
+    server: Option<ApiServer>,
+    seen_writes: u64,
+}
+
+impl ServerView {
+    /// Loads the saved settings and starts the server if it was left enabled.
+    pub fn load(&mut self, repo: &Arc<dyn Repository>) -> RepoResult<()> {
+        let settings = ApiSettings::load(repo.as_ref())?;
+        self.port = settings.port.to_string();
+        let enabled = settings.enabled;
+        self.settings = Some(settings);
+        if enabled {
+            self.start(repo)?;
+        }
+        Ok(())
+    }
+
+    /// True once per batch of changes made through the API since the last call.
+    pub fn poll_changes(&mut self, ctx: &egui::Context) -> bool {
+        let Some(server) = &self.server else {
+            return false;
+        };
+        // Keep polling while the window is idle so API writes show up.
+        ctx.request_repaint_after(Duration::from_secs(1));
+        let writes = server.writes();
+        let changed = writes != self.seen_writes;
+        self.seen_writes = writes;
+        changed
+    }
+
+    fn start(&mut self, repo: &Arc<dyn Repository>) -> RepoResult<()> {
+        let Some(settings) = &self.settings else {
+            return Ok(());
+        };
+        self.server = None;
+        self.seen_writes = 0;
+        self.server = Some(ApiServer::start(Arc::clone(repo), settings)?);
+        Ok(())
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context, repo: &Arc<dyn Repository>) -> RepoResult<()> {
+        let mut open = self.open;
+        let mut result = Ok(());
+        egui::Window::new("Local server")
+            .open(&mut open)
+            .default_width(420.0)
+            .show(ctx, |ui| {
+                result = self.server_ui(ui, repo);
+            });
+        self.open = open;
+        result
+    }
+
+    fn server_ui(&mut self, ui: &mut egui::Ui, repo: &Arc<dyn Repository>) -> RepoResult<()> {
+        if self.settings.is_none() {
+            self.load(repo)?;
+        }
+        let Some(settings) = self.settings.as_mut() else {
+            return Ok(());
+        };
+        let mut changed = false;
+        let mut restart = false;
+        changed |= ui
+            .checkbox(
+                &mut settings.enabled,
+                "Serve the API and web view on localhost",
+            )
+            .changed();
+        changed |= ui
+            .checkbox(&mut settings.web_edits, "Allow editing from the web view")
+            .on_hover_text("Status, rating and notes can be changed from the item pages")
+            .changed();
+        ui.horizontal(|ui| {
+            ui.label("Port:");
+            if ui
+                .add(TextEdit::singleline(&mut self.port).desired_width(60.0))
+                .lost_focus()
+            {
+                match self.port.trim().parse() {
+                    Ok(port) if port != settings.port => {
+                        settings.port = port;
+                        changed = true;
+                    }
+                    Ok(_) => {}
+                    Err(_) => self.port = settings.port.to_string(),
+                }
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label("Token:");
+            ui.label(RichText::new(&settings.token).monospace());
+            if ui.small_button("Copy").clicked() {
+                ui.output_mut(|o| o.copied_text = settings.token.clone());
+            }
+            if ui
+                .small_button("New token")
+                .on_hover_text("Clients using the old token stop working")
+                .clicked()
+            {
+                settings.token = api::generate_token();
+                changed = true;
+            }
+        });
+        if changed {
+            settings.save(repo.as_ref())?;
+            restart = true;
+        }
+
+        ui.separator();
+        if restart {
+            if self.settings.as_ref().is_some_and(|s| s.enabled) {
+                if let Err(e) = self.start(repo) {
+                    if let Some(settings) = self.settings.as_mut() {
+                        settings.enabled = false;
+                    }
+                    return Err(e);
+                }
+            } else {
+                self.server = None;
+            }
+        }
+        match &self.server {
+            Some(server) => {
+                let root = format!("http://{}", server.addr());
+                let token = self.settings.as_ref().map(|s| s.token.as_str());
+                ui.horizontal(|ui| {
+                    ui.label("Web view:");
+                    ui.hyperlink_to(
+                        &root,
+                        format!("{}/?token={}", root, token.unwrap_or_default()),
+                    );
+                });
+                let base = format!("{}/api", root);
+                ui.label(format!("API: {}", base));
+                ui.weak(format!("OpenAPI description: {}/openapi.json", base));
+                ui.weak(format!(
+                    "curl -H \"Authorization: Bearer <token>\" {}/items?q=cat:book",
+                    base
+                ));
+            }
+            None => {
+                ui.weak("Stopped");
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,324 @@
+use crate::models::{Category, MediaItem, Status};
+use crate::util::html_escape;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+const THUMBNAIL_WIDTH: u32 = 200;
+const THUMBNAIL_HEIGHT: u32 = 300;
+const COVERS_DIR: &str = "covers";
+
+const STYLE: &str = "body{font-family:sans-serif;margin:0 auto;max-width:1200px;padding:1em;color:#222}\
+nav a{margin-right:1em}a{color:#2a5db0}.stats{color:#555}\
+.filters{margin:1em 0;display:flex;gap:.5em;align-items:center}\
+.cards{display:grid;grid-template-columns:repeat(auto-fill,minmax(180px,1fr));gap:1em}\
+.card{border:1px solid #ddd;border-radius:6px;padding:.6em;background:#fafafa}\
+.card img,.card .nocover{width:100%;aspect-ratio:2/3;object-fit:cover;border-radius:4px;background:#e6e6e6}\
+.card h3{font-size:1em;margin:.4em 0 .2em}.meta,.tags{font-size:.85em;color:#666;margin:.2em 0}\
+.notes{font-size:.85em;white-space:pre-wrap;max-height:6em;overflow:hidden}";
+
+const SCRIPT: &str = "const q=document.getElementById('q'),s=document.getElementById('status');\
+function filter(){const words=q.value.toLowerCase().split(/\\s+/).filter(Boolean);let n=0;\
+document.querySelectorAll('.card').forEach(c=>{const ok=(!s.value||c.dataset.status===s.value)\
+&&words.every(w=>c.dataset.search.includes(w));c.hidden=!ok;if(ok)n++;});\
+document.getElementById('count').textContent=n+' shown';}\
+q.addEventListener('input',filter);s.addEventListener('change',filter);filter();";
+
+/// Writes a self-contained static site for `items` into `dir`: `index.html`
+/// with everything, one page per category, and cover thumbnails under
+/// `covers/`. Returns the number of pages written.
+pub fn export_site(items: &[MediaItem], dir: &Path) -> Result<usize, Box<dyn Error>> {
+    fs::create_dir_all(dir)?;
+    let covers = write_thumbnails(items, &dir.join(COVERS_DIR))?;
+    let categories: Vec<Category> = Category::ALL
+        .into_iter()
+        .filter(|c| items.iter().any(|i| i.category == *c))
+        .collect();
+    let nav = std::iter::once(("index.html".to_string(), "All".to_string()))
+        .chain(
+            categories
+                .iter()
+                .map(|c| (page_name(*c), c.as_str().to_string())),
+        )
+        .map(|(href, label)| format!("<a href=\"{}\">{}</a>", href, label))
+        .collect::<String>();
+
+    fs::write(
+        dir.join("index.html"),
+        site_page("Media Catalog", &nav, items, &covers, true),
+    )?;
+    for category in &categories {
+        let in_category: Vec<MediaItem> = items
+            .iter()
+            .filter(|i| i.category == *category)
+            .cloned()
+            .collect();
+        fs::write(
+            dir.join(page_name(*category)),
+            site_page(category.as_str(), &nav, &in_category, &covers, false),
+        )?;
+    }
+    Ok(categories.len() + 1)
+}
+
+/// Writes `items` as a single Markdown file with a summary and one table per
+/// category. Cover thumbnails go to `covers/` next to the file.
+pub fn export_markdown(items: &[MediaItem], path: &Path) -> Result<(), Box<dyn Error>> {
+    let dir = path.parent().unwrap_or(Path::new("."));
+    let covers = write_thumbnails(items, &dir.join(COVERS_DIR))?;
+    let mut md = String::from("# Media Catalog\n\n");
+    for line in summary(items, true) {
+        writeln!(md, "- {}", line)?;
+    }
+    for category in Category::ALL {
+        let in_category: Vec<&MediaItem> =
+            items.iter().filter(|i| i.category == category).collect();
+        if in_category.is_empty() {
+            continue;
+        }
+        writeln!(md, "\n## {}\n", category)?;
+        writeln!(md, "| Cover | Title | Status | Rating | Notes |")?;
+        writeln!(md, "|---|---|---|---|---|")?;
+        for item in in_category {
+            let cover = item
+                .id
+                .and_then(|id| covers.get(&id))
+                .map(|file| format!("![]({}/{})", COVERS_DIR, file))
+                .unwrap_or_default();
+            writeln!(
+                md,
+                "| {} | {} | {} | {} | {} |",
+                cover,
+                markdown_cell(&item.title),
+                item.status,
+                item.rating.map(|r| format!("{}/10", r)).unwrap_or_default(),
+                markdown_cell(item.notes.as_deref().unwrap_or_default()),
+            )?;
+        }
+    }
+    fs::write(path, md)?;
+    Ok(())
+}
+
+fn page_name(category: Category) -> String {
+    format!("{}.html", category.as_str().to_lowercase())
+}
+
+fn site_page(
+    title: &str,
+    nav: &str,
+    items: &[MediaItem],
+    covers: &HashMap<i64, String>,
+    per_category: bool,
+) -> String {
+    let mut html = format!(
+        "<!DOCTYPE html><html><head><meta charset=\"utf-8\">\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\
+         <title>{title}</title><style>{STYLE}</style></head><body>\
+         <h1>{title}</h1><nav>{nav}</nav><p class=\"stats\">{stats}</p>\
+         <div class=\"filters\"><input id=\"q\" type=\"search\" placeholder=\"Filter by title, tag or note\" size=\"40\">\
+         <select id=\"status\"><option value=\"\">Any status</option>{statuses}</select>\
+         <span id=\"count\" class=\"stats\"></span></div><div class=\"cards\">",
+        title = html_escape(title),
+        stats = html_escape(&summary(items, per_category).join(" · ")),
+        statuses = Status::ALL
+            .iter()
+            .map(|s| format!("<option value=\"{}\">{}</option>", s.as_key(), s))
+            .collect::<String>(),
+    );
+    for item in items {
+        html.push_str(&card(item, covers));
+    }
+    let _ = write!(html, "</div><script>{}</script></body></html>", SCRIPT);
+    html
+}
+
+fn card(item: &MediaItem, covers: &HashMap<i64, String>) -> String {
+    let tags = item.tags();
+    let search = format!(
+        "{} {} {}",
+        item.title,
+        tags.iter()
+            .map(|t| format!("#{}", t))
+            .collect::<Vec<_>>()
+            .join(" "),
+        item.notes.as_deref().unwrap_or_default()
+    )
+    .to_lowercase();
+    let cover = match item.id.and_then(|id| covers.get(&id)) {
+        Some(file) => format!(
+            "<img src=\"{}/{}\" alt=\"\" loading=\"lazy\">",
+            COVERS_DIR,
+            html_escape(file)
+        ),
+        None => "<div class=\"nocover\"></div>".into(),
+    };
+    let mut meta = format!("{} · {}", item.category, item.status);
+    if let Some(rating) = item.rating {
+        let _ = write!(meta, " · ★ {}/10", rating);
+    }
+    let mut html = format!(
+        "<div class=\"card\" data-status=\"{}\" data-search=\"{}\">{}<h3>{}</h3><p class=\"meta\">{}</p>",
+        item.status.as_key(),
+        html_escape(&search),
+        cover,
+        html_escape(&item.title),
+        meta
+    );
+    if !tags.is_empty() {
+        let tags = tags.iter().map(|t| format!("#{}", t)).collect::<Vec<_>>();
+        let _ = write!(
+            html,
+            "<p class=\"tags\">{}</p>",
+            html_escape(&tags.join(" "))
+        );
+    }
+    if let Some(notes) = item.notes.as_deref().filter(|n| !n.trim().is_empty()) {
+        let _ = write!(html, "<p class=\"notes\">{}</p>", html_escape(notes));
+    }
+    html.push_str("</div>");
+    html
+}
+
+// Short summary lines: totals by status, average rating and, if requested,
+// the number of items per category.
+fn summary(items: &[MediaItem], per_category: bool) -> Vec<String> {
+    let count = |status: Status| items.iter().filter(|i| i.status == status).count();
+    let mut lines = vec![
+        format!("{} items", items.len()),
+        format!("{} finished", count(Status::Finished)),
+        format!("{} in progress", count(Status::InProgress)),
+        format!("{} planned", count(Status::Planned)),
+    ];
+    if count(Status::Wishlist) > 0 {
+        lines.push(format!("{} on the wishlist", count(Status::Wishlist)));
+    }
+    let ratings: Vec<f64> = items
+        .iter()
+        .filter_map(|i| i.rating)
+        .map(f64::from)
+        .collect();
+    if !ratings.is_empty() {
+        lines.push(format!(
+            "average rating {:.1}",
+            ratings.iter().sum::<f64>() / ratings.len() as f64
+        ));
+    }
+    if per_category {
+        for category in Category::ALL {
+            let n = items.iter().filter(|i| i.category == category).count();
+            if n > 0 {
+                lines.push(format!("{}: {}", category, n));
+            }
+        }
+    }
+    lines
+}
+
+fn markdown_cell(text: &str) -> String {
+    text.replace('|', "\\|")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// Scales each readable cover down to a JPEG thumbnail named after the item id.
+// Missing or unreadable covers are skipped.
+fn write_thumbnails(
+    items: &[MediaItem],
+    dir: &Path,
+) -> Result<HashMap<i64, String>, Box<dyn Error>> {
+    let mut written = HashMap::new();
+    for item in items {
+        let (Some(id), Some(path)) = (item.id, item.cover_path.as_deref()) else {
+            continue;
+        };
+        let Ok(image) = image::open(path) else {
+            log::warn!("Skipping unreadable cover {}", path);
+            continue;
+        };
+        if written.is_empty() {
+            fs::create_dir_all(dir)?;
+        }
+        let file = format!("{}.jpg", id);
+        image
+            .thumbnail(THUMBNAIL_WIDTH, THUMBNAIL_HEIGHT)
+            .to_rgb8()
+            .save(dir.join(&file))?;
+        written.insert(id, file);
+    }
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("site-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn items() -> Vec<MediaItem> {
+        let mut dune = MediaItem::new("Dune <1965>", Category::Book);
+        dune.id = Some(1);
+        dune.status = Status::Finished;
+        dune.rating = Some(9);
+        dune.notes = Some("#SciFi | desert\nplanet".into());
+        let mut alien = MediaItem::new("Alien", Category::Movie);
+        alien.id = Some(2);
+        alien.rating = Some(6);
+        vec![dune, alien]
+    }
+
+    #[test]
+    fn summarizes_statuses_ratings_and_categories() {
+        assert_eq!(
+            summary(&items(), true),
+            vec![
+                "2 items",
+                "1 finished",
+                "0 in progress",
+                "1 planned",
+                "average rating 7.5",
+                "Book: 1",
+                "Movie: 1"
+            ]
+        );
+        assert_eq!(summary(&[], false).len(), 4);
+    }
+
+    #[test]
+    fn writes_one_page_per_category_with_escaped_cards() {
+        let dir = temp_dir("pages");
+        assert_eq!(export_site(&items(), &dir).unwrap(), 3);
+        let index = fs::read_to_string(dir.join("index.html")).unwrap();
+        assert!(index.contains("<h3>Dune &lt;1965&gt;</h3>"));
+        assert!(index.contains("data-status=\"finished\""));
+        assert!(index.contains("<p class=\"tags\">#scifi</p>"));
+        assert!(index.contains("<a href=\"movie.html\">Movie</a>"));
+        let movies = fs::read_to_string(dir.join("movie.html")).unwrap();
+        assert!(movies.contains("Alien") && !movies.contains("Dune"));
+        assert!(!dir.join("game.html").exists());
+        assert!(!dir.join(COVERS_DIR).exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn writes_markdown_tables_per_category() {
+        let dir = temp_dir("markdown");
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("catalog.md");
+        export_markdown(&items(), &path).unwrap();
+        let md = fs::read_to_string(&path).unwrap();
+        assert!(md.starts_with("# Media Catalog\n\n- 2 items\n"));
+        assert!(md.contains("\n## Book\n\n| Cover | Title | Status | Rating | Notes |\n"));
+        assert!(md.contains("|  | Dune <1965> | Finished | 9/10 | #SciFi \\| desert planet |"));
+        assert!(md.contains("\n## Movie\n"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
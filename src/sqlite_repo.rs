@@ -1,10 +1,27 @@
-use crate::models::{Category, MediaItem, Query, SortField, SortOrder, Status};
-use crate::repo::{RepoResult, Repository, Stats};
-use chrono::{Local, TimeZone};
+use crate::collation;
+use crate::dedup;
+use crate::export::ExportPreset;
+use crate::ids::ExternalId;
+use crate::models::{
+    Category, Condition, Format, Goal, Loan, MediaItem, OwnedCopy, Priority, Query, SavedSearch,
+    SortField, SortOrder, Status, WatchFolder,
+};
+use crate::repo::{DetailedStats, RepoError, RepoResult, Repository, Stats};
+use crate::scan::ScannedFile;
+use crate::search::{self, CmpOp, Expr, Field, Value};
+use crate::util::day_start;
+use chrono::{Datelike, Local, NaiveDate, TimeZone};
 use rusqlite::{params, params_from_iter, Connection, OptionalExtension, Row, ToSql};
+use std::collections::{BTreeMap, HashMap};
 use std::path::Path;
 use std::sync::Mutex;
 
+const ITEM_COLUMNS: &str = "id, title, category, status, rating, notes, cover_path, created_at, updated_at, sort_title, started_at, finished_at, \
+    (SELECT group_concat(scheme || ':' || value, char(10)) FROM external_ids WHERE item_id = media.id), \
+    progress, progress_total, file_path, format, priority, target_price, store";
+const COPY_COLUMNS: &str = "id, item_id, format, location, condition, purchased_on, price";
+const LOAN_COLUMNS: &str = "id, item_id, copy_id, borrower, lent_on, due_on, returned_on";
+
 pub struct SqliteRepo {
     conn: Mutex<Connection>,
 }
@@ -38,15 +55,114 @@ impl Repository for SqliteRepo {
             CREATE INDEX IF NOT EXISTS idx_media_title ON media(title);
             CREATE INDEX IF NOT EXISTS idx_media_category ON media(category);
             CREATE INDEX IF NOT EXISTS idx_media_status ON media(status);
+            CREATE INDEX IF NOT EXISTS idx_media_rating ON media(rating);
+            CREATE INDEX IF NOT EXISTS idx_media_created_at ON media(created_at);
+            CREATE INDEX IF NOT EXISTS idx_media_updated_at ON media(updated_at);
+            CREATE TABLE IF NOT EXISTS saved_searches (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                name TEXT NOT NULL UNIQUE COLLATE NOCASE,
+                query_json TEXT NOT NULL,
+                created_at INTEGER NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS goals (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                name TEXT NOT NULL,
+                category INTEGER,
+                target INTEGER NOT NULL,
+                start_date TEXT NOT NULL,
+                end_date TEXT NOT NULL,
+                backlog_only INTEGER NOT NULL DEFAULT 0
+            );
+            CREATE TABLE IF NOT EXISTS export_presets (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                name TEXT NOT NULL UNIQUE,
+                options_json TEXT NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS settings (
+                key TEXT PRIMARY KEY,
+                value TEXT NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS scanned_files (
+                path TEXT PRIMARY KEY,
+                modified INTEGER NOT NULL,
+                size INTEGER NOT NULL,
+                hash TEXT NOT NULL,
+                grouping TEXT NOT NULL,
+                item_id INTEGER REFERENCES media(id) ON DELETE SET NULL
+            );
+            CREATE INDEX IF NOT EXISTS idx_scanned_files_item ON scanned_files(item_id);
+            CREATE TABLE IF NOT EXISTS external_ids (
+                item_id INTEGER NOT NULL REFERENCES media(id) ON DELETE CASCADE,
+                scheme TEXT NOT NULL,
+                value TEXT NOT NULL,
+                PRIMARY KEY (scheme, value)
+            );
+            CREATE INDEX IF NOT EXISTS idx_external_ids_item ON external_ids(item_id);
+            CREATE TABLE IF NOT EXISTS copies (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                item_id INTEGER NOT NULL REFERENCES media(id) ON DELETE CASCADE,
+                format TEXT NOT NULL DEFAULT '',
+                location TEXT NOT NULL DEFAULT '',
+                condition INTEGER,
+                purchased_on TEXT,
+                price REAL
+            );
+            CREATE INDEX IF NOT EXISTS idx_copies_item ON copies(item_id);
+            CREATE TABLE IF NOT EXISTS loans (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                item_id INTEGER NOT NULL REFERENCES media(id) ON DELETE CASCADE,
+                copy_id INTEGER REFERENCES copies(id) ON DELETE SET NULL,
+                borrower TEXT NOT NULL,
+                lent_on TEXT NOT NULL,
+                due_on TEXT,
+                returned_on TEXT
+            );
+            CREATE INDEX IF NOT EXISTS idx_loans_item ON loans(item_id);
+            CREATE TABLE IF NOT EXISTS watch_folders (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                path TEXT NOT NULL UNIQUE,
+                category INTEGER NOT NULL,
+                status INTEGER NOT NULL,
+                enabled INTEGER NOT NULL DEFAULT 1
+            );
             "#,
         )?;
+        add_column_if_missing(&conn, "media", "sort_title", "TEXT")?;
+        add_column_if_missing(&conn, "media", "started_at", "INTEGER")?;
+        if add_column_if_missing(&conn, "media", "finished_at", "INTEGER")? {
+            // Best guess for items finished before completion dates were tracked.
+            conn.execute(
+                "UPDATE media SET finished_at = updated_at WHERE status = ?1",
+                [status_to_i(Status::Finished)],
+            )?;
+        }
+        add_column_if_missing(&conn, "media", "progress", "INTEGER")?;
+        add_column_if_missing(&conn, "media", "progress_total", "INTEGER")?;
+        add_column_if_missing(&conn, "media", "file_path", "TEXT")?;
+        add_column_if_missing(&conn, "media", "format", "INTEGER")?;
+        add_column_if_missing(&conn, "media", "priority", "INTEGER")?;
+        add_column_if_missing(&conn, "media", "target_price", "REAL")?;
+        add_column_if_missing(&conn, "media", "store", "TEXT")?;
+        add_column_if_missing(&conn, "media", "title_key", "TEXT")?;
+        add_column_if_missing(&conn, "media", "title_key_no_articles", "TEXT")?;
+        conn.execute_batch(
+            "CREATE INDEX IF NOT EXISTS idx_media_finished_at ON media(finished_at);
+            CREATE INDEX IF NOT EXISTS idx_media_title_key ON media(title_key);
+            CREATE INDEX IF NOT EXISTS idx_media_title_key_no_articles ON media(title_key_no_articles);",
+        )?;
+        migrate_title_keys(&conn)?;
+        if has_column(&conn, "media", "external_id")? {
+            migrate_external_ids(&conn)?;
+        }
+        migrate_saved_search_names(&conn)?;
         Ok(())
     }
 
     fn add(&self, item: &mut MediaItem) -> RepoResult<i64> {
         let conn = self.conn.lock().unwrap();
-        conn.execute(
-            "INSERT INTO media (title, category, status, rating, notes, cover_path, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        let tx = conn.unchecked_transaction()?;
+        tx.execute(
+            "INSERT INTO media (title, category, status, rating, notes, cover_path, created_at, updated_at, sort_title, started_at, finished_at, progress, progress_total, file_path, format, priority, target_price, store) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18)",
             params![
                 item.title,
                 cat_to_i(item.category),
@@ -56,28 +172,31 @@ impl Repository for SqliteRepo {
                 item.cover_path,
                 item.created_at.timestamp(),
                 item.updated_at.timestamp(),
+                item.sort_title,
+                item.started_at.map(|t| t.timestamp()),
+                item.finished_at.map(|t| t.timestamp()),
+                item.progress,
+                item.progress_total,
+                item.file_path,
+                item.format.map(format_to_i),
+                item.priority.map(priority_to_i),
+                item.target_price,
+                item.store,
             ],
         )?;
-        let id = conn.last_insert_rowid();
+        let id = tx.last_insert_rowid();
+        save_title_keys(&tx, id, item)?;
+        save_external_ids(&tx, id, &item.external_ids)?;
+        tx.commit()?;
         item.id = Some(id);
         Ok(id)
     }
 
     fn update(&self, item: &MediaItem) -> RepoResult<()> {
         let conn = self.conn.lock().unwrap();
-        conn.execute(
-            "UPDATE media SET title=?1, category=?2, status=?3, rating=?4, notes=?5, cover_path=?6, updated_at=?7 WHERE id=?8",
-            params![
-                item.title,
-                cat_to_i(item.category),
-                status_to_i(item.status),
-                item.rating.map(|r| r as i64),
-                item.notes,
-                item.cover_path,
-                item.updated_at.timestamp(),
-                item.id,
-            ],
-        )?;
+        let tx = conn.unchecked_transaction()?;
+        update_item(&tx, item)?;
+        tx.commit()?;
         Ok(())
     }
 
@@ -89,63 +208,41 @@ impl Repository for SqliteRepo {
 
     fn get(&self, id: i64) -> RepoResult<Option<MediaItem>> {
         let conn = self.conn.lock().unwrap();
-        let mut stmt = conn.prepare(
-            "SELECT id, title, category, status, rating, notes, cover_path, created_at, updated_at FROM media WHERE id=?1",
-        )?;
+        let mut stmt = conn.prepare(&format!("SELECT {} FROM media WHERE id=?1", ITEM_COLUMNS))?;
         let item = stmt
             .query_row(params![id], |row| Ok(row_to_item(row)))
             .optional()?;
         Ok(item)
     }
 
-    fn list(&self, q: &Query) -> RepoResult<Vec<MediaItem>> {
-        let mut sql = String::from(
-            "SELECT id, title, category, status, rating, notes, cover_path, created_at, updated_at FROM media",
-        );
-        let mut where_clauses: Vec<&str> = vec![];
-        let mut params_dyn: Vec<Box<dyn ToSql>> = vec![];
+    fn find_by_external_id(&self, id: &ExternalId) -> RepoResult<Option<MediaItem>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare_cached(&format!(
+            "SELECT {} FROM media WHERE id = (SELECT item_id FROM external_ids WHERE scheme = ?1 AND value = ?2)",
+            ITEM_COLUMNS
+        ))?;
+        let item = stmt
+            .query_row(params![id.scheme.as_key(), id.value], |row| {
+                Ok(row_to_item(row))
+            })
+            .optional()?;
+        Ok(item)
+    }
 
-        if !q.title_substr.trim().is_empty() {
-            where_clauses.push("title LIKE ?");
-            params_dyn.push(Box::new(format!("%{}%", q.title_substr.trim())));
-        }
-        if let Some(cat) = q.category {
-            where_clauses.push("category = ?");
-            params_dyn.push(Box::new(cat_to_i(cat)));
-        }
-        if let Some(st) = q.status {
-            where_clauses.push("status = ?");
-            params_dyn.push(Box::new(status_to_i(st)));
-        }
-        if let Some(minr) = q.min_rating {
-            where_clauses.push("rating >= ?");
-            params_dyn.push(Box::new(minr as i64));
-        }
-        if !where_clauses.is_empty() {
-            sql.push_str(" WHERE ");
-            sql.push_str(&where_clauses.join(" AND "));
-        }
-        use SortField::*;
-        use SortOrder::*;
-        let order_by = match (q.sort_field, q.sort_order) {
-            (Title, Asc) => "title ASC",
-            (Title, Desc) => "title DESC",
-            (Category, Asc) => "category ASC, title ASC",
-            (Category, Desc) => "category DESC, title ASC",
-            (Status, Asc) => "status ASC, updated_at DESC",
-            (Status, Desc) => "status DESC, updated_at DESC",
-            (Rating, Asc) => "rating ASC NULLS LAST, title ASC",
-            (Rating, Desc) => "rating DESC NULLS LAST, title ASC",
-            (CreatedAt, Asc) => "created_at ASC",
-            (CreatedAt, Desc) => "created_at DESC",
-            (UpdatedAt, Asc) => "updated_at ASC",
-            (UpdatedAt, Desc) => "updated_at DESC",
-        };
+    fn list(&self, q: &Query) -> RepoResult<Vec<MediaItem>> {
+        let mut sql = format!("SELECT {} FROM media", ITEM_COLUMNS);
+        let (where_sql, mut params_dyn) = where_clause(q)?;
+        sql.push_str(&where_sql);
         sql.push_str(" ORDER BY ");
-        sql.push_str(order_by);
+        sql.push_str(&order_by(q));
+        if let Some(limit) = q.limit {
+            sql.push_str(" LIMIT ? OFFSET ?");
+            params_dyn.push(Box::new(limit as i64));
+            params_dyn.push(Box::new(q.offset as i64));
+        }
 
         let conn = self.conn.lock().unwrap();
-        let mut stmt = conn.prepare(&sql)?;
+        let mut stmt = conn.prepare_cached(&sql)?;
         let params_iter = params_from_iter(params_dyn.iter().map(|p| p.as_ref()));
         let rows = stmt.query_map(params_iter, |row| Ok(row_to_item(row)))?;
         let mut out = vec![];
@@ -155,6 +252,52 @@ impl Repository for SqliteRepo {
         Ok(out)
     }
 
+    fn count(&self, q: &Query) -> RepoResult<usize> {
+        let (where_sql, params_dyn) = where_clause(q)?;
+        let sql = format!("SELECT COUNT(*) FROM media{}", where_sql);
+
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare_cached(&sql)?;
+        let params_iter = params_from_iter(params_dyn.iter().map(|p| p.as_ref()));
+        let count: i64 = stmt.query_row(params_iter, |r| r.get(0))?;
+        Ok(count as usize)
+    }
+
+    fn titles(&self, category: Category) -> RepoResult<Vec<String>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare_cached("SELECT title FROM media WHERE category = ?1")?;
+        let rows = stmt.query_map([cat_to_i(category)], |r| r.get(0))?;
+        let mut out = vec![];
+        for r in rows {
+            out.push(r?);
+        }
+        Ok(out)
+    }
+
+    fn merge(&self, merged: &MediaItem, duplicates: &[i64]) -> RepoResult<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        // Delete first: the survivor may take over a duplicate's external id.
+        for id in duplicates {
+            tx.execute(
+                "UPDATE scanned_files SET item_id = ?1 WHERE item_id = ?2",
+                params![merged.id, id],
+            )?;
+            tx.execute(
+                "UPDATE copies SET item_id = ?1 WHERE item_id = ?2",
+                params![merged.id, id],
+            )?;
+            tx.execute(
+                "UPDATE loans SET item_id = ?1 WHERE item_id = ?2",
+                params![merged.id, id],
+            )?;
+            tx.execute("DELETE FROM media WHERE id = ?1", params![id])?;
+        }
+        update_item(&tx, merged)?;
+        tx.commit()?;
+        Ok(())
+    }
+
     fn stats(&self) -> RepoResult<Stats> {
         let conn = self.conn.lock().unwrap();
         let total: i64 = conn.query_row("SELECT COUNT(*) FROM media", [], |r| r.get(0))?;
@@ -171,20 +314,1086 @@ impl Repository for SqliteRepo {
             by_category.push(row?);
         }
 
-        let finished: i64 = conn.query_row(
-            "SELECT COUNT(*) FROM media WHERE status = ?1",
-            [status_to_i(Status::Finished)],
-            |r| r.get(0),
-        )?;
-        let unfinished = total - finished;
+        let count_status = |status: Status| -> rusqlite::Result<i64> {
+            conn.query_row(
+                "SELECT COUNT(*) FROM media WHERE status = ?1",
+                [status_to_i(status)],
+                |r| r.get(0),
+            )
+        };
+        let finished = count_status(Status::Finished)?;
+        // Wishlist items are not owned yet, so they are not unfinished either.
+        let wishlist = count_status(Status::Wishlist)?;
+        let unfinished = total - finished - wishlist;
 
         Ok(Stats {
             total: total as usize,
             by_category,
             finished: finished as usize,
             unfinished: unfinished as usize,
+            wishlist: wishlist as usize,
+        })
+    }
+
+    fn detailed_stats(&self) -> RepoResult<DetailedStats> {
+        let conn = self.conn.lock().unwrap();
+        let finished = status_to_i(Status::Finished);
+        let wishlist = status_to_i(Status::Wishlist);
+
+        let finished_by_month = month_counts(
+            &conn,
+            "SELECT strftime('%Y-%m', finished_at, 'unixepoch', 'localtime') AS m, COUNT(*)
+             FROM media WHERE status = ?1 AND finished_at IS NOT NULL GROUP BY m",
+            &[&finished],
+        )?;
+        // The backlog is what was added and not finished yet, so wishlist
+        // items only join it once bought.
+        let added_by_month = month_counts(
+            &conn,
+            "SELECT strftime('%Y-%m', created_at, 'unixepoch', 'localtime') AS m, COUNT(*)
+             FROM media WHERE status != ?1 GROUP BY m",
+            &[&wishlist],
+        )?;
+
+        let months = month_range(
+            added_by_month.keys().chain(finished_by_month.keys()).min(),
+            added_by_month.keys().chain(finished_by_month.keys()).max(),
+        );
+        let mut finished_per_month = vec![];
+        let mut backlog_per_month = vec![];
+        let mut finished_per_year: BTreeMap<i32, usize> = BTreeMap::new();
+        let mut backlog = 0;
+        for month in months {
+            let done = finished_by_month.get(&month).copied().unwrap_or(0);
+            backlog += added_by_month.get(&month).copied().unwrap_or(0) - done;
+            if let Ok(year) = month[..4].parse() {
+                *finished_per_year.entry(year).or_default() += done as usize;
+            }
+            finished_per_month.push((month.clone(), done as usize));
+            backlog_per_month.push((month, backlog));
+        }
+
+        let mut rating_histogram = [0; 11];
+        let mut stmt = conn.prepare(
+            "SELECT rating, COUNT(*) FROM media WHERE rating BETWEEN 0 AND 10 GROUP BY rating",
+        )?;
+        let rows = stmt.query_map([], |r| Ok((r.get::<_, i64>(0)?, r.get::<_, i64>(1)?)))?;
+        for row in rows {
+            let (rating, n) = row?;
+            rating_histogram[rating as usize] = n as usize;
+        }
+
+        let mut stmt = conn.prepare(
+            "SELECT category, AVG(rating) FROM media WHERE rating IS NOT NULL
+             GROUP BY category ORDER BY category",
+        )?;
+        let rows = stmt.query_map([], |r| Ok((r.get::<_, i64>(0)?, r.get::<_, f64>(1)?)))?;
+        let mut avg_rating_by_category = vec![];
+        for row in rows {
+            let (cat, avg) = row?;
+            avg_rating_by_category.push((i_to_cat(cat).as_str().to_string(), avg));
+        }
+
+        let mut stmt = conn.prepare(
+            "SELECT (finished_at - COALESCE(started_at, created_at)) / 86400 FROM media
+             WHERE status = ?1 AND finished_at IS NOT NULL",
+        )?;
+        let rows = stmt.query_map([finished], |r| r.get::<_, i64>(0))?;
+        let mut days_to_finish = vec![];
+        for row in rows {
+            days_to_finish.push(row?.max(0));
+        }
+
+        Ok(DetailedStats {
+            finished_per_month,
+            finished_per_year: finished_per_year.into_iter().collect(),
+            rating_histogram,
+            avg_rating_by_category,
+            days_to_finish,
+            backlog_per_month,
         })
     }
+
+    fn list_saved_searches(&self) -> RepoResult<Vec<SavedSearch>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt =
+            conn.prepare("SELECT id, name, query_json FROM saved_searches ORDER BY name")?;
+        let rows = stmt.query_map([], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+            ))
+        })?;
+        let mut out = vec![];
+        for row in rows {
+            let (id, name, query_json) = row?;
+            out.push(SavedSearch {
+                id: Some(id),
+                name,
+                query: query_from_json(&query_json)?,
+            });
+        }
+        Ok(out)
+    }
+
+    fn save_search(&self, search: &mut SavedSearch) -> RepoResult<i64> {
+        let query_json = serde_json::to_string(&search.query)?;
+        let conn = self.conn.lock().unwrap();
+        let id = conn.query_row(
+            "INSERT INTO saved_searches (name, query_json, created_at) VALUES (?1, ?2, ?3)
+             ON CONFLICT(name) DO UPDATE SET query_json = excluded.query_json
+             RETURNING id",
+            params![search.name, query_json, Local::now().timestamp()],
+            |r| r.get(0),
+        )?;
+        search.id = Some(id);
+        Ok(id)
+    }
+
+    fn delete_saved_search(&self, id: i64) -> RepoResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("DELETE FROM saved_searches WHERE id = ?1", params![id])?;
+        Ok(())
+    }
+
+    fn list_export_presets(&self) -> RepoResult<Vec<ExportPreset>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt =
+            conn.prepare("SELECT id, name, options_json FROM export_presets ORDER BY name")?;
+        let rows = stmt.query_map([], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+            ))
+        })?;
+        let mut out = vec![];
+        for row in rows {
+            let (id, name, options_json) = row?;
+            out.push(ExportPreset {
+                id: Some(id),
+                name,
+                options: serde_json::from_str(&options_json)?,
+            });
+        }
+        Ok(out)
+    }
+
+    fn save_export_preset(&self, preset: &mut ExportPreset) -> RepoResult<i64> {
+        let options_json = serde_json::to_string(&preset.options)?;
+        let conn = self.conn.lock().unwrap();
+        let id = conn.query_row(
+            "INSERT INTO export_presets (name, options_json) VALUES (?1, ?2)
+             ON CONFLICT(name) DO UPDATE SET options_json = excluded.options_json
+             RETURNING id",
+            params![preset.name, options_json],
+            |r| r.get(0),
+        )?;
+        preset.id = Some(id);
+        Ok(id)
+    }
+
+    fn delete_export_preset(&self, id: i64) -> RepoResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("DELETE FROM export_presets WHERE id = ?1", params![id])?;
+        Ok(())
+    }
+
+    fn list_goals(&self) -> RepoResult<Vec<Goal>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, name, category, target, start_date, end_date, backlog_only
+             FROM goals ORDER BY end_date, name",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok(Goal {
+                id: Some(row.get(0)?),
+                name: row.get(1)?,
+                category: row.get::<_, Option<i64>>(2)?.map(i_to_cat),
+                target: row.get(3)?,
+                start: row.get(4)?,
+                end: row.get(5)?,
+                backlog_only: row.get(6)?,
+            })
+        })?;
+        let mut out = vec![];
+        for row in rows {
+            out.push(row?);
+        }
+        Ok(out)
+    }
+
+    fn save_goal(&self, goal: &mut Goal) -> RepoResult<i64> {
+        let conn = self.conn.lock().unwrap();
+        let id = match goal.id {
+            Some(id) => {
+                conn.execute(
+                    "UPDATE goals SET name=?1, category=?2, target=?3, start_date=?4, end_date=?5, backlog_only=?6 WHERE id=?7",
+                    params![
+                        goal.name,
+                        goal.category.map(cat_to_i),
+                        goal.target,
+                        goal.start,
+                        goal.end,
+                        goal.backlog_only,
+                        id,
+                    ],
+                )?;
+                id
+            }
+            None => {
+                conn.execute(
+                    "INSERT INTO goals (name, category, target, start_date, end_date, backlog_only) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                    params![
+                        goal.name,
+                        goal.category.map(cat_to_i),
+                        goal.target,
+                        goal.start,
+                        goal.end,
+                        goal.backlog_only,
+                    ],
+                )?;
+                conn.last_insert_rowid()
+            }
+        };
+        goal.id = Some(id);
+        Ok(id)
+    }
+
+    fn delete_goal(&self, id: i64) -> RepoResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("DELETE FROM goals WHERE id = ?1", params![id])?;
+        Ok(())
+    }
+
+    fn goal_progress(&self, goal: &Goal) -> RepoResult<usize> {
+        let start = day_start(goal.start);
+        let end = day_start(goal.end.succ_opt().unwrap_or(goal.end));
+        let mut sql = String::from(
+            "SELECT COUNT(*) FROM media WHERE status = ? AND finished_at >= ? AND finished_at < ?",
+        );
+        let mut params_dyn: Vec<Box<dyn ToSql>> = vec![
+            Box::new(status_to_i(Status::Finished)),
+            Box::new(start),
+            Box::new(end),
+        ];
+        if let Some(cat) = goal.category {
+            sql.push_str(" AND category = ?");
+            params_dyn.push(Box::new(cat_to_i(cat)));
+        }
+        if goal.backlog_only {
+            sql.push_str(" AND created_at < ?");
+            params_dyn.push(Box::new(start));
+        }
+
+        let conn = self.conn.lock().unwrap();
+        let params_iter = params_from_iter(params_dyn.iter().map(|p| p.as_ref()));
+        let count: i64 = conn.query_row(&sql, params_iter, |r| r.get(0))?;
+        Ok(count as usize)
+    }
+
+    fn get_setting(&self, key: &str) -> RepoResult<Option<String>> {
+        let conn = self.conn.lock().unwrap();
+        let value = conn
+            .query_row("SELECT value FROM settings WHERE key = ?1", [key], |r| {
+                r.get(0)
+            })
+            .optional()?;
+        Ok(value)
+    }
+
+    fn set_setting(&self, key: &str, value: &str) -> RepoResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO settings (key, value) VALUES (?1, ?2)
+             ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+            params![key, value],
+        )?;
+        Ok(())
+    }
+
+    fn scanned_files(&self, dir: &str) -> RepoResult<Vec<ScannedFile>> {
+        // "/music" must not match "/music2/a.mp3".
+        let sep = std::path::MAIN_SEPARATOR;
+        let folder = format!("{}{}", dir.trim_end_matches(['/', sep]), sep);
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT path, modified, size, hash, grouping, item_id FROM scanned_files
+             WHERE path = ?1 OR substr(path, 1, length(?2)) = ?2",
+        )?;
+        let rows = stmt.query_map([dir, folder.as_str()], |row| {
+            Ok(ScannedFile {
+                path: row.get(0)?,
+                modified: row.get(1)?,
+                size: row.get(2)?,
+                hash: row.get(3)?,
+                group: row.get(4)?,
+                item_id: row.get(5)?,
+            })
+        })?;
+        let mut out = vec![];
+        for row in rows {
+            out.push(row?);
+        }
+        Ok(out)
+    }
+
+    fn save_scanned_file(&self, file: &ScannedFile) -> RepoResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO scanned_files (path, modified, size, hash, grouping, item_id)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+             ON CONFLICT(path) DO UPDATE SET modified = excluded.modified,
+                 size = excluded.size, hash = excluded.hash,
+                 grouping = excluded.grouping, item_id = excluded.item_id",
+            params![
+                file.path,
+                file.modified,
+                file.size,
+                file.hash,
+                file.group,
+                file.item_id
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn delete_scanned_file(&self, path: &str) -> RepoResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("DELETE FROM scanned_files WHERE path = ?1", [path])?;
+        Ok(())
+    }
+
+    fn list_copies(&self, item_id: i64) -> RepoResult<Vec<OwnedCopy>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM copies WHERE item_id = ?1 ORDER BY id",
+            COPY_COLUMNS
+        ))?;
+        let rows = stmt.query_map([item_id], row_to_copy)?;
+        let mut out = vec![];
+        for row in rows {
+            out.push(row?);
+        }
+        Ok(out)
+    }
+
+    fn inventory(&self, query: &Query) -> RepoResult<Vec<(MediaItem, OwnedCopy)>> {
+        let items = self.list(&query.unpaged())?;
+        let (where_sql, params_dyn) = where_clause(query)?;
+        let sql = format!(
+            "SELECT {} FROM copies WHERE item_id IN (SELECT id FROM media{}) ORDER BY id",
+            COPY_COLUMNS, where_sql
+        );
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(&sql)?;
+        let params_iter = params_from_iter(params_dyn.iter().map(|p| p.as_ref()));
+        let mut copies: HashMap<i64, Vec<OwnedCopy>> = HashMap::new();
+        for row in stmt.query_map(params_iter, row_to_copy)? {
+            let copy = row?;
+            copies.entry(copy.item_id).or_default().push(copy);
+        }
+        let mut out = vec![];
+        for item in items {
+            for copy in item
+                .id
+                .and_then(|id| copies.remove(&id))
+                .unwrap_or_default()
+            {
+                out.push((item.clone(), copy));
+            }
+        }
+        Ok(out)
+    }
+
+    fn save_copy(&self, copy: &mut OwnedCopy) -> RepoResult<i64> {
+        let conn = self.conn.lock().unwrap();
+        save_copy(&conn, copy)
+    }
+
+    fn save_purchase(&self, item: &MediaItem, copy: &mut OwnedCopy) -> RepoResult<i64> {
+        let conn = self.conn.lock().unwrap();
+        let tx = conn.unchecked_transaction()?;
+        update_item(&tx, item)?;
+        let id = save_copy(&tx, copy)?;
+        tx.commit()?;
+        Ok(id)
+    }
+
+    fn delete_copy(&self, id: i64) -> RepoResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("DELETE FROM copies WHERE id = ?1", params![id])?;
+        Ok(())
+    }
+
+    fn list_loans(&self, item_id: i64) -> RepoResult<Vec<Loan>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM loans WHERE item_id = ?1 ORDER BY lent_on DESC, id DESC",
+            LOAN_COLUMNS
+        ))?;
+        let rows = stmt.query_map([item_id], row_to_loan)?;
+        let mut out = vec![];
+        for row in rows {
+            out.push(row?);
+        }
+        Ok(out)
+    }
+
+    fn open_loans(&self) -> RepoResult<Vec<Loan>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM loans WHERE returned_on IS NULL ORDER BY lent_on DESC, id DESC",
+            LOAN_COLUMNS
+        ))?;
+        let rows = stmt.query_map([], row_to_loan)?;
+        let mut out = vec![];
+        for row in rows {
+            out.push(row?);
+        }
+        Ok(out)
+    }
+
+    fn loans(&self, query: &Query) -> RepoResult<Vec<(MediaItem, Loan)>> {
+        let items: HashMap<i64, MediaItem> = self
+            .list(&query.unpaged())?
+            .into_iter()
+            .filter_map(|item| Some((item.id?, item)))
+            .collect();
+        let (where_sql, params_dyn) = where_clause(query)?;
+        let sql = format!(
+            "SELECT {} FROM loans WHERE item_id IN (SELECT id FROM media{}) \
+             ORDER BY lent_on DESC, id DESC",
+            LOAN_COLUMNS, where_sql
+        );
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(&sql)?;
+        let params_iter = params_from_iter(params_dyn.iter().map(|p| p.as_ref()));
+        let mut out = vec![];
+        for row in stmt.query_map(params_iter, row_to_loan)? {
+            let loan = row?;
+            if let Some(item) = items.get(&loan.item_id) {
+                out.push((item.clone(), loan));
+            }
+        }
+        Ok(out)
+    }
+
+    fn save_loan(&self, loan: &mut Loan) -> RepoResult<i64> {
+        let conn = self.conn.lock().unwrap();
+        let id = match loan.id {
+            Some(id) => {
+                conn.execute(
+                    "UPDATE loans SET item_id=?1, copy_id=?2, borrower=?3, lent_on=?4, due_on=?5, returned_on=?6 WHERE id=?7",
+                    params![
+                        loan.item_id,
+                        loan.copy_id,
+                        loan.borrower,
+                        loan.lent_on,
+                        loan.due_on,
+                        loan.returned_on,
+                        id,
+                    ],
+                )?;
+                id
+            }
+            None => {
+                conn.execute(
+                    "INSERT INTO loans (item_id, copy_id, borrower, lent_on, due_on, returned_on) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                    params![
+                        loan.item_id,
+                        loan.copy_id,
+                        loan.borrower,
+                        loan.lent_on,
+                        loan.due_on,
+                        loan.returned_on,
+                    ],
+                )?;
+                conn.last_insert_rowid()
+            }
+        };
+        loan.id = Some(id);
+        Ok(id)
+    }
+
+    fn delete_loan(&self, id: i64) -> RepoResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("DELETE FROM loans WHERE id = ?1", params![id])?;
+        Ok(())
+    }
+
+    fn list_watch_folders(&self) -> RepoResult<Vec<WatchFolder>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, path, category, status, enabled FROM watch_folders ORDER BY path",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok(WatchFolder {
+                id: Some(row.get(0)?),
+                path: row.get(1)?,
+                category: i_to_cat(row.get(2)?),
+                status: i_to_status(row.get(3)?),
+                enabled: row.get(4)?,
+            })
+        })?;
+        let mut out = vec![];
+        for row in rows {
+            out.push(row?);
+        }
+        Ok(out)
+    }
+
+    fn save_watch_folder(&self, folder: &mut WatchFolder) -> RepoResult<i64> {
+        let conn = self.conn.lock().unwrap();
+        let id = match folder.id {
+            Some(id) => {
+                conn.execute(
+                    "UPDATE watch_folders SET path=?1, category=?2, status=?3, enabled=?4 WHERE id=?5",
+                    params![
+                        folder.path,
+                        cat_to_i(folder.category),
+                        status_to_i(folder.status),
+                        folder.enabled,
+                        id,
+                    ],
+                )?;
+                id
+            }
+            None => {
+                conn.execute(
+                    "INSERT INTO watch_folders (path, category, status, enabled) VALUES (?1, ?2, ?3, ?4)",
+                    params![
+                        folder.path,
+                        cat_to_i(folder.category),
+                        status_to_i(folder.status),
+                        folder.enabled,
+                    ],
+                )?;
+                conn.last_insert_rowid()
+            }
+        };
+        folder.id = Some(id);
+        Ok(id)
+    }
+
+    fn delete_watch_folder(&self, id: i64) -> RepoResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("DELETE FROM watch_folders WHERE id = ?1", params![id])?;
+        Ok(())
+    }
+}
+
+fn save_copy(conn: &Connection, copy: &mut OwnedCopy) -> RepoResult<i64> {
+    let id = match copy.id {
+        Some(id) => {
+            conn.execute(
+                "UPDATE copies SET item_id=?1, format=?2, location=?3, condition=?4, purchased_on=?5, price=?6 WHERE id=?7",
+                params![
+                    copy.item_id,
+                    copy.format,
+                    copy.location,
+                    copy.condition.map(condition_to_i),
+                    copy.purchased_on,
+                    copy.price,
+                    id,
+                ],
+            )?;
+            id
+        }
+        None => {
+            conn.execute(
+                "INSERT INTO copies (item_id, format, location, condition, purchased_on, price) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    copy.item_id,
+                    copy.format,
+                    copy.location,
+                    copy.condition.map(condition_to_i),
+                    copy.purchased_on,
+                    copy.price,
+                ],
+            )?;
+            conn.last_insert_rowid()
+        }
+    };
+    copy.id = Some(id);
+    Ok(id)
+}
+
+fn update_item(conn: &Connection, item: &MediaItem) -> RepoResult<()> {
+    conn.execute(
+            "UPDATE media SET title=?1, category=?2, status=?3, rating=?4, notes=?5, cover_path=?6, updated_at=?7, sort_title=?9, started_at=?10, finished_at=?11, progress=?12, progress_total=?13, file_path=?14, format=?15, priority=?16, target_price=?17, store=?18 WHERE id=?8",
+            params![
+                item.title,
+                cat_to_i(item.category),
+                status_to_i(item.status),
+                item.rating.map(|r| r as i64),
+                item.notes,
+                item.cover_path,
+                item.updated_at.timestamp(),
+                item.id,
+                item.sort_title,
+                item.started_at.map(|t| t.timestamp()),
+                item.finished_at.map(|t| t.timestamp()),
+                item.progress,
+                item.progress_total,
+                item.file_path,
+                item.format.map(format_to_i),
+                item.priority.map(priority_to_i),
+                item.target_price,
+                item.store,
+            ],
+        )?;
+    if let Some(id) = item.id {
+        save_title_keys(conn, id, item)?;
+        save_external_ids(conn, id, &item.external_ids)?;
+    }
+    Ok(())
+}
+
+// Title sort keys are stored rather than computed by a collation, so sorting
+// a large catalog page by page can use an index.
+fn save_title_keys(conn: &Connection, id: i64, item: &MediaItem) -> RepoResult<()> {
+    let title = item.sort_title.as_deref().unwrap_or(&item.title);
+    conn.execute(
+        "UPDATE media SET title_key = ?1, title_key_no_articles = ?2 WHERE id = ?3",
+        params![
+            collation::sort_key(title, false),
+            collation::sort_key(title, true),
+            id
+        ],
+    )?;
+    Ok(())
+}
+
+// Replaces the external ids of item `id`. Fails if one belongs to another
+// item, so the same work is not imported twice.
+fn save_external_ids(conn: &Connection, id: i64, ids: &[ExternalId]) -> RepoResult<()> {
+    conn.execute("DELETE FROM external_ids WHERE item_id = ?1", [id])?;
+    let mut owner =
+        conn.prepare_cached("SELECT item_id FROM external_ids WHERE scheme = ?1 AND value = ?2")?;
+    let mut insert = conn
+        .prepare_cached("INSERT INTO external_ids (item_id, scheme, value) VALUES (?1, ?2, ?3)")?;
+    for ext in ids {
+        let key = params![ext.scheme.as_key(), ext.value];
+        match owner.query_row(key, |r| r.get::<_, i64>(0)).optional()? {
+            Some(owner) if owner == id => {}
+            Some(_) => return Err(RepoError::DuplicateId(ext.clone())),
+            None => {
+                insert.execute(params![id, ext.scheme.as_key(), ext.value])?;
+            }
+        }
+    }
+    Ok(())
+}
+
+// Fills in sort keys for rows written before they were stored.
+fn migrate_title_keys(conn: &Connection) -> RepoResult<()> {
+    let rows: Vec<(i64, String)> = {
+        let mut stmt = conn.prepare(
+            "SELECT id, COALESCE(sort_title, title) FROM media WHERE title_key IS NULL OR title_key_no_articles IS NULL",
+        )?;
+        let rows = stmt.query_map([], |r| Ok((r.get(0)?, r.get(1)?)))?;
+        rows.collect::<rusqlite::Result<_>>()?
+    };
+    if rows.is_empty() {
+        return Ok(());
+    }
+    let tx = conn.unchecked_transaction()?;
+    {
+        let mut update = tx
+            .prepare("UPDATE media SET title_key = ?1, title_key_no_articles = ?2 WHERE id = ?3")?;
+        for (id, title) in rows {
+            update.execute(params![
+                collation::sort_key(&title, false),
+                collation::sort_key(&title, true),
+                id
+            ])?;
+        }
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+// Makes saved search names unique regardless of case, as the CLI looks them
+// up. Names that clash only in case get their id appended.
+fn migrate_saved_search_names(conn: &Connection) -> RepoResult<()> {
+    let sql: String = conn.query_row(
+        "SELECT sql FROM sqlite_master WHERE type = 'table' AND name = 'saved_searches'",
+        [],
+        |r| r.get(0),
+    )?;
+    if sql.contains("COLLATE NOCASE") {
+        return Ok(());
+    }
+    let tx = conn.unchecked_transaction()?;
+    tx.execute_batch(
+        "UPDATE saved_searches SET name = name || ' (' || id || ')'
+         WHERE EXISTS (SELECT 1 FROM saved_searches o
+                       WHERE o.name = saved_searches.name COLLATE NOCASE AND o.id < saved_searches.id);
+         CREATE TABLE saved_searches_new (
+             id INTEGER PRIMARY KEY AUTOINCREMENT,
+             name TEXT NOT NULL UNIQUE COLLATE NOCASE,
+             query_json TEXT NOT NULL,
+             created_at INTEGER NOT NULL
+         );
+         INSERT INTO saved_searches_new (id, name, query_json, created_at)
+             SELECT id, name, query_json, created_at FROM saved_searches;
+         DROP TABLE saved_searches;
+         ALTER TABLE saved_searches_new RENAME TO saved_searches;",
+    )?;
+    tx.commit()?;
+    Ok(())
+}
+
+// Earlier versions kept one id per item in `media.external_id`; they move to
+// the external_ids table and the column is dropped.
+fn migrate_external_ids(conn: &Connection) -> RepoResult<()> {
+    let tx = conn.unchecked_transaction()?;
+    let rows: Vec<(i64, String)> = tx
+        .prepare("SELECT id, external_id FROM media WHERE external_id IS NOT NULL")?
+        .query_map([], |r| Ok((r.get(0)?, r.get(1)?)))?
+        .collect::<rusqlite::Result<_>>()?;
+    for (id, raw) in rows {
+        match ExternalId::parse(&raw) {
+            Ok(ext) => {
+                tx.execute(
+                    "INSERT OR IGNORE INTO external_ids (item_id, scheme, value) VALUES (?1, ?2, ?3)",
+                    params![id, ext.scheme.as_key(), ext.value],
+                )?;
+            }
+            Err(e) => log::warn!("Dropping external id of item {}: {}", id, e),
+        }
+    }
+    tx.execute_batch(
+        "DROP INDEX IF EXISTS idx_media_external_id;
+         ALTER TABLE media DROP COLUMN external_id;",
+    )?;
+    tx.commit()?;
+    Ok(())
+}
+
+fn where_clause(q: &Query) -> RepoResult<(String, Vec<Box<dyn ToSql>>)> {
+    let mut where_clauses: Vec<String> = vec![];
+    let mut params_dyn: Vec<Box<dyn ToSql>> = vec![];
+
+    if !q.search.trim().is_empty() {
+        let expr = search::parse(&q.search)?;
+        where_clauses.push(expr_to_sql(
+            &expr,
+            Local::now().timestamp(),
+            &mut params_dyn,
+        ));
+    }
+    if let Some(cat) = q.category {
+        where_clauses.push("category = ?".into());
+        params_dyn.push(Box::new(cat_to_i(cat)));
+    }
+    if let Some(st) = q.status {
+        where_clauses.push("status = ?".into());
+        params_dyn.push(Box::new(status_to_i(st)));
+    }
+    if let Some(minr) = q.min_rating {
+        where_clauses.push("rating >= ?".into());
+        params_dyn.push(Box::new(minr as i64));
+    }
+    if let Some(owned) = q.owned {
+        where_clauses.push(owned_sql(owned));
+    }
+    if let Some(on_loan) = q.on_loan {
+        where_clauses.push(on_loan_sql(on_loan));
+    }
+    if where_clauses.is_empty() {
+        Ok((String::new(), params_dyn))
+    } else {
+        Ok((
+            format!(" WHERE {}", where_clauses.join(" AND ")),
+            params_dyn,
+        ))
+    }
+}
+
+// Compiles a parsed search expression into a parameterized SQL condition.
+fn expr_to_sql(expr: &Expr, now: i64, params_dyn: &mut Vec<Box<dyn ToSql>>) -> String {
+    let like = |params_dyn: &mut Vec<Box<dyn ToSql>>, column: &str, op: CmpOp, pattern: String| {
+        params_dyn.push(Box::new(pattern));
+        match op {
+            CmpOp::Ne => format!("COALESCE({} NOT LIKE ? ESCAPE '\\', 1)", column),
+            _ => format!("{} LIKE ? ESCAPE '\\'", column),
+        }
+    };
+    match expr {
+        Expr::And(terms) | Expr::Or(terms) if terms.is_empty() => "1".into(),
+        Expr::And(terms) | Expr::Or(terms) => {
+            let joiner = if matches!(expr, Expr::And(_)) {
+                " AND "
+            } else {
+                " OR "
+            };
+            let parts: Vec<String> = terms
+                .iter()
+                .map(|t| expr_to_sql(t, now, params_dyn))
+                .collect();
+            format!("({})", parts.join(joiner))
+        }
+        // `IS NOT 1` so rows where the condition is NULL (e.g. unrated) match.
+        Expr::Not(inner) => format!("({}) IS NOT 1", expr_to_sql(inner, now, params_dyn)),
+        Expr::Text(text) => like(
+            params_dyn,
+            "title",
+            CmpOp::Eq,
+            format!("%{}%", search::like_escape(text)),
+        ),
+        Expr::Field(field, op, value) => match (field, value) {
+            (Field::Title, Value::Text(t)) => like(
+                params_dyn,
+                "title",
+                *op,
+                format!("%{}%", search::like_escape(t)),
+            ),
+            (Field::Notes, Value::Text(t)) => like(
+                params_dyn,
+                "notes",
+                *op,
+                format!("%{}%", search::like_escape(t)),
+            ),
+            // A whole `#tag`: it starts a word and no tag character follows
+            // it (see `MediaItem::tags`). The padding lets both ends match.
+            (Field::Tag, Value::Text(t)) => {
+                params_dyn.push(Box::new(format!(
+                    "*[ \t\r\n]#{}[^-_0-9a-z\u{80}-\u{10FFFF}]*",
+                    search::glob_escape(&t.to_lowercase())
+                )));
+                let matches = "(' ' || lower(notes) || ' ') GLOB ?";
+                if *op == CmpOp::Ne {
+                    format!("COALESCE(NOT {}, 1)", matches)
+                } else {
+                    matches.to_string()
+                }
+            }
+            (Field::Category, Value::Category(c)) => {
+                params_dyn.push(Box::new(cat_to_i(*c)));
+                format!("category {} ?", op.as_sql())
+            }
+            (Field::Status, Value::Status(s)) => {
+                params_dyn.push(Box::new(status_to_i(*s)));
+                format!("status {} ?", op.as_sql())
+            }
+            (Field::Owned, Value::Flag(owned)) => owned_sql(*owned != (*op == CmpOp::Ne)),
+            (Field::OnLoan, Value::Flag(on_loan)) => on_loan_sql(*on_loan != (*op == CmpOp::Ne)),
+            (Field::Overdue, Value::Flag(overdue)) => {
+                params_dyn.push(Box::new(Local::now().date_naive()));
+                let exists = "EXISTS (SELECT 1 FROM loans WHERE loans.item_id = media.id \
+                              AND loans.returned_on IS NULL AND loans.due_on < ?)";
+                if *overdue != (*op == CmpOp::Ne) {
+                    exists.into()
+                } else {
+                    format!("NOT {}", exists)
+                }
+            }
+            (Field::Borrower, Value::Text(t)) => {
+                params_dyn.push(Box::new(format!("%{}%", search::like_escape(t))));
+                let exists = "EXISTS (SELECT 1 FROM loans WHERE loans.item_id = media.id \
+                              AND loans.returned_on IS NULL AND loans.borrower LIKE ? ESCAPE '\\')";
+                if *op == CmpOp::Ne {
+                    format!("NOT {}", exists)
+                } else {
+                    exists.into()
+                }
+            }
+            (Field::Location, Value::Text(t)) => {
+                params_dyn.push(Box::new(format!("%{}%", search::like_escape(t))));
+                let exists = "EXISTS (SELECT 1 FROM copies WHERE copies.item_id = media.id \
+                              AND copies.location LIKE ? ESCAPE '\\')";
+                if *op == CmpOp::Ne {
+                    format!("NOT {}", exists)
+                } else {
+                    exists.into()
+                }
+            }
+            // The item's format, or a copy with that format ("Digital").
+            (Field::Format, Value::Format(f)) => {
+                params_dyn.push(Box::new(format_to_i(*f)));
+                params_dyn.push(Box::new(search::like_escape(f.as_str())));
+                let matches = "(format IS ? OR EXISTS (SELECT 1 FROM copies \
+                               WHERE copies.item_id = media.id \
+                               AND copies.format LIKE ? ESCAPE '\\'))";
+                if *op == CmpOp::Ne {
+                    format!("NOT {}", matches)
+                } else {
+                    matches.into()
+                }
+            }
+            (Field::Format, Value::Text(t)) => {
+                params_dyn.push(Box::new(format!("%{}%", search::like_escape(t))));
+                let exists = "EXISTS (SELECT 1 FROM copies WHERE copies.item_id = media.id \
+                              AND copies.format LIKE ? ESCAPE '\\')";
+                if *op == CmpOp::Ne {
+                    format!("NOT {}", exists)
+                } else {
+                    exists.into()
+                }
+            }
+            (Field::Rating, Value::Number(n)) => {
+                params_dyn.push(Box::new(*n));
+                if *op == CmpOp::Ne {
+                    "rating IS NOT ?".into()
+                } else {
+                    format!("rating {} ?", op.as_sql())
+                }
+            }
+            (Field::Created | Field::Updated, when) => {
+                let column = if *field == Field::Created {
+                    "created_at"
+                } else {
+                    "updated_at"
+                };
+                let (op, ts) = match (op, when) {
+                    // A bare date matches that whole day, which is not always
+                    // 24 hours long.
+                    (CmpOp::Eq | CmpOp::Ne, Value::Date(date)) => {
+                        params_dyn.push(Box::new(day_start(*date)));
+                        params_dyn.push(Box::new(day_start(date.succ_opt().unwrap_or(*date))));
+                        let day = format!("({0} >= ? AND {0} < ?)", column);
+                        return if *op == CmpOp::Ne {
+                            format!("NOT {}", day)
+                        } else {
+                            day
+                        };
+                    }
+                    // A bare age means "within": `updated:30d` == `updated:<30d`.
+                    (CmpOp::Eq, Value::Age(age)) => (CmpOp::Gt, now - age.num_seconds()),
+                    (CmpOp::Ne, Value::Age(age)) => (CmpOp::Le, now - age.num_seconds()),
+                    // "Less than 30 days ago" is "after now - 30 days".
+                    (op, Value::Age(age)) => (op.flipped(), now - age.num_seconds()),
+                    (op, Value::Date(date)) => (*op, day_start(*date)),
+                    _ => unreachable!("date fields always parse to a date value"),
+                };
+                params_dyn.push(Box::new(ts));
+                format!("{} {} ?", column, op.as_sql())
+            }
+            _ => unreachable!("the parser pairs each field with its value type"),
+        },
+    }
+}
+
+// Every ordering ends with `id` so pages stay stable when sort keys tie.
+fn order_by(q: &Query) -> String {
+    let mut keys: Vec<String> = q
+        .sort
+        .iter()
+        .map(|key| {
+            let column = match key.field {
+                SortField::Title => {
+                    let column = if q.ignore_articles {
+                        "title_key_no_articles"
+                    } else {
+                        "title_key"
+                    };
+                    // Titles that fold to the same key fall back to their spelling.
+                    return format!(
+                        "{0} {1}, COALESCE(sort_title, title) {1}",
+                        column,
+                        sort_dir(key.order)
+                    );
+                }
+                SortField::Category => "category",
+                SortField::Status => {
+                    return format!("{} {}", status_rank_sql(), sort_dir(key.order));
+                }
+                SortField::Rating => "rating",
+                SortField::CreatedAt => "created_at",
+                SortField::UpdatedAt => "updated_at",
+                SortField::Priority => "priority",
+            };
+            if matches!(key.field, SortField::Rating | SortField::Priority) {
+                format!("{} {} NULLS LAST", column, sort_dir(key.order))
+            } else {
+                format!("{} {}", column, sort_dir(key.order))
+            }
+        })
+        .collect();
+    keys.push("id ASC".into());
+    keys.join(", ")
+}
+
+// Wishlist is stored after Finished but ranks before Planned, as in
+// `dedup::status_rank`.
+fn status_rank_sql() -> String {
+    let arms: String = Status::ALL
+        .iter()
+        .map(|&s| format!(" WHEN {} THEN {}", status_to_i(s), dedup::status_rank(s)))
+        .collect();
+    format!("CASE status{} END", arms)
+}
+
+fn sort_dir(order: SortOrder) -> &'static str {
+    match order {
+        SortOrder::Asc => "ASC",
+        SortOrder::Desc => "DESC",
+    }
+}
+
+fn query_from_json(json: &str) -> RepoResult<Query> {
+    Ok(Query::from_json_value(serde_json::from_str(json)?)?)
+}
+
+fn month_counts(
+    conn: &Connection,
+    sql: &str,
+    params: &[&dyn ToSql],
+) -> RepoResult<BTreeMap<String, i64>> {
+    let mut stmt = conn.prepare(sql)?;
+    let rows = stmt.query_map(params, |r| Ok((r.get(0)?, r.get(1)?)))?;
+    let mut out = BTreeMap::new();
+    for row in rows {
+        let (month, n): (String, i64) = row?;
+        out.insert(month, n);
+    }
+    Ok(out)
+}
+
+// Every `YYYY-MM` from `first` to `last` inclusive.
+fn month_range(first: Option<&String>, last: Option<&String>) -> Vec<String> {
+    let parse = |m: &String| NaiveDate::parse_from_str(&format!("{}-01", m), "%Y-%m-%d").ok();
+    let (Some(mut month), Some(last)) = (first.and_then(parse), last.and_then(parse)) else {
+        return vec![];
+    };
+    let mut out = vec![];
+    while month <= last {
+        out.push(month.format("%Y-%m").to_string());
+        month = if month.month() == 12 {
+            NaiveDate::from_ymd_opt(month.year() + 1, 1, 1).unwrap()
+        } else {
+            NaiveDate::from_ymd_opt(month.year(), month.month() + 1, 1).unwrap()
+        };
+    }
+    out
+}
+
+// Schema upgrades for databases created before a column existed.
+fn has_column(conn: &Connection, table: &str, column: &str) -> rusqlite::Result<bool> {
+    conn.prepare(&format!(
+        "SELECT 1 FROM pragma_table_info('{}') WHERE name = ?1",
+        table
+    ))?
+    .exists([column])
+}
+
+fn add_column_if_missing(
+    conn: &Connection,
+    table: &str,
+    column: &str,
+    decl: &str,
+) -> rusqlite::Result<bool> {
+    let exists = has_column(conn, table, column)?;
+    if !exists {
+        conn.execute_batch(&format!(
+            "ALTER TABLE {} ADD COLUMN {} {}",
+            table, column, decl
+        ))?;
+    }
+    Ok(!exists)
 }
 
 fn row_to_item(row: &Row<'_>) -> MediaItem {
@@ -197,10 +1406,22 @@ fn row_to_item(row: &Row<'_>) -> MediaItem {
     let cover_path: Option<String> = row.get(6).unwrap();
     let created_at: i64 = row.get(7).unwrap();
     let updated_at: i64 = row.get(8).unwrap();
+    let sort_title: Option<String> = row.get(9).unwrap();
+    let started_at: Option<i64> = row.get(10).unwrap();
+    let finished_at: Option<i64> = row.get(11).unwrap();
+    let external_ids: Option<String> = row.get(12).unwrap();
+    let progress: Option<u32> = row.get(13).unwrap();
+    let progress_total: Option<u32> = row.get(14).unwrap();
+    let file_path: Option<String> = row.get(15).unwrap();
+    let format: Option<i64> = row.get(16).unwrap();
+    let priority: Option<i64> = row.get(17).unwrap();
+    let target_price: Option<f64> = row.get(18).unwrap();
+    let store: Option<String> = row.get(19).unwrap();
 
     MediaItem {
         id: Some(id),
         title,
+        sort_title,
         category: i_to_cat(category),
         status: i_to_status(status),
         rating: rating.map(|r| r as u8),
@@ -208,6 +1429,22 @@ fn row_to_item(row: &Row<'_>) -> MediaItem {
         cover_path,
         created_at: Local.timestamp_opt(created_at, 0).unwrap(),
         updated_at: Local.timestamp_opt(updated_at, 0).unwrap(),
+        started_at: started_at.and_then(|t| Local.timestamp_opt(t, 0).single()),
+        finished_at: finished_at.and_then(|t| Local.timestamp_opt(t, 0).single()),
+        external_ids: external_ids
+            .map(|ids| {
+                ids.lines()
+                    .filter_map(|id| ExternalId::parse(id).ok())
+                    .collect()
+            })
+            .unwrap_or_default(),
+        progress,
+        progress_total,
+        file_path,
+        format: format.map(i_to_format),
+        priority: priority.map(i_to_priority),
+        target_price,
+        store,
     }
 }
 
@@ -231,11 +1468,105 @@ fn i_to_cat(i: i64) -> Category {
     }
 }
 
+fn row_to_copy(row: &Row<'_>) -> rusqlite::Result<OwnedCopy> {
+    Ok(OwnedCopy {
+        id: Some(row.get(0)?),
+        item_id: row.get(1)?,
+        format: row.get(2)?,
+        location: row.get(3)?,
+        condition: row.get::<_, Option<i64>>(4)?.map(i_to_condition),
+        purchased_on: row.get(5)?,
+        price: row.get(6)?,
+    })
+}
+
+fn row_to_loan(row: &Row<'_>) -> rusqlite::Result<Loan> {
+    Ok(Loan {
+        id: Some(row.get(0)?),
+        item_id: row.get(1)?,
+        copy_id: row.get(2)?,
+        borrower: row.get(3)?,
+        lent_on: row.get(4)?,
+        due_on: row.get(5)?,
+        returned_on: row.get(6)?,
+    })
+}
+
+fn on_loan_sql(on_loan: bool) -> String {
+    let exists =
+        "EXISTS (SELECT 1 FROM loans WHERE loans.item_id = media.id AND loans.returned_on IS NULL)";
+    if on_loan {
+        exists.into()
+    } else {
+        format!("NOT {}", exists)
+    }
+}
+
+fn owned_sql(owned: bool) -> String {
+    let exists = "EXISTS (SELECT 1 FROM copies WHERE copies.item_id = media.id)";
+    if owned {
+        exists.into()
+    } else {
+        format!("NOT {}", exists)
+    }
+}
+
+fn condition_to_i(c: Condition) -> i64 {
+    match c {
+        Condition::New => 0,
+        Condition::LikeNew => 1,
+        Condition::Good => 2,
+        Condition::Fair => 3,
+        Condition::Poor => 4,
+    }
+}
+
+fn i_to_condition(i: i64) -> Condition {
+    match i {
+        0 => Condition::New,
+        1 => Condition::LikeNew,
+        2 => Condition::Good,
+        3 => Condition::Fair,
+        _ => Condition::Poor,
+    }
+}
+
+fn format_to_i(f: Format) -> i64 {
+    match f {
+        Format::Physical => 0,
+        Format::Digital => 1,
+    }
+}
+
+fn i_to_format(i: i64) -> Format {
+    match i {
+        0 => Format::Physical,
+        _ => Format::Digital,
+    }
+}
+
+fn priority_to_i(p: Priority) -> i64 {
+    match p {
+        Priority::High => 0,
+        Priority::Normal => 1,
+        Priority::Low => 2,
+    }
+}
+
+fn i_to_priority(i: i64) -> Priority {
+    match i {
+        0 => Priority::High,
+        2 => Priority::Low,
+        _ => Priority::Normal,
+    }
+}
+
 fn status_to_i(s: Status) -> i64 {
     match s {
         Status::Planned => 0,
         Status::InProgress => 1,
         Status::Finished => 2,
+        Status::Wishlist => 3,
     }
 }
 
@@ -243,6 +1574,608 @@ fn i_to_status(i: i64) -> Status {
     match i {
         1 => Status::InProgress,
         2 => Status::Finished,
+        3 => Status::Wishlist,
         _ => Status::Planned,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::SortKey;
+    use crate::util::parse_local_date;
+    use rusqlite::types::{ToSqlOutput, Value as SqlValue};
+
+    const NOW: i64 = 1_700_000_000;
+
+    // SQL and parameter values for a search string.
+    fn compile(search: &str) -> (String, Vec<SqlValue>) {
+        let mut params_dyn = vec![];
+        let sql = expr_to_sql(&search::parse(search).unwrap(), NOW, &mut params_dyn);
+        let values = params_dyn
+            .iter()
+            .map(|p| match p.to_sql().unwrap() {
+                ToSqlOutput::Borrowed(v) => v.into(),
+                ToSqlOutput::Owned(v) => v,
+                _ => unreachable!(),
+            })
+            .collect();
+        (sql, values)
+    }
+
+    fn memory_repo() -> SqliteRepo {
+        let repo = SqliteRepo::new(Path::new(":memory:"));
+        repo.init().unwrap();
+        repo
+    }
+
+    #[test]
+    fn compiles_text_and_fields() {
+        assert_eq!(
+            compile("dune"),
+            (
+                "title LIKE ? ESCAPE '\\'".into(),
+                vec![SqlValue::Text("%dune%".into())]
+            )
+        );
+        assert_eq!(
+            compile("50%"),
+            (
+                "title LIKE ? ESCAPE '\\'".into(),
+                vec![SqlValue::Text("%50\\%%".into())]
+            )
+        );
+        assert_eq!(
+            compile("cat:book rating>=7"),
+            (
+                "(category = ? AND rating >= ?)".into(),
+                vec![SqlValue::Integer(0), SqlValue::Integer(7)]
+            )
+        );
+        assert_eq!(
+            compile("notes:!draft"),
+            (
+                "COALESCE(notes NOT LIKE ? ESCAPE '\\', 1)".into(),
+                vec![SqlValue::Text("%draft%".into())]
+            )
+        );
+    }
+
+    #[test]
+    fn compiles_not_and_or() {
+        let (sql, values) = compile("-dune OR tag:scifi");
+        assert_eq!(
+            sql,
+            "((title LIKE ? ESCAPE '\\') IS NOT 1 OR (' ' || lower(notes) || ' ') GLOB ?)"
+        );
+        assert_eq!(
+            values,
+            vec![
+                SqlValue::Text("%dune%".into()),
+                SqlValue::Text("*[ \t\r\n]#scifi[^-_0-9a-z\u{80}-\u{10FFFF}]*".into())
+            ]
+        );
+    }
+
+    #[test]
+    fn compiles_dates_and_ages() {
+        let day = NaiveDate::from_ymd_opt(2024, 3, 31).unwrap();
+        assert_eq!(
+            compile("created:2024-03-31"),
+            (
+                "(created_at >= ? AND created_at < ?)".into(),
+                vec![
+                    SqlValue::Integer(day_start(day)),
+                    SqlValue::Integer(day_start(day.succ_opt().unwrap()))
+                ]
+            )
+        );
+        assert_eq!(
+            compile("updated:<30d"),
+            (
+                "updated_at > ?".into(),
+                vec![SqlValue::Integer(NOW - 30 * 24 * 60 * 60)]
+            )
+        );
+        assert_eq!(
+            compile("created:>2024-03-31"),
+            (
+                "created_at > ?".into(),
+                vec![SqlValue::Integer(day_start(day))]
+            )
+        );
+    }
+
+    #[test]
+    fn saved_search_names_ignore_case() {
+        let repo = memory_repo();
+        for name in ["Backlog", "backlog"] {
+            repo.save_search(&mut SavedSearch {
+                id: None,
+                name: name.into(),
+                query: Query::default(),
+            })
+            .unwrap();
+        }
+        let names: Vec<String> = repo
+            .list_saved_searches()
+            .unwrap()
+            .into_iter()
+            .map(|s| s.name)
+            .collect();
+        assert_eq!(names, vec!["Backlog"]);
+    }
+
+    #[test]
+    fn migrates_saved_searches_that_clash_in_case() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE saved_searches (id INTEGER PRIMARY KEY AUTOINCREMENT,
+                 name TEXT NOT NULL UNIQUE, query_json TEXT NOT NULL, created_at INTEGER NOT NULL);
+             INSERT INTO saved_searches (name, query_json, created_at)
+                 VALUES ('Backlog', '{}', 0), ('backlog', '{}', 0), ('Games', '{}', 0);",
+        )
+        .unwrap();
+        migrate_saved_search_names(&conn).unwrap();
+        let mut stmt = conn
+            .prepare("SELECT name FROM saved_searches ORDER BY id")
+            .unwrap();
+        let names: Vec<String> = stmt
+            .query_map([], |r| r.get(0))
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(names, vec!["Backlog", "backlog (2)", "Games"]);
+        assert!(conn
+            .execute(
+                "INSERT INTO saved_searches (name, query_json, created_at) VALUES ('GAMES', '{}', 0)",
+                [],
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn reads_saved_searches_with_a_single_sort_key() {
+        let json = r#"[{"name": "Old", "query": {"search": "cat:book",
+            "sort_field": "Rating", "sort_order": "Desc"}}]"#;
+        let searches: Vec<SavedSearch> = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            searches[0].query.sort,
+            vec![SortKey::new(SortField::Rating, SortOrder::Desc)]
+        );
+        assert_eq!(
+            query_from_json(r#"{"sort_field": "Title", "sort_order": "Asc"}"#)
+                .unwrap()
+                .sort,
+            vec![SortKey::new(SortField::Title, SortOrder::Asc)]
+        );
+    }
+
+    #[test]
+    fn sorts_titles_by_stored_keys() {
+        let repo = memory_repo();
+        for title in ["the Matrix", "Zelda", "Book 10", "Book 2", "Émile", "Nemo"] {
+            repo.add(&mut MediaItem::new(title, Category::Book))
+                .unwrap();
+        }
+        let mut item = MediaItem::new("Alien", Category::Movie);
+        repo.add(&mut item).unwrap();
+        item.sort_title = Some("Zz".into());
+        repo.update(&item).unwrap();
+        let titles = |ignore_articles: bool| -> Vec<String> {
+            repo.list(&Query {
+                sort: vec![SortKey::new(SortField::Title, SortOrder::Asc)],
+                ignore_articles,
+                ..Default::default()
+            })
+            .unwrap()
+            .into_iter()
+            .map(|i| i.title)
+            .collect()
+        };
+        assert_eq!(
+            titles(false),
+            vec![
+                "Book 2",
+                "Book 10",
+                "Émile",
+                "Nemo",
+                "the Matrix",
+                "Zelda",
+                "Alien"
+            ]
+        );
+        assert_eq!(
+            titles(true),
+            vec![
+                "Book 2",
+                "Book 10",
+                "Émile",
+                "the Matrix",
+                "Nemo",
+                "Zelda",
+                "Alien"
+            ]
+        );
+    }
+
+    #[test]
+    fn sorts_wishlist_before_planned() {
+        let repo = memory_repo();
+        for (title, status) in [
+            ("Done", Status::Finished),
+            ("Wanted", Status::Wishlist),
+            ("Reading", Status::InProgress),
+            ("Queued", Status::Planned),
+        ] {
+            let mut item = MediaItem::new(title, Category::Book);
+            item.status = status;
+            repo.add(&mut item).unwrap();
+        }
+        let titles = |order: SortOrder| -> Vec<String> {
+            repo.list(&Query {
+                sort: vec![SortKey::new(SortField::Status, order)],
+                ..Default::default()
+            })
+            .unwrap()
+            .into_iter()
+            .map(|i| i.title)
+            .collect()
+        };
+        assert_eq!(
+            titles(SortOrder::Asc),
+            vec!["Wanted", "Queued", "Reading", "Done"]
+        );
+        assert_eq!(
+            titles(SortOrder::Desc),
+            vec!["Done", "Reading", "Queued", "Wanted"]
+        );
+    }
+
+    #[test]
+    fn matches_whole_tags_only() {
+        let repo = memory_repo();
+        for notes in ["#science", "Great #Sci read", "#sci-fi", "x#sci", "#sci"] {
+            let mut item = MediaItem::new(notes, Category::Book);
+            item.notes = Some(notes.into());
+            repo.add(&mut item).unwrap();
+        }
+        repo.add(&mut MediaItem::new("no notes", Category::Book))
+            .unwrap();
+        let titles = |search: &str| -> Vec<String> {
+            repo.list(&Query {
+                search: search.into(),
+                ..Default::default()
+            })
+            .unwrap()
+            .into_iter()
+            .map(|i| i.title)
+            .collect()
+        };
+        assert_eq!(titles("tag:sci"), vec!["Great #Sci read", "#sci"]);
+        assert_eq!(titles("tag:#sci-fi"), vec!["#sci-fi"]);
+        assert_eq!(
+            titles("tag:!sci"),
+            vec!["#science", "#sci-fi", "x#sci", "no notes"]
+        );
+    }
+
+    fn dated_item(
+        title: &str,
+        category: Category,
+        status: Status,
+        created: &str,
+        rating: Option<u8>,
+    ) -> MediaItem {
+        let mut item = MediaItem::new(title, category);
+        item.status = status;
+        item.created_at = parse_local_date(created).unwrap();
+        item.rating = rating;
+        item
+    }
+
+    #[test]
+    fn aggregates_the_dashboard_statistics() {
+        let repo = memory_repo();
+        let mut dune = dated_item(
+            "Dune",
+            Category::Book,
+            Status::Finished,
+            "2024-01-15",
+            Some(8),
+        );
+        dune.started_at = parse_local_date("2024-02-10");
+        dune.finished_at = parse_local_date("2024-03-10");
+        repo.add(&mut dune).unwrap();
+        for mut item in [
+            dated_item(
+                "Alien",
+                Category::Movie,
+                Status::Planned,
+                "2024-01-20",
+                Some(6),
+            ),
+            dated_item(
+                "Emma",
+                Category::Book,
+                Status::InProgress,
+                "2024-03-05",
+                Some(10),
+            ),
+            dated_item(
+                "Piranesi",
+                Category::Book,
+                Status::Wishlist,
+                "2024-02-01",
+                None,
+            ),
+        ] {
+            repo.add(&mut item).unwrap();
+        }
+
+        let stats = repo.stats().unwrap();
+        assert_eq!(
+            (
+                stats.total,
+                stats.finished,
+                stats.unfinished,
+                stats.wishlist
+            ),
+            (4, 1, 2, 1)
+        );
+
+        let stats = repo.detailed_stats().unwrap();
+        let months = ["2024-01", "2024-02", "2024-03"].map(String::from);
+        assert_eq!(
+            stats.finished_per_month,
+            months
+                .clone()
+                .into_iter()
+                .zip([0, 0, 1])
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            stats.backlog_per_month,
+            months.into_iter().zip([2, 2, 2]).collect::<Vec<_>>()
+        );
+        assert_eq!(stats.finished_per_year, vec![(2024, 1)]);
+        assert_eq!(stats.rating_histogram, [0, 0, 0, 0, 0, 0, 1, 0, 1, 0, 1]);
+        assert_eq!(
+            stats.avg_rating_by_category,
+            vec![("Book".to_string(), 9.0), ("Movie".to_string(), 6.0)]
+        );
+        assert_eq!(stats.days_to_finish, vec![29]);
+    }
+
+    #[test]
+    fn counts_goal_progress_in_range_category_and_backlog() {
+        let repo = memory_repo();
+        let finish = |title: &str, category, created: &str, finished: &str| {
+            let mut item = dated_item(title, category, Status::Finished, created, None);
+            item.finished_at = parse_local_date(finished);
+            repo.add(&mut item).unwrap();
+        };
+        finish("Old book", Category::Book, "2023-06-01", "2024-01-01");
+        finish("New book", Category::Book, "2024-02-01", "2024-03-31");
+        finish("Movie", Category::Movie, "2023-06-01", "2024-02-15");
+        finish("Too late", Category::Book, "2023-06-01", "2024-04-01");
+        let mut planned = dated_item(
+            "Planned",
+            Category::Book,
+            Status::Planned,
+            "2023-01-01",
+            None,
+        );
+        repo.add(&mut planned).unwrap();
+
+        let (start, end) = Goal::quarter_range(2024, 1);
+        let mut goal = Goal::new("Q1", 3, start, end);
+        assert_eq!(repo.goal_progress(&goal).unwrap(), 3);
+        goal.category = Some(Category::Book);
+        assert_eq!(repo.goal_progress(&goal).unwrap(), 2);
+        goal.backlog_only = true;
+        assert_eq!(repo.goal_progress(&goal).unwrap(), 1);
+    }
+
+    #[test]
+    fn merge_updates_the_survivor_and_deletes_duplicates() {
+        let repo = memory_repo();
+        let mut items: Vec<MediaItem> = ["Dune", "Dune!", "Emma"]
+            .into_iter()
+            .map(|t| {
+                let mut item = MediaItem::new(t, Category::Book);
+                repo.add(&mut item).unwrap();
+                item
+            })
+            .collect();
+        items[0].rating = Some(8);
+        repo.merge(&items[0], &[items[1].id.unwrap()]).unwrap();
+
+        assert!(repo.get(items[1].id.unwrap()).unwrap().is_none());
+        let survivor = repo.get(items[0].id.unwrap()).unwrap().unwrap();
+        assert_eq!(survivor.rating, Some(8));
+        assert_eq!(repo.count(&Query::default()).unwrap(), 2);
+    }
+
+    #[test]
+    fn searches_titles_with_operator_characters() {
+        let repo = memory_repo();
+        for title in ["Mamma Mia!", "Mission: Impossible", "Dune"] {
+            repo.add(&mut MediaItem::new(title, Category::Movie))
+                .unwrap();
+        }
+        let count = |search: &str| {
+            repo.count(&Query {
+                search: search.into(),
+                ..Default::default()
+            })
+            .unwrap()
+        };
+        assert_eq!(count("Mamma Mia!"), 1);
+        assert_eq!(count("Mission: Impossible"), 1);
+        assert_eq!(count("-Dune"), 2);
+        assert_eq!(count("cat:movie -dune"), 2);
+    }
+
+    #[test]
+    fn lists_scanned_files_of_a_folder_but_not_its_siblings() {
+        let repo = memory_repo();
+        let sep = std::path::MAIN_SEPARATOR;
+        for path in ["music", "music/a.mp3", "music/x/b.mp3", "music2/c.mp3"] {
+            repo.save_scanned_file(&ScannedFile {
+                path: path.replace('/', &sep.to_string()),
+                modified: 0,
+                size: 0,
+                hash: String::new(),
+                group: String::new(),
+                item_id: None,
+            })
+            .unwrap();
+        }
+        let paths = |dir: &str| -> Vec<String> {
+            let mut paths: Vec<String> = repo
+                .scanned_files(&dir.replace('/', &sep.to_string()))
+                .unwrap()
+                .into_iter()
+                .map(|f| f.path.replace(sep, "/"))
+                .collect();
+            paths.sort();
+            paths
+        };
+        assert_eq!(paths("music"), ["music", "music/a.mp3", "music/x/b.mp3"]);
+        assert_eq!(paths("music/"), ["music/a.mp3", "music/x/b.mp3"]);
+        assert_eq!(paths("music2"), ["music2/c.mp3"]);
+        assert!(paths("mus").is_empty());
+    }
+
+    #[test]
+    fn matches_item_and_copy_formats() {
+        let repo = memory_repo();
+        let mut physical = MediaItem::new("Dune", Category::Book);
+        physical.format = Some(Format::Physical);
+        let mut records = MediaItem::new("Kind of Blue", Category::Music);
+        let mut streamed = MediaItem::new("Heat", Category::Movie);
+        let none = &mut MediaItem::new("Alien", Category::Movie);
+        for item in [&mut physical, &mut records, &mut streamed, none] {
+            repo.add(item).unwrap();
+        }
+        for (item, format, location) in [
+            (&records, "Vinyl", "Living room"),
+            (&streamed, "Digital", "Plex"),
+            (&physical, "Paperback", "Shelf 2"),
+        ] {
+            let mut copy = OwnedCopy::new(item.id.unwrap());
+            copy.format = format.into();
+            copy.location = location.into();
+            repo.save_copy(&mut copy).unwrap();
+        }
+        let query = |search: &str| Query {
+            search: search.into(),
+            ..Default::default()
+        };
+        let titles = |search: &str| -> Vec<String> {
+            repo.list(&query(search))
+                .unwrap()
+                .into_iter()
+                .map(|i| i.title)
+                .collect()
+        };
+        assert_eq!(titles("format:physical"), ["Dune"]);
+        assert_eq!(titles("format:digital"), ["Heat"]);
+        assert_eq!(titles("format:vinyl"), ["Kind of Blue"]);
+        assert_eq!(titles("format:!digital"), ["Dune", "Kind of Blue", "Alien"]);
+        assert_eq!(titles("format:!back"), ["Kind of Blue", "Heat", "Alien"]);
+
+        let copies = repo.inventory(&query("cat:music OR cat:book")).unwrap();
+        let found: Vec<(&str, &str)> = copies
+            .iter()
+            .map(|(item, copy)| (item.title.as_str(), copy.location.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            [("Dune", "Shelf 2"), ("Kind of Blue", "Living room")]
+        );
+    }
+
+    #[test]
+    fn saves_a_purchase_atomically() {
+        let repo = memory_repo();
+        let mut item = MediaItem::new("Dune", Category::Book);
+        item.status = Status::Wishlist;
+        let id = repo.add(&mut item).unwrap();
+        item.status = Status::Planned;
+
+        // A copy of a missing item fails, and the status change goes with it.
+        let mut stray = OwnedCopy::new(id + 1);
+        assert!(repo.save_purchase(&item, &mut stray).is_err());
+        assert_eq!(repo.get(id).unwrap().unwrap().status, Status::Wishlist);
+
+        let mut copy = OwnedCopy::new(id);
+        repo.save_purchase(&item, &mut copy).unwrap();
+        assert_eq!(repo.get(id).unwrap().unwrap().status, Status::Planned);
+        assert_eq!(repo.list_copies(id).unwrap()[0].id, copy.id);
+    }
+
+    #[test]
+    fn tracks_loans_and_finds_lent_items() {
+        let repo = memory_repo();
+        let mut dune = MediaItem::new("Dune", Category::Book);
+        let mut heat = MediaItem::new("Heat", Category::Movie);
+        let mut alien = MediaItem::new("Alien", Category::Movie);
+        for item in [&mut dune, &mut heat, &mut alien] {
+            repo.add(item).unwrap();
+        }
+        let mut copy = OwnedCopy::new(dune.id.unwrap());
+        repo.save_copy(&mut copy).unwrap();
+        let today = Local::now().date_naive();
+        let days = |n: i64| today + chrono::Duration::days(n);
+        let mut loans = [
+            (&dune, "Sam Park", -20, Some(-5), None),
+            (&heat, "Alex", -3, Some(7), None),
+            (&dune, "Robin", -60, Some(-40), Some(-45)),
+        ]
+        .map(|(item, borrower, lent, due, returned)| Loan {
+            borrower: borrower.into(),
+            lent_on: days(lent),
+            due_on: due.map(days),
+            returned_on: returned.map(days),
+            ..Loan::new(item.id.unwrap())
+        });
+        loans[0].copy_id = copy.id;
+        for loan in &mut loans {
+            repo.save_loan(loan).unwrap();
+        }
+
+        let titles = |search: &str| -> Vec<String> {
+            repo.list(&Query {
+                search: search.into(),
+                ..Default::default()
+            })
+            .unwrap()
+            .into_iter()
+            .map(|i| i.title)
+            .collect()
+        };
+        assert_eq!(titles("onloan:yes"), ["Dune", "Heat"]);
+        assert_eq!(titles("onloan:no"), ["Alien"]);
+        assert_eq!(titles("overdue:yes"), ["Dune"]);
+        assert_eq!(titles("borrower:sam"), ["Dune"]);
+        assert!(titles("borrower:robin").is_empty());
+
+        let borrowers: Vec<String> = repo
+            .list_loans(dune.id.unwrap())
+            .unwrap()
+            .into_iter()
+            .map(|l| l.borrower)
+            .collect();
+        assert_eq!(borrowers, ["Sam Park", "Robin"]);
+        assert_eq!(repo.open_loans().unwrap().len(), 2);
+        assert_eq!(repo.loans(&Query::default()).unwrap()[0].0.title, "Heat");
+
+        loans[0].returned_on = Some(today);
+        repo.save_loan(&mut loans[0]).unwrap();
+        assert_eq!(titles("overdue:yes"), Vec::<String>::new());
+        repo.delete_copy(copy.id.unwrap()).unwrap();
+        assert_eq!(repo.list_loans(dune.id.unwrap()).unwrap()[0].copy_id, None);
+    }
+}
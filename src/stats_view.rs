@@ -0,0 +1,181 @@
+use crate::repo::{DetailedStats, RepoResult, Repository};
+use eframe::egui;
+use egui_plot::{Bar, BarChart, Line, Plot, PlotPoints};
+
+const PLOT_HEIGHT: f32 = 260.0;
+
+// Upper bounds (inclusive, in days) of the time-to-finish histogram buckets.
+const FINISH_BUCKETS: [(i64, &str); 6] = [
+    (1, "≤ 1 day"),
+    (7, "≤ 1 week"),
+    (30, "≤ 1 month"),
+    (90, "≤ 3 months"),
+    (365, "≤ 1 year"),
+    (i64::MAX, "> 1 year"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum Tab {
+    #[default]
+    Finished,
+    Ratings,
+    TimeToFinish,
+    Backlog,
+}
+
+/// Statistics window with charts over [`DetailedStats`].
+#[derive(Default)]
+pub struct StatsView {
+    pub open: bool,
+    data: DetailedStats,
+    tab: Tab,
+    by_year: bool,
+}
+
+impl StatsView {
+    pub fn reload(&mut self, repo: &dyn Repository) -> RepoResult<()> {
+        self.data = repo.detailed_stats()?;
+        Ok(())
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context) {
+        let mut open = self.open;
+        egui::Window::new("Statistics")
+            .open(&mut open)
+            .default_width(560.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.selectable_value(&mut self.tab, Tab::Finished, "Finished");
+                    ui.selectable_value(&mut self.tab, Tab::Ratings, "Ratings");
+                    ui.selectable_value(&mut self.tab, Tab::TimeToFinish, "Time to finish");
+                    ui.selectable_value(&mut self.tab, Tab::Backlog, "Backlog");
+                });
+                ui.separator();
+                egui::ScrollArea::vertical().show(ui, |ui| match self.tab {
+                    Tab::Finished => self.finished_ui(ui),
+                    Tab::Ratings => self.ratings_ui(ui),
+                    Tab::TimeToFinish => self.time_to_finish_ui(ui),
+                    Tab::Backlog => self.backlog_ui(ui),
+                });
+            });
+        self.open = open;
+    }
+
+    fn finished_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Items finished per");
+            ui.selectable_value(&mut self.by_year, false, "month");
+            ui.selectable_value(&mut self.by_year, true, "year");
+        });
+        let counts: Vec<(String, f64)> = if self.by_year {
+            self.data
+                .finished_per_year
+                .iter()
+                .map(|(y, n)| (y.to_string(), *n as f64))
+                .collect()
+        } else {
+            self.data
+                .finished_per_month
+                .iter()
+                .map(|(m, n)| (m.clone(), *n as f64))
+                .collect()
+        };
+        labeled_bar_chart(ui, "finished_chart", counts);
+    }
+
+    fn ratings_ui(&mut self, ui: &mut egui::Ui) {
+        ui.label("Rating distribution");
+        let histogram = self
+            .data
+            .rating_histogram
+            .iter()
+            .enumerate()
+            .map(|(rating, n)| (rating.to_string(), *n as f64))
+            .collect();
+        labeled_bar_chart(ui, "rating_histogram", histogram);
+
+        ui.label("Average rating by category");
+        let averages = self.data.avg_rating_by_category.clone();
+        labeled_bar_chart(ui, "avg_rating_chart", averages);
+    }
+
+    fn time_to_finish_ui(&mut self, ui: &mut egui::Ui) {
+        let mut buckets = [0usize; FINISH_BUCKETS.len()];
+        for days in &self.data.days_to_finish {
+            let i = FINISH_BUCKETS
+                .iter()
+                .position(|(max, _)| days <= max)
+                .unwrap_or(FINISH_BUCKETS.len() - 1);
+            buckets[i] += 1;
+        }
+        let mut sorted = self.data.days_to_finish.clone();
+        sorted.sort_unstable();
+        match sorted.get(sorted.len() / 2) {
+            Some(median) => ui.label(format!(
+                "Time from start to finish (median {} days, {} items)",
+                median,
+                sorted.len()
+            )),
+            None => ui.label("Time from start to finish"),
+        };
+        let counts = FINISH_BUCKETS
+            .iter()
+            .zip(buckets)
+            .map(|((_, label), n)| (label.to_string(), n as f64))
+            .collect();
+        labeled_bar_chart(ui, "time_to_finish_chart", counts);
+    }
+
+    fn backlog_ui(&mut self, ui: &mut egui::Ui) {
+        ui.label("Unfinished items at the end of each month, not counting the wishlist");
+        let months: Vec<String> = self
+            .data
+            .backlog_per_month
+            .iter()
+            .map(|(m, _)| m.clone())
+            .collect();
+        let points: PlotPoints = self
+            .data
+            .backlog_per_month
+            .iter()
+            .enumerate()
+            .map(|(i, (_, n))| [i as f64, *n as f64])
+            .collect();
+        Plot::new("backlog_chart")
+            .height(PLOT_HEIGHT)
+            .include_y(0.0)
+            .allow_scroll(false)
+            .x_axis_formatter(move |mark, _, _| index_label(&months, mark.value))
+            .label_formatter(|_, point| format!("{:.0} items", point.y))
+            .show(ui, |plot_ui| {
+                plot_ui.line(Line::new(points).name("Backlog"))
+            });
+    }
+}
+
+// Bar chart with one bar per (label, value), labelled along the x axis.
+fn labeled_bar_chart(ui: &mut egui::Ui, id: &str, values: Vec<(String, f64)>) {
+    if values.is_empty() {
+        ui.weak("No data yet.");
+        return;
+    }
+    let bars = values
+        .iter()
+        .enumerate()
+        .map(|(i, (label, v))| Bar::new(i as f64, *v).name(label).width(0.7))
+        .collect();
+    let labels: Vec<String> = values.into_iter().map(|(label, _)| label).collect();
+    Plot::new(id)
+        .height(PLOT_HEIGHT)
+        .include_y(0.0)
+        .allow_scroll(false)
+        .x_axis_formatter(move |mark, _, _| index_label(&labels, mark.value))
+        .show(ui, |plot_ui| plot_ui.bar_chart(BarChart::new(bars)));
+}
+
+fn index_label(labels: &[String], value: f64) -> String {
+    if value < 0.0 || value.fract() != 0.0 {
+        return String::new();
+    }
+    labels.get(value as usize).cloned().unwrap_or_default()
+}
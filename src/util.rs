@@ -1,7 +1,7 @@
-use crate::models::MediaItem;
-use chrono::Local;
+use crate::models::SavedSearch;
+use chrono::{DateTime, Local, NaiveDate, TimeZone};
 use std::fs::File;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 pub fn default_db_path() -> PathBuf {
     let mut path = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
@@ -9,38 +9,128 @@ pub fn default_db_path() -> PathBuf {
     path
 }
 
-pub fn export_csv(items: &[MediaItem]) -> Result<PathBuf, Box<dyn std::error::Error>> {
-    let mut out = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
-    let filename = format!("export_{}.csv", Local::now().format("%Y%m%d_%H%M%S"));
-    out.push(filename);
-
-    let file = File::create(&out)?;
-    let mut wtr = csv::Writer::from_writer(file);
-    wtr.write_record([
-        "id",
-        "title",
-        "category",
-        "status",
-        "rating",
-        "notes",
-        "cover_path",
-        "created_at",
-        "updated_at",
-    ])?;
-    for item in items {
-        wtr.write_record([
-            item.id.map(|v| v.to_string()).unwrap_or_default(),
-            item.title.clone(),
-            item.category.to_string(),
-            item.status.to_string(),
-            item.rating.map(|v| v.to_string()).unwrap_or_default(),
-            item.notes.clone().unwrap_or_default(),
-            item.cover_path.clone().unwrap_or_default(),
-            item.created_at.format("%Y-%m-%d %H:%M:%S").to_string(),
-            item.updated_at.format("%Y-%m-%d %H:%M:%S").to_string(),
-        ])?;
+/// Where covers extracted by scans are stored, next to the database.
+pub fn covers_dir() -> PathBuf {
+    default_db_path().with_file_name("covers")
+}
+
+/// Unix timestamp of the local midnight starting `date`, or of the first
+/// hour of the day when a DST change skips midnight.
+pub fn day_start(date: NaiveDate) -> i64 {
+    (0..24)
+        .find_map(|h| {
+            Local
+                .from_local_datetime(&date.and_hms_opt(h, 0, 0)?)
+                .earliest()
+        })
+        .map(|t| t.timestamp())
+        .unwrap_or_else(|| {
+            date.and_hms_opt(0, 0, 0)
+                .unwrap_or_default()
+                .and_utc()
+                .timestamp()
+        })
+}
+
+/// Parses `YYYY-MM-DD` as noon local time on that day.
+pub fn parse_local_date(s: &str) -> Option<DateTime<Local>> {
+    let date = NaiveDate::parse_from_str(s.trim(), "%Y-%m-%d").ok()?;
+    Local
+        .from_local_datetime(&date.and_hms_opt(12, 0, 0)?)
+        .earliest()
+}
+
+pub fn export_saved_searches(
+    searches: &[SavedSearch],
+    path: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let file = File::create(path)?;
+    serde_json::to_writer_pretty(file, searches)?;
+    Ok(())
+}
+
+pub fn import_saved_searches(path: &Path) -> Result<Vec<SavedSearch>, Box<dyn std::error::Error>> {
+    let file = File::open(path)?;
+    Ok(serde_json::from_reader(std::io::BufReader::new(file))?)
+}
+
+/// Decoded `name=value` pairs of a URL query string.
+pub fn query_params(query_string: &str) -> Vec<(String, String)> {
+    query_string
+        .split('&')
+        .filter(|p| !p.is_empty())
+        .map(|pair| {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (percent_decode(name), percent_decode(value))
+        })
+        .collect()
+}
+
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => out.push(b' '),
+            b'%' => match s
+                .get(i + 1..i + 3)
+                .and_then(|h| u8::from_str_radix(h, 16).ok())
+            {
+                Some(b) => {
+                    out.push(b);
+                    i += 2;
+                }
+                None => out.push(b'%'),
+            },
+            b => out.push(b),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Encodes `s` for use as a URL query string value.
+pub fn percent_encode(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for b in s.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                out.push(b as char)
+            }
+            b' ' => out.push('+'),
+            _ => out.push_str(&format!("%{:02X}", b)),
+        }
     }
-    wtr.flush()?;
+    out
+}
 
-    Ok(out)
+/// Escapes text for use in HTML content and attribute values.
+pub fn html_escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// An optional price as typed by the user; a decimal comma is accepted.
+pub fn parse_price(text: &str) -> Result<Option<f64>, String> {
+    match text.trim() {
+        "" => Ok(None),
+        price => price
+            .replace(',', ".")
+            .parse::<f64>()
+            .ok()
+            .filter(|p| *p >= 0.0)
+            .map(Some)
+            .ok_or_else(|| format!("`{}` is not a price", price)),
+    }
 }
@@ -0,0 +1,175 @@
+use crate::models::{Category, Status, WatchFolder};
+use crate::repo::{RepoResult, Repository};
+use crate::util;
+use crate::watcher::{ActivityLog, FolderWatcher};
+use eframe::egui;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Window for the watched folders and what the watcher added from them.
+#[derive(Default)]
+pub struct WatchView {
+    pub open: bool,
+    folders: Vec<WatchFolder>,
+    watcher: Option<FolderWatcher>,
+    activity: ActivityLog,
+    seen_changes: u64,
+}
+
+impl WatchView {
+    /// Loads the watched folders and starts watching the enabled ones.
+    pub fn load(&mut self, repo: &Arc<dyn Repository>) -> RepoResult<()> {
+        self.folders = repo.list_watch_folders()?;
+        self.start(repo)
+    }
+
+    /// True once per batch of items the watcher added or updated since the last call.
+    pub fn poll_changes(&mut self, ctx: &egui::Context) -> bool {
+        let Some(watcher) = &self.watcher else {
+            return false;
+        };
+        // Keep polling while the window is idle so new files show up.
+        ctx.request_repaint_after(Duration::from_secs(1));
+        let changes = watcher.changes();
+        let changed = changes != self.seen_changes;
+        self.seen_changes = changes;
+        changed
+    }
+
+    fn start(&mut self, repo: &Arc<dyn Repository>) -> RepoResult<()> {
+        self.watcher = None;
+        self.seen_changes = 0;
+        if !self.folders.iter().any(|f| f.enabled) {
+            return Ok(());
+        }
+        self.watcher = Some(FolderWatcher::start(
+            Arc::clone(repo),
+            self.folders.clone(),
+            util::covers_dir(),
+            self.activity.clone(),
+        )?);
+        Ok(())
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context, repo: &Arc<dyn Repository>) -> RepoResult<()> {
+        let mut open = self.open;
+        let mut result = Ok(());
+        egui::Window::new("Watch folders")
+            .open(&mut open)
+            .default_width(520.0)
+            .show(ctx, |ui| {
+                result = self.watch_ui(ui, repo);
+            });
+        self.open = open;
+        result
+    }
+
+    fn watch_ui(&mut self, ui: &mut egui::Ui, repo: &Arc<dyn Repository>) -> RepoResult<()> {
+        let mut changed = None;
+        let mut delete = None;
+        if self.folders.is_empty() {
+            ui.weak("No watched folders yet.");
+        } else {
+            egui::Grid::new("watch_folders_grid")
+                .num_columns(5)
+                .spacing([8.0, 6.0])
+                .show(ui, |ui| {
+                    ui.label("");
+                    ui.strong("Folder");
+                    ui.strong("Category");
+                    ui.strong("New files →");
+                    ui.label("");
+                    ui.end_row();
+                    for (i, folder) in self.folders.iter_mut().enumerate() {
+                        let before = folder.clone();
+                        ui.checkbox(&mut folder.enabled, "")
+                            .on_hover_text("Watch this folder");
+                        ui.label(&folder.path);
+                        egui::ComboBox::from_id_source(("watch_cat", i))
+                            .selected_text(folder.category.as_str())
+                            .show_ui(ui, |ui| {
+                                for c in Category::ALL {
+                                    ui.selectable_value(&mut folder.category, c, c.as_str());
+                                }
+                            })
+                            .response
+                            .on_hover_text(
+                                "Music folders are scanned by album, Book folders for e-books, \
+                                 others as one item per file",
+                            );
+                        egui::ComboBox::from_id_source(("watch_status", i))
+                            .selected_text(format!("{} item", folder.status.as_str()))
+                            .show_ui(ui, |ui| {
+                                for s in Status::ALL {
+                                    ui.selectable_value(
+                                        &mut folder.status,
+                                        s,
+                                        format!("{} item", s.as_str()),
+                                    );
+                                }
+                            });
+                        if ui
+                            .small_button("🗑")
+                            .on_hover_text("Stop watching; items already added stay")
+                            .clicked()
+                        {
+                            delete = folder.id;
+                        }
+                        if *folder != before {
+                            changed = Some(i);
+                        }
+                        ui.end_row();
+                    }
+                });
+        }
+        let mut restart = false;
+        if let Some(i) = changed {
+            repo.save_watch_folder(&mut self.folders[i])?;
+            restart = true;
+        }
+        if let Some(id) = delete {
+            repo.delete_watch_folder(id)?;
+            self.folders.retain(|f| f.id != Some(id));
+            restart = true;
+        }
+        if ui.button("Add folder...").clicked() {
+            if let Some(dir) = rfd::FileDialog::new().pick_folder() {
+                let path = dir.to_string_lossy().to_string();
+                if !self.folders.iter().any(|f| f.path == path) {
+                    let mut folder = WatchFolder::new(path, Category::Other);
+                    repo.save_watch_folder(&mut folder)?;
+                    self.folders.push(folder);
+                    restart = true;
+                }
+            }
+        }
+        if restart {
+            self.start(repo)?;
+        }
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            ui.strong("Activity");
+            match &self.watcher {
+                Some(_) => ui.weak("watching"),
+                None => ui.weak("stopped"),
+            };
+        });
+        let entries = self.activity.entries();
+        if entries.is_empty() {
+            ui.weak("Nothing yet.");
+        }
+        egui::ScrollArea::vertical()
+            .max_height(240.0)
+            .auto_shrink([false, true])
+            .show(ui, |ui| {
+                for entry in entries {
+                    ui.horizontal_wrapped(|ui| {
+                        ui.weak(entry.at.format("%H:%M:%S").to_string());
+                        ui.label(entry.text);
+                    });
+                }
+            });
+        Ok(())
+    }
+}
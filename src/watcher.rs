@@ -0,0 +1,276 @@
+use crate::models::{Status, WatchFolder};
+use crate::repo::{RepoError, RepoResult, Repository};
+use crate::scan;
+use chrono::{DateTime, Local};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::{BTreeSet, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+// Folders are scanned once no event arrived for this long, so a copy in
+// progress is picked up when it is done rather than on every write.
+const DEBOUNCE: Duration = Duration::from_secs(2);
+const MAX_ACTIVITY: usize = 200;
+
+#[derive(Debug, Clone)]
+pub struct Activity {
+    pub at: DateTime<Local>,
+    pub text: String,
+}
+
+/// What the watcher did, newest first. Shared between the watcher thread and
+/// the window showing it, and kept when the watcher restarts.
+#[derive(Debug, Clone, Default)]
+pub struct ActivityLog(Arc<Mutex<VecDeque<Activity>>>);
+
+impl ActivityLog {
+    pub fn push(&self, text: impl Into<String>) {
+        let text = text.into();
+        log::info!("{}", text);
+        let mut entries = self.0.lock().unwrap();
+        entries.push_front(Activity {
+            at: Local::now(),
+            text,
+        });
+        entries.truncate(MAX_ACTIVITY);
+    }
+
+    pub fn entries(&self) -> Vec<Activity> {
+        self.0.lock().unwrap().iter().cloned().collect()
+    }
+}
+
+/// Watches the enabled folders for changes and scans them into the catalog
+/// from a background thread: music and e-book folders with their scanners,
+/// other folders as one item per file. New items get the folder's status.
+/// Folders are scanned once on start to catch up on changes made while
+/// nothing was watching. Stops when dropped.
+pub struct FolderWatcher {
+    _watcher: RecommendedWatcher,
+    changes: Arc<AtomicU64>,
+}
+
+impl FolderWatcher {
+    pub fn start(
+        repo: Arc<dyn Repository>,
+        folders: Vec<WatchFolder>,
+        covers: PathBuf,
+        activity: ActivityLog,
+    ) -> RepoResult<Self> {
+        let (sender, events) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(sender)
+            .map_err(|e| RepoError::Other(format!("Cannot watch folders: {}", e)))?;
+        let folders: Vec<WatchFolder> = folders.into_iter().filter(|f| f.enabled).collect();
+        for folder in &folders {
+            if let Err(e) = watcher.watch(Path::new(&folder.path), RecursiveMode::Recursive) {
+                activity.push(format!("Cannot watch {}: {}", folder.path, e));
+            }
+        }
+        let changes = Arc::new(AtomicU64::new(0));
+        {
+            let changes = Arc::clone(&changes);
+            // The thread ends when the watcher, which owns the sender, is dropped.
+            std::thread::spawn(move || {
+                let worker = Worker {
+                    repo,
+                    folders,
+                    covers,
+                    activity,
+                    changes,
+                };
+                worker.run(events);
+            });
+        }
+        Ok(Self {
+            _watcher: watcher,
+            changes,
+        })
+    }
+
+    /// Number of scans that changed the catalog so far.
+    pub fn changes(&self) -> u64 {
+        self.changes.load(Ordering::Relaxed)
+    }
+}
+
+struct Worker {
+    repo: Arc<dyn Repository>,
+    folders: Vec<WatchFolder>,
+    covers: PathBuf,
+    activity: ActivityLog,
+    changes: Arc<AtomicU64>,
+}
+
+impl Worker {
+    fn run(&self, events: Receiver<notify::Result<Event>>) {
+        for i in 0..self.folders.len() {
+            self.scan(i);
+        }
+        while let Ok(event) = events.recv() {
+            let mut dirty = BTreeSet::new();
+            self.note(event, &mut dirty);
+            loop {
+                match events.recv_timeout(DEBOUNCE) {
+                    Ok(event) => self.note(event, &mut dirty),
+                    Err(RecvTimeoutError::Timeout) => break,
+                    Err(RecvTimeoutError::Disconnected) => return,
+                }
+            }
+            for i in dirty {
+                self.scan(i);
+            }
+        }
+    }
+
+    // Adds the folders `event` touched to `dirty`.
+    fn note(&self, event: notify::Result<Event>, dirty: &mut BTreeSet<usize>) {
+        let event = match event {
+            Ok(event) => event,
+            Err(e) => {
+                self.activity.push(format!("Watch error: {}", e));
+                return;
+            }
+        };
+        if matches!(event.kind, EventKind::Access(_)) {
+            return;
+        }
+        for path in &event.paths {
+            // The innermost folder, when watched folders are nested.
+            let folder = self
+                .folders
+                .iter()
+                .enumerate()
+                .filter(|(_, f)| path.starts_with(&f.path))
+                .max_by_key(|(_, f)| f.path.len());
+            if let Some((i, _)) = folder {
+                dirty.insert(i);
+            }
+        }
+    }
+
+    fn scan(&self, i: usize) {
+        let folder = &self.folders[i];
+        let repo = self.repo.as_ref();
+        let report =
+            match scan::scan_folder(repo, Path::new(&folder.path), folder.category, &self.covers) {
+                Ok(report) => report,
+                Err(e) => {
+                    self.activity
+                        .push(format!("Scanning {} failed: {}", folder.path, e));
+                    return;
+                }
+            };
+        for (id, title) in &report.added {
+            if let Err(e) = set_status(repo, *id, folder.status) {
+                self.activity
+                    .push(format!("Cannot update “{}”: {}", title, e));
+            }
+            self.activity.push(format!(
+                "Added “{}” ({}, {}) from {}",
+                title,
+                folder.category.as_str(),
+                folder.status.as_str(),
+                folder.path
+            ));
+        }
+        for error in &report.errors {
+            self.activity.push(format!("Unreadable: {}", error));
+        }
+        if report.updated > 0 || report.removed > 0 {
+            self.activity.push(format!("{}: {}", folder.path, report));
+        }
+        if !report.added.is_empty() || report.updated > 0 {
+            self.changes.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+// Scanners add items as Planned; other rules are applied afterwards.
+fn set_status(repo: &dyn Repository, id: i64, status: Status) -> RepoResult<()> {
+    if status == Status::Planned {
+        return Ok(());
+    }
+    if let Some(mut item) = repo.get(id)? {
+        item.set_status(status);
+        repo.update(&item)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Category, Query};
+    use crate::sqlite_repo::SqliteRepo;
+    use notify::event::{AccessKind, CreateKind};
+
+    fn worker(folders: Vec<WatchFolder>) -> Worker {
+        let repo = SqliteRepo::new(Path::new(":memory:"));
+        repo.init().unwrap();
+        Worker {
+            repo: Arc::new(repo),
+            folders,
+            covers: std::env::temp_dir(),
+            activity: ActivityLog::default(),
+            changes: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    fn event(kind: EventKind, path: &str) -> notify::Result<Event> {
+        Ok(Event::new(kind).add_path(PathBuf::from(path)))
+    }
+
+    #[test]
+    fn marks_the_innermost_watched_folder_dirty() {
+        let worker = worker(vec![
+            WatchFolder::new("/media", Category::Other),
+            WatchFolder::new("/media/films", Category::Movie),
+            WatchFolder::new("/media/films2", Category::Movie),
+        ]);
+        let mut dirty = BTreeSet::new();
+        let create = EventKind::Create(CreateKind::File);
+        worker.note(event(create, "/media/films/a.mkv"), &mut dirty);
+        assert_eq!(dirty.iter().copied().collect::<Vec<_>>(), [1]);
+        worker.note(event(create, "/media/films2x/b.mkv"), &mut dirty);
+        worker.note(event(create, "/elsewhere/c.mkv"), &mut dirty);
+        assert_eq!(dirty.iter().copied().collect::<Vec<_>>(), [0, 1]);
+
+        let mut dirty = BTreeSet::new();
+        let read = EventKind::Access(AccessKind::Read);
+        worker.note(event(read, "/media/films/a.mkv"), &mut dirty);
+        worker.note(Err(notify::Error::generic("gone")), &mut dirty);
+        assert!(dirty.is_empty());
+        assert!(worker.activity.entries()[0].text.contains("gone"));
+    }
+
+    #[test]
+    fn adds_new_files_with_the_folder_status() {
+        let dir = std::env::temp_dir().join(format!("watcher-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("Heat.1995.mkv"), "film").unwrap();
+        std::fs::write(dir.join("notes.txt"), "not a film").unwrap();
+        let mut folder = WatchFolder::new(dir.to_string_lossy(), Category::Movie);
+        folder.status = Status::Finished;
+        let worker = worker(vec![folder]);
+
+        worker.scan(0);
+        let items = worker.repo.list(&Query::default()).unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(
+            (items[0].title.as_str(), items[0].status),
+            ("Heat", Status::Finished)
+        );
+        assert!(items[0].finished_at.is_some());
+        assert_eq!(worker.changes.load(Ordering::Relaxed), 1);
+        assert!(worker.activity.entries()[0]
+            .text
+            .starts_with("Added “Heat” (Movie, Finished)"));
+
+        worker.scan(0);
+        assert_eq!(worker.changes.load(Ordering::Relaxed), 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
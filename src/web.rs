@@ -0,0 +1,618 @@
+use crate::api::{self, ApiRequest, ApiResponse};
+use crate::models::{Category, MediaItem, SortField, SortKey, SortOrder, Status};
+use crate::repo::Repository;
+use crate::search;
+use crate::util::{self, html_escape};
+use chrono::{DateTime, Local};
+use std::net::SocketAddr;
+use std::path::Path;
+
+const PAGE_SIZE: usize = 50;
+const TOKEN_COOKIE: &str = "catalog_token";
+
+const SORT_OPTIONS: [(&str, &str); 6] = [
+    ("-updated", "Recently updated"),
+    ("title", "Title"),
+    ("-rating", "Rating"),
+    ("-created", "Recently added"),
+    ("category", "Category"),
+    ("status", "Status"),
+];
+
+const STYLE: &str = "body{font-family:sans-serif;margin:0 auto;max-width:1100px;padding:1em;color:#222}\
+a{color:#2a5db0;text-decoration:none}a:hover{text-decoration:underline}\
+form.filters{display:flex;flex-wrap:wrap;gap:.5em;align-items:end;margin-bottom:1em}\
+form label{display:flex;flex-direction:column;font-size:.8em;color:#555}\
+table{border-collapse:collapse;width:100%}th,td{text-align:left;padding:.3em .5em;border-bottom:1px solid #ddd}\
+tr:nth-child(even){background:#f6f6f6}td.cover img{max-height:48px;max-width:48px}\
+.error{color:#b00}.muted{color:#777}.notes{white-space:pre-wrap}\
+.detail{display:flex;gap:2em;align-items:flex-start}.detail img{max-width:300px}";
+
+/// Who may use the HTML view and what it may do.
+pub struct WebAccess<'a> {
+    /// The API token. A link with `?token=` signs the browser in with a cookie.
+    pub token: &'a str,
+    /// Where the server listens; requests naming another host are refused,
+    /// which defeats DNS rebinding.
+    pub addr: SocketAddr,
+    pub allow_edits: bool,
+    /// Only cover images inside this folder are served.
+    pub covers: &'a Path,
+}
+
+/// Serves the HTML pages: `/` lists items with the same filters as the app,
+/// `/items/{id}` shows one item and `/covers/{id}` its cover image. Items can
+/// only be changed from the browser when `allow_edits` is set, and only by
+/// forms on these pages.
+pub fn handle(repo: &dyn Repository, req: &ApiRequest, access: &WebAccess) -> ApiResponse {
+    let Some(host) = req.host.filter(|h| is_local_host(h, access.addr)) else {
+        return page(403, "Forbidden", "<p>Unknown host.</p>");
+    };
+    let (path, query_string) = req.url.split_once('?').unwrap_or((req.url, ""));
+    let params = util::query_params(query_string);
+    if let Some((_, given)) = params.iter().find(|(name, _)| name == "token") {
+        if !api::token_matches(access.token, given) {
+            return signed_out();
+        }
+        // Trade the token in the link for a cookie, and keep it out of the
+        // address bar and history.
+        let rest = encode_params(params.iter().filter(|(name, _)| name != "token"));
+        let location = if rest.is_empty() {
+            path.to_string()
+        } else {
+            format!("{}?{}", path, rest)
+        };
+        let mut response = page(303, "Signed in", "");
+        response.headers = vec![
+            ("Location", location),
+            (
+                "Set-Cookie",
+                format!(
+                    "{}={}; Path=/; HttpOnly; SameSite=Strict",
+                    TOKEN_COOKIE, access.token
+                ),
+            ),
+        ];
+        return response;
+    }
+    if !signed_in(access.token, req) {
+        return signed_out();
+    }
+    if req.method != "GET" && req.origin.is_some_and(|o| o != format!("http://{}", host)) {
+        return page(
+            403,
+            "Forbidden",
+            "<p>Requests from other sites are refused.</p>",
+        );
+    }
+
+    let allow_edits = access.allow_edits;
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    match (req.method, segments.as_slice()) {
+        ("GET", [""]) => list_page(repo, query_string),
+        ("GET", ["items", id]) => with_item(repo, id, |item| detail_page(item, allow_edits, None)),
+        ("POST", ["items", id]) if allow_edits => {
+            with_item(repo, id, |item| edit_item(repo, item, req.body))
+        }
+        ("POST", ["items", _]) => page(403, "Read-only", "<p>Editing is turned off.</p>"),
+        ("GET", ["covers", id]) => with_item(repo, id, |item| cover(item, access.covers)),
+        _ => page(
+            404,
+            "Not found",
+            "<p>No such page. <a href=\"/\">Back to the list</a></p>",
+        ),
+    }
+}
+
+// `127.0.0.1:<port>` or `localhost:<port>` of the server itself.
+fn is_local_host(host: &str, addr: SocketAddr) -> bool {
+    let port = addr.port().to_string();
+    match host.rsplit_once(':') {
+        Some((name, p)) => p == port && (name == "localhost" || name == addr.ip().to_string()),
+        None => false,
+    }
+}
+
+// Signed in by the token cookie, or by a bearer token like the API.
+fn signed_in(token: &str, req: &ApiRequest) -> bool {
+    let cookie = req.cookie.unwrap_or_default().split(';').find_map(|c| {
+        let (name, value) = c.trim().split_once('=')?;
+        (name == TOKEN_COOKIE).then_some(value)
+    });
+    let bearer = req.authorization.and_then(|h| h.strip_prefix("Bearer "));
+    cookie
+        .or(bearer)
+        .is_some_and(|given| api::token_matches(token, given))
+}
+
+fn signed_out() -> ApiResponse {
+    page(
+        401,
+        "Sign in",
+        "<p>Open the web view from the link in the app's Server window or \
+         printed by <code>serve</code>; it carries the access token.</p>",
+    )
+}
+
+fn encode_params<'a>(params: impl Iterator<Item = &'a (String, String)>) -> String {
+    params
+        .map(|(name, value)| {
+            format!(
+                "{}={}",
+                util::percent_encode(name),
+                util::percent_encode(value)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+fn with_item(
+    repo: &dyn Repository,
+    id: &str,
+    f: impl FnOnce(MediaItem) -> ApiResponse,
+) -> ApiResponse {
+    match id.parse().map(|id| repo.get(id)) {
+        Ok(Ok(Some(item))) => f(item),
+        Ok(Err(e)) => page(500, "Error", &error_html(&e.to_string())),
+        _ => page(
+            404,
+            "Not found",
+            "<p>No such item. <a href=\"/\">Back to the list</a></p>",
+        ),
+    }
+}
+
+fn list_page(repo: &dyn Repository, query_string: &str) -> ApiResponse {
+    let params = util::query_params(query_string);
+    let param = |name: &str| {
+        params
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+            .unwrap_or_default()
+    };
+    let mut html = filter_form(param);
+    let query = match api::query_from_params(&params) {
+        Ok(mut query) => {
+            if query.sort.is_empty() {
+                query.sort = vec![SortKey::new(SortField::UpdatedAt, SortOrder::Desc)];
+            }
+            query.page(query.offset, PAGE_SIZE)
+        }
+        Err(msg) => {
+            html.push_str(&error_html(&msg));
+            return page(400, "Media Catalog", &html);
+        }
+    };
+    let (total, items) = match repo.count(&query).and_then(|n| Ok((n, repo.list(&query)?))) {
+        Ok(result) => result,
+        Err(e) => {
+            html.push_str(&error_html(&e.to_string()));
+            return page(400, "Media Catalog", &html);
+        }
+    };
+
+    html.push_str(&format!("<p class=\"muted\">{} items</p>", total));
+    html.push_str(
+        "<table><tr><th></th><th>Title</th><th>Category</th><th>Status</th>\
+         <th>Rating</th><th>Updated</th></tr>",
+    );
+    for item in &items {
+        let id = item.id.unwrap_or_default();
+        let cover = if item.cover_path.is_some() {
+            format!("<img src=\"/covers/{}\" alt=\"\" loading=\"lazy\">", id)
+        } else {
+            String::new()
+        };
+        html.push_str(&format!(
+            "<tr><td class=\"cover\">{}</td><td><a href=\"/items/{}\">{}</a></td>\
+             <td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+            cover,
+            id,
+            html_escape(&item.title),
+            item.category,
+            item.status,
+            item.rating.map(|r| r.to_string()).unwrap_or_default(),
+            item.updated_at.format("%Y-%m-%d"),
+        ));
+    }
+    html.push_str("</table>");
+    html.push_str(&pager(&params, query.offset, total));
+    page(200, "Media Catalog", &html)
+}
+
+fn filter_form<'a>(param: impl Fn(&str) -> &'a str) -> String {
+    let options = |name: &str, values: &[(&str, &str)]| {
+        values
+            .iter()
+            .map(|(value, label)| {
+                let selected = if param(name) == *value {
+                    " selected"
+                } else {
+                    ""
+                };
+                format!(
+                    "<option value=\"{}\"{}>{}</option>",
+                    value,
+                    selected,
+                    html_escape(label)
+                )
+            })
+            .collect::<String>()
+    };
+    let categories: Vec<(&str, &str)> = std::iter::once(("", "Any"))
+        .chain(Category::ALL.iter().map(|c| (c.as_str(), c.as_str())))
+        .collect();
+    let statuses: Vec<(&str, &str)> = std::iter::once(("", "Any"))
+        .chain(Status::ALL.iter().map(|s| (s.as_key(), s.as_str())))
+        .collect();
+    format!(
+        "<form class=\"filters\" method=\"get\" action=\"/\">\
+         <label>Search<input name=\"q\" size=\"40\" value=\"{}\" placeholder=\"cat:book rating>=7 Dune\"></label>\
+         <label>Category<select name=\"category\">{}</select></label>\
+         <label>Status<select name=\"status\">{}</select></label>\
+         <label>Min rating<input name=\"min_rating\" size=\"3\" value=\"{}\"></label>\
+         <label>Sort by<select name=\"sort\">{}</select></label>\
+         <button>Filter</button> <a href=\"/\">Clear</a></form>",
+        html_escape(param("q")),
+        options("category", &categories),
+        options("status", &statuses),
+        html_escape(param("min_rating")),
+        options("sort", &SORT_OPTIONS),
+    )
+}
+
+fn pager(params: &[(String, String)], offset: usize, total: usize) -> String {
+    let link = |offset: usize, label: &str| {
+        let others = encode_params(params.iter().filter(|(name, _)| name != "offset"));
+        let query = if others.is_empty() {
+            format!("offset={}", offset)
+        } else {
+            format!("{}&offset={}", others, offset)
+        };
+        format!("<a href=\"/?{}\">{}</a>", html_escape(&query), label)
+    };
+    let mut html = String::from("<p>");
+    if offset > 0 {
+        html.push_str(&link(offset.saturating_sub(PAGE_SIZE), "← Previous"));
+        html.push(' ');
+    }
+    if total > 0 {
+        html.push_str(&format!(
+            "<span class=\"muted\">{}–{} of {}</span>",
+            offset + 1,
+            (offset + PAGE_SIZE).min(total),
+            total
+        ));
+    }
+    if offset + PAGE_SIZE < total {
+        html.push(' ');
+        html.push_str(&link(offset + PAGE_SIZE, "Next →"));
+    }
+    html.push_str("</p>");
+    html
+}
+
+fn detail_page(item: MediaItem, allow_edits: bool, message: Option<&str>) -> ApiResponse {
+    let id = item.id.unwrap_or_default();
+    let date = |t: Option<DateTime<Local>>| {
+        t.map(|t| t.format("%Y-%m-%d").to_string())
+            .unwrap_or_else(|| "—".into())
+    };
+    let mut html = String::from("<p><a href=\"/\">← All items</a></p>");
+    if let Some(message) = message {
+        html.push_str(&format!("<p class=\"muted\">{}</p>", html_escape(message)));
+    }
+    html.push_str("<div class=\"detail\">");
+    if item.cover_path.is_some() {
+        html.push_str(&format!("<img src=\"/covers/{}\" alt=\"Cover\">", id));
+    }
+    html.push_str("<div><table>");
+    let mut row = |label: &str, value: String| {
+        html.push_str(&format!("<tr><th>{}</th><td>{}</td></tr>", label, value));
+    };
+    row("Category", item.category.to_string());
+    row("Status", item.status.to_string());
+    row(
+        "Rating",
+        item.rating
+            .map(|r| format!("{}/10", r))
+            .unwrap_or_else(|| "—".into()),
+    );
+    if let Some(progress) = item.progress_text() {
+        row("Progress", progress);
+    }
+    if let Some(sort_title) = &item.sort_title {
+        row("Sort title", html_escape(sort_title));
+    }
+    row("Added", date(Some(item.created_at)));
+    row("Started", date(item.started_at));
+    row("Finished", date(item.finished_at));
+    row("Updated", date(Some(item.updated_at)));
+    let tags: Vec<String> = item
+        .tags()
+        .iter()
+        .map(|t| {
+            format!(
+                "<a href=\"/?q={}\">#{}</a>",
+                util::percent_encode(&format!("tag:{}", t)),
+                html_escape(t)
+            )
+        })
+        .collect();
+    if !tags.is_empty() {
+        row("Tags", tags.join(" "));
+    }
+    html.push_str("</table>");
+    if let Some(notes) = &item.notes {
+        html.push_str(&format!("<p class=\"notes\">{}</p>", html_escape(notes)));
+    }
+    if allow_edits {
+        html.push_str(&edit_form(&item));
+    }
+    html.push_str("</div></div>");
+    page(200, &item.title, &html)
+}
+
+fn edit_form(item: &MediaItem) -> String {
+    let statuses: String = Status::ALL
+        .iter()
+        .map(|s| {
+            let selected = if *s == item.status { " selected" } else { "" };
+            format!(
+                "<option value=\"{}\"{}>{}</option>",
+                s.as_key(),
+                selected,
+                s
+            )
+        })
+        .collect();
+    format!(
+        "<h3>Edit</h3><form method=\"post\" action=\"/items/{}\">\
+         <p><label>Status <select name=\"status\">{}</select></label> \
+         <label>Rating <input name=\"rating\" size=\"3\" value=\"{}\"></label></p>\
+         <p><textarea name=\"notes\" rows=\"5\" cols=\"60\">{}</textarea></p>\
+         <button>Save</button></form>",
+        item.id.unwrap_or_default(),
+        statuses,
+        item.rating.map(|r| r.to_string()).unwrap_or_default(),
+        html_escape(item.notes.as_deref().unwrap_or_default()),
+    )
+}
+
+fn edit_item(repo: &dyn Repository, mut item: MediaItem, body: &str) -> ApiResponse {
+    for (name, value) in util::query_params(body) {
+        match name.as_str() {
+            "status" => {
+                if let Some(status) = search::parse_status(&value) {
+                    item.set_status(status);
+                }
+            }
+            "rating" => match value.trim() {
+                "" => item.rating = None,
+                rating => match rating.parse::<u8>() {
+                    Ok(r) if r <= 10 => item.rating = Some(r),
+                    _ => return detail_page(item, true, Some("Rating must be 0–10")),
+                },
+            },
+            "notes" => {
+                let notes = value.replace("\r\n", "\n");
+                item.notes = (!notes.trim().is_empty()).then_some(notes);
+            }
+            _ => {}
+        }
+    }
+    item.updated_at = Local::now();
+    match repo.update(&item) {
+        Ok(()) => detail_page(item, true, Some("Saved.")),
+        Err(e) => page(500, "Error", &error_html(&e.to_string())),
+    }
+}
+
+fn cover(item: MediaItem, covers: &Path) -> ApiResponse {
+    let Some(path) = item.cover_path.as_deref() else {
+        return page(404, "Not found", "<p>This item has no cover.</p>");
+    };
+    // `cover_path` can be set through the API, so it may name any file.
+    let inside = match (Path::new(path).canonicalize(), covers.canonicalize()) {
+        (Ok(path), Ok(covers)) => path.starts_with(covers),
+        _ => false,
+    };
+    if !inside {
+        return page(404, "Not found", "<p>The cover file is missing.</p>");
+    }
+    let content_type = match Path::new(path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase())
+        .as_deref()
+    {
+        Some("png") => "image/png",
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("webp") => "image/webp",
+        _ => "application/octet-stream",
+    };
+    match std::fs::read(path) {
+        Ok(body) => ApiResponse {
+            status: 200,
+            content_type,
+            headers: vec![],
+            body,
+        },
+        Err(_) => page(404, "Not found", "<p>The cover file is missing.</p>"),
+    }
+}
+
+fn error_html(message: &str) -> String {
+    format!("<p class=\"error\">{}</p>", html_escape(message))
+}
+
+fn page(status: u16, title: &str, content: &str) -> ApiResponse {
+    let html = format!(
+        "<!DOCTYPE html><html><head><meta charset=\"utf-8\">\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\
+         <title>{}</title><style>{}</style></head><body><h1>{}</h1>{}</body></html>",
+        html_escape(title),
+        STYLE,
+        html_escape(title),
+        content
+    );
+    ApiResponse {
+        status,
+        content_type: "text/html; charset=utf-8",
+        headers: vec![],
+        body: html.into_bytes(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sqlite_repo::SqliteRepo;
+    use std::path::PathBuf;
+
+    const TOKEN: &str = "secret-token";
+    const HOST: &str = "127.0.0.1:8765";
+
+    fn memory_repo() -> SqliteRepo {
+        let repo = SqliteRepo::new(Path::new(":memory:"));
+        repo.init().unwrap();
+        let mut item = MediaItem::new("Dune", Category::Book);
+        repo.add(&mut item).unwrap();
+        repo
+    }
+
+    fn request<'a>(method: &'a str, url: &'a str) -> ApiRequest<'a> {
+        ApiRequest {
+            method,
+            url,
+            authorization: None,
+            host: Some(HOST),
+            origin: None,
+            cookie: Some("theme=dark; catalog_token=secret-token"),
+            body: "",
+        }
+    }
+
+    fn serve(repo: &dyn Repository, req: &ApiRequest, allow_edits: bool) -> ApiResponse {
+        let access = WebAccess {
+            token: TOKEN,
+            addr: HOST.parse().unwrap(),
+            allow_edits,
+            covers: Path::new("/nonexistent"),
+        };
+        handle(repo, req, &access)
+    }
+
+    fn text(response: &ApiResponse) -> String {
+        String::from_utf8_lossy(&response.body).into_owned()
+    }
+
+    #[test]
+    fn needs_the_token_and_the_server_host() {
+        let repo = memory_repo();
+        let signed_in = serve(&repo, &request("GET", "/"), false);
+        assert_eq!(signed_in.status, 200);
+        assert!(text(&signed_in).contains("Dune"));
+
+        for (cookie, authorization) in [
+            (None, None),
+            (Some("catalog_token=wrong"), None),
+            (None, Some("Bearer wrong")),
+        ] {
+            let req = ApiRequest {
+                cookie,
+                authorization,
+                ..request("GET", "/items/1")
+            };
+            assert_eq!(serve(&repo, &req, false).status, 401);
+        }
+        let bearer = ApiRequest {
+            cookie: None,
+            authorization: Some("Bearer secret-token"),
+            ..request("GET", "/items/1")
+        };
+        assert_eq!(serve(&repo, &bearer, false).status, 200);
+
+        for host in [None, Some("evil.example:8765"), Some("127.0.0.1:9999")] {
+            let req = ApiRequest {
+                host,
+                ..request("GET", "/")
+            };
+            assert_eq!(serve(&repo, &req, false).status, 403);
+        }
+        let localhost = ApiRequest {
+            host: Some("localhost:8765"),
+            ..request("GET", "/")
+        };
+        assert_eq!(serve(&repo, &localhost, false).status, 200);
+    }
+
+    #[test]
+    fn a_token_link_sets_the_cookie_and_drops_the_token() {
+        let repo = memory_repo();
+        let req = ApiRequest {
+            cookie: None,
+            ..request("GET", "/?q=dune&token=secret-token")
+        };
+        let response = serve(&repo, &req, false);
+        assert_eq!(response.status, 303);
+        assert_eq!(
+            response.headers,
+            vec![
+                ("Location", "/?q=dune".to_string()),
+                (
+                    "Set-Cookie",
+                    "catalog_token=secret-token; Path=/; HttpOnly; SameSite=Strict".to_string()
+                ),
+            ]
+        );
+        let wrong = ApiRequest {
+            cookie: None,
+            ..request("GET", "/?token=guess")
+        };
+        assert_eq!(serve(&repo, &wrong, false).status, 401);
+    }
+
+    #[test]
+    fn edits_only_from_the_same_origin_when_allowed() {
+        let repo = memory_repo();
+        let post = |origin: Option<&str>, allow_edits: bool| {
+            let req = ApiRequest {
+                origin,
+                body: "status=finished&rating=9&notes=",
+                ..request("POST", "/items/1")
+            };
+            serve(&repo, &req, allow_edits).status
+        };
+        assert_eq!(post(Some("http://evil.example"), true), 403);
+        assert_eq!(post(Some("null"), true), 403);
+        assert_eq!(post(Some("http://127.0.0.1:8765"), false), 403);
+        assert_eq!(repo.get(1).unwrap().unwrap().rating, None);
+
+        assert_eq!(post(Some("http://127.0.0.1:8765"), true), 200);
+        let item = repo.get(1).unwrap().unwrap();
+        assert_eq!((item.status, item.rating), (Status::Finished, Some(9)));
+    }
+
+    #[test]
+    fn serves_covers_from_the_covers_folder_only() {
+        let dir: PathBuf = std::env::temp_dir().join(format!("web-covers-{}", std::process::id()));
+        let covers = dir.join("covers");
+        std::fs::create_dir_all(&covers).unwrap();
+        std::fs::write(covers.join("dune.png"), b"png").unwrap();
+        std::fs::write(dir.join("secret.png"), b"secret").unwrap();
+
+        let mut item = MediaItem::new("Dune", Category::Book);
+        item.cover_path = Some(covers.join("dune.png").display().to_string());
+        let served = cover(item.clone(), &covers);
+        assert_eq!((served.status, served.body), (200, b"png".to_vec()));
+        for path in [dir.join("secret.png"), covers.join("../secret.png")] {
+            item.cover_path = Some(path.display().to_string());
+            assert_eq!(cover(item.clone(), &covers).status, 404);
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
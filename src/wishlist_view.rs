@@ -0,0 +1,208 @@
+use crate::models::{MediaItem, OwnedCopy, Priority, Query, SortField, SortKey, SortOrder, Status};
+use crate::repo::{RepoError, RepoResult, Repository};
+use crate::util;
+use chrono::Local;
+use eframe::egui::{self, RichText, TextEdit};
+
+/// Side panel listing the wishlist by priority, with a "Bought" form that
+/// turns an item into an owned Planned item and records the purchase as a
+/// copy.
+#[derive(Default)]
+pub struct WishlistView {
+    pub open: bool,
+    items: Vec<MediaItem>,
+    buying: Option<Purchase>,
+}
+
+// Details of a purchase being entered for a wishlist item.
+struct Purchase {
+    item_id: i64,
+    price: String,
+    format: String,
+    location: String,
+}
+
+impl WishlistView {
+    pub fn reload(&mut self, repo: &dyn Repository) -> RepoResult<()> {
+        self.items = repo.list(&Query {
+            status: Some(Status::Wishlist),
+            sort: vec![
+                SortKey::new(SortField::Priority, SortOrder::Asc),
+                SortKey::new(SortField::Title, SortOrder::Asc),
+            ],
+            ..Default::default()
+        })?;
+        if let Some(p) = &self.buying {
+            if !self.items.iter().any(|i| i.id == Some(p.item_id)) {
+                self.buying = None;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns true when an item was bought and the catalog needs a refresh.
+    pub fn show(&mut self, ctx: &egui::Context, repo: &dyn Repository) -> RepoResult<bool> {
+        if !self.open {
+            return Ok(false);
+        }
+        let mut bought = false;
+        let mut result = Ok(());
+        egui::SidePanel::right("wishlist")
+            .resizable(true)
+            .default_width(280.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.heading(format!("Wishlist ({})", self.items.len()));
+                    if ui.small_button("✕").on_hover_text("Close").clicked() {
+                        self.open = false;
+                    }
+                });
+                let total: f64 = self.items.iter().filter_map(|i| i.target_price).sum();
+                let unpriced = self
+                    .items
+                    .iter()
+                    .filter(|i| i.target_price.is_none())
+                    .count();
+                ui.weak(format!("Target prices add up to {:.2}", total));
+                if unpriced > 0 {
+                    ui.weak(format!("{} items have no target price.", unpriced));
+                }
+                ui.separator();
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    for item in self.items.clone() {
+                        ui.group(|ui| {
+                            ui.set_width(ui.available_width());
+                            self.item_ui(ui, &item);
+                            match self.purchase_ui(ui, repo, &item) {
+                                Ok(done) => bought |= done,
+                                Err(e) => result = Err(e),
+                            }
+                        });
+                    }
+                });
+            });
+        result?;
+        if bought {
+            self.reload(repo)?;
+        }
+        Ok(bought)
+    }
+
+    fn item_ui(&mut self, ui: &mut egui::Ui, item: &MediaItem) {
+        ui.horizontal_wrapped(|ui| {
+            ui.label(RichText::new(&item.title).strong());
+            ui.weak(item.category.to_string());
+        });
+        ui.horizontal_wrapped(|ui| {
+            if let Some(p) = item.priority {
+                let color = match p {
+                    Priority::High => ui.visuals().warn_fg_color,
+                    _ => ui.visuals().weak_text_color(),
+                };
+                ui.label(RichText::new(p.as_str()).small().color(color));
+            }
+            if let Some(price) = item.target_price {
+                ui.label(RichText::new(format!("≤ {:.2}", price)).small());
+            }
+            match item.store.as_deref() {
+                Some(url) if url.starts_with("http://") || url.starts_with("https://") => {
+                    ui.hyperlink_to(RichText::new(store_name(url)).small(), url);
+                }
+                Some(store) => {
+                    ui.label(RichText::new(store).small());
+                }
+                None => {}
+            }
+        });
+        if self.buying.is_none() && item.id.is_some() && ui.small_button("Bought...").clicked() {
+            self.buying = Some(Purchase {
+                item_id: item.id.unwrap_or_default(),
+                price: item
+                    .target_price
+                    .map(|p| format!("{:.2}", p))
+                    .unwrap_or_default(),
+                format: String::new(),
+                location: String::new(),
+            });
+        }
+    }
+
+    // Purchase form under the item being bought; returns true once confirmed
+    // and saved.
+    fn purchase_ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        repo: &dyn Repository,
+        item: &MediaItem,
+    ) -> RepoResult<bool> {
+        let Some(p) = self.buying.as_mut().filter(|p| item.id == Some(p.item_id)) else {
+            return Ok(false);
+        };
+        let mut confirm = false;
+        let mut cancel = false;
+        egui::Grid::new(("wishlist_purchase", p.item_id))
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("Price paid:");
+                ui.add(TextEdit::singleline(&mut p.price).desired_width(60.0));
+                ui.end_row();
+                ui.label("Format:");
+                ui.horizontal(|ui| {
+                    ui.add(TextEdit::singleline(&mut p.format).desired_width(100.0));
+                    let formats = item.category.copy_formats();
+                    if !formats.is_empty() {
+                        ui.menu_button("▾", |ui| {
+                            for f in formats {
+                                if ui.button(*f).clicked() {
+                                    p.format = f.to_string();
+                                    ui.close_menu();
+                                }
+                            }
+                        });
+                    }
+                });
+                ui.end_row();
+                ui.label("Location:");
+                ui.add(
+                    TextEdit::singleline(&mut p.location)
+                        .hint_text("Shelf, box, platform")
+                        .desired_width(100.0),
+                );
+                ui.end_row();
+            });
+        ui.horizontal(|ui| {
+            confirm = ui.button("Confirm").clicked();
+            cancel = ui.button("Cancel").clicked();
+        });
+        if confirm {
+            let mut copy = OwnedCopy::new(p.item_id);
+            copy.price = util::parse_price(&p.price).map_err(RepoError::Other)?;
+            copy.format = p.format.trim().to_string();
+            copy.location = p.location.trim().to_string();
+            buy(repo, item, &mut copy)?;
+            self.buying = None;
+            return Ok(true);
+        }
+        if cancel {
+            self.buying = None;
+        }
+        Ok(false)
+    }
+}
+
+/// Moves `item` off the wishlist to Planned and saves `copy` as the copy
+/// bought, dated today unless it has a purchase date.
+pub fn buy(repo: &dyn Repository, item: &MediaItem, copy: &mut OwnedCopy) -> RepoResult<()> {
+    copy.purchased_on
+        .get_or_insert_with(|| Local::now().date_naive());
+    let mut item = item.clone();
+    item.set_status(Status::Planned);
+    repo.save_purchase(&item, copy)?;
+    Ok(())
+}
+
+// Host name of a store URL, e.g. "www.example.com".
+fn store_name(url: &str) -> &str {
+    let rest = url.split("://").nth(1).unwrap_or(url);
+    rest.split('/').next().unwrap_or(rest)
+}